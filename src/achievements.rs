@@ -0,0 +1,198 @@
+//! Achievements unlocked by playing.
+//!
+//! The engine listens to `GameEvent`s and unlocks an `Achievement` the first
+//! time its condition is met. Unlocks (and the daily win streak) survive
+//! between sessions because the whole state serializes to JSON.
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::events::GameEvent;
+
+/// Winning faster than this unlocks `Achievement::SpeedWin`.
+pub const SPEED_WIN_MS: f64 = 3.0 * 60.0 * 1000.0;
+
+/// Number of consecutive days with a win needed for `Achievement::WeekStreak`.
+pub const STREAK_DAYS: u32 = 7;
+
+/// Every achievement the game knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Achievement {
+    /// Win a game without using undo.
+    NoUndoWin,
+    /// Win a game in under three minutes.
+    SpeedWin,
+    /// Complete a foundation pile from Ace to King for the first time.
+    FirstFoundation,
+    /// Win at least one game on seven days in a row.
+    WeekStreak,
+}
+
+impl Achievement {
+    /// All achievements, in the order they are listed to the player.
+    pub const ALL: [Achievement; 4] = [
+        Achievement::NoUndoWin,
+        Achievement::SpeedWin,
+        Achievement::FirstFoundation,
+        Achievement::WeekStreak,
+    ];
+
+    /// Stable identifier used by the JavaScript API and in saved data.
+    pub fn id(self) -> &'static str {
+        match self {
+            Achievement::NoUndoWin => "no_undo_win",
+            Achievement::SpeedWin => "speed_win",
+            Achievement::FirstFoundation => "first_foundation",
+            Achievement::WeekStreak => "week_streak",
+        }
+    }
+
+    /// Short description shown to the player.
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::NoUndoWin => "Win a game without using undo",
+            Achievement::SpeedWin => "Win a game in under three minutes",
+            Achievement::FirstFoundation => "Complete a foundation from Ace to King",
+            Achievement::WeekStreak => "Win a game on seven days in a row",
+        }
+    }
+}
+
+/// Unlock state plus what is needed to evaluate the remaining achievements.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Achievements {
+    unlocked: Vec<Achievement>,
+    streak_days: u32,
+    last_win_day: Option<i64>,
+}
+
+impl Achievements {
+    /// Feed one event that happened at `now_ms` (milliseconds since the Unix
+    /// epoch). Returns the achievements unlocked by it, usually none.
+    ///
+    /// A win is judged by what its event says: the undos and the time of
+    /// play of the game, which a saved game keeps and a pause does not add
+    /// to.
+    pub fn handle(&mut self, event: &GameEvent, now_ms: f64) -> Vec<Achievement> {
+        let mut earned = Vec::new();
+        match event {
+            GameEvent::FoundationCompleted { .. } => earned.push(Achievement::FirstFoundation),
+            GameEvent::GameWon { undos, time_ms, .. } => {
+                if *undos == 0 {
+                    earned.push(Achievement::NoUndoWin);
+                }
+                if (*time_ms as f64) < SPEED_WIN_MS {
+                    earned.push(Achievement::SpeedWin);
+                }
+                self.record_win_day(clock::day_number(now_ms));
+                if self.streak_days >= STREAK_DAYS {
+                    earned.push(Achievement::WeekStreak);
+                }
+            }
+            _ => {}
+        }
+        earned.retain(|a| !self.unlocked.contains(a));
+        self.unlocked.extend(earned.iter().copied());
+        earned
+    }
+
+    fn record_win_day(&mut self, day: i64) {
+        self.streak_days = match self.last_win_day {
+            Some(last) if last == day => self.streak_days,
+            Some(last) if last + 1 == day => self.streak_days + 1,
+            _ => 1,
+        };
+        self.last_win_day = Some(day);
+    }
+
+    /// Whether `achievement` has been unlocked.
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// Unlocked achievements in the order they were earned.
+    pub fn unlocked(&self) -> &[Achievement] {
+        &self.unlocked
+    }
+
+    /// Number of consecutive days, ending with the latest win, with a win.
+    pub fn streak_days(&self) -> u32 {
+        self.streak_days
+    }
+
+    /// Serialize the persistent part of the state.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("achievements always serialize")
+    }
+
+    /// Restore state saved with `to_json`.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::DAY_MS;
+
+    const WIN: GameEvent = GameEvent::GameWon {
+        moves: 100,
+        undos: 0,
+        time_ms: 60_000,
+        time_bonus: 11_666,
+    };
+
+    fn play(engine: &mut Achievements, start: f64, end: f64) -> Vec<Achievement> {
+        engine.handle(&GameEvent::GameStarted { seed: 1 }, start);
+        engine.handle(&WIN, end)
+    }
+
+    #[test]
+    fn quick_clean_win_unlocks_once() {
+        let mut engine = Achievements::default();
+        let earned = play(&mut engine, 0.0, 60_000.0);
+        assert_eq!(earned, vec![Achievement::NoUndoWin, Achievement::SpeedWin]);
+        assert!(play(&mut engine, 0.0, 60_000.0).is_empty());
+    }
+
+    #[test]
+    fn undo_and_slow_wins_do_not_count() {
+        let mut engine = Achievements::default();
+        let slow_with_undo = GameEvent::GameWon {
+            moves: 100,
+            undos: 1,
+            time_ms: SPEED_WIN_MS as u64,
+            time_bonus: 3_888,
+        };
+        // Paused for an hour, but the time of play is what counts.
+        engine.handle(&GameEvent::GameStarted { seed: 1 }, 0.0);
+        assert!(engine.handle(&slow_with_undo, 0.0).is_empty());
+        assert_eq!(
+            engine.handle(&WIN, 60.0 * 60_000.0),
+            vec![Achievement::NoUndoWin, Achievement::SpeedWin]
+        );
+
+        let earned = engine.handle(&GameEvent::FoundationCompleted { foundation: 2 }, 0.0);
+        assert_eq!(earned, vec![Achievement::FirstFoundation]);
+    }
+
+    #[test]
+    fn week_streak_survives_reload() {
+        let mut engine = Achievements::default();
+        for day in 0..6 {
+            let t = day as f64 * DAY_MS + 1_000_000.0;
+            play(&mut engine, t, t + SPEED_WIN_MS);
+        }
+        let mut engine = Achievements::from_json(&engine.to_json()).unwrap();
+        assert_eq!(engine.streak_days(), 6);
+
+        let t = 6.0 * DAY_MS;
+        assert_eq!(engine.handle(&WIN, t), vec![Achievement::WeekStreak]);
+
+        // Skipping a day starts the streak again.
+        engine.handle(&WIN, 8.0 * DAY_MS);
+        assert_eq!(engine.streak_days(), 1);
+    }
+}
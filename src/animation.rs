@@ -0,0 +1,412 @@
+// Animations played over the laid-out table.
+//
+// The rules change the board at once; animations only change how it looks
+// for a moment afterwards. A card that is turned over gets a `Flip`
+// component when its `CardFlipped` event is dispatched: over `FLIP_MS` it
+// narrows to nothing, swaps faces and widens back. `Flip::frame` tells the
+// renderer how to draw it at a given time, and `animation_system` takes the
+// component away again once the flip is over.
+//
+// When the table is laid out anew, as after a resize, cards glide to their
+// new places instead of jumping: `start_slides` gives every card that moved a
+// `Slide` holding how far off its new position it was drawn, and that offset
+// shrinks to nothing over `SLIDE_MS`. The same happens after every move,
+// and a new deal sends its cards out from the stock one after the other.
+//
+// How each kind of animation moves is up to the `AnimationStyle` resource:
+// one easing curve each for flips, moves, the deal and the win celebration.
+// Themes come with a style of their own, and each curve can be changed on
+// its own too.
+//
+// With the `ReducedMotion` resource, for players who asked their system for
+// less motion or turned it down in the game, nothing is tweened: cards are
+// drawn straight at their new places and faces, and no win celebration
+// flies about.
+
+use serde::{Deserialize, Serialize};
+
+use crate::easing::Easing;
+use crate::ecs::{Entity, World};
+use crate::events::GameEvent;
+use crate::game::{Pile, PileIndex};
+use crate::layout::{self, Position};
+
+/// How long turning a card over takes.
+pub const FLIP_MS: f64 = 240.0;
+/// How long cards take to reach their new places after a re-layout.
+pub const SLIDE_MS: f64 = 200.0;
+/// Time between two cards leaving the stock when dealing.
+pub const DEAL_STEP_MS: f64 = 40.0;
+
+/// Resource with the easing curve of each kind of animation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnimationStyle {
+    pub flip: Easing,
+    pub moves: Easing,
+    pub deal: Easing,
+    /// Spacing of the cards cascading off the foundations.
+    pub win: Easing,
+}
+
+impl Default for AnimationStyle {
+    fn default() -> Self {
+        AnimationStyle::theme(AnimationTheme::Classic)
+    }
+}
+
+/// The animation styles themes come with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationTheme {
+    /// Calm and quick.
+    Classic,
+    /// Moves overshoot and dealt cards bounce into place.
+    Playful,
+}
+
+impl AnimationStyle {
+    pub fn theme(theme: AnimationTheme) -> Self {
+        match theme {
+            AnimationTheme::Classic => AnimationStyle {
+                flip: Easing::Linear,
+                moves: Easing::EaseOutCubic,
+                deal: Easing::EaseOutCubic,
+                win: Easing::Linear,
+            },
+            AnimationTheme::Playful => AnimationStyle {
+                flip: Easing::EaseOutCubic,
+                moves: Easing::Back,
+                deal: Easing::Bounce,
+                win: Easing::EaseOutCubic,
+            },
+        }
+    }
+}
+
+/// The current style of `world`.
+pub fn style(world: &World) -> AnimationStyle {
+    world
+        .resource::<AnimationStyle>()
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Marker resource turning animations off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReducedMotion;
+
+pub fn reduced_motion(world: &World) -> bool {
+    world.resource::<ReducedMotion>().is_some()
+}
+
+/// Whether the player asked the system for reduced motion, with the
+/// `prefers-reduced-motion` media query.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub fn prefers_reduced_motion() -> bool {
+    web_sys::window()
+        .and_then(|w| w.match_media("(prefers-reduced-motion: reduce)").ok())
+        .flatten()
+        .is_some_and(|query| query.matches())
+}
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+pub fn prefers_reduced_motion() -> bool {
+    false
+}
+
+/// Component of a card being turned over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flip {
+    pub started_ms: f64,
+    /// The side that ends up showing.
+    pub face_up: bool,
+    pub easing: Easing,
+}
+
+/// How to draw a flipping card at one moment: squeezed horizontally about
+/// its centre to `scale_x` of its width, showing its face or its back.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FlipFrame {
+    pub scale_x: f32,
+    pub face_up: bool,
+}
+
+impl Flip {
+    /// The frame at `now_ms`, or `None` once the flip is over.
+    pub fn frame(&self, now_ms: f64) -> Option<FlipFrame> {
+        let t = ((now_ms - self.started_ms) / FLIP_MS).max(0.0);
+        if t >= 1.0 {
+            return None;
+        }
+        let turned = self.easing.apply(t as f32);
+        Some(FlipFrame {
+            scale_x: (1.0 - 2.0 * turned).abs(),
+            // The old side shows until the card is edge-on.
+            face_up: if turned < 0.5 {
+                !self.face_up
+            } else {
+                self.face_up
+            },
+        })
+    }
+}
+
+/// Component of a card gliding to its laid-out `Position`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slide {
+    pub started_ms: f64,
+    /// Where the card was drawn when the slide started, relative to its new
+    /// position.
+    pub dx: f32,
+    pub dy: f32,
+    pub easing: Easing,
+}
+
+impl Slide {
+    /// How far off its position to draw the card at `now_ms`, or `None`
+    /// once it has arrived. Before the slide starts the card waits where it
+    /// was.
+    pub fn offset(&self, now_ms: f64) -> Option<(f32, f32)> {
+        let t = ((now_ms - self.started_ms) / SLIDE_MS).max(0.0);
+        if t >= 1.0 {
+            return None;
+        }
+        let left = 1.0 - self.easing.apply(t as f32);
+        Some((self.dx * left, self.dy * left))
+    }
+}
+
+/// Start a flip for every card turned over in `events`, at `now_ms`.
+pub fn start_flips(world: &mut World, events: &[GameEvent], now_ms: f64) {
+    if reduced_motion(world) {
+        return;
+    }
+    let easing = style(world).flip;
+    for event in events {
+        if let GameEvent::CardFlipped { card, face_up } = *event {
+            world.add_component(
+                card,
+                Flip {
+                    started_ms: now_ms,
+                    face_up,
+                    easing,
+                },
+            );
+        }
+    }
+}
+
+/// Start a slide at `now_ms` for every card whose `Position` differs from
+/// the one it had in `before`. A card already sliding carries on from where
+/// it is drawn.
+pub fn start_slides(world: &mut World, before: &[(Entity, Position)], now_ms: f64, easing: Easing) {
+    if reduced_motion(world) {
+        return;
+    }
+    for &(entity, old) in before {
+        let Some(&new) = world.get_component::<Position>(entity) else {
+            continue;
+        };
+        let (sx, sy) = world
+            .get_component::<Slide>(entity)
+            .and_then(|slide| slide.offset(now_ms))
+            .unwrap_or_default();
+        let (dx, dy) = (old.x + sx - new.x, old.y + sy - new.y);
+        if dx != 0.0 || dy != 0.0 {
+            world.add_component(
+                entity,
+                Slide {
+                    started_ms: now_ms,
+                    dx,
+                    dy,
+                    easing,
+                },
+            );
+        }
+    }
+}
+
+/// The `Position` of every laid-out card.
+pub fn positions(world: &World) -> Vec<(Entity, Position)> {
+    world.iter::<Position>().map(|(e, &p)| (e, p)).collect()
+}
+
+/// Lay the table out again after the moves in `events` and slide the cards
+/// that moved from where they were drawn before.
+pub fn start_moves(world: &mut World, events: &[GameEvent], now_ms: f64) {
+    let moved = events.iter().any(|event| {
+        matches!(
+            event,
+            GameEvent::CardMoved { .. } | GameEvent::StockRecycled | GameEvent::UndoPerformed
+        )
+    });
+    if !moved {
+        return;
+    }
+    let before = positions(world);
+    layout::layout_system(world);
+    let easing = style(world).moves;
+    start_slides(world, &before, now_ms, easing);
+}
+
+/// Deal the cards out from the stock at `now_ms`: lay the table out and
+/// slide every card outside the stock there from the stock, row by row
+/// across the tableau, as a dealer would.
+pub fn start_deal(world: &mut World, now_ms: f64) {
+    layout::layout_system(world);
+    if reduced_motion(world) {
+        return;
+    }
+    let easing = style(world).deal;
+    let (stock_x, stock_y) = layout::metrics(world).pile_origin(Pile::Stock);
+    let mut dealt: Vec<(PileIndex, Pile, Entity, Position)> = world
+        .iter::<Position>()
+        .filter_map(|(entity, &position)| {
+            let pile = *world.get_component::<Pile>(entity)?;
+            let index = *world.get_component::<PileIndex>(entity)?;
+            (pile != Pile::Stock).then_some((index, pile, entity, position))
+        })
+        .collect();
+    dealt.sort_by_key(|&(index, pile, ..)| (index, pile_order(pile)));
+    for (i, (_, _, entity, position)) in dealt.into_iter().enumerate() {
+        world.add_component(
+            entity,
+            Slide {
+                started_ms: now_ms + i as f64 * DEAL_STEP_MS,
+                dx: stock_x - position.x,
+                dy: stock_y - position.y,
+                easing,
+            },
+        );
+    }
+}
+
+fn pile_order(pile: Pile) -> u8 {
+    match pile {
+        Pile::Tableau(i) => i,
+        Pile::Foundation(i) => 100 + i,
+        Pile::Waste => 200,
+        Pile::Stock => 201,
+    }
+}
+
+/// Remove the flips and slides that are over by `now_ms`, or all of them
+/// once motion is reduced.
+pub fn animation_system(world: &mut World, now_ms: f64) {
+    let reduced = reduced_motion(world);
+    let finished: Vec<Entity> = world
+        .iter::<Flip>()
+        .filter(|(_, flip)| reduced || flip.frame(now_ms).is_none())
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in finished {
+        world.remove_component::<Flip>(entity);
+    }
+    let arrived: Vec<Entity> = world
+        .iter::<Slide>()
+        .filter(|(_, slide)| reduced || slide.offset(now_ms).is_none())
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in arrived {
+        world.remove_component::<Slide>(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Deck;
+    use crate::rules;
+
+    #[test]
+    fn flips_narrow_swap_faces_and_widen_back() {
+        let mut world = World::new();
+        let card = world.spawn();
+        start_flips(
+            &mut world,
+            &[GameEvent::CardFlipped {
+                card,
+                face_up: true,
+            }],
+            1_000.0,
+        );
+        let flip = *world.get_component::<Flip>(card).unwrap();
+        assert_eq!(
+            flip.frame(1_000.0),
+            Some(FlipFrame {
+                scale_x: 1.0,
+                face_up: false
+            })
+        );
+        let late = flip.frame(1_000.0 + FLIP_MS * 0.75).unwrap();
+        assert!(late.face_up && (late.scale_x - 0.5).abs() < 1e-6);
+
+        animation_system(&mut world, 1_000.0 + FLIP_MS / 2.0);
+        assert!(world.get_component::<Flip>(card).is_some());
+        animation_system(&mut world, 1_000.0 + FLIP_MS);
+        assert!(world.get_component::<Flip>(card).is_none());
+    }
+
+    #[test]
+    fn moved_cards_glide_to_their_new_places() {
+        let mut world = World::new();
+        let card = world.spawn();
+        let still = world.spawn();
+        let at = |x| Position { x, y: 10.0, z: 0 };
+        world.add_component(card, at(100.0));
+        world.add_component(still, at(0.0));
+        let before = [(card, at(100.0)), (still, at(0.0))];
+        world.add_component(card, at(40.0));
+        start_slides(&mut world, &before, 0.0, Easing::Linear);
+
+        assert!(world.get_component::<Slide>(still).is_none());
+        let slide = *world.get_component::<Slide>(card).unwrap();
+        assert_eq!(slide.offset(0.0), Some((60.0, 0.0)));
+        assert_eq!(slide.offset(SLIDE_MS / 2.0), Some((30.0, 0.0)));
+        animation_system(&mut world, SLIDE_MS);
+        assert!(world.get_component::<Slide>(card).is_none());
+    }
+
+    #[test]
+    fn deals_send_cards_out_from_the_stock_in_turn() {
+        let mut world = World::new();
+        world.insert_resource(AnimationStyle::theme(AnimationTheme::Playful));
+        rules::deal(&mut world, &Deck::standard().cards);
+        start_deal(&mut world, 0.0);
+
+        let first = rules::pile_cards(&world, Pile::Tableau(0))[0];
+        let second = rules::pile_cards(&world, Pile::Tableau(1))[0];
+        let first = *world.get_component::<Slide>(first).unwrap();
+        let second = *world.get_component::<Slide>(second).unwrap();
+        assert_eq!(first.easing, Easing::Bounce);
+        assert_eq!(second.started_ms, first.started_ms + DEAL_STEP_MS);
+        // Waiting cards sit on the stock.
+        assert_eq!(second.offset(0.0), Some((second.dx, second.dy)));
+        assert_eq!(world.iter::<Slide>().count(), 28);
+    }
+
+    #[test]
+    fn reduced_motion_puts_cards_straight_in_place() {
+        let mut world = World::new();
+        rules::deal(&mut world, &Deck::standard().cards);
+        start_deal(&mut world, 0.0);
+        world.insert_resource(ReducedMotion);
+        // Animations under way stop at once.
+        animation_system(&mut world, 0.0);
+        assert_eq!(world.iter::<Slide>().count(), 0);
+
+        start_deal(&mut world, 0.0);
+        let card = rules::top_card(&world, Pile::Tableau(0)).unwrap();
+        start_flips(
+            &mut world,
+            &[GameEvent::CardFlipped {
+                card,
+                face_up: true,
+            }],
+            0.0,
+        );
+        assert_eq!(world.iter::<Slide>().count(), 0);
+        assert_eq!(world.iter::<Flip>().count(), 0);
+    }
+}
@@ -0,0 +1,149 @@
+//! Assembling the game from plugins.
+//!
+//! A `GameApp` owns a `World` and the `Schedule` that runs every frame.
+//! Features such as rendering, audio, networking or a solitaire variant are
+//! packaged as `Plugin`s, each registering its systems, resources and event
+//! types in one call:
+//!
+//! ```ignore
+//! struct AudioPlugin;
+//!
+//! impl Plugin for AudioPlugin {
+//!     fn build(&self, app: &mut GameApp) {
+//!         app.add_event::<GameEvent>()
+//!             .insert_resource(Mixer::default())
+//!             .add_system(System::new("play_sounds", play_sounds).after("rules"));
+//!     }
+//! }
+//!
+//! let mut app = GameApp::new();
+//! app.add_plugin(AudioPlugin);
+//! app.update()?;
+//! ```
+
+use std::any::type_name;
+
+use crate::ecs::{Events, Schedule, ScheduleError, System, World, update_events};
+
+/// A bundle of systems, resources and event types added to a `GameApp`
+/// together.
+pub trait Plugin: 'static {
+    fn build(&self, app: &mut GameApp);
+}
+
+/// The world, the per-frame schedule and the plugins that filled them.
+#[derive(Default)]
+pub struct GameApp {
+    world: World,
+    schedule: Schedule,
+    plugins: Vec<&'static str>,
+}
+
+impl GameApp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build `plugin` into the app. Adding the same plugin type twice does
+    /// nothing, so plugins may add the plugins they depend on.
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self {
+        if !self.has_plugin::<P>() {
+            self.plugins.push(type_name::<P>());
+            plugin.build(self);
+        }
+        self
+    }
+
+    pub fn has_plugin<P: Plugin>(&self) -> bool {
+        self.plugins.contains(&type_name::<P>())
+    }
+
+    pub fn add_system(&mut self, system: System) -> &mut Self {
+        self.schedule.add_system(system);
+        self
+    }
+
+    pub fn insert_resource<T: 'static>(&mut self, resource: T) -> &mut Self {
+        self.world.insert_resource(resource);
+        self
+    }
+
+    /// Register the event type `T`: create its `Events<T>` queue and
+    /// schedule the system that advances it every frame. Registering twice
+    /// is harmless.
+    pub fn add_event<T: 'static>(&mut self) -> &mut Self {
+        if self.world.resource::<Events<T>>().is_none() {
+            self.world.insert_resource(Events::<T>::default());
+            let name = format!("update_events<{}>", type_name::<T>());
+            self.add_system(System::new(&name, update_events::<T>).label("update_events"));
+        }
+        self
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Run one frame: every scheduled system, then clear the removal
+    /// trackers.
+    pub fn update(&mut self) -> Result<(), ScheduleError> {
+        self.schedule.run(&mut self.world)?;
+        self.world.clear_trackers();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::EventCursor;
+
+    struct Moved;
+
+    #[derive(Default)]
+    struct Score(u32);
+
+    struct ScoringPlugin;
+
+    impl Plugin for ScoringPlugin {
+        fn build(&self, app: &mut GameApp) {
+            let mut moves = EventCursor::<Moved>::default();
+            app.add_plugin(InputPlugin)
+                .add_event::<Moved>()
+                .insert_resource(Score::default())
+                .add_system(
+                    System::new("score", move |world| {
+                        let count = world.read_events(&mut moves).count() as u32;
+                        world.resource_mut::<Score>().unwrap().0 += count;
+                    })
+                    .after("input"),
+                );
+        }
+    }
+
+    struct InputPlugin;
+
+    impl Plugin for InputPlugin {
+        fn build(&self, app: &mut GameApp) {
+            app.add_event::<Moved>().add_system(
+                System::new("input", |world| world.send_event(Moved)).before("update_events"),
+            );
+        }
+    }
+
+    #[test]
+    fn plugins_register_everything_once() {
+        let mut app = GameApp::new();
+        app.add_plugin(ScoringPlugin).add_plugin(InputPlugin);
+        assert!(app.has_plugin::<InputPlugin>());
+
+        app.update().unwrap();
+        app.update().unwrap();
+        // One event per frame, each counted exactly once.
+        assert_eq!(app.world().resource::<Score>().unwrap().0, 2);
+    }
+}
@@ -0,0 +1,137 @@
+// A bump arena for short lists that come and go last in, first out.
+//
+// Every move on the undo stack remembers the cards it touched, and every node
+// of a solver search keeps the moves still to try from it. Giving each of
+// those lists a `Vec` of its own costs an allocation per move, thousands per
+// hint request, and the wasm allocator is slow at it.
+//
+// Both kinds of list are only ever dropped newest first: undo pops the last
+// move, and the search backtracks out of the deepest node. So a `StackArena`
+// keeps all of its lists back to back in one vector. A list is pushed onto
+// the end and named by its `Span`; releasing the newest list truncates the
+// vector, which keeps its memory for the lists to come.
+
+use std::ops::Range;
+
+/// Lists of `T`, stored back to back and released newest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackArena<T> {
+    items: Vec<T>,
+}
+
+/// Where one list lies in a `StackArena`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    start: u32,
+    end: u32,
+}
+
+impl Span {
+    pub fn len(self) -> usize {
+        (self.end - self.start) as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.start == self.end
+    }
+
+    fn range(self) -> Range<usize> {
+        self.start as usize..self.end as usize
+    }
+}
+
+impl<T> Default for StackArena<T> {
+    fn default() -> Self {
+        StackArena { items: Vec::new() }
+    }
+}
+
+impl<T> StackArena<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An arena with room for `capacity` items before it first grows.
+    pub fn with_capacity(capacity: usize) -> Self {
+        StackArena {
+            items: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Where the next list starts: pass it to `span_from` once the list has
+    /// been pushed.
+    pub fn top(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Add an item to the list being pushed.
+    pub fn push(&mut self, item: T) {
+        self.items.push(item);
+    }
+
+    /// The list of every item pushed since `top` returned `start`.
+    pub fn span_from(&self, start: usize) -> Span {
+        assert!(start <= self.items.len(), "lists start below the top");
+        Span {
+            start: start as u32,
+            end: self.items.len() as u32,
+        }
+    }
+
+    pub fn get(&self, span: Span) -> &[T] {
+        &self.items[span.range()]
+    }
+
+    /// Drop the list at `span` and every list pushed after it.
+    pub fn release(&mut self, span: Span) {
+        self.items.truncate(span.start as usize);
+    }
+
+    /// Number of items in all the lists.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Drop every list, keeping the memory.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+}
+
+impl<T> Extend<T> for StackArena<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+        self.items.extend(items);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_are_released_newest_first_and_reuse_the_memory() {
+        let mut arena = StackArena::with_capacity(8);
+        let start = arena.top();
+        arena.extend([1, 2, 3]);
+        let first = arena.span_from(start);
+        let start = arena.top();
+        arena.push(4);
+        let second = arena.span_from(start);
+        let empty = arena.span_from(arena.top());
+
+        assert_eq!(arena.get(first), &[1, 2, 3]);
+        assert_eq!(arena.get(second), &[4]);
+        assert!(empty.is_empty());
+        assert_eq!(arena.len(), 4);
+
+        arena.release(second);
+        assert_eq!(arena.get(first), &[1, 2, 3]);
+        arena.release(first);
+        assert!(arena.is_empty());
+        assert!(arena.items.capacity() >= 8);
+    }
+}
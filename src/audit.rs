@@ -0,0 +1,498 @@
+//! Tamper-evident record of a game for ranked play.
+//!
+//! Every move (and every undo) is appended to an `AuditLog` together with the
+//! deal seed and a timestamp. Entries are chained: each one stores the hash of
+//! the entry before it, so removing, reordering or editing an entry breaks
+//! every hash that follows. When a session key is set, each hash is also
+//! signed with HMAC-SHA256, so only the holder of the key can produce a valid
+//! log.
+//!
+//! Because the seed fully determines the deal, a server can replay the log
+//! with `verify` and check that it really ends in the position the client
+//! claims.
+//!
+//! Ranked games also stream every action to the server as it is made, as a
+//! `SignedMove` signed on its own with the key handed out when the session
+//! was authenticated. Each carries a nonce one higher than the last, so a
+//! `MoveVerifier` notices moves that were altered, dropped into the stream
+//! twice or sent again later, and `verify_moves` replays the stream like a
+//! log.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::error::GameError;
+use crate::events::GameEvent;
+use crate::game::{Card, FaceUp};
+use crate::rules::{self, Move};
+use crate::signing;
+
+/// `prev_hash` of the first entry in every log.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What the player did.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Move(Move),
+    /// A move played as part of the previous one, see `rules::apply_follow_up`.
+    FollowUp(Move),
+    Undo,
+}
+
+impl AuditAction {
+    /// Play the action on `world`, as the game did.
+    pub fn play(self, world: &mut World) -> Result<Vec<GameEvent>, GameError> {
+        match self {
+            AuditAction::Move(mv) => rules::apply(world, mv),
+            AuditAction::FollowUp(mv) => rules::apply_follow_up(world, mv),
+            AuditAction::Undo => rules::undo(world),
+        }
+    }
+}
+
+/// One link of the chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, starting at 0.
+    pub seq: u32,
+    /// Seed of the deal the action was played on.
+    pub seed: u64,
+    pub action: AuditAction,
+    /// Milliseconds since the Unix epoch when the action was made.
+    pub timestamp_ms: f64,
+    /// `hash` of the previous entry, or `GENESIS_HASH`.
+    pub prev_hash: String,
+    /// SHA-256 over `prev_hash` and the entry's content.
+    pub hash: String,
+    /// HMAC-SHA256 of `hash` with the session key, if one was set.
+    pub signature: Option<String>,
+}
+
+/// The fields covered by an entry's hash, in a fixed order.
+#[derive(Serialize)]
+struct HashedContent<'a> {
+    prev_hash: &'a str,
+    seq: u32,
+    seed: u64,
+    action: &'a AuditAction,
+    timestamp_ms: f64,
+}
+
+fn entry_hash(
+    prev_hash: &str,
+    seq: u32,
+    seed: u64,
+    action: &AuditAction,
+    timestamp_ms: f64,
+) -> String {
+    let content = HashedContent {
+        prev_hash,
+        seq,
+        seed,
+        action,
+        timestamp_ms,
+    };
+    signing::sha256_hex(&serde_json::to_vec(&content).expect("audit entries always serialize"))
+}
+
+/// World resource collecting the audit entries of the current game.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    seed: u64,
+    key: Option<String>,
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    /// Start an empty log for the deal created from `seed`, signing entries
+    /// with `key` when given.
+    pub fn new(seed: u64, key: Option<String>) -> Self {
+        Self {
+            seed,
+            key,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Append an action made at `timestamp_ms`.
+    pub fn record(&mut self, action: AuditAction, timestamp_ms: f64) {
+        let seq = self.entries.len() as u32;
+        let prev_hash = self
+            .entries
+            .last()
+            .map_or(GENESIS_HASH.to_string(), |e| e.hash.clone());
+        let hash = entry_hash(&prev_hash, seq, self.seed, &action, timestamp_ms);
+        let signature = self
+            .key
+            .as_deref()
+            .map(|key| signing::hmac_hex(key, hash.as_bytes()));
+        self.entries.push(AuditEntry {
+            seq,
+            seed: self.seed,
+            action,
+            timestamp_ms,
+            prev_hash,
+            hash,
+            signature,
+        });
+    }
+
+    /// Seed of the deal this log belongs to.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Encode the entries as a JSON array for transmission.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.entries).expect("audit entries always serialize")
+    }
+}
+
+/// An action as streamed to the server while the game is played.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedMove {
+    /// One more than the nonce of the session's previous move, across deals.
+    pub nonce: u64,
+    /// Seed of the deal the action was played on.
+    pub seed: u64,
+    pub action: AuditAction,
+    /// Milliseconds since the Unix epoch when the action was made.
+    pub timestamp_ms: f64,
+    /// HMAC-SHA256 of the fields above with the session key.
+    pub signature: String,
+}
+
+/// The fields covered by a move's signature, in a fixed order.
+#[derive(Serialize)]
+struct SignedContent<'a> {
+    nonce: u64,
+    seed: u64,
+    action: &'a AuditAction,
+    timestamp_ms: f64,
+}
+
+fn move_signature(key: &str, nonce: u64, seed: u64, action: &AuditAction, ts: f64) -> String {
+    let content = SignedContent {
+        nonce,
+        seed,
+        action,
+        timestamp_ms: ts,
+    };
+    let bytes = serde_json::to_vec(&content).expect("signed moves always serialize");
+    signing::hmac_hex(key, &bytes)
+}
+
+/// Signs the actions of a session with its key, numbering them as it goes.
+#[derive(Debug, Clone)]
+pub struct MoveSigner {
+    key: String,
+    next_nonce: u64,
+}
+
+impl MoveSigner {
+    pub fn new(key: String) -> Self {
+        Self { key, next_nonce: 0 }
+    }
+
+    /// Sign an action made at `timestamp_ms` on the deal of `seed`.
+    pub fn sign(&mut self, seed: u64, action: AuditAction, timestamp_ms: f64) -> SignedMove {
+        let nonce = self.next_nonce;
+        self.next_nonce += 1;
+        SignedMove {
+            nonce,
+            seed,
+            action,
+            timestamp_ms,
+            signature: move_signature(&self.key, nonce, seed, &action, timestamp_ms),
+        }
+    }
+}
+
+/// Checks the moves of one session as they arrive on the server.
+#[derive(Debug, Clone)]
+pub struct MoveVerifier {
+    key: String,
+    last_nonce: Option<u64>,
+}
+
+impl MoveVerifier {
+    pub fn new(key: String) -> Self {
+        Self {
+            key,
+            last_nonce: None,
+        }
+    }
+
+    /// Accept `mv` if it is signed with the session key and newer than
+    /// every move accepted before it.
+    pub fn check(&mut self, mv: &SignedMove) -> Result<(), AuditError> {
+        let expected = move_signature(&self.key, mv.nonce, mv.seed, &mv.action, mv.timestamp_ms);
+        if mv.signature != expected {
+            return Err(AuditError::BadMoveSignature { nonce: mv.nonce });
+        }
+        if self.last_nonce.is_some_and(|last| mv.nonce <= last) {
+            return Err(AuditError::ReplayedNonce { nonce: mv.nonce });
+        }
+        self.last_nonce = Some(mv.nonce);
+        Ok(())
+    }
+}
+
+/// Why a received log was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditError {
+    /// The log could not be decoded.
+    Malformed(String),
+    /// The log has no entries, so there is nothing to replay.
+    Empty,
+    /// An entry's `seq`, seed, `prev_hash` or `hash` does not match.
+    BrokenChain { seq: u32 },
+    /// An entry is unsigned or its signature does not match the key.
+    BadSignature { seq: u32 },
+    /// A streamed move is not signed with the session key.
+    BadMoveSignature { nonce: u64 },
+    /// A streamed move is no newer than one already accepted.
+    ReplayedNonce { nonce: u64 },
+    /// Replaying the entry failed because the move is not legal.
+    IllegalAction { seq: u32, error: GameError },
+    /// The replayed game does not end in the claimed position.
+    FinalStateMismatch,
+}
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditError::Malformed(msg) => write!(f, "malformed audit log: {msg}"),
+            AuditError::Empty => write!(f, "the audit log is empty"),
+            AuditError::BrokenChain { seq } => write!(f, "hash chain broken at entry {seq}"),
+            AuditError::BadSignature { seq } => write!(f, "bad signature on entry {seq}"),
+            AuditError::BadMoveSignature { nonce } => write!(f, "bad signature on move {nonce}"),
+            AuditError::ReplayedNonce { nonce } => write!(f, "move {nonce} was replayed"),
+            AuditError::IllegalAction { seq, error } => write!(f, "entry {seq}: {error}"),
+            AuditError::FinalStateMismatch => {
+                write!(f, "the log does not lead to the claimed final state")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+#[cfg(feature = "wasm")]
+impl From<AuditError> for wasm_bindgen::JsValue {
+    fn from(err: AuditError) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
+/// Check a log received as JSON.
+///
+/// The chain and (when `key` is given) every signature are checked first.
+/// The game is then replayed from the seed and its final position compared
+/// with `final_digest`, as produced by `state_digest` on the client. Ranked
+/// games are always single-deck Klondike, so that is what is replayed.
+pub fn verify(log_json: &str, key: Option<&str>, final_digest: &str) -> Result<(), AuditError> {
+    let entries: Vec<AuditEntry> =
+        serde_json::from_str(log_json).map_err(|e| AuditError::Malformed(e.to_string()))?;
+    let seed = entries.first().ok_or(AuditError::Empty)?.seed;
+
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (i, entry) in entries.iter().enumerate() {
+        let seq = entry.seq;
+        let expected = entry_hash(&prev_hash, seq, seed, &entry.action, entry.timestamp_ms);
+        if seq as usize != i
+            || entry.seed != seed
+            || entry.prev_hash != prev_hash
+            || entry.hash != expected
+        {
+            return Err(AuditError::BrokenChain { seq });
+        }
+        if let Some(key) = key
+            && !entry.signature.as_deref().is_some_and(|signature| {
+                signing::verify_hmac_hex(key, entry.hash.as_bytes(), signature)
+            })
+        {
+            return Err(AuditError::BadSignature { seq });
+        }
+        prev_hash = expected;
+    }
+
+    replay(seed, entries.iter().map(|e| e.action), final_digest)
+}
+
+/// Check a stream of `SignedMove`s from one deal received as JSON: every
+/// signature and nonce with `key`, then the replayed game against
+/// `final_digest`, as for `verify`. Errors on illegal moves count `seq`
+/// from the start of the stream.
+pub fn verify_moves(moves_json: &str, key: &str, final_digest: &str) -> Result<(), AuditError> {
+    let moves: Vec<SignedMove> =
+        serde_json::from_str(moves_json).map_err(|e| AuditError::Malformed(e.to_string()))?;
+    let seed = moves.first().ok_or(AuditError::Empty)?.seed;
+    let mut verifier = MoveVerifier::new(key.to_string());
+    for mv in &moves {
+        verifier.check(mv)?;
+        if mv.seed != seed {
+            return Err(AuditError::Malformed(
+                "moves from more than one deal".into(),
+            ));
+        }
+    }
+    replay(seed, moves.iter().map(|m| m.action), final_digest)
+}
+
+/// Replay `actions` on the deal of `seed` and compare the final position
+/// with `final_digest`.
+fn replay(
+    seed: u64,
+    actions: impl Iterator<Item = AuditAction>,
+    final_digest: &str,
+) -> Result<(), AuditError> {
+    let mut world = World::new();
+    rules::deal_from_seed(&mut world, seed);
+    for (seq, action) in actions.enumerate() {
+        action
+            .play(&mut world)
+            .map_err(|error| AuditError::IllegalAction {
+                seq: seq as u32,
+                error,
+            })?;
+    }
+
+    if state_digest(&world) == final_digest {
+        Ok(())
+    } else {
+        Err(AuditError::FinalStateMismatch)
+    }
+}
+
+/// A short fingerprint of the position: every pile, in a fixed order, with
+/// its cards and whether each one is face up.
+pub fn state_digest(world: &World) -> String {
+    let mut text = String::new();
+    for pile in rules::all_piles(world) {
+        text.push_str(&format!("{pile}:"));
+        for entity in rules::pile_cards(world, pile) {
+            let card = world
+                .get_component::<Card>(entity)
+                .expect("cards have a Card");
+            let face_up = world.get_component::<FaceUp>(entity).is_some_and(|f| f.0);
+            text.push_str(&format!(
+                "{:?}{:?}{},",
+                card.rank,
+                card.suit,
+                u8::from(face_up)
+            ));
+        }
+        text.push(';');
+    }
+    signing::sha256_hex(text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u64 = 7;
+
+    /// Play a few draws and an undo on a seeded deal.
+    fn played(key: Option<&str>) -> (World, AuditLog) {
+        let mut world = World::new();
+        rules::deal_from_seed(&mut world, SEED);
+        let mut log = AuditLog::new(SEED, key.map(str::to_string));
+        for t in 0..3 {
+            rules::apply(&mut world, Move::Draw).unwrap();
+            log.record(AuditAction::Move(Move::Draw), t as f64);
+        }
+        rules::undo(&mut world).unwrap();
+        log.record(AuditAction::Undo, 3.0);
+        (world, log)
+    }
+
+    #[test]
+    fn valid_log_verifies() {
+        let (world, log) = played(Some("key"));
+        assert_eq!(
+            verify(&log.to_json(), Some("key"), &state_digest(&world)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let (world, log) = played(Some("key"));
+        let digest = state_digest(&world);
+
+        assert_eq!(
+            verify(&log.to_json(), Some("other"), &digest),
+            Err(AuditError::BadSignature { seq: 0 })
+        );
+
+        let mut entries = log.entries().to_vec();
+        entries[1].timestamp_ms += 1.0;
+        let json = serde_json::to_string(&entries).unwrap();
+        assert_eq!(
+            verify(&json, Some("key"), &digest),
+            Err(AuditError::BrokenChain { seq: 1 })
+        );
+
+        let mut entries = log.entries().to_vec();
+        entries.pop();
+        let json = serde_json::to_string(&entries).unwrap();
+        assert_eq!(
+            verify(&json, Some("key"), &digest),
+            Err(AuditError::FinalStateMismatch)
+        );
+    }
+
+    #[test]
+    fn unsigned_log_fails_signed_check() {
+        let (world, log) = played(None);
+        let digest = state_digest(&world);
+        assert_eq!(verify(&log.to_json(), None, &digest), Ok(()));
+        assert_eq!(
+            verify(&log.to_json(), Some("key"), &digest),
+            Err(AuditError::BadSignature { seq: 0 })
+        );
+    }
+
+    #[test]
+    fn streamed_moves_are_signed_and_numbered() {
+        let mut world = World::new();
+        rules::deal_from_seed(&mut world, SEED);
+        let mut signer = MoveSigner::new("key".into());
+        let moves: Vec<SignedMove> = (0..3)
+            .map(|t| {
+                rules::apply(&mut world, Move::Draw).unwrap();
+                signer.sign(SEED, AuditAction::Move(Move::Draw), t as f64)
+            })
+            .collect();
+        let digest = state_digest(&world);
+        let json = serde_json::to_string(&moves).unwrap();
+        assert_eq!(verify_moves(&json, "key", &digest), Ok(()));
+        assert_eq!(
+            verify_moves(&json, "other", &digest),
+            Err(AuditError::BadMoveSignature { nonce: 0 })
+        );
+
+        let mut verifier = MoveVerifier::new("key".into());
+        verifier.check(&moves[0]).unwrap();
+        verifier.check(&moves[2]).unwrap();
+        assert_eq!(
+            verifier.check(&moves[1]),
+            Err(AuditError::ReplayedNonce { nonce: 1 })
+        );
+        let mut tampered = moves[2].clone();
+        tampered.nonce = 3;
+        assert_eq!(
+            verifier.check(&tampered),
+            Err(AuditError::BadMoveSignature { nonce: 3 })
+        );
+    }
+}
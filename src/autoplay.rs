@@ -0,0 +1,357 @@
+// Letting the game play itself: auto-complete and solution playback.
+//
+// Once every tableau card is face up the game is as good as won, and the
+// player can ask the game to finish it. Likewise a solution found by the
+// hint engine can be played back for a guided finish. Both run through the
+// same machinery: an `AutoPlay` resource present while the game is playing
+// itself, paced by the `AutoPlaySettings` chosen by the player. The frontend
+// calls `SolitaireGame::advance` every frame and animates the moves it plays.
+//
+// Single clicks are a smaller kind of auto-play: clicking a card sends it to
+// a foundation. With *smart moves* switched on, only moves that are provably
+// safe are made, so a click never buries a card the tableau still needs.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::game::{Card, FaceUp, Pile, PileIndex};
+use crate::rules::{self, History, Move};
+use crate::variant::{self, Table};
+
+/// The most moves played in one frame without animation. Anything left
+/// over is played in the frames after, so the page stays responsive.
+pub const MAX_UNANIMATED_MOVES: usize = 1_000;
+
+/// Resource with the player's auto-play preferences.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AutoPlaySettings {
+    /// How many cards move per second while animating.
+    pub cards_per_second: f64,
+    /// `false` jumps straight to the end instead of animating each move.
+    pub animate: bool,
+    /// Clicks only send cards to the foundations when that is safe.
+    #[serde(default)]
+    pub smart_moves: bool,
+}
+
+impl Default for AutoPlaySettings {
+    fn default() -> Self {
+        AutoPlaySettings {
+            cards_per_second: 8.0,
+            animate: true,
+            smart_moves: false,
+        }
+    }
+}
+
+/// What the game is playing by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoPlayMode {
+    /// Moving every card to the foundations.
+    AutoComplete,
+    /// Following the solver's winning line.
+    #[cfg(feature = "solver")]
+    Solution,
+}
+
+/// Resource present while the game plays itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoPlay {
+    pub mode: AutoPlayMode,
+    /// Whether a move has been played yet. The rest follow up on it.
+    pub started: bool,
+    // Fraction of a move carried over between frames.
+    carry: f64,
+    passes: StockPasses,
+}
+
+impl AutoPlay {
+    pub fn new(mode: AutoPlayMode) -> Self {
+        AutoPlay {
+            mode,
+            started: false,
+            carry: 0.0,
+            passes: StockPasses::default(),
+        }
+    }
+
+    /// How many moves are due after another `dt_ms` milliseconds. Without
+    /// animation everything is due at once, up to `MAX_UNANIMATED_MOVES`.
+    pub fn due(&mut self, dt_ms: f64, settings: &AutoPlaySettings) -> usize {
+        if !settings.animate {
+            return MAX_UNANIMATED_MOVES;
+        }
+        self.carry += dt_ms.max(0.0) / 1000.0 * settings.cards_per_second.max(0.0);
+        let due = self.carry.floor();
+        self.carry -= due;
+        due as usize
+    }
+
+    /// The next auto-complete move, or `None` once going round the stock
+    /// again would get no further than the last time.
+    pub fn auto_complete_move(&mut self, world: &World) -> Option<Move> {
+        auto_complete_move(world).filter(|&mv| self.passes.allows(mv))
+    }
+}
+
+/// Watches auto-complete go round the stock. A pass from one recycle to the
+/// next that sends nothing to the foundations leaves the stock as it was, so
+/// the next pass would go the same way, forever.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct StockPasses {
+    recycled: bool,
+    progress: bool,
+}
+
+impl StockPasses {
+    /// Whether `mv` is still worth playing.
+    fn allows(&mut self, mv: Move) -> bool {
+        match mv {
+            Move::Recycle => {
+                let stuck = self.recycled && !self.progress;
+                self.recycled = true;
+                self.progress = false;
+                !stuck
+            }
+            Move::Transfer { .. } => {
+                self.progress = true;
+                true
+            }
+            Move::Draw => true,
+        }
+    }
+}
+
+/// Whether auto-complete can finish the game: no tableau card is face down,
+/// and every card in the stock comes up in time.
+pub fn can_auto_complete(world: &World) -> bool {
+    !rules::is_won(world)
+        && world
+            .iter::<Pile>()
+            .filter(|(_, pile)| matches!(pile, Pile::Tableau(_)))
+            .all(|(entity, _)| world.get_component::<FaceUp>(entity).is_some_and(|f| f.0))
+        && finishes(world)
+}
+
+/// Whether auto-complete wins from here, rehearsed on a copy of the cards.
+/// Drawing three, or with the redeals used up, some stock cards may never
+/// come up at all.
+fn finishes(world: &World) -> bool {
+    let table = variant::table(world);
+    let recycles = world.resource::<History>().map_or(0, History::recycles);
+    let cards: Vec<(Card, Pile, PileIndex, FaceUp)> = world
+        .iter::<Card>()
+        .filter_map(|(entity, &card)| {
+            Some((
+                card,
+                *world.get_component::<Pile>(entity)?,
+                *world.get_component::<PileIndex>(entity)?,
+                *world.get_component::<FaceUp>(entity)?,
+            ))
+        })
+        .collect();
+    let mut rehearsal = World::new();
+    rehearsal.insert_resource(Table {
+        redeal_limit: table
+            .redeal_limit
+            .map(|limit| limit.saturating_sub(recycles)),
+        ..table
+    });
+    rehearsal.spawn_batch(cards);
+    let mut passes = StockPasses::default();
+    while let Some(mv) = auto_complete_move(&rehearsal).filter(|&mv| passes.allows(mv)) {
+        if rules::apply(&mut rehearsal, mv).is_err() {
+            return false;
+        }
+    }
+    rules::is_won(&rehearsal)
+}
+
+/// The next auto-complete move: a card to a foundation when one fits,
+/// otherwise a draw or recycle to dig further into the stock.
+pub fn auto_complete_move(world: &World) -> Option<Move> {
+    let table = variant::table(world);
+    let sources = std::iter::once(Pile::Waste).chain((0..table.tableau_piles).map(Pile::Tableau));
+    for from in sources {
+        for f in 0..table.foundation_piles {
+            let mv = Move::Transfer {
+                from,
+                to: Pile::Foundation(f),
+                count: 1,
+            };
+            if rules::validate(world, mv).is_ok() {
+                return Some(mv);
+            }
+        }
+    }
+    [Move::Draw, Move::Recycle]
+        .into_iter()
+        .find(|&mv| rules::validate(world, mv).is_ok())
+}
+
+/// What a single click on the top card of `from` does: the stock draws or
+/// recycles, any other pile sends its top card to a foundation. With
+/// `smart` set the foundation move is only made when `is_safe_to_found`.
+pub fn click_move(world: &World, from: Pile, smart: bool) -> Option<Move> {
+    if from == Pile::Stock {
+        return [Move::Draw, Move::Recycle]
+            .into_iter()
+            .find(|&mv| rules::validate(world, mv).is_ok());
+    }
+    let card = *world.get_component::<Card>(rules::top_card(world, from)?)?;
+    if smart && !is_safe_to_found(world, card) {
+        return None;
+    }
+    (0..variant::table(world).foundation_piles)
+        .map(|f| Move::Transfer {
+            from,
+            to: Pile::Foundation(f),
+            count: 1,
+        })
+        .find(|&mv| rules::validate(world, mv).is_ok())
+}
+
+/// Whether `card` can go to a foundation without ever being missed on the
+/// tableau. Nothing can be built on the first two cards of a foundation
+/// suit, and once every off-colour card one rank lower is founded, nothing
+/// is left for `card` to hold up.
+pub fn is_safe_to_found(world: &World, card: Card) -> bool {
+    let table = variant::table(world);
+    let base = table.foundation_base;
+    if card.rank == base || card.rank == base.next_wrapping() {
+        return true;
+    }
+    let needed = usize::from(table.decks) * 2;
+    let founded = world
+        .iter::<Card>()
+        .filter(|&(entity, c)| {
+            c.suit.is_red() != card.suit.is_red()
+                && c.rank.next_wrapping() == card.rank
+                && matches!(
+                    world.get_component::<Pile>(entity),
+                    Some(Pile::Foundation(_))
+                )
+        })
+        .count();
+    founded == needed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Card, Deck, PileIndex};
+
+    #[test]
+    fn pacing_follows_the_settings() {
+        let settings = AutoPlaySettings {
+            cards_per_second: 4.0,
+            ..AutoPlaySettings::default()
+        };
+        let mut auto = AutoPlay::new(AutoPlayMode::AutoComplete);
+        assert_eq!(auto.due(100.0, &settings), 0);
+        assert_eq!(auto.due(200.0, &settings), 1);
+        assert_eq!(auto.due(1000.0, &settings), 4);
+        let jump = AutoPlaySettings {
+            animate: false,
+            ..settings
+        };
+        assert_eq!(auto.due(0.0, &jump), MAX_UNANIMATED_MOVES);
+    }
+
+    #[test]
+    fn auto_complete_finishes_the_game() {
+        // Spades and hearts in the stock, the other suits face up on
+        // tableau 0 and 1 with the Ace on top.
+        let deck = Deck::standard();
+        let mut layout: Vec<(Card, Pile, PileIndex, FaceUp)> = Vec::new();
+        for (i, suit) in deck.cards.chunks(13).enumerate() {
+            for (height, &card) in suit.iter().rev().enumerate() {
+                let (pile, face_up) = match i {
+                    0 | 1 => (Pile::Tableau(i as u8), true),
+                    _ => (Pile::Stock, false),
+                };
+                let index = if i == 3 { height + 13 } else { height };
+                layout.push((card, pile, PileIndex(index), FaceUp(face_up)));
+            }
+        }
+        let mut world = World::new();
+        world.spawn_batch(layout);
+
+        assert!(can_auto_complete(&world));
+        let mut moves = 0;
+        while let Some(mv) = auto_complete_move(&world) {
+            rules::apply(&mut world, mv).unwrap();
+            moves += 1;
+            assert!(moves < 1_000, "auto-complete must make progress");
+        }
+        assert!(rules::is_won(&world));
+        assert!(!can_auto_complete(&world));
+    }
+
+    #[test]
+    fn auto_complete_gives_up_on_a_stock_it_cannot_get_through() {
+        use crate::game::{Rank, Suit};
+        // Drawing three turns the Ace up under the Three and the Two, and
+        // every pass after turns them up the same way.
+        let stock: Vec<_> = [Rank::Two, Rank::Three, Rank::Ace]
+            .into_iter()
+            .enumerate()
+            .map(|(i, rank)| {
+                let card = Card::new(Suit::Spades, rank);
+                (card, Pile::Stock, PileIndex(i), FaceUp(false))
+            })
+            .collect();
+        for (draw_count, finishes) in [(1, true), (3, false)] {
+            let mut world = World::new();
+            world.insert_resource(Table {
+                draw_count,
+                ..Table::KLONDIKE
+            });
+            world.spawn_batch(stock.clone());
+            assert_eq!(can_auto_complete(&world), finishes);
+
+            let mut auto = AutoPlay::new(AutoPlayMode::AutoComplete);
+            let mut moves = 0;
+            while let Some(mv) = auto.auto_complete_move(&world) {
+                rules::apply(&mut world, mv).unwrap();
+                moves += 1;
+                assert!(moves < 100, "auto-complete must stop");
+            }
+            assert_eq!(rules::is_won(&world), finishes);
+        }
+    }
+
+    #[test]
+    fn smart_clicks_only_found_safe_cards() {
+        use crate::game::{Rank, Suit};
+        let card =
+            |suit, rank, pile, index| (Card::new(suit, rank), pile, PileIndex(index), FaceUp(true));
+        let mut world = World::new();
+        world.spawn_batch([
+            card(Suit::Hearts, Rank::Ace, Pile::Foundation(0), 0),
+            card(Suit::Hearts, Rank::Two, Pile::Foundation(0), 1),
+            card(Suit::Clubs, Rank::Ace, Pile::Foundation(1), 0),
+            card(Suit::Clubs, Rank::Two, Pile::Foundation(1), 1),
+            card(Suit::Hearts, Rank::Three, Pile::Tableau(0), 0),
+            card(Suit::Clubs, Rank::Three, Pile::Tableau(1), 0),
+        ]);
+        // The Three of Clubs may still be needed to hold the Two of Diamonds.
+        assert_eq!(click_move(&world, Pile::Tableau(1), true), None);
+        assert!(click_move(&world, Pile::Tableau(1), false).is_some());
+
+        world.spawn_batch([
+            card(Suit::Diamonds, Rank::Ace, Pile::Foundation(2), 0),
+            card(Suit::Diamonds, Rank::Two, Pile::Foundation(2), 1),
+        ]);
+        assert_eq!(
+            click_move(&world, Pile::Tableau(1), true),
+            Some(Move::Transfer {
+                from: Pile::Tableau(1),
+                to: Pile::Foundation(1),
+                count: 1,
+            })
+        );
+        // The Spade Two is still out, so the Three of Hearts is not safe.
+        assert_eq!(click_move(&world, Pile::Tableau(0), true), None);
+    }
+}
@@ -0,0 +1,200 @@
+// Micro-benchmarks run on the player's device.
+//
+// How fast the game runs depends on the device far more than on anything we
+// can measure on a desktop: a budget phone may lay out the board ten times
+// slower than a laptop. The benchmarks here time the work a frame and a save
+// actually do — spawning and querying entities, writing and reading a save,
+// laying out the board — so an integrator can pick the renderer, animation
+// quality or autosave interval for the device at hand.
+//
+// Each benchmark repeats its work on a freshly dealt board until its time
+// budget has run out and reports the operations per second. Keep budgets
+// short, a hundred milliseconds or so, since they block the thread.
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::clock;
+use crate::ecs::World;
+use crate::error::GameError;
+use crate::game::{Card, Deck, FaceUp, Pile, PileIndex};
+use crate::layout;
+use crate::options::GameOptions;
+use crate::rules::{self, Move};
+use crate::save::SavedGame;
+
+/// Rounds after which a benchmark stops even if the clock has not moved,
+/// as on `wasm32` without JavaScript.
+const MAX_ROUNDS: u32 = 100_000;
+
+/// Seed of the board the benchmarks work on.
+const SEED: u64 = 1;
+
+/// What can be measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bench {
+    /// Entities spawned with the components of a card.
+    EcsSpawn,
+    /// Entities visited by a query for the components of a card.
+    EcsQuery,
+    /// Saves written as JSON and read back.
+    Serialization,
+    /// Layout passes over the whole board.
+    Layout,
+}
+
+impl Bench {
+    pub const ALL: [Bench; 4] = [
+        Bench::EcsSpawn,
+        Bench::EcsQuery,
+        Bench::Serialization,
+        Bench::Layout,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Bench::EcsSpawn => "ecs_spawn",
+            Bench::EcsQuery => "ecs_query",
+            Bench::Serialization => "serialization",
+            Bench::Layout => "layout",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, GameError> {
+        Bench::ALL
+            .into_iter()
+            .find(|bench| bench.name() == name)
+            .ok_or_else(|| GameError::InvalidOptions(format!("unknown benchmark \"{name}\"")))
+    }
+}
+
+/// How one benchmark went.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub bench: Bench,
+    pub ops: u64,
+    pub elapsed_ms: f64,
+    /// Zero when the clock did not move.
+    pub ops_per_sec: f64,
+}
+
+/// Run `bench` for about `budget_ms` milliseconds.
+pub fn run(bench: Bench, budget_ms: f64) -> BenchResult {
+    let mut world = dealt();
+    let mut round: Box<dyn FnMut() -> u64> = match bench {
+        Bench::EcsSpawn => {
+            let deck = Deck::standard().cards;
+            Box::new(move || {
+                let mut world = World::new();
+                world.spawn_batch(
+                    deck.iter()
+                        .map(|&card| (card, Pile::Stock, PileIndex(0), FaceUp(false))),
+                );
+                deck.len() as u64
+            })
+        }
+        Bench::EcsQuery => Box::new(move || {
+            world
+                .query::<(&Card, &Pile, &PileIndex, Option<&FaceUp>)>()
+                .count() as u64
+        }),
+        Bench::Serialization => Box::new(move || {
+            let saved = SavedGame::capture(&world, GameOptions::default(), 0, 0.0);
+            SavedGame::from_json(&saved.to_json()).expect("saves read back");
+            1
+        }),
+        Bench::Layout => Box::new(move || {
+            layout::layout_system(&mut world);
+            1
+        }),
+    };
+
+    let start = clock::now_ms();
+    let mut ops = 0;
+    let mut elapsed_ms = 0.0;
+    for _ in 0..MAX_ROUNDS {
+        ops += round();
+        elapsed_ms = clock::now_ms() - start;
+        if elapsed_ms >= budget_ms {
+            break;
+        }
+    }
+    BenchResult {
+        bench,
+        ops,
+        elapsed_ms,
+        ops_per_sec: if elapsed_ms > 0.0 {
+            ops as f64 * 1000.0 / elapsed_ms
+        } else {
+            0.0
+        },
+    }
+}
+
+/// Run every benchmark, each for about `budget_ms` milliseconds.
+pub fn run_all(budget_ms: f64) -> Vec<BenchResult> {
+    Bench::ALL
+        .into_iter()
+        .map(|bench| run(bench, budget_ms))
+        .collect()
+}
+
+/// A board part way through a game, so saves have actions and undo
+/// history to write.
+fn dealt() -> World {
+    let mut world = World::new();
+    rules::deal_from_seed(&mut world, SEED);
+    world.insert_resource(AuditLog::new(SEED, None));
+    for _ in 0..12 {
+        let action = AuditAction::Move(Move::Draw);
+        if action.play(&mut world).is_ok() {
+            world
+                .resource_mut::<AuditLog>()
+                .unwrap()
+                .record(action, 0.0);
+        }
+    }
+    world
+}
+
+#[cfg(feature = "wasm")]
+pub use web::{run_benchmark, run_benchmarks};
+
+#[cfg(feature = "wasm")]
+mod web {
+    use wasm_bindgen::prelude::*;
+
+    use super::*;
+
+    /// Operations per second of the benchmark called `name` (`"ecs_spawn"`,
+    /// `"ecs_query"`, `"serialization"` or `"layout"`), run for about
+    /// `budget_ms` milliseconds.
+    #[wasm_bindgen]
+    pub fn run_benchmark(name: &str, budget_ms: f64) -> Result<f64, GameError> {
+        Ok(run(Bench::from_name(name)?, budget_ms).ops_per_sec)
+    }
+
+    /// Every benchmark, each run for about `budget_ms` milliseconds, as JSON:
+    /// `[{ bench, ops, elapsed_ms, ops_per_sec }, ...]`.
+    #[wasm_bindgen]
+    pub fn run_benchmarks(budget_ms: f64) -> String {
+        serde_json::to_string(&run_all(budget_ms)).expect("results always serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_benchmark_does_its_work_within_the_budget() {
+        for result in run_all(5.0) {
+            assert!(result.ops > 0, "{result:?}");
+            assert!(result.elapsed_ms < 1_000.0, "{result:?}");
+            assert_eq!(Bench::from_name(result.bench.name()), Ok(result.bench));
+        }
+        assert_eq!(run(Bench::EcsSpawn, 0.0).ops, 52);
+        assert!(Bench::from_name("physics").is_err());
+    }
+}
@@ -0,0 +1,480 @@
+// A single-deck board packed into a handful of words, for the solver.
+//
+// `solver::Position` keeps every pile in a `Vec`, so each node of a search
+// clones a dozen vectors and hashes them to remember that it was seen. A
+// `Bitboard` is `Copy` and needs no allocation at all. Cards are 6-bit
+// `Card::to_index`es, and:
+//
+// * `talon` holds the stock and the waste as one sequence: the stock from
+//   the bottom card up, then the waste from the top card down. Drawing only
+//   moves the boundary between the two, and recycling moves it back;
+// * `tableau` holds each tableau pile in a `u128`, bottom card in the low
+//   bits, so the top card and any run under it are a shift away;
+// * `foundations` holds the length and top card of every foundation, which
+//   is all there is to know about a pile built up in suit;
+// * `lens` holds the length of the talon, the stock and each tableau pile;
+// * `face_up` has bit `i` set when card `i` lies face up on the tableau.
+//
+// The moves are generated straight from the packed words, in the same order
+// as `Position::moves`, so a search finds the same lines either way. Seen
+// positions are remembered exactly rather than by a 64-bit hash.
+//
+// Only the 13 piles of a single-deck Klondike table fit; `from_position`
+// returns `None` for anything else, such as Double Klondike, which the
+// solver keeps searching as `Position`s.
+
+use rand::seq::SliceRandom;
+
+use crate::game::{Card, Pile};
+use crate::rng::GameRng;
+use crate::rules::{self, Move};
+use crate::solver::{Position, TableauPile};
+use crate::variant::Table;
+
+const FOUNDATION_PILES: u8 = 4;
+const TABLEAU_PILES: u8 = 7;
+const PER_WORD: usize = 10;
+const TALON_CAPACITY: usize = 3 * PER_WORD;
+const TABLEAU_CAPACITY: usize = 128 / 6;
+const KING: u8 = 12;
+
+/// A single-deck Klondike position, packed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bitboard {
+    talon: [u64; 3],
+    tableau: [u128; TABLEAU_PILES as usize],
+    foundations: u64,
+    lens: u64,
+    face_up: u64,
+    recycles: u32,
+    table: Table,
+}
+
+/// Read the `i`th 6-bit field of `words`.
+fn field(words: &[u64], i: usize) -> u8 {
+    (words[i / PER_WORD] >> (i % PER_WORD * 6) & 63) as u8
+}
+
+fn set_field(words: &mut [u64], i: usize, value: u8) {
+    let shift = i % PER_WORD * 6;
+    let word = &mut words[i / PER_WORD];
+    *word = *word & !(63 << shift) | u64::from(value) << shift;
+}
+
+fn card(index: u8) -> Card {
+    Card::from_index(index).expect("bitboards only hold card indices")
+}
+
+fn rank(index: u8) -> u8 {
+    index % 13
+}
+
+fn is_red(index: u8) -> bool {
+    matches!(index / 13, 1 | 2)
+}
+
+/// The card of the same suit one rank up, wrapping from King to Ace.
+fn next_wrapping(index: u8) -> u8 {
+    index - rank(index) + (rank(index) + 1) % 13
+}
+
+fn previous_wrapping(index: u8) -> u8 {
+    index - rank(index) + (rank(index) + 12) % 13
+}
+
+/// The lowest `count` cards of a tableau word.
+fn low_cards(count: usize) -> u128 {
+    (1u128 << (6 * count)) - 1
+}
+
+// Slots of the 5-bit lengths in `lens`.
+const TALON: usize = 0;
+const STOCK: usize = 1;
+const TABLEAU: usize = 2;
+
+impl Bitboard {
+    /// Pack `position`, or `None` when its table is not a single-deck
+    /// Klondike table.
+    pub fn from_position(position: &Position) -> Option<Self> {
+        let table = position.table;
+        if table.decks != 1
+            || table.foundation_piles != FOUNDATION_PILES
+            || table.tableau_piles != TABLEAU_PILES
+            || position.foundations.len() != usize::from(FOUNDATION_PILES)
+            || position.tableau.len() != usize::from(TABLEAU_PILES)
+            || position.stock.len() + position.waste.len() > TALON_CAPACITY
+        {
+            return None;
+        }
+        let mut board = Bitboard {
+            talon: [0; 3],
+            tableau: [0; TABLEAU_PILES as usize],
+            foundations: 0,
+            lens: 0,
+            face_up: 0,
+            recycles: position.recycles,
+            table,
+        };
+
+        let talon = position.stock.iter().chain(position.waste.iter().rev());
+        for (i, c) in talon.enumerate() {
+            set_field(&mut board.talon, i, c.to_index());
+        }
+        board.set_len(TALON, position.stock.len() + position.waste.len());
+        board.set_len(STOCK, position.stock.len());
+
+        for (f, foundation) in position.foundations.iter().enumerate() {
+            let Some(top) = foundation.last() else {
+                continue;
+            };
+            // Only a run in suit is told by its top card.
+            let run = foundation
+                .windows(2)
+                .all(|w| next_wrapping(w[0].to_index()) == w[1].to_index());
+            if !run || foundation.len() > 13 {
+                return None;
+            }
+            board.set_foundation(f, foundation.len(), top.to_index());
+        }
+
+        for (t, pile) in position.tableau.iter().enumerate() {
+            let len = pile.hidden.len() + pile.face_up.len();
+            if len > TABLEAU_CAPACITY {
+                return None;
+            }
+            for (k, c) in pile.hidden.iter().chain(&pile.face_up).enumerate() {
+                board.tableau[t] |= u128::from(c.to_index()) << (6 * k);
+            }
+            for c in &pile.face_up {
+                board.face_up |= 1 << c.to_index();
+            }
+            board.set_len(TABLEAU + t, len);
+        }
+        Some(board)
+    }
+
+    /// Unpack into a `Position`.
+    pub fn to_position(&self) -> Position {
+        let mut position = Position::empty_table(&self.table);
+        position.recycles = self.recycles;
+        let talon = |range: std::ops::Range<usize>| range.map(|i| card(field(&self.talon, i)));
+        position.stock = talon(0..self.len(STOCK)).collect();
+        position.waste = talon(self.len(STOCK)..self.len(TALON)).rev().collect();
+        for (f, foundation) in position.foundations.iter_mut().enumerate() {
+            let (len, top) = self.foundation(f);
+            let mut index = top;
+            for _ in 0..len {
+                foundation.push(card(index));
+                index = previous_wrapping(index);
+            }
+            foundation.reverse();
+        }
+        for (t, pile) in position.tableau.iter_mut().enumerate() {
+            let (hidden, face_up) = (0..self.len(TABLEAU + t))
+                .map(|k| self.tableau_card(t, k))
+                .partition::<Vec<_>, _>(|&i| !self.is_face_up(i));
+            *pile = TableauPile {
+                hidden: hidden.into_iter().map(card).collect(),
+                face_up: face_up.into_iter().map(card).collect(),
+            };
+        }
+        position
+    }
+
+    fn len(&self, slot: usize) -> usize {
+        (self.lens >> (5 * slot) & 31) as usize
+    }
+
+    fn set_len(&mut self, slot: usize, len: usize) {
+        let shift = 5 * slot;
+        self.lens = self.lens & !(31 << shift) | (len as u64) << shift;
+    }
+
+    /// The length and top card of foundation `f`.
+    fn foundation(&self, f: usize) -> (usize, u8) {
+        let bits = self.foundations >> (10 * f);
+        ((bits & 15) as usize, (bits >> 4 & 63) as u8)
+    }
+
+    fn set_foundation(&mut self, f: usize, len: usize, top: u8) {
+        let shift = 10 * f;
+        let bits = len as u64 | u64::from(top) << 4;
+        self.foundations = self.foundations & !(1023 << shift) | bits << shift;
+    }
+
+    fn tableau_card(&self, t: usize, k: usize) -> u8 {
+        (self.tableau[t] >> (6 * k) & 63) as u8
+    }
+
+    fn waste_top(&self) -> Option<u8> {
+        let stock = self.len(STOCK);
+        (stock < self.len(TALON)).then(|| field(&self.talon, stock))
+    }
+
+    fn is_face_up(&self, index: u8) -> bool {
+        self.face_up & 1 << index != 0
+    }
+
+    /// Whether every card has reached the foundations.
+    pub fn is_won(&self) -> bool {
+        // The stock is part of the talon, so every length is zero.
+        self.lens == 0
+    }
+
+    /// Every move worth trying here, in the order of `Position::moves`.
+    pub fn moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        self.moves_into(&mut moves);
+        moves
+    }
+
+    /// Append `moves()` to `out`, allocating nothing of its own, so the
+    /// solver can keep the moves of every node in one `StackArena`.
+    pub fn moves_into(&self, out: &mut impl Extend<Move>) {
+        let lens: [usize; TABLEAU_PILES as usize] = std::array::from_fn(|t| self.len(TABLEAU + t));
+        // A face-down top card cannot move, nor take another card.
+        let tops: [Option<u8>; TABLEAU_PILES as usize] = std::array::from_fn(|t| {
+            (lens[t] > 0)
+                .then(|| self.tableau_card(t, lens[t] - 1))
+                .filter(|&i| self.is_face_up(i))
+        });
+        let fits = |c: u8, t: usize| match tops[t] {
+            None => lens[t] == 0 && rank(c) == KING,
+            Some(top) => is_red(top) != is_red(c) && rank(top) == rank(c) + 1,
+        };
+        let waste = self.waste_top();
+
+        let to_foundation = || {
+            std::iter::once((Pile::Waste, waste))
+                .chain(
+                    tops.iter()
+                        .enumerate()
+                        .map(|(t, &top)| (Pile::Tableau(t as u8), top)),
+                )
+                .filter_map(|(from, top)| {
+                    let c = top?;
+                    let f = self.foundation_for(c)?;
+                    let mv = Move::Transfer {
+                        from,
+                        to: Pile::Foundation(f),
+                        count: 1,
+                    };
+                    Some((c, mv))
+                })
+        };
+        let base = self.table.foundation_base as u8;
+        if let Some((_, mv)) =
+            to_foundation().find(|&(c, _)| rank(c) == base || rank(c) == (base + 1) % 13)
+        {
+            out.extend([mv]);
+            return;
+        }
+        out.extend(to_foundation().map(|(_, mv)| mv));
+
+        // Moves exposing a card come first, then the rest.
+        for exposing in [true, false] {
+            for (i, &len) in lens.iter().enumerate() {
+                let run = (0..len)
+                    .rev()
+                    .take_while(|&k| self.is_face_up(self.tableau_card(i, k)))
+                    .count();
+                let has_hidden = run < len;
+                for count in 1..=run {
+                    let c = self.tableau_card(i, len - count);
+                    let clears = count == run;
+                    if clears && !has_hidden && rank(c) == KING
+                        || (clears && has_hidden) != exposing
+                    {
+                        continue;
+                    }
+                    for j in 0..usize::from(TABLEAU_PILES) {
+                        if i != j && fits(c, j) {
+                            out.extend([Move::Transfer {
+                                from: Pile::Tableau(i as u8),
+                                to: Pile::Tableau(j as u8),
+                                count,
+                            }]);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(c) = waste {
+            for j in 0..usize::from(TABLEAU_PILES) {
+                if fits(c, j) {
+                    out.extend([Move::Transfer {
+                        from: Pile::Waste,
+                        to: Pile::Tableau(j as u8),
+                        count: 1,
+                    }]);
+                }
+            }
+        }
+
+        if self.len(STOCK) > 0 {
+            out.extend([Move::Draw]);
+        } else if waste.is_some()
+            && self
+                .table
+                .redeal_limit
+                .is_none_or(|limit| self.recycles < limit)
+        {
+            out.extend([Move::Recycle]);
+        }
+
+        for f in 0..usize::from(FOUNDATION_PILES) {
+            let (len, top) = self.foundation(f);
+            if len == 0 {
+                continue;
+            }
+            for j in 0..usize::from(TABLEAU_PILES) {
+                if fits(top, j) {
+                    out.extend([Move::Transfer {
+                        from: Pile::Foundation(f as u8),
+                        to: Pile::Tableau(j as u8),
+                        count: 1,
+                    }]);
+                }
+            }
+        }
+    }
+
+    /// Play a move returned by `moves`.
+    pub fn apply(&mut self, mv: Move) {
+        match mv {
+            Move::Draw => {
+                let stock = self.len(STOCK);
+                assert!(stock > 0, "draw needs a stock card");
+                let count = usize::from(self.table.draw_count.max(1)).min(stock);
+                self.set_len(STOCK, stock - count);
+            }
+            Move::Recycle => {
+                let len = self.len(TALON);
+                if self.table.reshuffle_on_recycle {
+                    let mut stock = [0u8; TALON_CAPACITY];
+                    let stock = &mut stock[..len];
+                    for (i, c) in stock.iter_mut().enumerate() {
+                        *c = field(&self.talon, i);
+                    }
+                    let seed = rules::reshuffle_seed(stock.iter().map(|&i| card(i)), self.recycles);
+                    stock.shuffle(&mut GameRng::from_seed(seed));
+                    for (i, &c) in stock.iter().enumerate() {
+                        set_field(&mut self.talon, i, c);
+                    }
+                }
+                self.set_len(STOCK, len);
+                self.recycles += 1;
+            }
+            Move::Transfer { from, to, count } => {
+                let run = self.take(from, count);
+                match to {
+                    Pile::Foundation(f) => {
+                        let f = usize::from(f);
+                        let index = run as u8;
+                        self.face_up &= !(1 << index);
+                        self.set_foundation(f, self.foundation(f).0 + 1, index);
+                    }
+                    Pile::Tableau(t) => {
+                        let t = usize::from(t);
+                        let len = self.len(TABLEAU + t);
+                        if count == 1 {
+                            self.face_up |= 1 << (run as u8);
+                        }
+                        self.tableau[t] |= run << (6 * len);
+                        self.set_len(TABLEAU + t, len + count);
+                    }
+                    Pile::Stock | Pile::Waste => unreachable!("cards never move onto {to}"),
+                }
+            }
+        }
+    }
+
+    /// Lift the top `count` cards off `from`, packed like a tableau pile.
+    fn take(&mut self, from: Pile, count: usize) -> u128 {
+        match from {
+            Pile::Waste => {
+                let (stock, len) = (self.len(STOCK), self.len(TALON));
+                let index = field(&self.talon, stock);
+                for i in stock..len - 1 {
+                    let next = field(&self.talon, i + 1);
+                    set_field(&mut self.talon, i, next);
+                }
+                set_field(&mut self.talon, len - 1, 0);
+                self.set_len(TALON, len - 1);
+                u128::from(index)
+            }
+            Pile::Foundation(f) => {
+                let f = usize::from(f);
+                let (len, top) = self.foundation(f);
+                let below = if len > 1 { previous_wrapping(top) } else { 0 };
+                self.set_foundation(f, len - 1, below);
+                u128::from(top)
+            }
+            Pile::Tableau(t) => {
+                let t = usize::from(t);
+                let rest = self.len(TABLEAU + t) - count;
+                let run = self.tableau[t] >> (6 * rest);
+                self.tableau[t] &= low_cards(rest);
+                self.set_len(TABLEAU + t, rest);
+                if rest > 0 {
+                    self.face_up |= 1 << self.tableau_card(t, rest - 1);
+                }
+                run
+            }
+            Pile::Stock => unreachable!("the stock is only drawn from"),
+        }
+    }
+
+    /// The foundation `c` can be played to, if any.
+    fn foundation_for(&self, c: u8) -> Option<u8> {
+        (0..FOUNDATION_PILES).find(|&f| match self.foundation(usize::from(f)) {
+            (0, _) => {
+                rank(c) == self.table.foundation_base as u8
+                    && self
+                        .table
+                        .foundation_suit(f)
+                        .is_none_or(|suit| suit as u8 == c / 13)
+            }
+            (_, top) => next_wrapping(top) == c,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::Variant;
+
+    #[test]
+    fn bitboards_play_like_positions() {
+        let table = Table {
+            draw_count: 3,
+            reshuffle_on_recycle: true,
+            ..Table::KLONDIKE
+        };
+        for seed in 0..20u64 {
+            let mut position = Position::from_deal_seed(seed);
+            position.table = table;
+            let mut board = Bitboard::from_position(&position).unwrap();
+            assert_eq!(board.to_position(), position);
+            // Follow the moves both generate, picking one by the seed.
+            for step in 0..200u64 {
+                let moves = position.moves();
+                assert_eq!(board.moves(), moves);
+                let Some(&mv) = moves.get(((seed + step) % moves.len().max(1) as u64) as usize)
+                else {
+                    break;
+                };
+                position.apply(mv);
+                board.apply(mv);
+                assert_eq!(board.to_position(), position);
+                assert_eq!(board.is_won(), position.is_won());
+            }
+        }
+
+        let mut double = Position::empty_table(&Variant::DoubleKlondike.table());
+        double.stock.push(Card::from_index(0).unwrap());
+        assert!(Bitboard::from_position(&double).is_none());
+    }
+}
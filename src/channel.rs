@@ -0,0 +1,296 @@
+//! Logical channels multiplexed over one connection.
+//!
+//! Every outgoing message travels on one of four channels: `Game` for moves
+//! and everything else the match depends on, `VoiceSignaling` for setting up
+//! voice calls, `Chat`, and `Telemetry`. Each channel has its own queue and
+//! settings:
+//!
+//! * channels go out in order of priority, so a move never waits behind a
+//!   burst of chat;
+//! * the urgent channels are sent at once, the others at most
+//!   `BACKGROUND_FRAMES_PER_TICK` frames per tick between them;
+//! * ordered channels deliver received messages strictly in sequence,
+//!   holding back any that arrive early, though no more than
+//!   `MAX_EARLY_FRAMES`: past that, the missing ones are given up on;
+//! * unreliable channels keep a short backlog and drop the oldest frames
+//!   beyond it, and drop received frames older than ones already delivered.
+//!
+//! When the server supports the `channels` feature of the handshake, each
+//! message is wrapped in a `Frame` naming its channel and sequence number:
+//!
+//! ```json
+//! { "ch": "chat", "seq": 3, "msg": "{\"type\":\"chat\",...}" }
+//! ```
+//!
+//! Otherwise messages go out as they are, still in order of priority.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::NetMessage;
+
+/// Frames of the background channels sent per tick.
+pub const BACKGROUND_FRAMES_PER_TICK: usize = 4;
+/// Frames an unreliable channel keeps waiting before dropping the oldest.
+const UNRELIABLE_BACKLOG: usize = 8;
+/// Frames an ordered channel holds back waiting for one that is missing.
+pub const MAX_EARLY_FRAMES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Game,
+    VoiceSignaling,
+    Chat,
+    Telemetry,
+}
+
+/// How a channel treats its messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelSettings {
+    /// Deliver received messages in the order they were sent.
+    pub ordered: bool,
+    /// Never drop a message.
+    pub reliable: bool,
+    /// Send everything at once rather than a few frames per tick.
+    pub urgent: bool,
+}
+
+impl Channel {
+    /// Every channel, most important first.
+    pub const ALL: [Channel; 4] = [
+        Channel::Game,
+        Channel::VoiceSignaling,
+        Channel::Chat,
+        Channel::Telemetry,
+    ];
+
+    pub fn settings(self) -> ChannelSettings {
+        match self {
+            Channel::Game | Channel::VoiceSignaling => ChannelSettings {
+                ordered: true,
+                reliable: true,
+                urgent: true,
+            },
+            Channel::Chat => ChannelSettings {
+                ordered: true,
+                reliable: true,
+                urgent: false,
+            },
+            Channel::Telemetry => ChannelSettings {
+                ordered: false,
+                reliable: false,
+                urgent: false,
+            },
+        }
+    }
+
+    pub fn of(message: &NetMessage) -> Self {
+        match message {
+            NetMessage::Chat { .. } | NetMessage::Emote { .. } => Channel::Chat,
+            NetMessage::VoiceSignal { .. } => Channel::VoiceSignaling,
+            NetMessage::Telemetry { .. } => Channel::Telemetry,
+            _ => Channel::Game,
+        }
+    }
+
+    /// The channel of a text frame; frames that are not protocol messages
+    /// go on `Game`.
+    pub fn of_frame(text: &str) -> Self {
+        NetMessage::from_json(text).map_or(Channel::Game, |m| Self::of(&m))
+    }
+
+    fn index(self) -> usize {
+        Channel::ALL
+            .iter()
+            .position(|&c| c == self)
+            .expect("ALL holds every channel")
+    }
+}
+
+/// A message wrapped with its channel and sequence number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    pub ch: Channel,
+    pub seq: u64,
+    pub msg: String,
+}
+
+/// The receiving side of one channel.
+#[derive(Debug, Clone, Default)]
+struct Incoming {
+    next_seq: u64,
+    early: BTreeMap<u64, String>,
+}
+
+/// The channels of one connection.
+#[derive(Debug, Clone, Default)]
+pub struct Mux {
+    /// Whether messages are wrapped in `Frame`s.
+    framed: bool,
+    queues: [VecDeque<String>; 4],
+    next_seq: [u64; 4],
+    incoming: [Incoming; 4],
+    background_sent: usize,
+}
+
+impl Mux {
+    /// Wrap messages in `Frame`s from now on, once the server agreed to.
+    pub fn set_framed(&mut self, framed: bool) {
+        self.framed = framed;
+    }
+
+    /// Queue `text` on `channel`. Returns how many frames were dropped to
+    /// make room.
+    pub fn push(&mut self, channel: Channel, text: &str) -> usize {
+        let i = channel.index();
+        let frame = if self.framed {
+            let seq = self.next_seq[i];
+            self.next_seq[i] += 1;
+            serde_json::to_string(&Frame {
+                ch: channel,
+                seq,
+                msg: text.to_string(),
+            })
+            .expect("frames always serialize")
+        } else {
+            text.to_string()
+        };
+        let queue = &mut self.queues[i];
+        queue.push_back(frame);
+        let mut dropped = 0;
+        while !channel.settings().reliable && queue.len() > UNRELIABLE_BACKLOG {
+            queue.pop_front();
+            dropped += 1;
+        }
+        dropped
+    }
+
+    /// The frames to send now, most important channel first.
+    pub fn drain(&mut self) -> Vec<String> {
+        let mut frames = Vec::new();
+        for channel in Channel::ALL {
+            let queue = &mut self.queues[channel.index()];
+            if channel.settings().urgent {
+                frames.extend(queue.drain(..));
+                continue;
+            }
+            while self.background_sent < BACKGROUND_FRAMES_PER_TICK
+                && let Some(frame) = queue.pop_front()
+            {
+                frames.push(frame);
+                self.background_sent += 1;
+            }
+        }
+        frames
+    }
+
+    /// Start a new tick, with a fresh allowance for the background
+    /// channels.
+    pub fn tick(&mut self) {
+        self.background_sent = 0;
+    }
+
+    /// The messages a received frame makes ready, in the order to handle
+    /// them. Frames that are not `Frame`s are passed on as they are.
+    pub fn receive(&mut self, text: &str) -> Vec<String> {
+        let Ok(frame) = serde_json::from_str::<Frame>(text) else {
+            return vec![text.to_string()];
+        };
+        let settings = frame.ch.settings();
+        let incoming = &mut self.incoming[frame.ch.index()];
+        if frame.seq < incoming.next_seq {
+            // Seen already, or overtaken on an unreliable channel.
+            return Vec::new();
+        }
+        if !settings.ordered {
+            incoming.next_seq = frame.seq + 1;
+            return vec![frame.msg];
+        }
+        incoming.early.insert(frame.seq, frame.msg);
+        if incoming.early.len() > MAX_EARLY_FRAMES
+            && let Some(&oldest) = incoming.early.keys().next()
+        {
+            // Whatever is missing is not coming: carry on from what is here.
+            incoming.next_seq = oldest;
+        }
+        let mut ready = Vec::new();
+        while let Some(msg) = incoming.early.remove(&incoming.next_seq) {
+            ready.push(msg);
+            incoming.next_seq += 1;
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(ch: Channel, seq: u64, msg: &str) -> String {
+        serde_json::to_string(&Frame {
+            ch,
+            seq,
+            msg: msg.into(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn moves_overtake_chat_and_ordered_channels_stay_in_order() {
+        let chat = NetMessage::Chat {
+            from: "ann".into(),
+            text: "hi".into(),
+        }
+        .to_json();
+        assert_eq!(Channel::of_frame(&chat), Channel::Chat);
+
+        let mut mux = Mux::default();
+        mux.set_framed(true);
+        for _ in 0..10 {
+            mux.push(Channel::Chat, &chat);
+        }
+        mux.push(Channel::Game, "move");
+        let sent = mux.drain();
+        assert_eq!(sent.len(), 1 + BACKGROUND_FRAMES_PER_TICK);
+        assert_eq!(sent[0], frame(Channel::Game, 0, "move"));
+        assert!(mux.drain().is_empty());
+        mux.tick();
+        assert_eq!(mux.drain().len(), BACKGROUND_FRAMES_PER_TICK);
+
+        for _ in 0..20 {
+            mux.push(Channel::Telemetry, "sample");
+        }
+        mux.tick();
+        let telemetry = mux.drain().iter().filter(|f| f.contains("sample")).count();
+        assert!(telemetry < BACKGROUND_FRAMES_PER_TICK);
+
+        let mut peer = Mux::default();
+        assert!(peer.receive(&frame(Channel::Game, 1, "second")).is_empty());
+        assert_eq!(
+            peer.receive(&frame(Channel::Game, 0, "first")),
+            ["first", "second"]
+        );
+        assert!(peer.receive(&frame(Channel::Game, 0, "first")).is_empty());
+        assert_eq!(peer.receive(&frame(Channel::Telemetry, 5, "new")), ["new"]);
+        assert!(
+            peer.receive(&frame(Channel::Telemetry, 2, "old"))
+                .is_empty()
+        );
+        assert_eq!(peer.receive("plain"), ["plain"]);
+    }
+
+    #[test]
+    fn a_gap_that_never_fills_is_given_up_on() {
+        let mut peer = Mux::default();
+        for seq in 1..=MAX_EARLY_FRAMES as u64 {
+            assert!(peer.receive(&frame(Channel::Chat, seq, "late")).is_empty());
+        }
+        let next = MAX_EARLY_FRAMES as u64 + 1;
+        let ready = peer.receive(&frame(Channel::Chat, next, "late"));
+        assert_eq!(ready.len(), MAX_EARLY_FRAMES + 1);
+        assert!(peer.incoming[Channel::Chat.index()].early.is_empty());
+        assert_eq!(peer.receive(&frame(Channel::Chat, next + 1, "on")), ["on"]);
+    }
+}
@@ -0,0 +1,90 @@
+// Wall-clock time in milliseconds.
+//
+// Browsers do not provide `std::time::SystemTime` to WebAssembly, so on that
+// target we ask JavaScript's `Date.now()` instead. Native builds use the
+// standard library. Both return milliseconds since the Unix epoch.
+
+/// Current time in milliseconds since the Unix epoch.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+/// Current time in milliseconds since the Unix epoch.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0)
+}
+
+/// Without JavaScript there is no clock on `wasm32`, so time stands still.
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm")))]
+pub fn now_ms() -> f64 {
+    0.0
+}
+
+/// Milliseconds in one day.
+pub const DAY_MS: f64 = 86_400_000.0;
+
+/// The number of whole days since the Unix epoch (UTC) at time `ms`.
+pub fn day_number(ms: f64) -> i64 {
+    (ms / DAY_MS).floor() as i64
+}
+
+/// Resource measuring how long the current game has been played. Time
+/// spent paused does not count.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameClock {
+    started_ms: f64,
+    paused_at: Option<f64>,
+}
+
+impl GameClock {
+    /// A clock that started at time `now`.
+    pub fn start(now: f64) -> Self {
+        GameClock {
+            started_ms: now,
+            paused_at: None,
+        }
+    }
+
+    /// Milliseconds of play at time `now`.
+    pub fn elapsed_ms(&self, now: f64) -> f64 {
+        (self.paused_at.unwrap_or(now) - self.started_ms).max(0.0)
+    }
+
+    /// Stop counting at time `now`.
+    pub fn pause(&mut self, now: f64) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    /// Carry on counting from time `now`.
+    pub fn resume(&mut self, now: f64) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.started_ms += (now - paused_at).max(0.0);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paused_time_does_not_count() {
+        let mut clock = GameClock::start(1_000.0);
+        clock.pause(3_000.0);
+        assert_eq!(clock.elapsed_ms(10_000.0), 2_000.0);
+        clock.resume(10_000.0);
+        assert_eq!(clock.elapsed_ms(11_000.0), 3_000.0);
+    }
+}
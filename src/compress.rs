@@ -0,0 +1,131 @@
+// Compressing saves and replays for storage and transmission.
+//
+// Saves and audit logs are JSON, which repeats the same field names for every
+// action: the replay of a long Double Klondike game runs to hundreds of
+// kilobytes, a good part of the few megabytes `localStorage` allows. Packed,
+// they are deflated and then base64 encoded, since both `localStorage` and
+// the JavaScript API carry strings, and marked with `PACKED_PREFIX` so that
+// `unpack` still reads plain JSON saved before there was packing.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::GameError;
+
+/// Marks packed text.
+pub const PACKED_PREFIX: &str = "deflate:";
+
+/// Packed text is refused if it would inflate to more than this, so a
+/// corrupt or hostile save cannot exhaust memory.
+pub const MAX_UNPACKED_BYTES: usize = 16 * 1024 * 1024;
+
+const LEVEL: u8 = 6;
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Deflate `json` and encode it as text.
+pub fn pack(json: &str) -> String {
+    let deflated = miniz_oxide::deflate::compress_to_vec(json.as_bytes(), LEVEL);
+    format!("{PACKED_PREFIX}{}", base64_encode(&deflated))
+}
+
+/// The JSON of text made by `pack`, or the text itself if it is plain JSON.
+pub fn unpack(text: &str) -> Result<String, GameError> {
+    let Some(encoded) = text.strip_prefix(PACKED_PREFIX) else {
+        return Ok(text.to_string());
+    };
+    let invalid = |msg: &str| GameError::InvalidOptions(format!("corrupt packed data: {msg}"));
+    let deflated = base64_decode(encoded).ok_or_else(|| invalid("not base64"))?;
+    let json = miniz_oxide::inflate::decompress_to_vec_with_limit(&deflated, MAX_UNPACKED_BYTES)
+        .map_err(|e| invalid(&e.to_string()))?;
+    String::from_utf8(json).map_err(|_| invalid("not UTF-8"))
+}
+
+/// How much packing saved on one piece of JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SizeStats {
+    pub json_bytes: usize,
+    /// Bytes of the packed text, base64 included.
+    pub packed_bytes: usize,
+    /// `packed_bytes / json_bytes`; below 1 when packing pays.
+    pub ratio: f64,
+}
+
+impl SizeStats {
+    pub fn of(json: &str) -> Self {
+        let packed_bytes = pack(json).len();
+        SizeStats {
+            json_bytes: json.len(),
+            packed_bytes,
+            ratio: if json.is_empty() {
+                1.0
+            } else {
+                packed_bytes as f64 / json.len() as f64
+            },
+        }
+    }
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for chunk in text.chunks(4) {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 {
+            return None;
+        }
+        let mut n = 0u32;
+        for &c in &chunk[..4 - padding] {
+            let value = ALPHABET.iter().position(|&a| a == c)?;
+            n = n << 6 | value as u32;
+        }
+        n <<= 6 * padding;
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packing_round_trips_and_shrinks_repetitive_json() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foob"] {
+            assert_eq!(base64_decode(&base64_encode(data)).unwrap(), data);
+        }
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+
+        let json = format!("[{}]", vec![r#"{"Move":"Draw"}"#; 500].join(","));
+        let packed = pack(&json);
+        assert!(packed.starts_with(PACKED_PREFIX));
+        assert_eq!(unpack(&packed).unwrap(), json);
+        assert_eq!(unpack(&json).unwrap(), json);
+        assert!(unpack("deflate:@@@@").is_err());
+
+        let stats = SizeStats::of(&json);
+        assert_eq!(stats.json_bytes, json.len());
+        assert!(stats.ratio < 0.1, "{stats:?}");
+    }
+}
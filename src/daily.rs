@@ -0,0 +1,177 @@
+// The daily challenge: one deal a day that everybody plays.
+//
+// The game server hands out each day's official challenge in answer to
+// `protocol::NetMessage::FetchDaily`: the date, the seed, the options it is
+// played with and the deadline for submitting scores to its leaderboard. The
+// answer is kept in the `OfficialDaily` resource.
+//
+// Without a server, or before it has answered, `local` derives the
+// challenge from the date and variant alone: the seed is taken from a hash
+// of both, the options are the variant's defaults and the deadline is the
+// end of the day in UTC. Every offline player still gets the same deal, but
+// the challenge is marked as not `official`, since the server may run a
+// different one that day.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::options::GameOptions;
+use crate::signing::sha256_hex;
+use crate::variant::Variant;
+
+const DAY_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// One day's challenge.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyChallenge {
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    pub seed: u64,
+    pub options: GameOptions,
+    /// Milliseconds since the Unix epoch after which scores are refused.
+    pub deadline_ms: f64,
+    /// Whether the server handed it out, rather than it being derived here.
+    #[serde(default)]
+    pub official: bool,
+}
+
+impl DailyChallenge {
+    /// The options to deal the challenge with.
+    pub fn game_options(&self) -> GameOptions {
+        GameOptions {
+            seed: Some(self.seed),
+            daily: true,
+            mulligans: 0,
+            ..self.options
+        }
+    }
+
+    pub fn is_open(&self, now_ms: f64) -> bool {
+        now_ms <= self.deadline_ms
+    }
+}
+
+/// Resource with the latest challenge the server handed out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OfficialDaily(pub DailyChallenge);
+
+/// The challenge of `date` in `variant` as derived without a server, or
+/// `None` if `date` is not a `YYYY-MM-DD` date.
+pub fn local(date: &str, variant: Variant) -> Option<DailyChallenge> {
+    let midnight = midnight_ms(date)?;
+    let digest = sha256_hex(format!("daily/{variant}/{date}").as_bytes());
+    let seed = u64::from_str_radix(&digest[..16], 16).expect("digests are hex");
+    Some(DailyChallenge {
+        date: date.to_string(),
+        seed,
+        options: GameOptions {
+            variant,
+            ..GameOptions::default()
+        },
+        deadline_ms: midnight + DAY_MS - 1.0,
+        official: false,
+    })
+}
+
+/// The challenge of `date`: the server's if it handed one out for that day
+/// and variant, or the local one.
+pub fn challenge(world: &World, date: &str, variant: Variant) -> Option<DailyChallenge> {
+    match world.resource::<OfficialDaily>() {
+        Some(OfficialDaily(daily)) if daily.date == date && daily.options.variant == variant => {
+            Some(daily.clone())
+        }
+        _ => local(date, variant),
+    }
+}
+
+/// Record the challenge the server handed out.
+pub fn receive(world: &mut World, mut daily: DailyChallenge) {
+    daily.official = true;
+    world.insert_resource(OfficialDaily(daily));
+}
+
+/// Midnight UTC at the start of `date`, in milliseconds since the Unix
+/// epoch.
+fn midnight_ms(date: &str) -> Option<f64> {
+    let mut parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days since 1970-01-01 of a proleptic Gregorian date, counting years
+    // from March so the leap day comes last.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    Some(days as f64 * DAY_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_server_challenge_wins_over_the_local_one() {
+        let local_daily = local("2025-07-31", Variant::Klondike).unwrap();
+        assert_eq!(
+            local("2025-07-31", Variant::Klondike),
+            Some(local_daily.clone())
+        );
+        assert_ne!(
+            local("2025-08-01", Variant::Klondike).unwrap().seed,
+            local_daily.seed
+        );
+        assert_eq!(local_daily.deadline_ms, 1_754_006_399_999.0);
+        assert!(!local_daily.official && local_daily.is_open(1_754_000_000_000.0));
+        assert_eq!(local("yesterday", Variant::Klondike), None);
+
+        let mut world = World::new();
+        let server: DailyChallenge = serde_json::from_str(
+            r#"{ "date": "2025-07-31", "seed": 42, "options": { "draw_count": 3 },
+                 "deadline_ms": 1754092800000 }"#,
+        )
+        .unwrap();
+        receive(&mut world, server);
+        let daily = challenge(&world, "2025-07-31", Variant::Klondike).unwrap();
+        assert!(daily.official);
+        let options = daily.game_options();
+        assert_eq!(
+            (options.seed, options.draw_count, options.daily),
+            (Some(42), Some(3), true)
+        );
+        // Another day falls back to the local challenge.
+        assert!(
+            !challenge(&world, "2025-08-01", Variant::Klondike)
+                .unwrap()
+                .official
+        );
+    }
+
+    #[test]
+    fn the_challenge_options_last_for_the_challenge_only() {
+        use crate::SolitaireGame;
+
+        let mut game = SolitaireGame::new_game(r#"{"mulligans":3}"#).unwrap();
+        game.start_daily_challenge("2025-07-31").unwrap();
+        assert_eq!(game.mulligans_left(), 0);
+        #[cfg(feature = "networking")]
+        assert_eq!(
+            game.daily_submission(),
+            Err(crate::error::GameError::DailyClosed)
+        );
+        game.reset();
+        assert!(game.options().contains(r#""daily":true"#));
+
+        game.setup_board();
+        assert!(game.options().contains(r#""daily":false"#));
+        assert_eq!(game.mulligans_left(), 3);
+        #[cfg(feature = "networking")]
+        assert_eq!(
+            game.daily_submission(),
+            Err(crate::error::GameError::NoDailyResult)
+        );
+    }
+}
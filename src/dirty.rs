@@ -0,0 +1,161 @@
+// Dirty regions, for renderers that only repaint what changed.
+//
+// Every run of the layout system ends with `track`, which compares each card
+// with how it looked the last time round: where it was, how high it was
+// stacked and which side was up. A card that changed marks both its old and
+// its new rectangle dirty, since the old spot has to be repainted without
+// it. The score and move counters are tracked the same way, as a flag.
+//
+// The renderer collects the regions with `take` after each layout and
+// repaints just those rectangles, or the whole canvas when `full` is set:
+// on the first layout of a deal, when the card size changed, or after
+// `invalidate`, as when the theme changes the look of everything.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::game::FaceUp;
+use crate::layout::Position;
+use crate::rules::History;
+use crate::scoring::Score;
+
+/// A rectangle on the canvas, in CSS pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// What needs repainting since the regions were last taken.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirtyRegions {
+    /// Repaint the whole canvas; `rects` may then be incomplete.
+    pub full: bool,
+    pub rects: Vec<Rect>,
+    /// The score or the move count changed.
+    pub counters: bool,
+}
+
+/// How a card looked when it was last laid out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Painted {
+    position: Position,
+    face_up: bool,
+}
+
+/// Resource collecting the dirty regions between two `take`s.
+#[derive(Debug, Clone, PartialEq)]
+struct Tracker {
+    card_size: (f32, f32),
+    counters: (i32, u32),
+    dirty: DirtyRegions,
+}
+
+/// Compare the cards' new `Position`s, for cards `card_size` large, with
+/// the ones they had before and record what changed.
+pub fn track(world: &mut World, card_size: (f32, f32)) {
+    let counters = (
+        world.resource::<Score>().map_or(0, Score::points),
+        world.resource::<History>().map_or(0, History::moves),
+    );
+    let mut tracker = match world.remove_resource::<Tracker>() {
+        Some(tracker) if tracker.card_size == card_size => tracker,
+        Some(tracker) => Tracker {
+            card_size,
+            counters: tracker.counters,
+            dirty: DirtyRegions {
+                full: true,
+                ..tracker.dirty
+            },
+        },
+        None => Tracker {
+            card_size,
+            counters,
+            dirty: DirtyRegions {
+                full: true,
+                ..DirtyRegions::default()
+            },
+        },
+    };
+    let (width, height) = card_size;
+    let rect = |p: Position| Rect {
+        x: p.x,
+        y: p.y,
+        width,
+        height,
+    };
+
+    let painted: Vec<_> = world
+        .iter::<Position>()
+        .map(|(entity, &position)| {
+            let face_up = world.get_component::<FaceUp>(entity).is_some_and(|f| f.0);
+            (entity, Painted { position, face_up })
+        })
+        .collect();
+    for (entity, now) in painted {
+        match world.get_component::<Painted>(entity).copied() {
+            Some(before) if before == now => continue,
+            Some(before) if before.position != now.position => {
+                tracker.dirty.rects.push(rect(before.position));
+                tracker.dirty.rects.push(rect(now.position));
+            }
+            Some(_) | None => tracker.dirty.rects.push(rect(now.position)),
+        }
+        world.add_component(entity, now);
+    }
+
+    if counters != tracker.counters {
+        tracker.counters = counters;
+        tracker.dirty.counters = true;
+    }
+    world.insert_resource(tracker);
+}
+
+/// Have the whole canvas repainted next time.
+pub fn invalidate(world: &mut World) {
+    world.remove_resource::<Tracker>();
+}
+
+/// The regions that changed since the last call.
+pub fn take(world: &mut World) -> DirtyRegions {
+    match world.resource_mut::<Tracker>() {
+        Some(tracker) => std::mem::take(&mut tracker.dirty),
+        None => DirtyRegions {
+            full: true,
+            ..DirtyRegions::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Deck, Pile};
+    use crate::layout;
+    use crate::rules::{self, Move};
+
+    #[test]
+    fn only_changed_cards_are_dirty() {
+        let mut world = World::new();
+        rules::deal(&mut world, &Deck::standard().cards);
+        layout::layout_system(&mut world);
+        assert!(take(&mut world).full);
+        layout::layout_system(&mut world);
+        assert_eq!(take(&mut world), DirtyRegions::default());
+
+        rules::apply(&mut world, Move::Draw).unwrap();
+        layout::layout_system(&mut world);
+        let dirty = take(&mut world);
+        assert!(!dirty.full);
+        // The drawn card leaves the stock for the waste.
+        assert_eq!(dirty.rects.len(), 2);
+        let waste = rules::top_card(&world, Pile::Waste).unwrap();
+        let position = *world.get_component::<Position>(waste).unwrap();
+        assert_eq!(
+            (dirty.rects[1].x, dirty.rects[1].y),
+            (position.x, position.y)
+        );
+    }
+}
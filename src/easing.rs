@@ -0,0 +1,78 @@
+// Easing curves for animations.
+//
+// An easing curve maps how far an animation is through its time, from 0 to
+// 1, to how far it is through its motion. All of them start at 0 and end at
+// 1; `Back` overshoots on the way and `Bounce` comes to rest in a few hops.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    /// Fast at first, slowing down towards the end.
+    #[default]
+    EaseOutCubic,
+    /// Running slightly past the end and settling back.
+    Back,
+    /// Dropping onto the end and bouncing off it a few times.
+    Bounce,
+}
+
+impl Easing {
+    /// The progress of the motion at time `t`, clamped to `0..=1`.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::Back => {
+                const OVERSHOOT: f32 = 1.70158;
+                let u = t - 1.0;
+                1.0 + (OVERSHOOT + 1.0) * u.powi(3) + OVERSHOOT * u.powi(2)
+            }
+            Easing::Bounce => bounce(t),
+        }
+    }
+}
+
+fn bounce(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_run_from_start_to_end() {
+        let all = [
+            Easing::Linear,
+            Easing::EaseOutCubic,
+            Easing::Back,
+            Easing::Bounce,
+        ];
+        for easing in all {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{easing:?}");
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{easing:?}");
+        }
+        assert_eq!(Easing::Linear.apply(0.25), 0.25);
+        assert!(Easing::EaseOutCubic.apply(0.5) > 0.5);
+        let samples = |easing: Easing| (0..=100).map(move |i| easing.apply(i as f32 / 100.0));
+        assert!(samples(Easing::Back).any(|p| p > 1.0));
+        assert!(samples(Easing::Bounce).all(|p| p <= 1.0 + 1e-6));
+    }
+}
@@ -0,0 +1,103 @@
+// Bundles: groups of components spawned together.
+//
+// A bundle is a tuple such as `(Card, Pile, FaceUp)`. `World::spawn_batch`
+// takes many bundles at once, reserves a contiguous range of entity IDs and
+// then fills each component store in one go, instead of looking up every
+// store again for every single entity.
+
+use std::ops::Range;
+
+use super::{Entity, World};
+
+/// A tuple of components that can be spawned together.
+///
+/// Implemented for tuples of up to six component types. Each type may
+/// appear only once in a bundle.
+pub trait Bundle: Sized {
+    /// Insert `bundles[i]` on entity `entities.start + i`.
+    fn insert_batch(world: &mut World, entities: Range<Entity>, bundles: Vec<Self>);
+}
+
+macro_rules! impl_bundle {
+    ($($component:ident $index:tt),+) => {
+        impl<$($component: 'static),+> Bundle for ($($component,)+) {
+            fn insert_batch(world: &mut World, entities: Range<Entity>, bundles: Vec<Self>) {
+                // Split the rows into one column per component type.
+                let count = bundles.len();
+                let mut columns = ($(Vec::<$component>::with_capacity(count),)+);
+                for bundle in bundles {
+                    $(columns.$index.push(bundle.$index);)+
+                }
+                $(world.insert_column(entities.start, columns.$index);)+
+            }
+        }
+    };
+}
+
+impl_bundle!(A 0);
+impl_bundle!(A 0, B 1);
+impl_bundle!(A 0, B 1, C 2);
+impl_bundle!(A 0, B 1, C 2, D 3);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, G 5);
+
+impl World {
+    /// Spawn one entity per bundle and return the range of their IDs.
+    ///
+    /// ```ignore
+    /// let cards = world.spawn_batch(deck.cards.iter().map(|&c| (c, Pile::Stock, FaceUp(false))));
+    /// ```
+    pub fn spawn_batch<B: Bundle, I: IntoIterator<Item = B>>(
+        &mut self,
+        bundles: I,
+    ) -> Range<Entity> {
+        let bundles: Vec<B> = bundles.into_iter().collect();
+        let start = self.next_id;
+        self.next_id += bundles.len() as Entity;
+        let entities = start..self.next_id;
+        B::insert_batch(self, entities.clone(), bundles);
+        entities
+    }
+
+    /// Insert `values[i]` as the `T` component of entity `first + i`,
+    /// looking the store up only once.
+    fn insert_column<T: 'static>(&mut self, first: Entity, values: Vec<T>) {
+        if values.is_empty() {
+            return;
+        }
+        if self.storage::<T>().is_none() {
+            self.register_component::<T>(super::StorageKind::default());
+        }
+        let store = self.storage_mut::<T>().expect("registered above");
+        store.reserve(first as usize + values.len(), values.len());
+        for (offset, value) in values.into_iter().enumerate() {
+            store.insert(first + offset as Entity, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::StorageKind;
+
+    #[derive(Debug, PartialEq)]
+    struct Rank(u8);
+    #[derive(Debug, PartialEq)]
+    struct Tag;
+
+    #[test]
+    fn spawn_batch_inserts_every_component() {
+        let mut world = World::new();
+        let first = world.spawn();
+        world.register_component::<Tag>(StorageKind::Sparse);
+
+        let range = world.spawn_batch((0..104).map(|i| (Rank(i % 13), Tag)));
+        assert_eq!(range, first + 1..first + 105);
+        assert_eq!(world.spawn(), 105);
+
+        assert_eq!(world.get_component::<Rank>(14), Some(&Rank(0)));
+        assert_eq!(world.iter::<Tag>().count(), 104);
+        assert_eq!(world.get_component::<Tag>(first), None);
+    }
+}
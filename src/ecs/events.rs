@@ -0,0 +1,165 @@
+// Double-buffered event queues.
+//
+// `Events<T>` is a resource holding the events of type `T` sent during the
+// current frame and the one before. Sending never consumes anything: every
+// system that cares keeps its own `EventCursor<T>` and reads the events it
+// has not seen yet, so scoring and rendering can both watch `CardMoved`
+// without stealing events from each other.
+//
+// Once per frame `Events::update` (usually scheduled as the
+// `update_events::<T>` system) drops the older buffer. An event therefore
+// lives for two frames, long enough for a system running before the sender
+// in the schedule to see it on the next frame, and no longer.
+
+use std::marker::PhantomData;
+
+use super::World;
+
+/// Resource queueing the events of type `T` for the current and previous
+/// frame.
+pub struct Events<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    // Number of events dropped so far, which is the sequence number of the
+    // first event in `previous`.
+    dropped: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Events {
+            previous: Vec::new(),
+            current: Vec::new(),
+            dropped: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// End the frame: forget the events of the previous frame and start a
+    /// new buffer for this one.
+    pub fn update(&mut self) {
+        self.dropped += self.previous.len();
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+
+    /// Number of events still retained.
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sequence number the next sent event will get.
+    fn end(&self) -> usize {
+        self.dropped + self.len()
+    }
+}
+
+/// One reader's position in an `Events<T>` queue.
+///
+/// A fresh cursor starts at the oldest retained event.
+pub struct EventCursor<T> {
+    next: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventCursor<T> {
+    fn default() -> Self {
+        EventCursor {
+            next: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> EventCursor<T> {
+    /// The events sent since this cursor last read, oldest first. Events
+    /// that were dropped before the cursor got to them are skipped.
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> + use<'a, T> {
+        let skip = self.next.saturating_sub(events.dropped);
+        self.next = events.end();
+        events.previous.iter().chain(&events.current).skip(skip)
+    }
+}
+
+/// System advancing the `Events<T>` queue by one frame. Schedule it once per
+/// frame for each event type.
+pub fn update_events<T: 'static>(world: &mut World) {
+    if let Some(events) = world.resource_mut::<Events<T>>() {
+        events.update();
+    }
+}
+
+impl World {
+    /// Queue an event, creating the `Events<T>` resource on first use.
+    pub fn send_event<T: 'static>(&mut self, event: T) {
+        if self.resource::<Events<T>>().is_none() {
+            self.insert_resource(Events::<T>::default());
+        }
+        self.resource_mut::<Events<T>>()
+            .expect("inserted above")
+            .send(event);
+    }
+
+    /// The `T` events `cursor` has not seen yet.
+    pub fn read_events<'a, T: 'static>(
+        &'a self,
+        cursor: &mut EventCursor<T>,
+    ) -> impl Iterator<Item = &'a T> + use<'a, T> {
+        self.resource::<Events<T>>()
+            .map(|events| cursor.read(events))
+            .into_iter()
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct CardMoved(u32);
+
+    #[test]
+    fn readers_do_not_steal_events() {
+        let mut world = World::new();
+        let mut scoring = EventCursor::<CardMoved>::default();
+        let mut rendering = EventCursor::<CardMoved>::default();
+
+        world.send_event(CardMoved(1));
+        world.send_event(CardMoved(2));
+        let seen: Vec<_> = world.read_events(&mut scoring).collect();
+        assert_eq!(seen, [&CardMoved(1), &CardMoved(2)]);
+        assert_eq!(world.read_events(&mut scoring).count(), 0);
+
+        update_events::<CardMoved>(&mut world);
+        world.send_event(CardMoved(3));
+        let seen: Vec<_> = world.read_events(&mut rendering).collect();
+        assert_eq!(seen, [&CardMoved(1), &CardMoved(2), &CardMoved(3)]);
+        let seen: Vec<_> = world.read_events(&mut scoring).collect();
+        assert_eq!(seen, [&CardMoved(3)]);
+    }
+
+    #[test]
+    fn events_expire_after_two_frames() {
+        let mut world = World::new();
+        let mut late = EventCursor::<CardMoved>::default();
+        world.send_event(CardMoved(1));
+        update_events::<CardMoved>(&mut world);
+        world.send_event(CardMoved(2));
+        update_events::<CardMoved>(&mut world);
+
+        let events = world.resource::<Events<CardMoved>>().unwrap();
+        assert_eq!(events.len(), 1);
+        let seen: Vec<_> = world.read_events(&mut late).collect();
+        assert_eq!(seen, [&CardMoved(2)]);
+    }
+}
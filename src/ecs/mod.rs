@@ -0,0 +1,509 @@
+// A lightweight Entity Component System (ECS) implementation.
+// This file defines the core structures needed to build an ECS without
+// relying on any external game engine crates.
+//
+// The ECS pattern helps organise game logic by separating data (components)
+// from behaviour (systems). An entity is simply an identifier that can
+// have multiple components attached to it. Systems operate on collections
+// of entities that contain specific component sets.
+//
+// We keep the design minimal for clarity and to make it easy for
+// beginners to understand. Rust's type system ensures components are
+// strongly typed, while dynamic downcasting allows us to store different
+// component types in a single map.
+//
+// The ECS is split over a few files:
+// * `storage.rs` – how the values of one component type are stored,
+// * `query.rs`   – fetching several components of an entity at once,
+// * `bundle.rs`  – spawning many entities with several components at once,
+// * `schedule.rs` – running systems in a well-defined order,
+// * `events.rs`  – events that any number of systems can read,
+// * `state.rs`   – state machines with enter/exit/update systems,
+// * `reflect.rs` – reading and writing components by name at runtime,
+// * `scene.rs`   – spawning entities described in JSON scene files,
+// * `relation.rs` – entities lying on other entities, indexed both ways.
+
+use std::any::{Any, TypeId, type_name};
+use std::collections::HashMap;
+use std::fmt;
+
+mod bundle;
+mod events;
+mod query;
+mod reflect;
+mod relation;
+mod scene;
+mod schedule;
+mod state;
+mod storage;
+
+pub use bundle::Bundle;
+pub use events::{EventCursor, Events, update_events};
+pub use query::{Fetch, Query};
+pub use reflect::{ComponentInfo, ComponentRegistry, ReflectError};
+pub use relation::{CoveredBy, OnTopOf};
+pub use scene::{Scene, SceneEntity, SceneError};
+pub use schedule::{
+    RunCondition, Schedule, ScheduleError, System, SystemFn, any_with_component, resource_exists,
+};
+pub use state::{State, StateSet, States, in_state};
+pub use storage::{RawStore, StorageKind, StoreUsage};
+
+use storage::{AnyStorage, ComponentStorage};
+
+/// Represents an entity in the world.
+///
+/// Each entity is identified by a unique integer. Component stores are
+/// indexed by this ID.
+pub type Entity = u32;
+
+/// The `World` manages entities and their components.
+///
+/// Components are kept in one store per component type, found through a
+/// `HashMap` keyed on the component's `TypeId`. Each store is boxed so that
+/// any component type can be stored, and can be laid out densely or as a
+/// sparse set (see `StorageKind`).
+///
+/// The world can also hold *resources*: global values that do not belong to
+/// any particular entity (for example the random number generator). There is
+/// at most one resource of each type.
+///
+/// Entities may be given a unique name (see `World::name`), which the world
+/// indexes so they can be found again without keeping their ID around.
+#[derive(Default)]
+pub struct World {
+    next_id: Entity,
+    components: HashMap<TypeId, Box<dyn AnyStorage>>,
+    resources: HashMap<TypeId, Box<dyn Any>>,
+    names: HashMap<String, Entity>,
+    // Entities that lost a component since the last `clear_trackers`, by
+    // component type.
+    removed: HashMap<TypeId, Vec<Entity>>,
+}
+
+/// Component holding the unique name given to an entity with `World::name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name(pub String);
+
+/// Why `World::single` could not return an entity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SingleError {
+    /// No entity has the component.
+    NoEntities { component: &'static str },
+    /// More than one entity has it.
+    MultipleEntities {
+        component: &'static str,
+        count: usize,
+    },
+}
+
+impl fmt::Display for SingleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SingleError::NoEntities { component } => {
+                write!(f, "no entity has a {component}")
+            }
+            SingleError::MultipleEntities { component, count } => {
+                write!(f, "expected one entity with a {component}, found {count}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SingleError {}
+
+impl World {
+    /// Creates an empty world with no entities or components.
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            components: HashMap::new(),
+            resources: HashMap::new(),
+            names: HashMap::new(),
+            removed: HashMap::new(),
+        }
+    }
+
+    /// Spawns a new entity and returns its ID.
+    pub fn spawn(&mut self) -> Entity {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Chooses how components of type `T` are stored.
+    ///
+    /// Components use `StorageKind::Dense` unless registered otherwise.
+    /// Registering after components were added moves them into the new
+    /// layout.
+    pub fn register_component<T: 'static>(&mut self, kind: StorageKind) {
+        let storage = match self.components.remove(&TypeId::of::<T>()) {
+            Some(old) => {
+                let old: Box<dyn Any> = old;
+                let old = *old
+                    .downcast::<ComponentStorage<T>>()
+                    .expect("stores are keyed by their component type");
+                old.convert(kind)
+            }
+            None => ComponentStorage::<T>::new(kind),
+        };
+        self.components.insert(TypeId::of::<T>(), Box::new(storage));
+    }
+
+    /// The storage layout used for components of type `T`.
+    pub fn storage_kind<T: 'static>(&self) -> StorageKind {
+        self.components
+            .get(&TypeId::of::<T>())
+            .map_or(StorageKind::default(), |store| store.kind())
+    }
+
+    fn storage<T: 'static>(&self) -> Option<&ComponentStorage<T>> {
+        let store: &dyn Any = self.components.get(&TypeId::of::<T>())?.as_ref();
+        store.downcast_ref()
+    }
+
+    fn storage_mut<T: 'static>(&mut self) -> Option<&mut ComponentStorage<T>> {
+        let store: &mut dyn Any = self.components.get_mut(&TypeId::of::<T>())?.as_mut();
+        store.downcast_mut()
+    }
+
+    /// Adds a component to the given entity.
+    pub fn add_component<T: 'static>(&mut self, entity: Entity, component: T) {
+        if self.storage::<T>().is_none() {
+            self.register_component::<T>(StorageKind::default());
+        }
+        self.storage_mut::<T>()
+            .expect("registered above")
+            .insert(entity, component);
+    }
+
+    /// Removes the component of type `T` from the given entity and returns
+    /// it, if there was one.
+    pub fn remove_component<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        let component = self.storage_mut::<T>()?.remove(entity)?;
+        self.removed
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(entity);
+        Some(component)
+    }
+
+    /// Entities whose `T` component was removed since the last
+    /// `clear_trackers`, in the order they lost it.
+    ///
+    /// This lets, say, the renderer delete the DOM node of a card whose
+    /// `Position` went away without any bookkeeping of its own.
+    pub fn removed<T: 'static>(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.removed
+            .get(&TypeId::of::<T>())
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    /// Forget all recorded removals. Run once per frame, after every system
+    /// that looks at `removed`.
+    pub fn clear_trackers(&mut self) {
+        for entities in self.removed.values_mut() {
+            entities.clear();
+        }
+    }
+
+    /// Attempts to fetch an immutable reference to a component of type `T`
+    /// from the given entity.
+    pub fn get_component<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        self.storage::<T>()?.get(entity)
+    }
+
+    /// Attempts to fetch a mutable reference to a component of type `T`
+    /// from the given entity.
+    pub fn get_component_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.storage_mut::<T>()?.get_mut(entity)
+    }
+
+    /// Number of entities that have a component of type `T`.
+    pub fn component_count<T: 'static>(&self) -> usize {
+        self.components
+            .get(&TypeId::of::<T>())
+            .map_or(0, |store| store.len())
+    }
+
+    /// Iterates over every entity that has a component of type `T`, yielding
+    /// `(Entity, &T)` pairs. Densely stored components come out in ascending
+    /// entity order.
+    pub fn iter<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.storage::<T>()
+            .into_iter()
+            .flat_map(|store| store.iter())
+    }
+
+    /// The only entity with a component of type `T`, such as the drag
+    /// cursor, together with that component.
+    pub fn single<T: 'static>(&self) -> Result<(Entity, &T), SingleError> {
+        let mut matches = self.iter::<T>();
+        let first = matches.next().ok_or(SingleError::NoEntities {
+            component: type_name::<T>(),
+        })?;
+        if matches.next().is_some() {
+            return Err(SingleError::MultipleEntities {
+                component: type_name::<T>(),
+                count: self.component_count::<T>(),
+            });
+        }
+        Ok(first)
+    }
+
+    /// Like `single`, but the component is returned mutably.
+    pub fn single_mut<T: 'static>(&mut self) -> Result<(Entity, &mut T), SingleError> {
+        let (entity, _) = self.single::<T>()?;
+        let component = self.get_component_mut(entity).expect("found above");
+        Ok((entity, component))
+    }
+
+    /// Iterates over all entities that have a component of type `T`,
+    /// applying the given closure to each `(Entity, &mut T)` pair.
+    ///
+    /// This is a simple way to implement systems that operate on one
+    /// component type at a time.
+    pub fn for_each<T: 'static, F: FnMut(Entity, &mut T)>(&mut self, mut f: F) {
+        if let Some(store) = self.storage_mut::<T>() {
+            for (entity, component) in store.iter_mut() {
+                f(entity, component);
+            }
+        }
+    }
+
+    /// Estimates the memory held by each component store, largest first.
+    ///
+    /// Stores keep their capacity after components are removed, so a world
+    /// that once held a large board reports more than it currently needs
+    /// until `shrink_to_fit` is called.
+    pub fn memory_usage(&self) -> Vec<StoreUsage> {
+        let mut usage: Vec<StoreUsage> = self.components.values().map(|s| s.usage()).collect();
+        usage.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.component.cmp(b.component)));
+        usage
+    }
+
+    /// Releases spare capacity held by component stores and internal maps.
+    pub fn shrink_to_fit(&mut self) {
+        for store in self.components.values_mut() {
+            store.shrink_to_fit();
+        }
+        self.components.shrink_to_fit();
+        self.resources.shrink_to_fit();
+        self.names.shrink_to_fit();
+        self.removed.retain(|_, entities| !entities.is_empty());
+        for entities in self.removed.values_mut() {
+            entities.shrink_to_fit();
+        }
+    }
+
+    /// Gives `entity` a unique name such as `"stock"` or `"foundation-2"`.
+    ///
+    /// The entity loses any previous name, and an entity that held `name`
+    /// before loses it.
+    pub fn name(&mut self, entity: Entity, name: &str) {
+        if let Some(Name(old)) = self.remove_component::<Name>(entity) {
+            self.names.remove(&old);
+        }
+        if let Some(previous) = self.names.insert(name.to_string(), entity) {
+            self.remove_component::<Name>(previous);
+        }
+        self.add_component(entity, Name(name.to_string()));
+    }
+
+    /// Finds the entity that was given `name`.
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.names.get(name).copied()
+    }
+
+    /// The name given to `entity`, if any.
+    pub fn name_of(&self, entity: Entity) -> Option<&str> {
+        self.get_component::<Name>(entity).map(|n| n.0.as_str())
+    }
+
+    /// Stores a resource in the world, replacing any previous value of the
+    /// same type.
+    pub fn insert_resource<T: 'static>(&mut self, resource: T) {
+        self.resources.insert(TypeId::of::<T>(), Box::new(resource));
+    }
+
+    /// Fetches an immutable reference to the resource of type `T`.
+    pub fn resource<T: 'static>(&self) -> Option<&T> {
+        self.resources
+            .get(&TypeId::of::<T>())
+            .and_then(|boxed| boxed.downcast_ref())
+    }
+
+    /// Fetches a mutable reference to the resource of type `T`.
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.resources
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|boxed| boxed.downcast_mut())
+    }
+
+    /// Removes the resource of type `T` from the world and returns it.
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        self.resources
+            .remove(&TypeId::of::<T>())
+            .and_then(|boxed| boxed.downcast().ok())
+            .map(|boxed| *boxed)
+    }
+
+    /// Removes every resource, keeping the entities and their components.
+    pub fn clear_resources(&mut self) {
+        self.resources.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+
+    #[test]
+    fn basic_usage() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.add_component(entity, Health(100));
+
+        // Retrieve the component immutably
+        let health = world.get_component::<Health>(entity).unwrap();
+        assert_eq!(*health, Health(100));
+
+        // Modify the component using a system-like closure
+        world.for_each::<Health, _>(|_, h| h.0 -= 50);
+        let health = world.get_component::<Health>(entity).unwrap();
+        assert_eq!(*health, Health(50));
+    }
+
+    #[test]
+    fn storage_kinds_behave_alike() {
+        for kind in [StorageKind::Dense, StorageKind::Sparse] {
+            let mut world = World::new();
+            world.register_component::<Health>(kind);
+            let entities: Vec<Entity> = (0..4).map(|_| world.spawn()).collect();
+            for &e in &entities {
+                world.add_component(e, Health(e * 10));
+            }
+
+            assert_eq!(world.remove_component::<Health>(1), Some(Health(10)));
+            assert_eq!(world.remove_component::<Health>(1), None);
+            world.get_component_mut::<Health>(3).unwrap().0 += 1;
+
+            let mut seen: Vec<(Entity, u32)> =
+                world.iter::<Health>().map(|(e, h)| (e, h.0)).collect();
+            seen.sort();
+            assert_eq!(seen, vec![(0, 0), (2, 20), (3, 31)], "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn registering_converts_existing_components() {
+        let mut world = World::new();
+        let e = world.spawn();
+        world.add_component(e, Health(7));
+        assert_eq!(world.storage_kind::<Health>(), StorageKind::Dense);
+
+        world.register_component::<Health>(StorageKind::Sparse);
+        assert_eq!(world.storage_kind::<Health>(), StorageKind::Sparse);
+        assert_eq!(world.get_component::<Health>(e), Some(&Health(7)));
+    }
+
+    #[test]
+    fn shrinking_releases_capacity() {
+        let mut world = World::new();
+        let entities: Vec<Entity> = (0..104).map(|_| world.spawn()).collect();
+        for &e in &entities {
+            world.add_component(e, Health(e));
+        }
+        let before = world.memory_usage()[0].bytes;
+
+        for &e in &entities[10..] {
+            world.remove_component::<Health>(e);
+        }
+        assert_eq!(world.memory_usage()[0].bytes, before);
+
+        world.shrink_to_fit();
+        let usage = &world.memory_usage()[0];
+        assert_eq!(usage.len, 10);
+        assert!(usage.bytes < before);
+        assert!(usage.component.ends_with("Health"));
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let mut world = World::new();
+        let a = world.spawn();
+        let b = world.spawn();
+        world.name(a, "stock");
+        assert_eq!(world.find_by_name("stock"), Some(a));
+
+        // Renaming frees the old name.
+        world.name(a, "waste");
+        assert_eq!(world.find_by_name("stock"), None);
+        assert_eq!(world.name_of(a), Some("waste"));
+
+        // Reusing a name takes it away from its previous owner.
+        world.name(b, "waste");
+        assert_eq!(world.find_by_name("waste"), Some(b));
+        assert_eq!(world.name_of(a), None);
+    }
+
+    #[test]
+    fn resources() {
+        let mut world = World::new();
+        assert!(world.resource::<Health>().is_none());
+
+        world.insert_resource(Health(10));
+        world.resource_mut::<Health>().unwrap().0 += 5;
+        assert_eq!(world.resource::<Health>(), Some(&Health(15)));
+
+        assert_eq!(world.remove_resource::<Health>(), Some(Health(15)));
+        assert!(world.resource::<Health>().is_none());
+    }
+
+    #[test]
+    fn removals_are_tracked_until_cleared() {
+        let mut world = World::new();
+        let a = world.spawn();
+        let b = world.spawn();
+        world.add_component(a, Health(1));
+        world.add_component(b, Health(2));
+
+        world.remove_component::<Health>(b);
+        world.remove_component::<Health>(b);
+        world.remove_component::<Name>(a);
+        assert_eq!(world.removed::<Health>().collect::<Vec<_>>(), vec![b]);
+        assert_eq!(world.removed::<Name>().count(), 0);
+
+        world.clear_trackers();
+        assert_eq!(world.removed::<Health>().count(), 0);
+    }
+
+    #[test]
+    fn single_requires_exactly_one_entity() {
+        let mut world = World::new();
+        assert!(matches!(
+            world.single::<Health>(),
+            Err(SingleError::NoEntities { .. })
+        ));
+
+        let cursor = world.spawn();
+        world.add_component(cursor, Health(1));
+        world.single_mut::<Health>().unwrap().1.0 += 1;
+        assert_eq!(world.single::<Health>(), Ok((cursor, &Health(2))));
+
+        let other = world.spawn();
+        world.add_component(other, Health(1));
+        assert_eq!(
+            world.single::<Health>(),
+            Err(SingleError::MultipleEntities {
+                component: type_name::<Health>(),
+                count: 2,
+            })
+        );
+    }
+}
@@ -0,0 +1,266 @@
+// Queries that fetch several components of an entity at once.
+//
+// `World::query::<(&Card, &mut Position, Option<&FaceUp>)>()` walks every
+// entity that has a `Card` and a `Position`, handing out the components as a
+// tuple. `Option<&T>` members never filter entities out; they are simply
+// `None` when the component is missing.
+//
+// Handing out several `&mut` references from one `World` is something the
+// borrow checker cannot verify on its own, so this file uses a little
+// `unsafe` code. It is sound because:
+//
+// * a query never names the same component type twice when one of the
+//   accesses is mutable (checked at runtime, see `World::query`), so each
+//   `&mut T` points into a component store no other member touches,
+// * every entity is visited at most once, so two items never share a
+//   component, and
+// * the stores are only borrowed once, before the first item, to take
+//   `RawStore` pointers to their vectors. An item is then reached by
+//   pointer arithmetic, which borrows neither the store nor any component
+//   but its own, so the items handed out before stay valid.
+
+use std::any::TypeId;
+
+use super::storage::RawStore;
+use super::{Entity, World};
+
+/// How one member of a query tuple reads its component.
+///
+/// Implemented for `&T`, `&mut T`, `Option<&T>` and `Option<&mut T>`.
+pub trait Fetch {
+    /// What the member yields, e.g. `&'w T`.
+    type Item<'w>;
+    /// Whether the component is borrowed mutably.
+    const MUTABLE: bool;
+    /// Whether entities without the component still match.
+    const OPTIONAL: bool;
+
+    /// The component type being accessed.
+    fn component() -> TypeId;
+
+    /// Fetch the component of `entity` from `store`, which is `None` when
+    /// no entity has ever had this component. Returns `None` when the
+    /// entity does not match.
+    ///
+    /// # Safety
+    ///
+    /// `store` must be taken from the store of this member's component,
+    /// which stays unchanged for `'w`, and nothing else may access the
+    /// returned component mutably while the item is alive.
+    unsafe fn fetch<'w>(store: Option<RawStore>, entity: Entity) -> Option<Self::Item<'w>>;
+}
+
+impl<T: 'static> Fetch for &T {
+    type Item<'w> = &'w T;
+    const MUTABLE: bool = false;
+    const OPTIONAL: bool = false;
+
+    fn component() -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    unsafe fn fetch<'w>(store: Option<RawStore>, entity: Entity) -> Option<&'w T> {
+        unsafe { store?.get(entity) }
+    }
+}
+
+impl<T: 'static> Fetch for &mut T {
+    type Item<'w> = &'w mut T;
+    const MUTABLE: bool = true;
+    const OPTIONAL: bool = false;
+
+    fn component() -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    unsafe fn fetch<'w>(store: Option<RawStore>, entity: Entity) -> Option<&'w mut T> {
+        unsafe { store?.get_mut(entity) }
+    }
+}
+
+impl<F: Fetch> Fetch for Option<F> {
+    type Item<'w> = Option<F::Item<'w>>;
+    const MUTABLE: bool = F::MUTABLE;
+    const OPTIONAL: bool = true;
+
+    fn component() -> TypeId {
+        F::component()
+    }
+
+    unsafe fn fetch<'w>(store: Option<RawStore>, entity: Entity) -> Option<Self::Item<'w>> {
+        Some(unsafe { F::fetch(store, entity) })
+    }
+}
+
+/// A tuple of `Fetch` members, e.g. `(&Card, &mut Position)`.
+pub trait Query {
+    type Item<'w>;
+
+    /// `(component, mutable, optional)` for each member, in order.
+    fn access() -> Vec<(TypeId, bool, bool)>;
+
+    /// # Safety
+    ///
+    /// `stores[i]` must be taken from the store of member `i`, if any, and
+    /// the safety rules of `Fetch::fetch` apply to every member.
+    unsafe fn fetch<'w>(stores: &[Option<RawStore>], entity: Entity) -> Option<Self::Item<'w>>;
+}
+
+macro_rules! impl_query {
+    ($($member:ident $index:tt),+) => {
+        impl<$($member: Fetch),+> Query for ($($member,)+) {
+            type Item<'w> = ($($member::Item<'w>,)+);
+
+            fn access() -> Vec<(TypeId, bool, bool)> {
+                vec![$(($member::component(), $member::MUTABLE, $member::OPTIONAL)),+]
+            }
+
+            unsafe fn fetch<'w>(stores: &[Option<RawStore>], entity: Entity) -> Option<Self::Item<'w>> {
+                Some(($(unsafe { $member::fetch(stores[$index], entity) }?,)+))
+            }
+        }
+    };
+}
+
+impl_query!(A 0);
+impl_query!(A 0, B 1);
+impl_query!(A 0, B 1, C 2);
+impl_query!(A 0, B 1, C 2, D 3);
+impl_query!(A 0, B 1, C 2, D 3, E 4);
+impl_query!(A 0, B 1, C 2, D 3, E 4, G 5);
+
+impl World {
+    /// Iterate over every entity matching the query `Q`, yielding the entity
+    /// together with a tuple of its components, in ascending entity order.
+    ///
+    /// ```ignore
+    /// for (entity, (card, pos, face_up)) in world.query::<(&Card, &mut Position, Option<&FaceUp>)>() {
+    ///     // ...
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the query borrows a component type mutably more than once,
+    /// or both mutably and immutably.
+    pub fn query<Q: Query>(&mut self) -> impl Iterator<Item = (Entity, Q::Item<'_>)> + '_ {
+        let access = Q::access();
+        for (i, (id, mutable, _)) in access.iter().enumerate() {
+            for (other, other_mutable, _) in &access[..i] {
+                assert!(
+                    id != other || !(*mutable || *other_mutable),
+                    "a query may not borrow the same component mutably twice"
+                );
+            }
+        }
+
+        // Walk the smallest required store; with only optional members every
+        // entity ever spawned is a candidate.
+        let mut required = access
+            .iter()
+            .filter(|(_, _, optional)| !optional)
+            .peekable();
+        let mut entities: Vec<Entity> = if required.peek().is_none() {
+            (0..self.next_id).collect()
+        } else {
+            required
+                .map(|(id, ..)| self.components.get(id))
+                .collect::<Option<Vec<_>>>()
+                .and_then(|stores| stores.into_iter().min_by_key(|store| store.len()))
+                .map_or_else(Vec::new, |store| store.entities())
+        };
+        entities.sort_unstable();
+
+        // A single pass over the stores takes the pointers into each one.
+        let mut stores = vec![None; access.len()];
+        for (id, store) in self.components.iter_mut() {
+            for (slot, (wanted, ..)) in stores.iter_mut().zip(&access) {
+                if wanted == id {
+                    *slot = Some(store.raw());
+                }
+            }
+        }
+
+        entities.into_iter().filter_map(move |entity| {
+            unsafe { Q::fetch(&stores, entity) }.map(|item| (entity, item))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::StorageKind;
+
+    #[derive(Debug, PartialEq)]
+    struct Card(u8);
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    #[derive(Debug, PartialEq)]
+    struct FaceUp;
+
+    #[test]
+    fn tuple_query_with_optional_member() {
+        let mut world = World::new();
+        for i in 0..3 {
+            let e = world.spawn();
+            world.add_component(e, Card(i));
+            world.add_component(e, Position(0));
+            if i == 1 {
+                world.add_component(e, FaceUp);
+            }
+        }
+        // An entity without a `Card` is skipped.
+        let other = world.spawn();
+        world.add_component(other, Position(0));
+
+        let mut seen = Vec::new();
+        for (entity, (card, pos, face_up)) in
+            world.query::<(&Card, &mut Position, Option<&FaceUp>)>()
+        {
+            pos.0 = card.0 as i32 * 10;
+            seen.push((entity, face_up.is_some()));
+        }
+        assert_eq!(seen, vec![(0, false), (1, true), (2, false)]);
+        assert_eq!(world.get_component::<Position>(2), Some(&Position(20)));
+        assert_eq!(world.get_component::<Position>(other), Some(&Position(0)));
+    }
+
+    #[test]
+    fn items_stay_valid_while_later_ones_are_fetched() {
+        let mut world = World::new();
+        world.register_component::<Position>(StorageKind::Sparse);
+        for i in 0..4 {
+            let e = world.spawn();
+            world.add_component(e, Position(i));
+            if i % 2 == 1 {
+                world.add_component(e, FaceUp);
+            }
+        }
+        // Every item is held until the iteration is over.
+        let items: Vec<_> = world.query::<(&mut Position, Option<&FaceUp>)>().collect();
+        for (_, (pos, face_up)) in items {
+            if face_up.is_some() {
+                pos.0 = -pos.0;
+            }
+        }
+        let positions: Vec<i32> = world.query::<(&Position,)>().map(|(_, (p,))| p.0).collect();
+        assert_eq!(positions, vec![0, -1, 2, -3]);
+    }
+
+    #[test]
+    fn missing_required_component_matches_nothing() {
+        let mut world = World::new();
+        let e = world.spawn();
+        world.add_component(e, Card(1));
+        assert_eq!(world.query::<(&Card, &FaceUp)>().count(), 0);
+        assert_eq!(world.query::<(Option<&FaceUp>,)>().count(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "mutably twice")]
+    fn aliasing_mutable_access_panics() {
+        let mut world = World::new();
+        let _ = world.query::<(&mut Card, &Card)>();
+    }
+}
@@ -0,0 +1,209 @@
+// Runtime component reflection.
+//
+// Tools such as an inspector, the save system or scripting want to read and
+// write components knowing only their name as a string. A
+// `ComponentRegistry` maps such names to a small table of functions,
+// generated once per component type at registration time, that convert the
+// component to and from JSON values and copy it between entities.
+
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{Entity, World};
+
+/// Why a reflected read or write failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReflectError {
+    /// No component was registered under this name.
+    UnknownComponent(String),
+    /// The value does not describe a valid component.
+    InvalidValue { component: String, message: String },
+}
+
+impl fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflectError::UnknownComponent(name) => write!(f, "unknown component \"{name}\""),
+            ReflectError::InvalidValue { component, message } => {
+                write!(f, "invalid {component}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReflectError {}
+
+/// The reflected operations of one component type.
+///
+/// `Copy`, so it can be taken out of a registry stored as a resource before
+/// being handed the `&mut World`.
+#[derive(Clone, Copy)]
+pub struct ComponentInfo {
+    name: &'static str,
+    type_id: TypeId,
+    read: fn(&World, Entity) -> Option<Value>,
+    write: fn(&mut World, Entity, Value) -> Result<(), serde_json::Error>,
+    copy: fn(&mut World, Entity, Entity) -> bool,
+}
+
+impl ComponentInfo {
+    fn of<T: Serialize + DeserializeOwned + Clone + 'static>(name: &'static str) -> Self {
+        ComponentInfo {
+            name,
+            type_id: TypeId::of::<T>(),
+            read: |world, entity| {
+                let component = world.get_component::<T>(entity)?;
+                Some(serde_json::to_value(component).expect("components serialize"))
+            },
+            write: |world, entity, value| {
+                world.add_component(entity, serde_json::from_value::<T>(value)?);
+                Ok(())
+            },
+            copy: |world, from, to| match world.get_component::<T>(from).cloned() {
+                Some(component) => {
+                    world.add_component(to, component);
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The component of `entity` as JSON, if it has one.
+    pub fn read(&self, world: &World, entity: Entity) -> Option<Value> {
+        (self.read)(world, entity)
+    }
+
+    /// Insert or replace the component of `entity` from JSON.
+    pub fn write(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        value: Value,
+    ) -> Result<(), ReflectError> {
+        (self.write)(world, entity, value).map_err(|e| ReflectError::InvalidValue {
+            component: self.name.to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Copy the component of `from` onto `to`. Returns `false` when `from`
+    /// has none.
+    pub fn clone_to(&self, world: &mut World, from: Entity, to: Entity) -> bool {
+        (self.copy)(world, from, to)
+    }
+}
+
+/// Component types that can be reached by name.
+#[derive(Clone, Default)]
+pub struct ComponentRegistry {
+    // Sorted, so listings come out in a stable order.
+    by_name: BTreeMap<&'static str, ComponentInfo>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `T` reachable as `name`, replacing any type registered under
+    /// that name before.
+    pub fn register<T: Serialize + DeserializeOwned + Clone + 'static>(
+        &mut self,
+        name: &'static str,
+    ) -> &mut Self {
+        self.by_name.insert(name, ComponentInfo::of::<T>(name));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Result<ComponentInfo, ReflectError> {
+        self.by_name
+            .get(name)
+            .copied()
+            .ok_or_else(|| ReflectError::UnknownComponent(name.to_string()))
+    }
+
+    /// Names of every registered component, alphabetically.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.by_name.keys().copied()
+    }
+
+    /// Every registered component of `entity`, by name.
+    pub fn components_of(&self, world: &World, entity: Entity) -> BTreeMap<&'static str, Value> {
+        self.by_name
+            .values()
+            .filter_map(|info| Some((info.name, info.read(world, entity)?)))
+            .collect()
+    }
+
+    /// Copy every registered component of `from` onto `to`.
+    pub fn clone_entity(&self, world: &mut World, from: Entity, to: Entity) {
+        for info in self.by_name.values() {
+            info.clone_to(world, from, to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Health(u32);
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Label {
+        text: String,
+    }
+
+    #[test]
+    fn components_by_name() {
+        let mut registry = ComponentRegistry::new();
+        registry
+            .register::<Health>("Health")
+            .register::<Label>("Label");
+        let mut world = World::new();
+        let entity = world.spawn();
+
+        let health = registry.get("Health").unwrap();
+        health
+            .write(&mut world, entity, serde_json::json!(7))
+            .unwrap();
+        assert_eq!(world.get_component::<Health>(entity), Some(&Health(7)));
+        assert!(matches!(
+            health.write(&mut world, entity, serde_json::json!("seven")),
+            Err(ReflectError::InvalidValue { .. })
+        ));
+        assert_eq!(
+            registry.get("Mana").err(),
+            Some(ReflectError::UnknownComponent("Mana".into()))
+        );
+
+        world.add_component(
+            entity,
+            Label {
+                text: "king".into(),
+            },
+        );
+        let copy = world.spawn();
+        registry.clone_entity(&mut world, entity, copy);
+        let components = registry.components_of(&world, copy);
+        assert_eq!(components["Health"], serde_json::json!(7));
+        assert_eq!(components["Label"], serde_json::json!({ "text": "king" }));
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["Health", "Label"]);
+    }
+}
@@ -0,0 +1,132 @@
+// Relationships between entities that the world keeps consistent.
+//
+// A card lying on other cards carries `OnTopOf` listing them: one card for a
+// tableau stack, two for a Pyramid or TriPeaks card overlapping the row
+// below. Rules mostly ask the opposite question, "is anything still covering
+// this card?", so the world maintains the reverse index `CoveredBy` on the
+// cards underneath.
+//
+// Both components are only changed through `World::place_on` and
+// `World::lift`, which update the two sides together. Removing either
+// component directly with `remove_component` would break the index.
+
+use super::{Entity, World};
+
+/// The entities this one lies on, in the order they were given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnTopOf(Vec<Entity>);
+
+impl OnTopOf {
+    pub fn entities(&self) -> &[Entity] {
+        &self.0
+    }
+}
+
+/// The entities lying directly on this one. Maintained by the world.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoveredBy(Vec<Entity>);
+
+impl CoveredBy {
+    pub fn entities(&self) -> &[Entity] {
+        &self.0
+    }
+}
+
+impl World {
+    /// Put `entity` on top of `below`, replacing whatever it lay on before.
+    pub fn place_on(&mut self, entity: Entity, below: &[Entity]) {
+        self.lift(entity);
+        if below.is_empty() {
+            return;
+        }
+        for &under in below {
+            match self.get_component_mut::<CoveredBy>(under) {
+                Some(covered) => covered.0.push(entity),
+                None => self.add_component(under, CoveredBy(vec![entity])),
+            }
+        }
+        self.add_component(entity, OnTopOf(below.to_vec()));
+    }
+
+    /// Take `entity` off the entities it lay on and return them.
+    pub fn lift(&mut self, entity: Entity) -> Vec<Entity> {
+        let Some(OnTopOf(below)) = self.remove_component::<OnTopOf>(entity) else {
+            return Vec::new();
+        };
+        for &under in &below {
+            let now_empty = self.get_component_mut::<CoveredBy>(under).is_some_and(|c| {
+                c.0.retain(|&e| e != entity);
+                c.0.is_empty()
+            });
+            if now_empty {
+                self.remove_component::<CoveredBy>(under);
+            }
+        }
+        below
+    }
+
+    /// The entities lying directly on `entity`.
+    pub fn covered_by(&self, entity: Entity) -> &[Entity] {
+        self.get_component::<CoveredBy>(entity)
+            .map_or(&[], CoveredBy::entities)
+    }
+
+    /// Whether anything lies on `entity`, for instance whether a Pyramid card
+    /// is free to be played.
+    pub fn is_covered(&self, entity: Entity) -> bool {
+        !self.covered_by(entity).is_empty()
+    }
+
+    /// Everything resting on `entity`, directly or indirectly, nearest
+    /// first: the cards that come along when it is dragged.
+    pub fn stack_above(&self, entity: Entity) -> Vec<Entity> {
+        let mut stack: Vec<Entity> = Vec::new();
+        let mut next = 0;
+        stack.extend(self.covered_by(entity));
+        while let Some(&current) = stack.get(next) {
+            next += 1;
+            for &above in self.covered_by(current) {
+                if !stack.contains(&above) {
+                    stack.push(above);
+                }
+            }
+        }
+        stack
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverse_index_follows_moves() {
+        let mut world = World::new();
+        let [a, b, c, d] = [0; 4].map(|_| world.spawn());
+        world.place_on(b, &[a]);
+        world.place_on(c, &[b]);
+        assert_eq!(world.covered_by(a), [b]);
+        assert_eq!(world.stack_above(a), vec![b, c]);
+
+        // Dragging b and c onto d.
+        world.place_on(b, &[d]);
+        assert!(!world.is_covered(a));
+        assert_eq!(world.stack_above(d), vec![b, c]);
+        assert_eq!(world.lift(b), vec![d]);
+        assert!(world.get_component::<CoveredBy>(d).is_none());
+    }
+
+    #[test]
+    fn pyramid_cards_cover_two() {
+        let mut world = World::new();
+        let [left, right, top] = [0; 3].map(|_| world.spawn());
+        world.place_on(top, &[left, right]);
+        assert!(world.is_covered(left) && world.is_covered(right));
+        assert_eq!(
+            world.get_component::<OnTopOf>(top).unwrap().entities(),
+            [left, right]
+        );
+        world.lift(top);
+        assert!(!world.is_covered(left) && !world.is_covered(right));
+    }
+}
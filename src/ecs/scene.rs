@@ -0,0 +1,166 @@
+// Scenes: entities and their components described as data.
+//
+// A scene is JSON listing entities, each with an optional name and its
+// components keyed by registered component name:
+//
+// ```json
+// { "entities": [
+//     { "name": "tableau-0", "components": { "PileAnchor": { "Tableau": 0 } } },
+//     { "components": { "Card": { "suit": "Spades", "rank": "King" },
+//                       "Pile": { "Tableau": 0 }, "PileIndex": 0, "FaceUp": true } }
+// ] }
+// ```
+//
+// Components are looked up in the `ComponentRegistry` resource, so alternative
+// board layouts and test fixtures can be written without touching Rust code.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{ComponentRegistry, Entity, ReflectError, World};
+
+/// The parsed form of a scene file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub entities: Vec<SceneEntity>,
+}
+
+/// One entity of a scene.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneEntity {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub components: BTreeMap<String, Value>,
+}
+
+/// Why a scene could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SceneError {
+    /// The text is not a valid scene document.
+    Malformed(String),
+    /// The world holds no `ComponentRegistry` resource.
+    NoRegistry,
+    /// Entity number `entity` of the scene has a bad component.
+    Component { entity: usize, error: ReflectError },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Malformed(message) => write!(f, "malformed scene: {message}"),
+            SceneError::NoRegistry => write!(f, "no component registry to load the scene with"),
+            SceneError::Component { entity, error } => {
+                write!(f, "scene entity {entity}: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl World {
+    /// Spawn the entities described by the JSON `scene` and return them in
+    /// scene order.
+    ///
+    /// Every component name is checked before anything is spawned, but a
+    /// component whose value fails to parse leaves the entities spawned before
+    /// it in place.
+    pub fn load_scene(&mut self, scene: &str) -> Result<Vec<Entity>, SceneError> {
+        let scene: Scene =
+            serde_json::from_str(scene).map_err(|e| SceneError::Malformed(e.to_string()))?;
+        let registry = self
+            .resource::<ComponentRegistry>()
+            .ok_or(SceneError::NoRegistry)?;
+
+        let mut rows = Vec::with_capacity(scene.entities.len());
+        for (index, entity) in scene.entities.into_iter().enumerate() {
+            let mut components = Vec::with_capacity(entity.components.len());
+            for (name, value) in entity.components {
+                let info = registry.get(&name).map_err(|error| SceneError::Component {
+                    entity: index,
+                    error,
+                })?;
+                components.push((info, value));
+            }
+            rows.push((entity.name, components));
+        }
+
+        let mut spawned = Vec::with_capacity(rows.len());
+        for (index, (name, components)) in rows.into_iter().enumerate() {
+            let entity = self.spawn();
+            spawned.push(entity);
+            if let Some(name) = name {
+                self.name(entity, &name);
+            }
+            for (info, value) in components {
+                info.write(self, entity, value)
+                    .map_err(|error| SceneError::Component {
+                        entity: index,
+                        error,
+                    })?;
+            }
+        }
+        Ok(spawned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Health(u32);
+
+    fn world() -> World {
+        let mut registry = ComponentRegistry::new();
+        registry.register::<Health>("Health");
+        let mut world = World::new();
+        world.insert_resource(registry);
+        world
+    }
+
+    #[test]
+    fn loads_named_entities_and_components() {
+        let mut world = world();
+        let entities = world
+            .load_scene(
+                r#"{ "entities": [
+                    { "name": "hero", "components": { "Health": 10 } },
+                    { }
+                ] }"#,
+            )
+            .unwrap();
+        assert_eq!(entities.len(), 2);
+        assert_eq!(world.find_by_name("hero"), Some(entities[0]));
+        assert_eq!(
+            world.get_component::<Health>(entities[0]),
+            Some(&Health(10))
+        );
+        assert!(world.get_component::<Health>(entities[1]).is_none());
+    }
+
+    #[test]
+    fn unknown_components_spawn_nothing() {
+        let mut world = world();
+        let result = world.load_scene(
+            r#"{ "entities": [ { "components": { "Health": 1 } },
+                               { "components": { "Mana": 1 } } ] }"#,
+        );
+        assert_eq!(
+            result,
+            Err(SceneError::Component {
+                entity: 1,
+                error: ReflectError::UnknownComponent("Mana".into()),
+            })
+        );
+        assert_eq!(world.component_count::<Health>(), 0);
+        assert!(matches!(
+            world.load_scene("[]"),
+            Err(SceneError::Malformed(_))
+        ));
+    }
+}
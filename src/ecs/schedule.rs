@@ -0,0 +1,289 @@
+// Systems and the schedule that runs them.
+//
+// A system is just a function that reads and writes the `World`. The
+// `Schedule` runs its systems one after another. By default they run in the
+// order they were added, but a system can also declare that it must run
+// `before` or `after` other systems. Constraints refer to *labels*: every
+// system is labelled with its own name and may carry extra labels shared
+// with other systems (for example `"input"`), so one constraint can order a
+// whole group.
+//
+// The order is worked out with a topological sort whenever systems change,
+// so plugins can register their systems in any order and still get, say,
+// move validation before animation and network broadcast.
+//
+// Systems may also carry *run conditions*: cheap checks evaluated right
+// before the system would run. A system whose conditions are not all met is
+// skipped for that run, so an idle board costs next to nothing per frame.
+
+use std::fmt;
+
+use super::World;
+
+/// A function run by the schedule.
+pub type SystemFn = Box<dyn FnMut(&mut World)>;
+
+/// A check deciding whether a system runs this time.
+pub type RunCondition = Box<dyn Fn(&World) -> bool>;
+
+/// A named system together with its ordering constraints.
+pub struct System {
+    name: String,
+    labels: Vec<String>,
+    before: Vec<String>,
+    after: Vec<String>,
+    conditions: Vec<RunCondition>,
+    run: SystemFn,
+}
+
+impl System {
+    /// Wrap `run` as a system called `name`. The name doubles as a label.
+    pub fn new(name: &str, run: impl FnMut(&mut World) + 'static) -> Self {
+        Self {
+            name: name.to_string(),
+            labels: vec![name.to_string()],
+            before: Vec::new(),
+            after: Vec::new(),
+            conditions: Vec::new(),
+            run: Box::new(run),
+        }
+    }
+
+    /// Attach an extra label other systems can order themselves against.
+    pub fn label(mut self, label: &str) -> Self {
+        self.labels.push(label.to_string());
+        self
+    }
+
+    /// Run before every system carrying `label`.
+    pub fn before(mut self, label: &str) -> Self {
+        self.before.push(label.to_string());
+        self
+    }
+
+    /// Run after every system carrying `label`.
+    pub fn after(mut self, label: &str) -> Self {
+        self.after.push(label.to_string());
+        self
+    }
+
+    /// Only run while `condition` holds. Several conditions must all hold.
+    pub fn run_if(mut self, condition: impl Fn(&World) -> bool + 'static) -> Self {
+        self.conditions.push(Box::new(condition));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn has_label(&self, label: &str) -> bool {
+        self.labels.iter().any(|l| l == label)
+    }
+
+    fn should_run(&self, world: &World) -> bool {
+        self.conditions.iter().all(|condition| condition(world))
+    }
+}
+
+/// Run condition: at least one entity has a `T` component.
+pub fn any_with_component<T: 'static>() -> impl Fn(&World) -> bool {
+    |world: &World| world.component_count::<T>() > 0
+}
+
+/// Run condition: the world holds a resource of type `T`.
+pub fn resource_exists<T: 'static>() -> impl Fn(&World) -> bool {
+    |world: &World| world.resource::<T>().is_some()
+}
+
+/// Why the systems of a schedule could not be ordered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// The constraints contradict each other. Lists the systems involved.
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Cycle(names) => {
+                write!(f, "system ordering cycle between: {}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+/// An ordered collection of systems.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<System>,
+    // Indices into `systems` in run order; `None` when it must be rebuilt.
+    order: Option<Vec<usize>>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a system. Constraints naming labels that no system carries are
+    /// ignored, so optional features can be left out freely.
+    pub fn add_system(&mut self, system: System) -> &mut Self {
+        self.systems.push(system);
+        self.order = None;
+        self
+    }
+
+    /// The names of the systems in the order they will run.
+    pub fn order(&mut self) -> Result<Vec<&str>, ScheduleError> {
+        let order = self.resolve()?.to_vec();
+        Ok(order.iter().map(|&i| self.systems[i].name()).collect())
+    }
+
+    /// Run every system whose conditions hold once, in order.
+    ///
+    /// Conditions are checked just before each system, so they see the
+    /// changes made by the systems that ran earlier.
+    pub fn run(&mut self, world: &mut World) -> Result<(), ScheduleError> {
+        let order = self.resolve()?.to_vec();
+        for i in order {
+            let system = &mut self.systems[i];
+            if system.should_run(world) {
+                (system.run)(world);
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(&mut self) -> Result<&[usize], ScheduleError> {
+        if self.order.is_none() {
+            self.order = Some(self.sort()?);
+        }
+        Ok(self.order.as_deref().expect("resolved above"))
+    }
+
+    /// Kahn's algorithm, always picking the earliest registered system that
+    /// is ready so unconstrained systems keep their registration order.
+    fn sort(&self) -> Result<Vec<usize>, ScheduleError> {
+        let n = self.systems.len();
+        // `edges[a]` lists the systems that must run after system `a`.
+        let mut edges = vec![Vec::new(); n];
+        for (i, system) in self.systems.iter().enumerate() {
+            for (j, other) in self.systems.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                if system.before.iter().any(|l| other.has_label(l)) {
+                    edges[i].push(j);
+                }
+                if system.after.iter().any(|l| other.has_label(l)) {
+                    edges[j].push(i);
+                }
+            }
+        }
+
+        let mut incoming = vec![0usize; n];
+        for targets in &edges {
+            for &t in targets {
+                incoming[t] += 1;
+            }
+        }
+
+        let mut done = vec![false; n];
+        let mut order = Vec::with_capacity(n);
+        while order.len() < n {
+            let Some(next) = (0..n).find(|&i| !done[i] && incoming[i] == 0) else {
+                let stuck = (0..n)
+                    .filter(|&i| !done[i])
+                    .map(|i| self.systems[i].name.clone())
+                    .collect();
+                return Err(ScheduleError::Cycle(stuck));
+            };
+            done[next] = true;
+            order.push(next);
+            for &t in &edges[next] {
+                incoming[t] -= 1;
+            }
+        }
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Resource recording the order systems ran in.
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    fn logger(name: &'static str) -> impl FnMut(&mut World) {
+        move |world: &mut World| world.resource_mut::<Log>().unwrap().0.push(name)
+    }
+
+    #[test]
+    fn constraints_override_registration_order() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(System::new("broadcast", logger("broadcast")).after("validate"))
+            .add_system(System::new("animate", logger("animate")).after("validate"))
+            .add_system(System::new("validate", logger("validate")).after("input"))
+            .add_system(System::new("pointer", logger("pointer")).label("input"))
+            .add_system(System::new("keyboard", logger("keyboard")).label("input"));
+
+        let mut world = World::new();
+        world.insert_resource(Log::default());
+        schedule.run(&mut world).unwrap();
+        assert_eq!(
+            world.resource::<Log>().unwrap().0,
+            vec!["pointer", "keyboard", "validate", "broadcast", "animate"]
+        );
+    }
+
+    #[test]
+    fn run_conditions_skip_idle_systems() {
+        struct Animation;
+        struct Connected;
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(
+                System::new("animate", logger("animate")).run_if(any_with_component::<Animation>()),
+            )
+            .add_system(System::new("sync", logger("sync")).run_if(resource_exists::<Connected>()))
+            .add_system(
+                System::new("start", |world| {
+                    let e = world.spawn();
+                    world.add_component(e, Animation);
+                })
+                .before("animate"),
+            );
+
+        let mut world = World::new();
+        world.insert_resource(Log::default());
+        schedule.run(&mut world).unwrap();
+        assert_eq!(world.resource::<Log>().unwrap().0, vec!["animate"]);
+
+        world.insert_resource(Connected);
+        schedule.run(&mut world).unwrap();
+        assert_eq!(
+            world.resource::<Log>().unwrap().0,
+            vec!["animate", "sync", "animate"]
+        );
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(System::new("a", |_| {}).before("b"))
+            .add_system(System::new("b", |_| {}).before("a"))
+            .add_system(System::new("c", |_| {}).before("missing"));
+        assert_eq!(
+            schedule.order(),
+            Err(ScheduleError::Cycle(vec!["a".into(), "b".into()]))
+        );
+    }
+}
@@ -0,0 +1,214 @@
+// State machines driven by the ECS.
+//
+// A game moves through a handful of states: main menu, dealing, playing,
+// paused, won. The current one is kept in a `State<S>` resource. Systems ask
+// for a transition with `State::set`; nothing changes until `States::run`
+// applies it, which first runs the `OnExit` systems of the old state, then
+// the `OnEnter` systems of the new one, and finally the `OnUpdate` systems
+// of whatever state is current.
+//
+// This makes transitions declarative: "stop the clock on pause" is an
+// `OnEnter(Paused)` system, and the win animation is an `OnEnter(Won)`
+// system that by construction plays exactly once.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::{Schedule, ScheduleError, System, World};
+
+/// Resource holding the current state and any requested transition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State<S> {
+    current: S,
+    next: Option<S>,
+}
+
+impl<S: Copy + Eq> State<S> {
+    pub fn new(initial: S) -> Self {
+        State {
+            current: initial,
+            next: None,
+        }
+    }
+
+    pub fn get(&self) -> S {
+        self.current
+    }
+
+    /// Request a transition to `next`. It happens at the next `States::run`;
+    /// a later request in the same frame wins.
+    pub fn set(&mut self, next: S) {
+        self.next = Some(next);
+    }
+
+    /// The transition waiting to be applied, if any.
+    pub fn pending(&self) -> Option<S> {
+        self.next
+    }
+}
+
+/// When a group of state systems runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateSet<S> {
+    /// Once, when the state is entered.
+    OnEnter(S),
+    /// Once, when the state is left.
+    OnExit(S),
+    /// Every run while the state is current.
+    OnUpdate(S),
+}
+
+/// The systems attached to the states of type `S`.
+pub struct States<S> {
+    sets: HashMap<StateSet<S>, Schedule>,
+    // Whether the initial state has been entered yet.
+    started: bool,
+}
+
+impl<S> Default for States<S> {
+    fn default() -> Self {
+        States {
+            sets: HashMap::new(),
+            started: false,
+        }
+    }
+}
+
+impl<S: Copy + Eq + Hash + 'static> States<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `system` at `set`. Systems in the same set are ordered with the
+    /// usual `before`/`after` constraints.
+    pub fn add_system(&mut self, set: StateSet<S>, system: System) -> &mut Self {
+        self.sets.entry(set).or_default().add_system(system);
+        self
+    }
+
+    /// Apply a pending transition, then run the systems of the current state.
+    ///
+    /// The first run enters the initial state. Does nothing while the world
+    /// holds no `State<S>`.
+    pub fn run(&mut self, world: &mut World) -> Result<(), ScheduleError> {
+        let Some(state) = world.resource::<State<S>>() else {
+            return Ok(());
+        };
+        let current = state.current;
+        if !self.started {
+            self.started = true;
+            self.run_set(StateSet::OnEnter(current), world)?;
+        }
+
+        let next = world.resource_mut::<State<S>>().and_then(|s| s.next.take());
+        if let Some(next) = next
+            && next != current
+        {
+            self.run_set(StateSet::OnExit(current), world)?;
+            if let Some(state) = world.resource_mut::<State<S>>() {
+                state.current = next;
+            }
+            self.run_set(StateSet::OnEnter(next), world)?;
+        }
+
+        match world.resource::<State<S>>() {
+            Some(state) => {
+                let current = state.current;
+                self.run_set(StateSet::OnUpdate(current), world)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn run_set(&mut self, set: StateSet<S>, world: &mut World) -> Result<(), ScheduleError> {
+        match self.sets.get_mut(&set) {
+            Some(schedule) => schedule.run(world),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Run condition: the current `S` state is `state`.
+pub fn in_state<S: Copy + Eq + 'static>(state: S) -> impl Fn(&World) -> bool {
+    move |world: &World| {
+        world
+            .resource::<State<S>>()
+            .is_some_and(|s| s.get() == state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum GameState {
+        Playing,
+        Paused,
+        Won,
+    }
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Clock {
+        ticks: u32,
+        running: bool,
+        win_animations: u32,
+    }
+
+    fn clock(world: &mut World) -> &mut Clock {
+        world.resource_mut::<Clock>().unwrap()
+    }
+
+    #[test]
+    fn transitions_run_enter_and_exit_once() {
+        let mut states = States::new();
+        states
+            .add_system(
+                StateSet::OnEnter(GameState::Playing),
+                System::new("start_clock", |w| clock(w).running = true),
+            )
+            .add_system(
+                StateSet::OnUpdate(GameState::Playing),
+                System::new("tick", |w| clock(w).ticks += 1),
+            )
+            .add_system(
+                StateSet::OnExit(GameState::Playing),
+                System::new("stop_clock", |w| clock(w).running = false),
+            )
+            .add_system(
+                StateSet::OnEnter(GameState::Won),
+                System::new("celebrate", |w| clock(w).win_animations += 1),
+            );
+
+        let mut world = World::new();
+        world.insert_resource(Clock::default());
+        world.insert_resource(State::new(GameState::Playing));
+        states.run(&mut world).unwrap();
+        states.run(&mut world).unwrap();
+        assert!(in_state(GameState::Playing)(&world));
+
+        world
+            .resource_mut::<State<GameState>>()
+            .unwrap()
+            .set(GameState::Paused);
+        states.run(&mut world).unwrap();
+        states.run(&mut world).unwrap();
+        assert_eq!(
+            world.resource::<Clock>(),
+            Some(&Clock {
+                ticks: 2,
+                running: false,
+                win_animations: 0,
+            })
+        );
+
+        world
+            .resource_mut::<State<GameState>>()
+            .unwrap()
+            .set(GameState::Won);
+        states.run(&mut world).unwrap();
+        states.run(&mut world).unwrap();
+        assert_eq!(world.resource::<Clock>().unwrap().win_animations, 1);
+        assert!(in_state(GameState::Won)(&world));
+    }
+}
@@ -0,0 +1,392 @@
+// Component storage strategies.
+//
+// Every component type gets its own store. Two layouts are available:
+//
+// * `Dense`: a `Vec<Option<T>>` indexed directly by entity ID. Lookups are a
+//   single index operation and iteration walks the vector in entity order.
+//   This suits components nearly every entity has, such as `Card`.
+// * `Sparse`: a sparse set. A (sparse) index vector maps entity IDs to
+//   positions in tightly packed entity and value vectors. Iteration only
+//   touches entities that actually have the component, which suits rare
+//   components such as `Dragging`.
+//
+// Dense is the default. A component opts into sparse storage by calling
+// `World::register_component::<T>(StorageKind::Sparse)`.
+
+use std::any::Any;
+use std::mem::size_of;
+
+use serde::Serialize;
+
+use super::Entity;
+
+/// How the values of one component type are laid out in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum StorageKind {
+    /// Indexed directly by entity ID. Best for common components.
+    #[default]
+    Dense,
+    /// Packed sparse set. Best for components few entities have.
+    Sparse,
+}
+
+/// Memory used by the store of one component type, as reported by
+/// `World::memory_usage`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoreUsage {
+    /// Rust type name of the component.
+    pub component: &'static str,
+    pub kind: StorageKind,
+    /// Number of entities that have the component.
+    pub len: usize,
+    /// Bytes reserved by the store's vectors. This is a shallow estimate:
+    /// heap data owned by the components themselves (a `String`'s text, for
+    /// example) is not included.
+    pub bytes: usize,
+}
+
+/// The operations the `World` needs without knowing the component type.
+pub(crate) trait AnyStorage: Any {
+    fn kind(&self) -> StorageKind;
+    fn len(&self) -> usize;
+    fn entities(&self) -> Vec<Entity>;
+    fn usage(&self) -> StoreUsage;
+    fn shrink_to_fit(&mut self);
+    fn raw(&mut self) -> RawStore;
+}
+
+/// The vectors of a store as raw pointers, with the component type erased.
+/// `World::query` takes one of each store before it hands out any item, so
+/// fetching a component later never borrows the store, or the whole of its
+/// vectors, again.
+#[derive(Debug, Clone, Copy)]
+pub struct RawStore {
+    /// The `Option<T>` slots of a dense store, or the packed values of a
+    /// sparse one.
+    values: *mut (),
+    /// The index of a sparse store from entity ID to packed position; null
+    /// for a dense store.
+    sparse: *const Option<usize>,
+    /// Number of slots, or of entries in the sparse index.
+    len: usize,
+}
+
+impl RawStore {
+    /// The component of `entity`, or `None` when it has none.
+    ///
+    /// # Safety
+    ///
+    /// `T` must be the component type of the store this was taken from, the
+    /// store must not have changed since, and nothing may hold the component
+    /// mutably while the reference is alive.
+    pub unsafe fn get<'w, T>(self, entity: Entity) -> Option<&'w T> {
+        unsafe {
+            match self.locate::<T>(entity)? {
+                Slot::Dense(slot) => (*slot).as_ref(),
+                Slot::Packed(value) => Some(&*value),
+            }
+        }
+    }
+
+    /// The component of `entity`, mutably.
+    ///
+    /// # Safety
+    ///
+    /// As for `get`, and nothing else may access the component at all while
+    /// the reference is alive.
+    pub unsafe fn get_mut<'w, T>(self, entity: Entity) -> Option<&'w mut T> {
+        unsafe {
+            match self.locate::<T>(entity)? {
+                Slot::Dense(slot) => (*slot).as_mut(),
+                Slot::Packed(value) => Some(&mut *value),
+            }
+        }
+    }
+
+    /// Where the component of `entity` is kept, found with pointer
+    /// arithmetic alone: only the one element is ever dereferenced.
+    unsafe fn locate<T>(self, entity: Entity) -> Option<Slot<T>> {
+        let index = entity as usize;
+        if index >= self.len {
+            return None;
+        }
+        unsafe {
+            if self.sparse.is_null() {
+                Some(Slot::Dense(self.values.cast::<Option<T>>().add(index)))
+            } else {
+                let packed = (*self.sparse.add(index))?;
+                Some(Slot::Packed(self.values.cast::<T>().add(packed)))
+            }
+        }
+    }
+}
+
+enum Slot<T> {
+    Dense(*mut Option<T>),
+    Packed(*mut T),
+}
+
+/// Storage for all components of type `T`.
+pub(crate) enum ComponentStorage<T> {
+    Dense {
+        slots: Vec<Option<T>>,
+        len: usize,
+    },
+    Sparse {
+        sparse: Vec<Option<usize>>,
+        entities: Vec<Entity>,
+        values: Vec<T>,
+    },
+}
+
+impl<T> ComponentStorage<T> {
+    pub fn new(kind: StorageKind) -> Self {
+        match kind {
+            StorageKind::Dense => ComponentStorage::Dense {
+                slots: Vec::new(),
+                len: 0,
+            },
+            StorageKind::Sparse => ComponentStorage::Sparse {
+                sparse: Vec::new(),
+                entities: Vec::new(),
+                values: Vec::new(),
+            },
+        }
+    }
+
+    /// Insert or replace the component of `entity`.
+    pub fn insert(&mut self, entity: Entity, value: T) {
+        let index = entity as usize;
+        match self {
+            ComponentStorage::Dense { slots, len } => {
+                if slots.len() <= index {
+                    slots.resize_with(index + 1, || None);
+                }
+                if slots[index].replace(value).is_none() {
+                    *len += 1;
+                }
+            }
+            ComponentStorage::Sparse {
+                sparse,
+                entities,
+                values,
+            } => {
+                if sparse.len() <= index {
+                    sparse.resize(index + 1, None);
+                }
+                match sparse[index] {
+                    Some(packed) => values[packed] = value,
+                    None => {
+                        sparse[index] = Some(values.len());
+                        entities.push(entity);
+                        values.push(value);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        let index = entity as usize;
+        match self {
+            ComponentStorage::Dense { slots, .. } => slots.get(index)?.as_ref(),
+            ComponentStorage::Sparse { sparse, values, .. } => {
+                sparse.get(index).copied().flatten().map(|p| &values[p])
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        let index = entity as usize;
+        match self {
+            ComponentStorage::Dense { slots, .. } => slots.get_mut(index)?.as_mut(),
+            ComponentStorage::Sparse { sparse, values, .. } => {
+                sparse.get(index).copied().flatten().map(|p| &mut values[p])
+            }
+        }
+    }
+
+    /// Make room for `additional` more components on entities whose IDs are
+    /// below `entity_count`, so inserting them does not reallocate.
+    pub fn reserve(&mut self, entity_count: usize, additional: usize) {
+        match self {
+            ComponentStorage::Dense { slots, .. } => {
+                if slots.len() < entity_count {
+                    slots.resize_with(entity_count, || None);
+                }
+            }
+            ComponentStorage::Sparse {
+                sparse,
+                entities,
+                values,
+            } => {
+                if sparse.len() < entity_count {
+                    sparse.resize(entity_count, None);
+                }
+                entities.reserve(additional);
+                values.reserve(additional);
+            }
+        }
+    }
+
+    /// Remove the component of `entity`, returning it.
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let index = entity as usize;
+        match self {
+            ComponentStorage::Dense { slots, len } => {
+                let value = slots.get_mut(index)?.take();
+                if value.is_some() {
+                    *len -= 1;
+                }
+                value
+            }
+            ComponentStorage::Sparse {
+                sparse,
+                entities,
+                values,
+            } => {
+                let packed = sparse.get_mut(index)?.take()?;
+                // Fill the hole with the last element to keep the vectors
+                // packed, then fix up the index of the element that moved.
+                entities.swap_remove(packed);
+                let value = values.swap_remove(packed);
+                if let Some(&moved) = entities.get(packed) {
+                    sparse[moved as usize] = Some(packed);
+                }
+                Some(value)
+            }
+        }
+    }
+
+    /// Iterate over `(Entity, &T)` pairs. Dense stores yield entities in
+    /// ascending order; sparse stores in packed order.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (Entity, &T)> + '_> {
+        match self {
+            ComponentStorage::Dense { slots, .. } => Box::new(
+                slots
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, slot)| slot.as_ref().map(|v| (i as Entity, v))),
+            ),
+            ComponentStorage::Sparse {
+                entities, values, ..
+            } => Box::new(entities.iter().copied().zip(values.iter())),
+        }
+    }
+
+    /// Iterate over `(Entity, &mut T)` pairs in the same order as `iter`.
+    pub fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (Entity, &mut T)> + '_> {
+        match self {
+            ComponentStorage::Dense { slots, .. } => Box::new(
+                slots
+                    .iter_mut()
+                    .enumerate()
+                    .filter_map(|(i, slot)| slot.as_mut().map(|v| (i as Entity, v))),
+            ),
+            ComponentStorage::Sparse {
+                entities, values, ..
+            } => Box::new(entities.iter().copied().zip(values.iter_mut())),
+        }
+    }
+
+    /// Move every component into a store of a different kind.
+    pub fn convert(self, kind: StorageKind) -> Self {
+        let mut converted = ComponentStorage::new(kind);
+        let pairs: Vec<(Entity, T)> = match self {
+            ComponentStorage::Dense { slots, .. } => slots
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, slot)| slot.map(|v| (i as Entity, v)))
+                .collect(),
+            ComponentStorage::Sparse {
+                entities, values, ..
+            } => entities.into_iter().zip(values).collect(),
+        };
+        for (entity, value) in pairs {
+            converted.insert(entity, value);
+        }
+        converted
+    }
+}
+
+impl<T: 'static> AnyStorage for ComponentStorage<T> {
+    fn kind(&self) -> StorageKind {
+        match self {
+            ComponentStorage::Dense { .. } => StorageKind::Dense,
+            ComponentStorage::Sparse { .. } => StorageKind::Sparse,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            ComponentStorage::Dense { len, .. } => *len,
+            ComponentStorage::Sparse { values, .. } => values.len(),
+        }
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.iter().map(|(entity, _)| entity).collect()
+    }
+
+    fn usage(&self) -> StoreUsage {
+        let bytes = match self {
+            ComponentStorage::Dense { slots, .. } => slots.capacity() * size_of::<Option<T>>(),
+            ComponentStorage::Sparse {
+                sparse,
+                entities,
+                values,
+            } => {
+                sparse.capacity() * size_of::<Option<usize>>()
+                    + entities.capacity() * size_of::<Entity>()
+                    + values.capacity() * size_of::<T>()
+            }
+        };
+        StoreUsage {
+            component: std::any::type_name::<T>(),
+            kind: self.kind(),
+            len: self.len(),
+            bytes,
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        match self {
+            ComponentStorage::Dense { slots, .. } => {
+                // Slots past the last occupied one only cost memory.
+                while slots.last().is_some_and(Option::is_none) {
+                    slots.pop();
+                }
+                slots.shrink_to_fit();
+            }
+            ComponentStorage::Sparse {
+                sparse,
+                entities,
+                values,
+            } => {
+                while sparse.last().is_some_and(Option::is_none) {
+                    sparse.pop();
+                }
+                sparse.shrink_to_fit();
+                entities.shrink_to_fit();
+                values.shrink_to_fit();
+            }
+        }
+    }
+
+    fn raw(&mut self) -> RawStore {
+        // `as_mut_ptr` and `as_ptr` make no reference to the elements, so
+        // the pointers do not invalidate components handed out before.
+        match self {
+            ComponentStorage::Dense { slots, .. } => RawStore {
+                values: slots.as_mut_ptr().cast(),
+                sparse: std::ptr::null(),
+                len: slots.len(),
+            },
+            ComponentStorage::Sparse { sparse, values, .. } => RawStore {
+                values: values.as_mut_ptr().cast(),
+                sparse: sparse.as_ptr(),
+                len: sparse.len(),
+            },
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// Quick reactions between the players of a session.
+//
+// Instead of typing in the chat, players can send one of a few predefined
+// `Emote`s, such as a wave or a "good game". They travel as
+// `protocol::NetMessage::Emote` on the chat channel, under their own rate
+// limit, and the server fills in who sent them.
+//
+// A received emote pops up over the table for a moment as an `EmoteBubble`
+// entity, in the top-right corner, stacked below the ones still showing. It
+// drifts up and fades out before `emote_system` retires it, and the
+// renderers draw what `visible` returns over everything but particles.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{Entity, World};
+use crate::layout::CanvasSize;
+
+/// How long an emote shows, in milliseconds.
+pub const EMOTE_MS: f64 = 2_500.0;
+/// Bubbles stacked before the newest goes back to the top.
+const SLOTS: u32 = 4;
+/// Distance from the top-right corner of the table, and between bubbles.
+const SPACING: f32 = 48.0;
+/// How far a bubble drifts up while it shows.
+const DRIFT: f32 = 24.0;
+
+/// The emotes players can send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Emote {
+    Wave,
+    ThumbsUp,
+    Laugh,
+    Wow,
+    Oops,
+    GoodGame,
+}
+
+impl Emote {
+    pub const ALL: [Emote; 6] = [
+        Emote::Wave,
+        Emote::ThumbsUp,
+        Emote::Laugh,
+        Emote::Wow,
+        Emote::Oops,
+        Emote::GoodGame,
+    ];
+
+    /// The emote with `id`, such as `"thumbs_up"`.
+    pub fn from_id(id: &str) -> Option<Emote> {
+        serde_json::from_value(serde_json::Value::from(id)).ok()
+    }
+
+    /// What renderers draw for the emote.
+    pub fn glyph(self) -> &'static str {
+        match self {
+            Emote::Wave => "👋",
+            Emote::ThumbsUp => "👍",
+            Emote::Laugh => "😂",
+            Emote::Wow => "😮",
+            Emote::Oops => "😅",
+            Emote::GoodGame => "GG",
+        }
+    }
+}
+
+/// Component of an emote showing over the table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmoteBubble {
+    pub from: String,
+    pub emote: Emote,
+    /// Where it is stacked, from the top.
+    slot: u32,
+    from_ms: f64,
+}
+
+/// An emote as it should be painted: `glyph` centred on `x` and `y`, in CSS
+/// pixels, at `opacity`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmoteView {
+    pub from: String,
+    pub emote: Emote,
+    pub glyph: String,
+    pub x: f32,
+    pub y: f32,
+    pub opacity: f32,
+}
+
+/// Show `emote` from `from`, as received at `now_ms`.
+pub fn show(world: &mut World, from: String, emote: Emote, now_ms: f64) {
+    let showing = world.iter::<EmoteBubble>().count() as u32;
+    let entity = world.spawn();
+    world.add_component(
+        entity,
+        EmoteBubble {
+            from,
+            emote,
+            slot: showing % SLOTS,
+            from_ms: now_ms,
+        },
+    );
+}
+
+/// Retire the emotes that have shown long enough.
+pub fn emote_system(world: &mut World, now_ms: f64) {
+    let expired: Vec<Entity> = world
+        .iter::<EmoteBubble>()
+        .filter(|(_, bubble)| now_ms >= bubble.from_ms + EMOTE_MS)
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in expired {
+        world.remove_component::<EmoteBubble>(entity);
+    }
+}
+
+/// The emotes showing at `now_ms`, oldest first.
+pub fn visible(world: &World, now_ms: f64) -> Vec<EmoteView> {
+    let canvas = world.resource::<CanvasSize>().copied().unwrap_or_default();
+    world
+        .iter::<EmoteBubble>()
+        .filter(|(_, bubble)| (bubble.from_ms..bubble.from_ms + EMOTE_MS).contains(&now_ms))
+        .map(|(_, bubble)| {
+            let t = ((now_ms - bubble.from_ms) / EMOTE_MS) as f32;
+            EmoteView {
+                from: bubble.from.clone(),
+                emote: bubble.emote,
+                glyph: bubble.emote.glyph().to_string(),
+                x: canvas.width - SPACING,
+                y: SPACING * (bubble.slot + 1) as f32 - DRIFT * t,
+                // Fully shown for the first two thirds, then fading out.
+                opacity: (3.0 * (1.0 - t)).min(1.0),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emotes_stack_drift_and_go_away() {
+        assert_eq!(Emote::from_id("thumbs_up"), Some(Emote::ThumbsUp));
+        assert_eq!(Emote::from_id("shrug"), None);
+
+        let mut world = World::new();
+        show(&mut world, "ann".into(), Emote::Wave, 0.0);
+        show(&mut world, "bo".into(), Emote::GoodGame, 500.0);
+        let shown = visible(&world, 500.0);
+        assert_eq!(shown.len(), 2);
+        assert!(shown[1].y > shown[0].y);
+        assert_eq!(shown[1].glyph, "GG");
+
+        let later = visible(&world, 2_400.0);
+        assert!(later[0].opacity < 0.5 && later[1].opacity > later[0].opacity);
+        assert!(later[1].y < shown[1].y);
+
+        emote_system(&mut world, EMOTE_MS);
+        assert_eq!(world.iter::<EmoteBubble>().count(), 1);
+        emote_system(&mut world, 10_000.0);
+        assert!(visible(&world, 10_000.0).is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+//! Which servers the game may connect to.
+//!
+//! `connect` takes its WebSocket URL straight from the page, and a typo or a
+//! stale setting there used to surface as whatever the browser throws from
+//! `new WebSocket`, if it throws at all. The URL is now checked first
+//! against a `ServerPolicy`, and anything wrong with it comes back as a typed
+//! `UrlError` naming the problem:
+//!
+//! * the URL must be `ws://` or `wss://`, with a host and nothing else
+//!   that a WebSocket URL cannot hold, such as credentials or a fragment.
+//!   A host name holds letters, digits, dots and dashes only, an IPv6
+//!   address hex digits and colons. Browsers end the host at a `\`, and
+//!   may decode `%2F` into a `/`, so with anything more the socket could
+//!   open to another host than the one the policy checked;
+//! * with `secure_only`, plain `ws://` is refused;
+//! * with a list of `allowed_hosts`, the host must be on it. An entry is
+//!   either a host name, matched exactly, or `*.` and a domain, which
+//!   matches any host below that domain but not the domain itself.
+//!
+//! The default policy lets any well-formed URL through, as before; the
+//! embedder tightens it with `set_server_policy`.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// What is wrong with a server URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlError {
+    /// The text is not a URL with a host. The message says what is off.
+    Malformed(String),
+    /// The URL is not a WebSocket URL; the scheme it has instead.
+    Scheme(String),
+    /// `ws://` while the policy only allows `wss://`.
+    Insecure,
+    /// The host is not on the policy's list.
+    HostNotAllowed(String),
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::Malformed(reason) => write!(f, "not a server URL: {reason}"),
+            UrlError::Scheme(scheme) => {
+                write!(f, "server URLs start with ws:// or wss://, not {scheme}://")
+            }
+            UrlError::Insecure => write!(f, "only secure (wss://) servers are allowed"),
+            UrlError::HostNotAllowed(host) => write!(f, "the server {host} is not allowed"),
+        }
+    }
+}
+
+/// A checked server URL, split into the parts the policy looks at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerUrl {
+    pub secure: bool,
+    /// The host, lowercased, without brackets around an IPv6 address.
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl ServerUrl {
+    pub fn parse(url: &str) -> Result<Self, UrlError> {
+        let malformed = |reason: &str| UrlError::Malformed(reason.to_string());
+        if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(malformed("it contains spaces or control characters"));
+        }
+        let (scheme, rest) = url
+            .split_once("://")
+            .ok_or_else(|| malformed("it has no scheme"))?;
+        let secure = match scheme.to_ascii_lowercase().as_str() {
+            "wss" => true,
+            "ws" => false,
+            _ => return Err(UrlError::Scheme(scheme.to_string())),
+        };
+        if rest.contains('#') {
+            return Err(malformed("WebSocket URLs cannot have a fragment"));
+        }
+        let authority = rest.split(['/', '?']).next().unwrap_or_default();
+        if authority.contains('@') {
+            return Err(malformed("credentials do not belong in the URL"));
+        }
+        let (host, port, ipv6) = match authority.strip_prefix('[') {
+            Some(ipv6) => {
+                let (host, after) = ipv6
+                    .split_once(']')
+                    .ok_or_else(|| malformed("an IPv6 address is missing its ]"))?;
+                match after.strip_prefix(':') {
+                    Some(port) => (host, Some(port), true),
+                    None if after.is_empty() => (host, None, true),
+                    None => {
+                        return Err(malformed(
+                            "something other than a port follows an IPv6 address",
+                        ));
+                    }
+                }
+            }
+            None => match authority.split_once(':') {
+                Some((host, port)) => (host, Some(port), false),
+                None => (authority, None, false),
+            },
+        };
+        if host.is_empty() {
+            return Err(malformed("it has no host"));
+        }
+        let allowed = |c: char| {
+            if ipv6 {
+                c.is_ascii_hexdigit() || c == ':' || c == '.'
+            } else {
+                c.is_ascii_alphanumeric() || c == '.' || c == '-'
+            }
+        };
+        if !host.chars().all(allowed) {
+            return Err(malformed("its host holds characters no host name has"));
+        }
+        let port = port
+            .map(|port| {
+                port.parse()
+                    .map_err(|_| malformed("its port is not a number"))
+            })
+            .transpose()?;
+        Ok(ServerUrl {
+            secure,
+            host: host.to_ascii_lowercase(),
+            port,
+        })
+    }
+}
+
+/// The servers `connect` accepts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerPolicy {
+    /// Refuse `ws://`.
+    pub secure_only: bool,
+    /// Hosts that may be connected to; any host when empty.
+    pub allowed_hosts: Vec<String>,
+}
+
+impl ServerPolicy {
+    /// Check `url` against the policy.
+    pub fn check(&self, url: &str) -> Result<ServerUrl, UrlError> {
+        let server = ServerUrl::parse(url)?;
+        if self.secure_only && !server.secure {
+            return Err(UrlError::Insecure);
+        }
+        if !self.allowed_hosts.is_empty()
+            && !self
+                .allowed_hosts
+                .iter()
+                .any(|allowed| host_matches(allowed, &server.host))
+        {
+            return Err(UrlError::HostNotAllowed(server.host));
+        }
+        Ok(server)
+    }
+}
+
+fn host_matches(allowed: &str, host: &str) -> bool {
+    let allowed = allowed.to_ascii_lowercase();
+    match allowed.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+        None => allowed == host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_websocket_urls_to_allowed_hosts_get_through() {
+        let any = ServerPolicy::default();
+        assert_eq!(
+            any.check("WSS://Play.Example.com:8443/ws?room=1"),
+            Ok(ServerUrl {
+                secure: true,
+                host: "play.example.com".to_string(),
+                port: Some(8443),
+            })
+        );
+        assert_eq!(any.check("ws://[::1]:9000").unwrap().host, "::1");
+        assert_eq!(
+            any.check("https://play.example.com"),
+            Err(UrlError::Scheme("https".to_string()))
+        );
+        for url in [
+            "play.example.com",
+            "ws://",
+            "ws://:80/",
+            "ws://host:port",
+            "ws://user:secret@host",
+            "wss://host/#top",
+            "wss://host /ws",
+            "ws://[::1",
+            "ws://[::1]80",
+            "wss://evil.net\\.example.com/ws",
+            "wss://evil.net%2F.example.com/ws",
+            "wss://evil.net%2f.example.com",
+            "ws://[::1%2F]",
+        ] {
+            assert!(
+                matches!(any.check(url), Err(UrlError::Malformed(_))),
+                "{url}"
+            );
+        }
+
+        let strict = ServerPolicy {
+            secure_only: true,
+            allowed_hosts: vec!["localhost".to_string(), "*.Example.com".to_string()],
+        };
+        assert_eq!(strict.check("ws://localhost"), Err(UrlError::Insecure));
+        assert!(strict.check("wss://localhost:8080").is_ok());
+        assert!(strict.check("wss://eu.play.example.com/ws").is_ok());
+        assert!(matches!(
+            strict.check("wss://evil.net\\.example.com/ws"),
+            Err(UrlError::Malformed(_))
+        ));
+        for host in ["example.com", "evilexample.com", "example.com.evil.net"] {
+            assert_eq!(
+                strict.check(&format!("wss://{host}")),
+                Err(UrlError::HostNotAllowed(host.to_string()))
+            );
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! The error type shared by the game's public API.
+//!
+//! Every fallible operation returns a `GameError` describing what went wrong.
+//! When the `wasm` feature is enabled the error converts into a JavaScript
+//! `Error`, so `Result<_, GameError>` methods throw a readable exception in
+//! the browser.
+
+use std::fmt;
+
+use crate::endpoint::UrlError;
+use crate::game::Pile;
+use crate::handshake::IncompatibleProtocol;
+
+/// Everything that can go wrong while playing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    /// A pile name sent from JavaScript could not be understood.
+    UnknownPile(String),
+    /// A variant name sent from JavaScript could not be understood.
+    UnknownVariant(String),
+    /// Game options or a deal code could not be read.
+    InvalidOptions(String),
+    /// There are not enough cards in the pile for the requested move.
+    NotEnoughCards { pile: Pile, requested: usize },
+    /// The move breaks the rules of the game. The message explains why.
+    IllegalMove(&'static str),
+    /// `undo` was called but no move has been made yet.
+    NothingToUndo,
+    /// The game is paused, so the board does not accept moves.
+    Paused,
+    /// The undo policy of this game does not allow undo at all.
+    UndoNotAllowed,
+    /// Every undo allowed by the undo policy has been used.
+    UndoLimitReached,
+    /// Mulligans are switched off, or this is a daily challenge.
+    MulliganNotAllowed,
+    /// Every mulligan of this session has been used.
+    NoMulligansLeft,
+    /// Only a won daily challenge has a result to submit.
+    NoDailyResult,
+    /// The deadline for submitting scores to the daily challenge has passed.
+    DailyClosed,
+    /// The countdown of a time attack has run out.
+    TimeUp,
+    /// The game was given up; deal a new one to play on.
+    Abandoned,
+    /// `abandon` was called with no game in progress.
+    NoGameInProgress,
+    /// The solver could not find a way to win from the current position.
+    NoSolution,
+    /// An invite can only be made from inside a room.
+    NotInRoom,
+    /// The text given is not an invite token or link.
+    InvalidInvite,
+    /// Tournament play needs a bracket to be joined first.
+    NotInBracket,
+    /// The current round of the bracket has not been paired yet, or its
+    /// result is in already.
+    NoPairing,
+    /// There is no saved game to load.
+    NoSavedGame,
+    /// Sending needs a connection to the server first.
+    NotConnected,
+    /// `connect` was called while a connection is open or opening.
+    AlreadyConnected,
+    /// The server URL given to `connect` is malformed or not allowed by the
+    /// server policy.
+    InvalidServerUrl(UrlError),
+    /// The browser refused to open the connection. The message is its own.
+    ConnectionFailed(String),
+    /// The server speaks no protocol this client does.
+    Incompatible(IncompatibleProtocol),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::UnknownPile(msg) => write!(f, "{msg}"),
+            GameError::UnknownVariant(msg) => write!(f, "{msg}"),
+            GameError::InvalidOptions(msg) => write!(f, "{msg}"),
+            GameError::NotEnoughCards { pile, requested } => {
+                write!(f, "{pile} does not hold {requested} movable card(s)")
+            }
+            GameError::IllegalMove(reason) => write!(f, "illegal move: {reason}"),
+            GameError::NothingToUndo => write!(f, "there is nothing to undo"),
+            GameError::Paused => write!(f, "the game is paused"),
+            GameError::UndoNotAllowed => write!(f, "undo is disabled in this game"),
+            GameError::UndoLimitReached => write!(f, "no undos left in this game"),
+            GameError::MulliganNotAllowed => write!(f, "this deal cannot be rejected"),
+            GameError::NoMulligansLeft => write!(f, "no mulligans left in this session"),
+            GameError::NoDailyResult => write!(f, "there is no won daily challenge to submit"),
+            GameError::DailyClosed => write!(f, "the daily challenge takes no more scores"),
+            GameError::TimeUp => write!(f, "time is up"),
+            GameError::Abandoned => write!(f, "the game was abandoned"),
+            GameError::NoGameInProgress => write!(f, "no game is in progress"),
+            GameError::NoSolution => write!(f, "no winning line was found from here"),
+            GameError::NotInRoom => write!(f, "join a room before inviting others to it"),
+            GameError::InvalidInvite => write!(f, "this is not an invite link"),
+            GameError::NotInBracket => write!(f, "join a tournament bracket first"),
+            GameError::NoPairing => write!(f, "there is no round to play in the bracket now"),
+            GameError::NoSavedGame => write!(f, "there is no saved game"),
+            GameError::NotConnected => write!(f, "not connected"),
+            GameError::AlreadyConnected => write!(f, "already connected; disconnect first"),
+            GameError::InvalidServerUrl(error) => write!(f, "{error}"),
+            GameError::ConnectionFailed(msg) => write!(f, "could not connect: {msg}"),
+            GameError::Incompatible(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+#[cfg(feature = "wasm")]
+impl From<GameError> for wasm_bindgen::JsValue {
+    fn from(err: GameError) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolitaireGame;
+
+    #[test]
+    fn bad_input_from_javascript_is_an_error_not_a_panic() {
+        let mut game = SolitaireGame::with_seed(3);
+        game.setup_board();
+        for (from, to, count) in [
+            ("tableau-6", "foundation-7", 1),
+            ("tableau-9", "tableau-0", 1),
+            ("tableau-0", "tableau-1", usize::MAX),
+            ("waste", "stock", 0),
+            ("tableau-", "", 1),
+        ] {
+            assert!(game.move_cards(from, to, count).is_err(), "{from} {to}");
+        }
+        assert!(matches!(
+            game.pick_up("tableau-3", usize::MAX),
+            Err(GameError::NotEnoughCards { .. })
+        ));
+        assert!(game.drop_on("foundation-7").is_err());
+        assert!(game.click("foundation-200").is_err());
+        assert_eq!(game.inspect_entity(u32::MAX), "{}");
+        for text in ["", "null", "{\"header\":1}", "deflate:@@"] {
+            assert!(game.load_replay(text).is_err());
+            assert!(game.import_stats_json(text).is_err());
+        }
+        assert!(SolitaireGame::new_game("\"klondike\"").is_err());
+        game.set_autoplay_settings(f64::NAN, true);
+        assert_eq!(game.advance(f64::INFINITY), 0);
+    }
+}
@@ -0,0 +1,144 @@
+//! Events describing what happened during play.
+//!
+//! The rules never call into the UI, the achievements engine or the network
+//! directly. Instead every move returns a list of `GameEvent`s and whoever
+//! is interested reacts to them. This keeps each subsystem independent and
+//! easy to test in isolation.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::Entity;
+use crate::game::Pile;
+use crate::rating::PlayerRating;
+use crate::scoring::ScoreReason;
+use crate::timeattack::TimeAttackResult;
+
+/// Something noteworthy that happened in the game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// A fresh board was dealt.
+    GameStarted { seed: u64 },
+    /// A card moved from one pile to another.
+    CardMoved { card: Entity, from: Pile, to: Pile },
+    /// A card was turned over.
+    CardFlipped { card: Entity, face_up: bool },
+    /// The waste was turned back over to form a new stock.
+    StockRecycled,
+    /// A foundation now holds all thirteen cards of its suit.
+    FoundationCompleted { foundation: u8 },
+    /// The last move was taken back.
+    UndoPerformed,
+    /// The game was paused. When `hide_cards` is set the renderer should
+    /// cover the board so the pause cannot be used to study it.
+    Paused { hide_cards: bool },
+    /// Play carries on after a pause.
+    Resumed,
+    /// The score changed by `amount` points for `reason`. `card` is the card
+    /// that earned them, for showing the points next to it.
+    ScoreDelta {
+        reason: ScoreReason,
+        amount: i32,
+        card: Option<Entity>,
+    },
+    /// A foundation play continued a streak of `streak` plays, multiplying
+    /// its points by `multiplier`.
+    Combo { streak: u32, multiplier: u32 },
+    /// A time attack is over, cleared or out of time, with
+    /// `foundation_cards` cards founded and `remaining_ms` left on the clock.
+    TimeAttackEnded {
+        result: TimeAttackResult,
+        foundation_cards: u32,
+        remaining_ms: u64,
+    },
+    /// No legal move is left that could get anywhere: the UI should offer
+    /// a new game.
+    NoMovesLeft,
+    /// Every card reached the foundations after `moves` moves and `time_ms`
+    /// of play, with `undos` moves taken back along the way. `time_bonus`
+    /// points were added to the score for speed.
+    GameWon {
+        moves: u32,
+        undos: u32,
+        time_ms: u64,
+        time_bonus: u32,
+    },
+    /// The player gave the game up after `moves` moves and `time_ms` of
+    /// play.
+    GameAbandoned { moves: u32, time_ms: u64 },
+    /// The host of the room left and `to` took over from `from`.
+    HostMigrated { from: String, to: String },
+    /// The server confirmed the player's ranked rating, `change` points
+    /// away from the one known before.
+    RatingChanged { rating: PlayerRating, change: f64 },
+    /// The server let this client into the tournament `bracket`.
+    BracketJoined { bracket: String },
+    /// A round of the bracket was paired: deal it with `start_round` and
+    /// play `opponent`, or nobody on a bye.
+    RoundPaired {
+        round: u32,
+        opponent: Option<String>,
+    },
+    /// The standing of the bracket changed; `champion` is set once it is
+    /// over.
+    StandingUpdated {
+        round: u32,
+        champion: Option<String>,
+    },
+}
+
+/// Events held back to be handed to JavaScript together, once a frame.
+///
+/// Calling into JavaScript costs far more than raising an event, and a deal
+/// or an auto-complete raises hundreds of them within one frame. Queued
+/// here, they cross the boundary as a single array instead.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventBatch {
+    events: Vec<GameEvent>,
+}
+
+impl EventBatch {
+    pub fn push(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The queued events as one JSON array, oldest first, emptying the
+    /// batch. `None` when nothing was queued, so quiet frames cost nothing.
+    pub fn take_json(&mut self) -> Option<String> {
+        if self.events.is_empty() {
+            return None;
+        }
+        let json = serde_json::to_string(&self.events).expect("events always serialize");
+        self.events.clear();
+        Some(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_go_out_as_one_array_and_start_over() {
+        let mut batch = EventBatch::default();
+        assert_eq!(batch.take_json(), None);
+        batch.push(GameEvent::GameStarted { seed: 7 });
+        batch.push(GameEvent::StockRecycled);
+        assert_eq!(batch.len(), 2);
+        let json = batch.take_json().unwrap();
+        let events: Vec<GameEvent> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            events,
+            [GameEvent::GameStarted { seed: 7 }, GameEvent::StockRecycled]
+        );
+        assert!(batch.is_empty());
+        assert_eq!(batch.take_json(), None);
+    }
+}
@@ -0,0 +1,479 @@
+// Card faces drawn in code, as SVG.
+//
+// The game needs no image files to be playable: `card_svg` draws any card
+// from scratch, with rank and suit in two corners, the pips laid out as on a
+// printed deck and, for the court cards, a framed placeholder with a large
+// letter. The suit glyphs are paths rather than text, so the faces look the
+// same whatever fonts are installed. For players who cannot tell red from
+// black easily, `SuitStyle` can give every suit a colour of its own, as in
+// four-colour decks, and put a lettered badge under each corner index.
+// `back_svg` draws the back in one of the
+// `CardBack` designs, or frames an image of the player's choosing.
+//
+// Renderers load their faces through the atlas: one entry per card plus the
+// back, each either generated SVG or the URL of an image. Generated faces are
+// simply the default source; a theme with artwork of its own switches the
+// atlas to image URLs instead. Such artwork brings its own back, which shows
+// unless the theme picks another design.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::game::{Card, Deck, Rank, Suit};
+
+/// Size of a face, in SVG user units. Renderers scale it to the card size.
+pub(crate) const WIDTH: f32 = 100.0;
+pub(crate) const HEIGHT: f32 = 140.0;
+
+const RED: &str = "#c0262d";
+const BLACK: &str = "#1a1a1a";
+const BLUE: &str = "#1f5fbf";
+const GREEN: &str = "#1e7b34";
+
+/// Resource with how suits are told apart on the faces. Without it, hearts
+/// and diamonds are red, clubs and spades black.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SuitStyle {
+    /// Diamonds blue and clubs green.
+    pub four_color: bool,
+    /// A badge with the suit's initial under each corner index.
+    pub badges: bool,
+}
+
+impl SuitStyle {
+    /// The colour `suit` is drawn in.
+    pub fn color(self, suit: Suit) -> &'static str {
+        match suit {
+            Suit::Diamonds if self.four_color => BLUE,
+            Suit::Clubs if self.four_color => GREEN,
+            suit if suit.is_red() => RED,
+            _ => BLACK,
+        }
+    }
+
+    /// The name of that colour, for text descriptions.
+    pub fn color_name(self, suit: Suit) -> &'static str {
+        match self.color(suit) {
+            RED => "red",
+            BLUE => "blue",
+            GREEN => "green",
+            _ => "black",
+        }
+    }
+}
+
+/// The suit style of `world`.
+pub fn suit_style(world: &World) -> SuitStyle {
+    world.resource::<SuitStyle>().copied().unwrap_or_default()
+}
+
+/// Where the atlas takes its faces from.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FaceSource {
+    /// Draw every face with `card_svg`.
+    #[default]
+    Generated,
+    /// Load `{base_url}/{key}.png` for every atlas key.
+    Images { base_url: String },
+}
+
+/// The design on the back of every card.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "design", rename_all = "snake_case")]
+pub enum CardBack {
+    /// Blue with a white lattice.
+    #[default]
+    Lattice,
+    /// Red with diagonal stripes.
+    Stripes,
+    /// Green with white dots.
+    Dots,
+    /// Plain blue.
+    Plain,
+    /// An image, such as a photo, framed like the other designs.
+    Image { url: String },
+}
+
+/// The image for one atlas entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum FaceImage {
+    Svg { svg: String },
+    Url { url: String },
+}
+
+/// One face in the atlas. `key` is `"back"` or names the card, as in
+/// `"hearts-7"` or `"spades-king"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasEntry {
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub card: Option<Card>,
+    #[serde(flatten)]
+    pub image: FaceImage,
+}
+
+/// Every face a renderer needs: the 52 cards, then the back.
+pub fn atlas(source: &FaceSource, back: &CardBack, suits: SuitStyle) -> Vec<AtlasEntry> {
+    let image = |key: &str, svg: &dyn Fn() -> String| match source {
+        FaceSource::Generated => FaceImage::Svg { svg: svg() },
+        FaceSource::Images { base_url } => FaceImage::Url {
+            url: format!("{}/{key}.png", base_url.trim_end_matches('/')),
+        },
+    };
+    let mut entries: Vec<AtlasEntry> = Deck::standard()
+        .cards
+        .into_iter()
+        .map(|card| {
+            let key = card_key(card);
+            AtlasEntry {
+                image: image(&key, &|| card_svg(card, suits)),
+                key,
+                card: Some(card),
+            }
+        })
+        .collect();
+    let back_image = match (source, back) {
+        (FaceSource::Images { .. }, CardBack::Lattice) => image("back", &|| back_svg(back)),
+        _ => FaceImage::Svg {
+            svg: back_svg(back),
+        },
+    };
+    entries.push(AtlasEntry {
+        image: back_image,
+        key: "back".to_string(),
+        card: None,
+    });
+    entries
+}
+
+/// The atlas key of a card.
+pub fn card_key(card: Card) -> String {
+    let suit = match card.suit {
+        Suit::Clubs => "clubs",
+        Suit::Diamonds => "diamonds",
+        Suit::Hearts => "hearts",
+        Suit::Spades => "spades",
+    };
+    let rank = match card.rank {
+        Rank::Ace => "ace".to_string(),
+        Rank::Jack => "jack".to_string(),
+        Rank::Queen => "queen".to_string(),
+        Rank::King => "king".to_string(),
+        rank => rank.value().to_string(),
+    };
+    format!("{suit}-{rank}")
+}
+
+/// The face of `card` as a standalone SVG document.
+pub fn card_svg(card: Card, suits: SuitStyle) -> String {
+    format!("{}{}</svg>", document_start(), card_content(card, suits))
+}
+
+/// The back shared by every card, as a standalone SVG document.
+pub fn back_svg(back: &CardBack) -> String {
+    format!(
+        "{}{}{}</svg>",
+        document_start(),
+        back_defs(back),
+        back_content(back)
+    )
+}
+
+/// The elements drawing `card`'s face, `WIDTH` by `HEIGHT` units from the
+/// origin, for embedding in a larger drawing.
+pub(crate) fn card_content(card: Card, suits: SuitStyle) -> String {
+    let colour = suits.color(card.suit);
+    let mut svg = String::new();
+    svg.push_str(&format!(
+        r##"<rect x="0.5" y="0.5" width="{}" height="{}" rx="8" fill="#fff" stroke="#999"/>"##,
+        WIDTH - 1.0,
+        HEIGHT - 1.0
+    ));
+    svg.push_str(&format!(r#"<g fill="{colour}">"#));
+
+    // Corner indices, the bottom one turned upside down.
+    let label = rank_label(card.rank);
+    let badge = if suits.badges {
+        format!(
+            r##"<rect x="3" y="38" width="14" height="13" rx="3"/><text x="10" y="48.5" font-family="sans-serif" font-size="10" font-weight="bold" text-anchor="middle" fill="#fff">{}</text>"##,
+            suit_initial(card.suit)
+        )
+    } else {
+        String::new()
+    };
+    for transform in [
+        "",
+        &format!(
+            " transform=\"rotate(180 {} {})\"",
+            WIDTH / 2.0,
+            HEIGHT / 2.0
+        ),
+    ] {
+        svg.push_str(&format!(
+            r#"<g{transform}><text x="10" y="20" font-family="sans-serif" font-size="16" font-weight="bold" text-anchor="middle">{label}</text>{}{badge}</g>"#,
+            glyph(card.suit, 10.0, 30.0, 5.0, false)
+        ));
+    }
+
+    match card.rank {
+        Rank::Jack | Rank::Queen | Rank::King => {
+            svg.push_str(&format!(
+                r#"<rect x="22" y="22" width="56" height="96" rx="4" fill="none" stroke="{colour}" stroke-width="2"/>"#
+            ));
+            svg.push_str(&format!(
+                r#"<text x="50" y="84" font-family="serif" font-size="44" text-anchor="middle">{label}</text>"#
+            ));
+            svg.push_str(&glyph(card.suit, 34.0, 34.0, 7.0, false));
+            svg.push_str(&glyph(card.suit, 66.0, 106.0, 7.0, true));
+        }
+        Rank::Ace => svg.push_str(&glyph(card.suit, 50.0, 70.0, 18.0, false)),
+        rank => {
+            for &(x, y) in pips(rank) {
+                svg.push_str(&glyph(card.suit, x, y, 8.0, y > HEIGHT / 2.0));
+            }
+        }
+    }
+    svg.push_str("</g>");
+    svg
+}
+
+/// Definitions used by `back_content`, to include once per drawing.
+pub(crate) fn back_defs(back: &CardBack) -> String {
+    let pattern = match back {
+        CardBack::Lattice => {
+            r##"<pattern id="lattice" width="10" height="10" patternUnits="userSpaceOnUse"><path d="M0,0 L10,10 M10,0 L0,10" stroke="#6f8fd0" stroke-width="1.5"/></pattern>"##
+        }
+        CardBack::Stripes => {
+            r##"<pattern id="stripes" width="8" height="8" patternUnits="userSpaceOnUse" patternTransform="rotate(45)"><rect width="4" height="8" fill="#d8545a"/></pattern>"##
+        }
+        CardBack::Dots => {
+            r##"<pattern id="dots" width="10" height="10" patternUnits="userSpaceOnUse"><circle cx="5" cy="5" r="1.8" fill="#e8f3ea"/></pattern>"##
+        }
+        CardBack::Plain => "",
+        CardBack::Image { .. } => {
+            r#"<clipPath id="back-image"><rect x="7" y="7" width="86" height="126" rx="4"/></clipPath>"#
+        }
+    };
+    format!("<defs>{pattern}</defs>")
+}
+
+/// The elements drawing the back, like `card_content`.
+pub(crate) fn back_content(back: &CardBack) -> String {
+    let (w, h) = (WIDTH - 14.0, HEIGHT - 14.0);
+    let inset = |fill: &str| {
+        format!(r#"<rect x="7" y="7" width="{w}" height="{h}" rx="4" fill="{fill}"/>"#)
+    };
+    let design = match back {
+        CardBack::Lattice => inset("#2a4b9b") + &inset("url(#lattice)"),
+        CardBack::Stripes => inset("#9b2a30") + &inset("url(#stripes)"),
+        CardBack::Dots => inset("#2a7b4b") + &inset("url(#dots)"),
+        CardBack::Plain => inset("#2a4b9b"),
+        CardBack::Image { url } => format!(
+            r#"<image href="{}" x="7" y="7" width="{w}" height="{h}" preserveAspectRatio="xMidYMid slice" clip-path="url(#back-image)"/>"#,
+            escape_attribute(url)
+        ),
+    };
+    format!(
+        r##"<rect x="0.5" y="0.5" width="{}" height="{}" rx="8" fill="#fff" stroke="#999"/>{design}"##,
+        WIDTH - 1.0,
+        HEIGHT - 1.0,
+    )
+}
+
+/// `value` made safe to put in a double-quoted SVG attribute.
+pub(crate) fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+fn document_start() -> String {
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {WIDTH} {HEIGHT}" width="{WIDTH}" height="{HEIGHT}">"#
+    )
+}
+
+fn suit_initial(suit: Suit) -> char {
+    match suit {
+        Suit::Clubs => 'C',
+        Suit::Diamonds => 'D',
+        Suit::Hearts => 'H',
+        Suit::Spades => 'S',
+    }
+}
+
+fn rank_label(rank: Rank) -> String {
+    match rank {
+        Rank::Ace => "A".to_string(),
+        Rank::Jack => "J".to_string(),
+        Rank::Queen => "Q".to_string(),
+        Rank::King => "K".to_string(),
+        rank => rank.value().to_string(),
+    }
+}
+
+/// Where the pips of a number card go, as on a printed deck.
+fn pips(rank: Rank) -> &'static [(f32, f32)] {
+    match rank {
+        Rank::Two => &[(50.0, 25.0), (50.0, 115.0)],
+        Rank::Three => &[(50.0, 25.0), (50.0, 70.0), (50.0, 115.0)],
+        Rank::Four => &[(30.0, 25.0), (70.0, 25.0), (30.0, 115.0), (70.0, 115.0)],
+        Rank::Five => &[
+            (30.0, 25.0),
+            (70.0, 25.0),
+            (50.0, 70.0),
+            (30.0, 115.0),
+            (70.0, 115.0),
+        ],
+        Rank::Six => &[
+            (30.0, 25.0),
+            (70.0, 25.0),
+            (30.0, 70.0),
+            (70.0, 70.0),
+            (30.0, 115.0),
+            (70.0, 115.0),
+        ],
+        Rank::Seven => &[
+            (30.0, 25.0),
+            (70.0, 25.0),
+            (50.0, 47.5),
+            (30.0, 70.0),
+            (70.0, 70.0),
+            (30.0, 115.0),
+            (70.0, 115.0),
+        ],
+        Rank::Eight => &[
+            (30.0, 25.0),
+            (70.0, 25.0),
+            (50.0, 47.5),
+            (30.0, 70.0),
+            (70.0, 70.0),
+            (50.0, 92.5),
+            (30.0, 115.0),
+            (70.0, 115.0),
+        ],
+        Rank::Nine => &[
+            (30.0, 25.0),
+            (70.0, 25.0),
+            (30.0, 55.0),
+            (70.0, 55.0),
+            (50.0, 70.0),
+            (30.0, 85.0),
+            (70.0, 85.0),
+            (30.0, 115.0),
+            (70.0, 115.0),
+        ],
+        Rank::Ten => &[
+            (30.0, 25.0),
+            (70.0, 25.0),
+            (50.0, 40.0),
+            (30.0, 55.0),
+            (70.0, 55.0),
+            (30.0, 85.0),
+            (70.0, 85.0),
+            (50.0, 100.0),
+            (30.0, 115.0),
+            (70.0, 115.0),
+        ],
+        Rank::Ace | Rank::Jack | Rank::Queen | Rank::King => &[],
+    }
+}
+
+/// A suit glyph centred on `(x, y)`, `size` units from its centre to its
+/// top, optionally upside down.
+fn glyph(suit: Suit, x: f32, y: f32, size: f32, upside_down: bool) -> String {
+    let rotation = if upside_down { " rotate(180)" } else { "" };
+    format!(
+        r#"<path class="pip" transform="translate({x} {y}) scale({size}){rotation}" d="{}"/>"#,
+        glyph_path(suit)
+    )
+}
+
+/// Outline of a suit glyph, in a box from -1 to 1 either way.
+fn glyph_path(suit: Suit) -> &'static str {
+    match suit {
+        Suit::Diamonds => "M0,-1 L0.75,0 L0,1 L-0.75,0 Z",
+        Suit::Hearts => {
+            "M0,1 C-0.2,0.7 -1,0.2 -1,-0.35 C-1,-0.8 -0.45,-1.05 0,-0.55 \
+             C0.45,-1.05 1,-0.8 1,-0.35 C1,0.2 0.2,0.7 0,1 Z"
+        }
+        Suit::Spades => {
+            "M0,-1 C-0.2,-0.7 -1,-0.2 -1,0.3 C-1,0.75 -0.45,0.95 -0.08,0.55 \
+             L-0.3,1 L0.3,1 L0.08,0.55 C0.45,0.95 1,0.75 1,0.3 C1,-0.2 0.2,-0.7 0,-1 Z"
+        }
+        Suit::Clubs => {
+            "M-0.38,-0.5 a0.38,0.38 0 1,0 0.76,0 a0.38,0.38 0 1,0 -0.76,0 Z \
+             M-0.88,0.2 a0.38,0.38 0 1,0 0.76,0 a0.38,0.38 0 1,0 -0.76,0 Z \
+             M0.12,0.2 a0.38,0.38 0 1,0 0.76,0 a0.38,0.38 0 1,0 -0.76,0 Z \
+             M-0.1,0.1 L-0.3,1 L0.3,1 L0.1,0.1 Z"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_are_generated_unless_images_are_configured() {
+        let seven = card_svg(Card::new(Suit::Hearts, Rank::Seven), SuitStyle::default());
+        assert!(seven.starts_with("<svg") && seven.ends_with("</svg>"));
+        assert!(seven.contains(RED));
+        // Seven pips and the two corner glyphs.
+        assert_eq!(seven.matches(r#"class="pip""#).count(), 9);
+
+        let generated = atlas(
+            &FaceSource::Generated,
+            &CardBack::default(),
+            SuitStyle::default(),
+        );
+        assert_eq!(generated.len(), 53);
+        assert!(
+            generated
+                .iter()
+                .all(|e| matches!(&e.image, FaceImage::Svg { svg } if svg.starts_with("<svg")))
+        );
+
+        let source = FaceSource::Images {
+            base_url: "/cards/".to_string(),
+        };
+        let images = atlas(&source, &CardBack::default(), SuitStyle::default());
+        assert_eq!(
+            images[0].image,
+            FaceImage::Url {
+                url: "/cards/clubs-ace.png".to_string()
+            }
+        );
+        assert_eq!(images[52].key, "back");
+        assert_eq!(
+            images[52].image,
+            FaceImage::Url {
+                url: "/cards/back.png".to_string()
+            }
+        );
+
+        // A back picked by the theme shows on every deck.
+        let striped = atlas(&source, &CardBack::Stripes, SuitStyle::default());
+        assert!(
+            matches!(&striped[52].image, FaceImage::Svg { svg } if svg.contains("url(#stripes)"))
+        );
+        let photo = back_svg(&CardBack::Image {
+            url: "/me.jpg?a=1&b=2".to_string(),
+        });
+        assert!(photo.contains(r#"href="/me.jpg?a=1&amp;b=2""#));
+
+        let accessible = SuitStyle {
+            four_color: true,
+            badges: true,
+        };
+        let diamonds = card_svg(Card::new(Suit::Diamonds, Rank::Two), accessible);
+        assert!(diamonds.contains(BLUE) && !diamonds.contains(RED));
+        assert_eq!(diamonds.matches(">D</text>").count(), 2);
+        assert_eq!(accessible.color_name(Suit::Clubs), "green");
+        assert_eq!(SuitStyle::default().color_name(Suit::Clubs), "black");
+    }
+}
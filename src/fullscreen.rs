@@ -0,0 +1,78 @@
+//! Fullscreen play, through the browser's Fullscreen API.
+//!
+//! `Fullscreen::request` puts an element, usually the canvas or its
+//! container, into fullscreen mode and listens for `fullscreenchange`. Every
+//! change, entering or leaving, is remembered with the element's new size;
+//! the game picks it up with `take_resize` before its next layout and fits
+//! the table to it, so the host page needs no resize glue of its own.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::{JsCast, closure::Closure};
+use web_sys::{Document, Element};
+
+/// An element that was put into fullscreen mode, and the size it changed to.
+pub struct Fullscreen {
+    document: Document,
+    pending: Rc<Cell<Option<(f32, f32)>>>,
+    // Kept alive for as long as the listener is registered.
+    on_change: Closure<dyn FnMut()>,
+}
+
+impl Fullscreen {
+    /// Ask the browser to show `element` fullscreen. Browsers only allow
+    /// this in response to a user gesture such as a click.
+    pub fn request(element: &Element) -> Result<Fullscreen, JsValue> {
+        let document = element
+            .owner_document()
+            .ok_or_else(|| JsValue::from_str("No document"))?;
+        let pending: Rc<Cell<Option<(f32, f32)>>> = Rc::default();
+        let on_change = {
+            let pending = Rc::clone(&pending);
+            let element = element.clone();
+            Closure::<dyn FnMut()>::new(move || {
+                pending.set(Some((
+                    element.client_width() as f32,
+                    element.client_height() as f32,
+                )));
+            })
+        };
+        element.request_fullscreen()?;
+        document.add_event_listener_with_callback(
+            "fullscreenchange",
+            on_change.as_ref().unchecked_ref(),
+        )?;
+        Ok(Fullscreen {
+            document,
+            pending,
+            on_change,
+        })
+    }
+
+    /// Leave fullscreen mode, if the page is in it.
+    pub fn exit(&self) {
+        if self.is_active() {
+            self.document.exit_fullscreen();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.document.fullscreen_element().is_some()
+    }
+
+    /// The size the element changed to since the last call, in CSS pixels.
+    pub fn take_resize(&self) -> Option<(f32, f32)> {
+        self.pending.take()
+    }
+}
+
+impl Drop for Fullscreen {
+    fn drop(&mut self) {
+        let _ = self.document.remove_event_listener_with_callback(
+            "fullscreenchange",
+            self.on_change.as_ref().unchecked_ref(),
+        );
+    }
+}
@@ -2,15 +2,29 @@
 // This file contains the core data types used to model the game state.
 // Everything is documented thoroughly so beginners can easily follow along.
 
+use std::fmt;
+use std::str::FromStr;
+
 // We import a few utilities from the `rand` crate to shuffle the deck.
+use rand::Rng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::ComponentRegistry;
+
+/// Number of tableau piles in Klondike.
+pub const TABLEAU_PILES: u8 = 7;
 
-use crate::ecs::Entity;
+/// Number of foundation piles, one per suit.
+pub const FOUNDATION_PILES: u8 = 4;
+
+/// The most tableau and foundation piles any variant uses (Double Klondike).
+pub const MAX_TABLEAU_PILES: u8 = 9;
+pub const MAX_FOUNDATION_PILES: u8 = 8;
 
 /// Represents the four suits found in a standard deck of cards.
 /// Using an enum ensures each suit is a distinct value at compile time.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Suit {
     Clubs,
     Diamonds,
@@ -18,9 +32,19 @@ pub enum Suit {
     Spades,
 }
 
+impl Suit {
+    /// Every suit, in the order of a fresh deck.
+    pub const ALL: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+    /// Diamonds and hearts are red, clubs and spades are black.
+    pub fn is_red(self) -> bool {
+        matches!(self, Suit::Diamonds | Suit::Hearts)
+    }
+}
+
 /// Values for playing cards, ranging from Ace to King.
 /// In solitaire we only need the rank information, so we use an enum here too.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Rank {
     Ace,
     Two,
@@ -37,8 +61,38 @@ pub enum Rank {
     King,
 }
 
+impl Rank {
+    /// Every rank from Ace to King.
+    pub const ALL: [Rank; 13] = [
+        Rank::Ace,
+        Rank::Two,
+        Rank::Three,
+        Rank::Four,
+        Rank::Five,
+        Rank::Six,
+        Rank::Seven,
+        Rank::Eight,
+        Rank::Nine,
+        Rank::Ten,
+        Rank::Jack,
+        Rank::Queen,
+        Rank::King,
+    ];
+
+    /// The numeric value of the rank: 1 for an Ace up to 13 for a King.
+    pub fn value(self) -> u8 {
+        self as u8 + 1
+    }
+
+    /// The next rank up, wrapping from King round to Ace. Foundations that
+    /// start on another rank than the Ace build this way.
+    pub fn next_wrapping(self) -> Rank {
+        Rank::ALL[(self as usize + 1) % Rank::ALL.len()]
+    }
+}
+
 /// A simple card made of a `Suit` and `Rank`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Card {
     pub suit: Suit,
     pub rank: Rank,
@@ -49,6 +103,80 @@ impl Card {
     pub fn new(suit: Suit, rank: Rank) -> Self {
         Self { suit, rank }
     }
+
+    /// The card's place in a fresh `Deck::standard`, from 0 for the Ace of
+    /// Clubs to 51 for the King of Spades.
+    pub fn to_index(self) -> u8 {
+        self.suit as u8 * 13 + self.rank as u8
+    }
+
+    /// The card with `Card::to_index` `index`, or `None` past 51.
+    pub fn from_index(index: u8) -> Option<Card> {
+        let suit = *Suit::ALL.get(usize::from(index / 13))?;
+        Some(Card::new(suit, Rank::ALL[usize::from(index % 13)]))
+    }
+}
+
+/// Serde helpers writing cards as their `Card::to_index`, for payloads that
+/// carry many of them: `#[serde(with = "card_index::vec")]`.
+pub mod card_index {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Card;
+
+    fn card<E: Error>(index: u8) -> Result<Card, E> {
+        Card::from_index(index).ok_or_else(|| E::custom(format!("no card has index {index}")))
+    }
+
+    /// `Option<Card>`, as an index or `null`.
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(card: &Option<Card>, s: S) -> Result<S::Ok, S::Error> {
+            match card {
+                Some(card) => s.serialize_some(&card.to_index()),
+                None => s.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Card>, D::Error> {
+            Option::<u8>::deserialize(d)?.map(card).transpose()
+        }
+    }
+
+    /// `Vec<Card>`, as an array of indices.
+    pub mod vec {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(cards: &[Card], s: S) -> Result<S::Ok, S::Error> {
+            s.collect_seq(cards.iter().map(|c| c.to_index()))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Card>, D::Error> {
+            Vec::<u8>::deserialize(d)?.into_iter().map(card).collect()
+        }
+    }
+
+    /// `Vec<Vec<Card>>`, as arrays of indices.
+    pub mod nested {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(piles: &[Vec<Card>], s: S) -> Result<S::Ok, S::Error> {
+            s.collect_seq(
+                piles
+                    .iter()
+                    .map(|pile| pile.iter().map(|c| c.to_index()).collect::<Vec<_>>()),
+            )
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Vec<Card>>, D::Error> {
+            Vec::<Vec<u8>>::deserialize(d)?
+                .into_iter()
+                .map(|pile| pile.into_iter().map(card).collect())
+                .collect()
+        }
+    }
 }
 
 /// A `Deck` is just a vector of cards.
@@ -61,36 +189,29 @@ pub struct Deck {
 impl Deck {
     /// Generate a full deck of 52 unique cards in order.
     pub fn standard() -> Self {
-        let mut cards = Vec::with_capacity(52);
-        for &suit in &[Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades] {
-            for &rank in &[
-                Rank::Ace,
-                Rank::Two,
-                Rank::Three,
-                Rank::Four,
-                Rank::Five,
-                Rank::Six,
-                Rank::Seven,
-                Rank::Eight,
-                Rank::Nine,
-                Rank::Ten,
-                Rank::Jack,
-                Rank::Queen,
-                Rank::King,
-            ] {
-                cards.push(Card::new(suit, rank));
-            }
+        Self {
+            cards: (0..52).filter_map(Card::from_index).collect(),
+        }
+    }
+
+    /// Several standard decks stacked one after the other, for games like
+    /// Double Klondike. Every card appears `decks` times.
+    pub fn with_decks(decks: u8) -> Self {
+        let single = Self::standard().cards;
+        let mut cards = Vec::with_capacity(single.len() * usize::from(decks));
+        for _ in 0..decks {
+            cards.extend_from_slice(&single);
         }
         Self { cards }
     }
 
-    /// Shuffle the deck using a random number generator.
+    /// Shuffle the deck using the given random number generator.
     ///
     /// We rely on the `rand` crate so that the shuffle works the same on
-    /// native and WASM targets.
-    pub fn shuffle(&mut self) {
-        let mut rng = thread_rng();
-        self.cards.shuffle(&mut rng);
+    /// native and WASM targets. Passing a seeded generator (see
+    /// `rng::GameRng`) makes the shuffle reproducible.
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.cards.shuffle(rng);
     }
 }
 
@@ -98,19 +219,120 @@ impl Deck {
 ///
 /// We keep this structure very small so it is easy to store as a component in
 /// the ECS `World`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Pile {
     /// The facedown stock pile that players draw cards from.
     Stock,
     /// The faceup waste pile where drawn cards go.
     Waste,
-    /// One of the four foundation piles where cards are stacked by suit.
+    /// One of the foundation piles where cards are stacked by suit: four in
+    /// Klondike, eight in Double Klondike.
     Foundation(u8),
-    /// One of the seven tableau piles used during play.
+    /// One of the tableau piles used during play: seven in Klondike, nine in
+    /// Double Klondike.
     Tableau(u8),
 }
 
+/// Piles are written as short lowercase names such as `"stock"`, `"waste"`,
+/// `"foundation-2"` or `"tableau-6"`. This is the form used by the JavaScript
+/// API, so the frontend never needs to know about Rust enums. Parsing
+/// accepts the piles of every variant; the rules reject piles that are not
+/// on the current table.
+impl fmt::Display for Pile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pile::Stock => write!(f, "stock"),
+            Pile::Waste => write!(f, "waste"),
+            Pile::Foundation(i) => write!(f, "foundation-{i}"),
+            Pile::Tableau(i) => write!(f, "tableau-{i}"),
+        }
+    }
+}
+
+impl FromStr for Pile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("unknown pile \"{s}\"");
+        let index = |prefix: &str, count: u8| -> Result<u8, String> {
+            let index: u8 = s[prefix.len()..].parse().map_err(|_| invalid())?;
+            if index < count {
+                Ok(index)
+            } else {
+                Err(invalid())
+            }
+        };
+        match s {
+            "stock" => Ok(Pile::Stock),
+            "waste" => Ok(Pile::Waste),
+            _ if s.starts_with("foundation-") => {
+                index("foundation-", MAX_FOUNDATION_PILES).map(Pile::Foundation)
+            }
+            _ if s.starts_with("tableau-") => {
+                index("tableau-", MAX_TABLEAU_PILES).map(Pile::Tableau)
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Component marking the entity that stands for a pile itself rather than a
+/// card in it. Pile entities are named after the pile (`"tableau-3"`), so
+/// they can be found with `World::find_by_name`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PileAnchor(pub Pile);
+
 /// Simple component used to mark whether a card is face up on the table.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FaceUp(pub bool);
 
+/// Component storing how far from the bottom of its pile a card sits.
+///
+/// The bottom card of every pile has index 0, so the card with the highest
+/// index is the one on top that the player can grab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PileIndex(pub usize);
+
+/// A registry of the card and pile components, named after their types, for
+/// tools that work with components by name.
+pub fn component_registry() -> ComponentRegistry {
+    let mut registry = ComponentRegistry::new();
+    registry
+        .register::<Card>("Card")
+        .register::<Pile>("Pile")
+        .register::<PileIndex>("PileIndex")
+        .register::<FaceUp>("FaceUp")
+        .register::<PileAnchor>("PileAnchor");
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn card_indices_follow_a_fresh_deck() {
+        let deck = Deck::standard().cards;
+        for (index, &card) in deck.iter().enumerate() {
+            assert_eq!(card.to_index(), index as u8);
+            assert_eq!(Card::from_index(index as u8), Some(card));
+        }
+        assert_eq!(Card::from_index(52), None);
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Piles {
+            #[serde(with = "card_index::vec")]
+            cards: Vec<Card>,
+            #[serde(with = "card_index::option")]
+            top: Option<Card>,
+        }
+        let piles = Piles {
+            cards: deck[..3].to_vec(),
+            top: deck.last().copied(),
+        };
+        let json = serde_json::to_string(&piles).unwrap();
+        assert_eq!(json, r#"{"cards":[0,1,2],"top":51}"#);
+        assert_eq!(serde_json::from_str::<Piles>(&json).unwrap(), piles);
+        assert!(serde_json::from_str::<Piles>(r#"{"cards":[52],"top":null}"#).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! Agreeing on a protocol with the server before anything else is said.
+//!
+//! The first message either way on a new connection is a `Hello`: the range
+//! of protocol versions the side speaks, its crate version, the encodings it
+//! can read and the optional features it supports. `negotiate` settles on
+//! the newest version both speak, the first encoding of the client's that
+//! the server can read, and the features both have. When the versions do not
+//! overlap, or there is no common encoding, the result is a typed
+//! `IncompatibleProtocol` error and the client stops talking on the
+//! connection at once, instead of failing later on messages it cannot read.
+//!
+//! Only JSON is implemented in this crate, so that is all the client offers;
+//! `Encoding::Binary` is there for the servers and clients that add it.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+
+/// The newest protocol version this crate speaks.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// The oldest protocol version this crate still speaks.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The optional parts of the protocol this crate supports.
+pub const FEATURES: &[&str] = &[
+    "coop",
+    "spectate",
+    "signed_moves",
+    "lobby",
+    "invites",
+    "resume",
+    "channels",
+];
+
+/// How messages are put into frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// JSON text frames.
+    Json,
+    /// Binary frames.
+    Binary,
+}
+
+/// What one side of a connection speaks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    pub min_protocol: u32,
+    pub protocol: u32,
+    pub crate_version: String,
+    /// The encodings the side can read, preferred first.
+    pub encodings: Vec<Encoding>,
+    pub features: Vec<String>,
+}
+
+impl Hello {
+    /// This crate's `Hello`.
+    pub fn ours() -> Self {
+        Hello {
+            min_protocol: MIN_PROTOCOL_VERSION,
+            protocol: PROTOCOL_VERSION,
+            crate_version: CRATE_VERSION.to_string(),
+            encodings: vec![Encoding::Json],
+            features: FEATURES.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+}
+
+/// What the two sides settled on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Agreement {
+    pub protocol: u32,
+    pub encoding: Encoding,
+    /// The features both sides support.
+    pub features: Vec<String>,
+    /// The crate version of the other side, for bug reports.
+    pub peer_version: String,
+}
+
+impl Agreement {
+    pub fn has(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+/// Why the two sides cannot talk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum IncompatibleProtocol {
+    /// No protocol version is spoken by both.
+    Version {
+        ours: (u32, u32),
+        theirs: (u32, u32),
+    },
+    /// Neither side can read an encoding the other writes.
+    Encoding,
+}
+
+impl fmt::Display for IncompatibleProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncompatibleProtocol::Version { ours, theirs } => write!(
+                f,
+                "incompatible protocol: we speak versions {} to {}, the server {} to {}",
+                ours.0, ours.1, theirs.0, theirs.1
+            ),
+            IncompatibleProtocol::Encoding => {
+                write!(f, "incompatible protocol: no common encoding")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IncompatibleProtocol {}
+
+#[cfg(feature = "wasm")]
+impl From<IncompatibleProtocol> for wasm_bindgen::JsValue {
+    fn from(err: IncompatibleProtocol) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
+/// Settle on what `ours` and `theirs` both speak. The encoding follows the
+/// preference of `ours`.
+pub fn negotiate(ours: &Hello, theirs: &Hello) -> Result<Agreement, IncompatibleProtocol> {
+    let protocol = ours.protocol.min(theirs.protocol);
+    if protocol < ours.min_protocol.max(theirs.min_protocol) {
+        return Err(IncompatibleProtocol::Version {
+            ours: (ours.min_protocol, ours.protocol),
+            theirs: (theirs.min_protocol, theirs.protocol),
+        });
+    }
+    let encoding = *ours
+        .encodings
+        .iter()
+        .find(|e| theirs.encodings.contains(e))
+        .ok_or(IncompatibleProtocol::Encoding)?;
+    Ok(Agreement {
+        protocol,
+        encoding,
+        features: ours
+            .features
+            .iter()
+            .filter(|f| theirs.features.contains(f))
+            .cloned()
+            .collect(),
+        peer_version: theirs.crate_version.clone(),
+    })
+}
+
+/// Resource with how the handshake on the current connection is going.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum Handshake {
+    /// Not connected, or the connection has not opened yet.
+    #[default]
+    Idle,
+    /// Our `Hello` is out; waiting for the server's.
+    Sent,
+    Agreed {
+        agreement: Agreement,
+    },
+    Incompatible {
+        error: IncompatibleProtocol,
+    },
+}
+
+/// A new connection was made: greet the server once it opens.
+pub fn connected(world: &mut World) {
+    world.insert_resource(Handshake::Idle);
+}
+
+/// Our `Hello`, if it is due on the connection.
+pub fn take_hello(world: &mut World) -> Option<Hello> {
+    match world.resource::<Handshake>() {
+        Some(Handshake::Idle) => {
+            world.insert_resource(Handshake::Sent);
+            Some(Hello::ours())
+        }
+        _ => None,
+    }
+}
+
+/// Settle the handshake with the server's `Hello`.
+pub fn receive(world: &mut World, theirs: &Hello) -> Result<Agreement, IncompatibleProtocol> {
+    let outcome = negotiate(&Hello::ours(), theirs);
+    world.insert_resource(match &outcome {
+        Ok(agreement) => Handshake::Agreed {
+            agreement: agreement.clone(),
+        },
+        Err(error) => Handshake::Incompatible {
+            error: error.clone(),
+        },
+    });
+    outcome
+}
+
+/// The reason the server cannot be talked to, once the handshake failed.
+pub fn incompatible(world: &World) -> Option<&IncompatibleProtocol> {
+    match world.resource::<Handshake>() {
+        Some(Handshake::Incompatible { error }) => Some(error),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hellos_settle_on_what_both_sides_speak() {
+        let mut world = World::new();
+        connected(&mut world);
+        let hello = take_hello(&mut world).unwrap();
+        assert_eq!(take_hello(&mut world), None);
+
+        let server = Hello {
+            min_protocol: 1,
+            protocol: 3,
+            crate_version: "9.0.0".into(),
+            encodings: vec![Encoding::Binary, Encoding::Json],
+            features: vec!["resume".into(), "tournaments".into()],
+        };
+        let agreement = receive(&mut world, &server).unwrap();
+        assert_eq!(agreement.protocol, PROTOCOL_VERSION);
+        assert_eq!(agreement.encoding, Encoding::Json);
+        assert_eq!(agreement.features, ["resume"]);
+        assert!(incompatible(&world).is_none());
+
+        let newer = Hello {
+            min_protocol: PROTOCOL_VERSION + 1,
+            ..server.clone()
+        };
+        assert!(matches!(
+            receive(&mut world, &newer),
+            Err(IncompatibleProtocol::Version { .. })
+        ));
+        assert!(incompatible(&world).is_some());
+        let binary_only = Hello {
+            encodings: vec![Encoding::Binary],
+            ..server
+        };
+        assert_eq!(
+            negotiate(&hello, &binary_only),
+            Err(IncompatibleProtocol::Encoding)
+        );
+    }
+}
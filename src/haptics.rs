@@ -0,0 +1,152 @@
+// Haptic feedback through the Vibration API.
+//
+// Three moments get a short vibration: cards landing after a move, a move
+// the rules turn down, and winning. Players can turn the pulses off, and
+// they never happen where `navigator.vibrate` does not exist, as on most
+// desktop browsers and on iOS.
+
+use crate::error::GameError;
+use crate::events::GameEvent;
+
+/// Something worth a vibration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pulse {
+    Drop,
+    Illegal,
+    Win,
+}
+
+impl Pulse {
+    /// Alternating vibration and pause lengths, in milliseconds.
+    pub fn pattern(self) -> &'static [u32] {
+        match self {
+            Pulse::Drop => &[15],
+            Pulse::Illegal => &[40, 60, 40],
+            Pulse::Win => &[80, 60, 80, 60, 160],
+        }
+    }
+
+    /// The pulse for the events of one move: the win if it was won, else a
+    /// drop if any card moved.
+    pub fn for_events(events: &[GameEvent]) -> Option<Pulse> {
+        if events
+            .iter()
+            .any(|e| matches!(e, GameEvent::GameWon { .. }))
+        {
+            Some(Pulse::Win)
+        } else if events
+            .iter()
+            .any(|e| matches!(e, GameEvent::CardMoved { .. }))
+        {
+            Some(Pulse::Drop)
+        } else {
+            None
+        }
+    }
+
+    /// The pulse for a move that was turned down. Moves refused for other
+    /// reasons than the rules, such as a pause, get none.
+    pub fn for_error(error: &GameError) -> Option<Pulse> {
+        match error {
+            GameError::IllegalMove(_) | GameError::NotEnoughCards { .. } => Some(Pulse::Illegal),
+            _ => None,
+        }
+    }
+}
+
+/// The player's choice, and whether the device can vibrate at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Haptics {
+    pub enabled: bool,
+    supported: bool,
+}
+
+impl Default for Haptics {
+    fn default() -> Self {
+        Haptics {
+            enabled: true,
+            supported: is_supported(),
+        }
+    }
+}
+
+impl Haptics {
+    /// Whether pulses are felt: turned on and possible on this device.
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.supported
+    }
+
+    /// Vibrate for `pulse`, if active.
+    pub fn play(&self, pulse: Option<Pulse>) {
+        if let Some(pulse) = pulse
+            && self.is_active()
+        {
+            vibrate(pulse.pattern());
+        }
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn is_supported() -> bool {
+    use wasm_bindgen::JsValue;
+    web_sys::window().is_some_and(|w| {
+        js_sys::Reflect::has(&w.navigator(), &JsValue::from_str("vibrate")).unwrap_or(false)
+    })
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn vibrate(pattern: &[u32]) {
+    if let Some(window) = web_sys::window() {
+        let pattern: js_sys::Array = pattern
+            .iter()
+            .copied()
+            .map(wasm_bindgen::JsValue::from)
+            .collect();
+        window.navigator().vibrate_with_pattern(&pattern);
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+fn is_supported() -> bool {
+    false
+}
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+fn vibrate(_pattern: &[u32]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::World;
+    use crate::game::Pile;
+
+    #[test]
+    fn moves_wins_and_refusals_get_their_own_pulse() {
+        let card = World::new().spawn();
+        let moved = GameEvent::CardMoved {
+            card,
+            from: Pile::Waste,
+            to: Pile::Tableau(0),
+        };
+        assert_eq!(
+            Pulse::for_events(std::slice::from_ref(&moved)),
+            Some(Pulse::Drop)
+        );
+        let won = GameEvent::GameWon {
+            moves: 90,
+            undos: 0,
+            time_ms: 1,
+            time_bonus: 0,
+        };
+        assert_eq!(Pulse::for_events(&[moved, won]), Some(Pulse::Win));
+        assert_eq!(Pulse::for_events(&[GameEvent::StockRecycled]), None);
+
+        assert_eq!(
+            Pulse::for_error(&GameError::IllegalMove("no")),
+            Some(Pulse::Illegal)
+        );
+        assert_eq!(Pulse::for_error(&GameError::Paused), None);
+        // Nothing vibrates off the web.
+        assert!(!Haptics::default().is_active());
+    }
+}
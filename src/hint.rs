@@ -0,0 +1,76 @@
+// Hints backed by the solver.
+//
+// Asking for the full solution runs the solver on the live board. A winning
+// line is cached in the `Solution` resource together with the position it
+// starts from, so stepping through it one move at a time does not search
+// again. As soon as the player makes a move of their own the cached start no
+// longer matches the board and the next request solves afresh.
+
+use std::collections::VecDeque;
+
+use crate::ecs::World;
+use crate::rules::Move;
+use crate::solver::{self, Outcome, Position};
+
+/// Resource caching the winning line found for a position.
+#[derive(Debug, Clone)]
+pub struct Solution {
+    start: Position,
+    moves: VecDeque<Move>,
+}
+
+/// The full winning line from the current position, or `None` when the
+/// solver could not prove the position winnable within `node_limit` nodes.
+pub fn solution(world: &mut World, node_limit: usize) -> Option<Vec<Move>> {
+    cached(world, node_limit).map(|s| s.moves.iter().copied().collect())
+}
+
+/// The next move of the winning line. The cache is advanced past it, so
+/// the caller is expected to play it.
+pub fn next_move(world: &mut World, node_limit: usize) -> Option<Move> {
+    let solution = cached(world, node_limit)?;
+    let mv = solution.moves.pop_front()?;
+    solution.start.apply(mv);
+    Some(mv)
+}
+
+fn cached(world: &mut World, node_limit: usize) -> Option<&mut Solution> {
+    let here = Position::from_world(world);
+    let valid = world
+        .resource::<Solution>()
+        .is_some_and(|s| s.start == here);
+    if !valid {
+        match solver::solve(&here, node_limit) {
+            Outcome::Winnable { moves } => world.insert_resource(Solution {
+                start: here,
+                moves: moves.into(),
+            }),
+            Outcome::Unknown | Outcome::Dead => {
+                world.remove_resource::<Solution>();
+            }
+        }
+    }
+    world.resource_mut::<Solution>()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SolitaireGame;
+    use crate::solver;
+
+    #[test]
+    fn stepping_through_the_solution_wins() {
+        let (seed, moves) = solver::find_winnable_deal(1, 20, 20_000).unwrap();
+        let mut game = SolitaireGame::with_seed(seed);
+        game.setup_board();
+        let shown: Vec<crate::rules::Move> =
+            serde_json::from_str(&game.show_solution().unwrap()).unwrap();
+        assert_eq!(shown, moves);
+
+        for _ in 0..moves.len() {
+            game.play_solution_step().unwrap();
+        }
+        assert!(game.is_won());
+        assert_eq!(game.show_solution().as_deref(), Some("[]"));
+    }
+}
@@ -0,0 +1,120 @@
+// Host migration for co-op rooms.
+//
+// One player of a room is its host: the authority whose board the others
+// take over when theirs may have drifted. Every side works the host out the
+// same way, from the `Roster`: the host keeps the role while it is in the
+// session, and when it leaves, the first player still listed takes over.
+// Each handover raises the `Host` epoch, so a snapshot from a host that has
+// since been replaced is told apart from one of the current host.
+//
+// The new host sends its co-op timeline as the authoritative
+// `HostSnapshot`, and the other sides adopt it with `rollback::adopt`,
+// re-simulating their boards, so the game carries on without a restart. A
+// `GameEvent::HostMigrated` tells the page who took over.
+//
+// Players reach each other through the server's relay rather than peer to
+// peer, so nothing has to be reconnected when the host changes: only the
+// authority moves.
+
+use crate::ecs::World;
+use crate::inbox::Roster;
+
+/// Resource with the host of the room, as this side sees it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Host {
+    pub player: Option<String>,
+    /// How many times the host has changed.
+    pub epoch: u32,
+}
+
+/// A handover from one host to another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub from: String,
+    pub to: String,
+    pub epoch: u32,
+}
+
+/// Bring the `Host` up to date with the `Roster`, returning the handover
+/// if the host left.
+pub fn host_system(world: &mut World) -> Option<Migration> {
+    let players = world
+        .resource::<Roster>()
+        .map(|r| r.players.clone())
+        .unwrap_or_default();
+    let host = world.resource::<Host>().cloned().unwrap_or_default();
+    let next = match &host.player {
+        Some(player) if players.contains(player) => return None,
+        _ => players.first().cloned(),
+    };
+    if next == host.player {
+        return None;
+    }
+    let epoch = host.epoch + u32::from(host.player.is_some());
+    world.insert_resource(Host {
+        player: next.clone(),
+        epoch,
+    });
+    Some(Migration {
+        from: host.player?,
+        to: next?,
+        epoch,
+    })
+}
+
+/// Whether a snapshot sent by `player` for `epoch` comes from the current
+/// host.
+pub fn is_authority(world: &World, player: &str, epoch: u32) -> bool {
+    world
+        .resource::<Host>()
+        .is_some_and(|h| h.player.as_deref() == Some(player) && h.epoch == epoch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::state_digest;
+    use crate::rollback;
+    use crate::rules::{self, Move};
+
+    fn roster(world: &mut World, players: &[&str]) {
+        world.insert_resource(Roster {
+            players: players.iter().map(|p| p.to_string()).collect(),
+        });
+    }
+
+    #[test]
+    fn the_next_player_takes_over_and_their_board_wins() {
+        let mut world = World::new();
+        roster(&mut world, &["ann", "bo", "cy"]);
+        assert_eq!(host_system(&mut world), None);
+        assert!(is_authority(&world, "ann", 0));
+        roster(&mut world, &["ann", "cy"]);
+        assert_eq!(host_system(&mut world), None);
+
+        roster(&mut world, &["cy", "bo"]);
+        let migration = host_system(&mut world).unwrap();
+        assert_eq!(
+            (migration.from.as_str(), migration.to.as_str()),
+            ("ann", "cy")
+        );
+        assert!(is_authority(&world, "cy", 1));
+        assert!(!is_authority(&world, "ann", 0));
+
+        // Cy's board saw a draw that never reached this one; adopting Cy's
+        // timeline brings it in.
+        let seed = 5;
+        let mut boards = [World::new(), World::new()];
+        for (board, player) in boards.iter_mut().zip(["cy", "bo"]) {
+            rules::deal_from_seed(board, seed);
+            rollback::start(board, player.into(), seed, 0.0);
+        }
+        let [cy, bo] = &mut boards;
+        rollback::schedule(cy, Move::Draw).unwrap();
+        rollback::step(cy, 10.0 * rollback::TICK_MS);
+        rollback::step(bo, 10.0 * rollback::TICK_MS);
+        assert_ne!(state_digest(cy), state_digest(bo));
+        assert!(rollback::adopt(bo, rollback::timeline(cy).unwrap()));
+        assert_eq!(state_digest(cy), state_digest(bo));
+    }
+}
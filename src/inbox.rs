@@ -0,0 +1,494 @@
+// Messages from the network, as ECS events.
+//
+// The WebSocket hands over whatever it decodes as soon as it arrives, long
+// before the game gets to look at it. `receive` leaves each message in the
+// `NetInbox` resource, and `inbox_system`, run once per tick, moves them
+// onto the `Events<NetMessage>` bus where any system can read them with its
+// own cursor, without a round trip through JavaScript.
+//
+// The systems here are the ones the game itself needs: `roster_system`
+// keeps the `Roster` of players in the session, `chat_system` the recent
+// `ChatLog`, `remote_moves` picks out the moves other players made on the
+// shared deal so the game can play them on this board too, `coop_inputs`
+// the tick-stamped moves of a co-op game for `rollback`, `host_snapshots`
+// the timelines hosts send when taking over, `session_keys` the
+// keys to sign ranked moves with, `hellos` the server's side of the
+// `handshake`, `session_messages` those for `session`,
+// `lobby_system` the latest `RoomsPage` and the `JoinStatus`,
+// `tournament_messages` those for `tournament`, `ratings` the confirmed
+// ranked ratings, `daily_challenges` the official `daily` challenges, `emotes` the quick
+// reactions of other players, and
+// `board_snapshots` the boards they stream for `spectator` and
+// `board_summaries` the progress they share for `progress`.
+//
+// `receive_text` decodes frames as they come off the wire and counts them
+// in the `NetStats` resource, along with what was sent, frames that could not
+// be decoded or sent, and reconnections, for diagnosing flaky sessions.
+//
+// The session outlives single deals, so `NetState::take` and
+// `NetState::restore` carry all of it over when a new board is set up.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::daily::{DailyChallenge, OfficialDaily};
+use crate::ecs::{EventCursor, Events, World, update_events};
+use crate::emote::Emote;
+use crate::handshake::{Handshake, Hello};
+use crate::host::Host;
+use crate::lobby::{JoinStatus, RoomsPage};
+use crate::logging;
+use crate::progress::{BoardSummary, OpponentProgress};
+use crate::protocol::NetMessage;
+use crate::rating::PlayerRating;
+use crate::rollback::{Input, Timeline};
+use crate::rules::Move;
+use crate::session::Session;
+use crate::spectator::{BoardSnapshot, Spectators};
+use crate::tournament::Tournament;
+
+/// How many chat lines `ChatLog` keeps.
+pub const CHAT_LINES: usize = 100;
+
+/// Resource with the messages received since the last tick.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NetInbox(Vec<NetMessage>);
+
+/// Resource with the players in the session, as last announced.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Roster {
+    pub players: Vec<String>,
+}
+
+/// One line of chat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatLine {
+    pub from: String,
+    pub text: String,
+}
+
+/// Resource with the latest `CHAT_LINES` lines of chat, oldest first.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatLog {
+    pub lines: VecDeque<ChatLine>,
+}
+
+/// Resource counting the traffic of the session.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetStats {
+    pub messages_sent: u64,
+    pub bytes_sent: u64,
+    pub messages_received: u64,
+    pub bytes_received: u64,
+    /// Frames received that were not protocol messages, and messages that
+    /// could not be sent.
+    pub dropped: u64,
+    /// Connections made after the first.
+    pub reconnects: u32,
+}
+
+/// The `NetStats` of `world`, to count in.
+pub fn stats_mut(world: &mut World) -> &mut NetStats {
+    if world.resource::<NetStats>().is_none() {
+        world.insert_resource(NetStats::default());
+    }
+    world.resource_mut::<NetStats>().expect("inserted above")
+}
+
+/// Resource with where each system here has read the bus up to.
+#[derive(Default)]
+struct Readers {
+    roster: EventCursor<NetMessage>,
+    chat: EventCursor<NetMessage>,
+    moves: EventCursor<NetMessage>,
+    coop: EventCursor<NetMessage>,
+    host: EventCursor<NetMessage>,
+    auth: EventCursor<NetMessage>,
+    hello: EventCursor<NetMessage>,
+    session: EventCursor<NetMessage>,
+    lobby: EventCursor<NetMessage>,
+    snapshots: EventCursor<NetMessage>,
+    emotes: EventCursor<NetMessage>,
+    summaries: EventCursor<NetMessage>,
+    tournament: EventCursor<NetMessage>,
+    ratings: EventCursor<NetMessage>,
+    daily: EventCursor<NetMessage>,
+}
+
+/// Leave `message` for the next tick.
+pub fn receive(world: &mut World, message: NetMessage) {
+    match world.resource_mut::<NetInbox>() {
+        Some(inbox) => inbox.0.push(message),
+        None => world.insert_resource(NetInbox(vec![message])),
+    }
+}
+
+/// Decode a text frame from the network and leave it for the next tick,
+/// counting it either way.
+pub fn receive_text(world: &mut World, text: &str) -> Result<(), serde_json::Error> {
+    let stats = stats_mut(world);
+    stats.messages_received += 1;
+    stats.bytes_received += text.len() as u64;
+    log::trace!(target: "network", frame = text; "received frame");
+    match NetMessage::from_json(text) {
+        Ok(message) => {
+            if log::log_enabled!(target: "network", log::Level::Debug) {
+                let kind = logging::message_type(text);
+                log::debug!(target: "network", kind:%, bytes = text.len(); "message received");
+            }
+            receive(world, message);
+            Ok(())
+        }
+        Err(e) => {
+            log::warn!(target: "network", error:% = e, bytes = text.len(); "dropped a frame that is not a protocol message");
+            stats_mut(world).dropped += 1;
+            Err(e)
+        }
+    }
+}
+
+/// Advance the bus by one tick and send everything in the inbox on it.
+pub fn inbox_system(world: &mut World) {
+    update_events::<NetMessage>(world);
+    let received = world.remove_resource::<NetInbox>().unwrap_or_default();
+    for message in received.0 {
+        world.send_event(message);
+    }
+}
+
+/// Read the bus with one of the `Readers`.
+fn read(
+    world: &mut World,
+    cursor: impl Fn(&mut Readers) -> &mut EventCursor<NetMessage>,
+) -> Vec<NetMessage> {
+    let mut readers = world.remove_resource::<Readers>().unwrap_or_default();
+    let messages = world.read_events(cursor(&mut readers)).cloned().collect();
+    world.insert_resource(readers);
+    messages
+}
+
+/// Keep the `Roster` up to date.
+pub fn roster_system(world: &mut World) {
+    for message in read(world, |r| &mut r.roster) {
+        if let NetMessage::Roster { players } = message {
+            world.insert_resource(Roster { players });
+        }
+    }
+}
+
+/// Keep the latest lines of chat in the `ChatLog`.
+pub fn chat_system(world: &mut World) {
+    let mut log = world.remove_resource::<ChatLog>().unwrap_or_default();
+    for message in read(world, |r| &mut r.chat) {
+        if let NetMessage::Chat { from, text } = message {
+            log.lines.push_back(ChatLine { from, text });
+            if log.lines.len() > CHAT_LINES {
+                log.lines.pop_front();
+            }
+        }
+    }
+    world.insert_resource(log);
+}
+
+/// Keep the latest page of rooms and the outcome of joining as resources.
+pub fn lobby_system(world: &mut World) {
+    for message in read(world, |r| &mut r.lobby) {
+        match message {
+            NetMessage::Rooms(page) => world.insert_resource(page),
+            NetMessage::Joined { room } => world.insert_resource(JoinStatus::Joined { room }),
+            NetMessage::JoinFailed { reason } => {
+                world.insert_resource(JoinStatus::Failed { reason })
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The moves other players made since the last call.
+pub fn remote_moves(world: &mut World) -> Vec<Move> {
+    read(world, |r| &mut r.moves)
+        .into_iter()
+        .filter_map(|message| match message {
+            NetMessage::PlayerMove { mv, .. } => Some(mv),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The co-op moves other players made since the last call.
+pub fn coop_inputs(world: &mut World) -> Vec<Input> {
+    read(world, |r| &mut r.coop)
+        .into_iter()
+        .filter_map(|message| match message {
+            NetMessage::CoopMove(input) => Some(input),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The timelines sent by hosts taking over since the last call, with the
+/// host's name and epoch.
+pub fn host_snapshots(world: &mut World) -> Vec<(String, u32, Timeline)> {
+    read(world, |r| &mut r.host)
+        .into_iter()
+        .filter_map(|message| match message {
+            NetMessage::HostSnapshot {
+                host,
+                epoch,
+                timeline,
+            } => Some((host, epoch, timeline)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The `Hello`s received since the last call.
+pub fn hellos(world: &mut World) -> Vec<Hello> {
+    read(world, |r| &mut r.hello)
+        .into_iter()
+        .filter_map(|message| match message {
+            NetMessage::Hello(hello) => Some(hello),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The messages about resuming the session since the last call.
+pub fn session_messages(world: &mut World) -> Vec<NetMessage> {
+    read(world, |r| &mut r.session)
+        .into_iter()
+        .filter(|message| {
+            matches!(
+                message,
+                NetMessage::SessionStarted { .. }
+                    | NetMessage::Resumed
+                    | NetMessage::ResumeFailed
+                    | NetMessage::CatchUp { .. }
+            )
+        })
+        .collect()
+}
+
+/// The emotes received since the last call, with who sent them.
+pub fn emotes(world: &mut World) -> Vec<(String, Emote)> {
+    read(world, |r| &mut r.emotes)
+        .into_iter()
+        .filter_map(|message| match message {
+            NetMessage::Emote { from, emote } => Some((from, emote)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The messages about tournament brackets since the last call.
+pub fn tournament_messages(world: &mut World) -> Vec<NetMessage> {
+    read(world, |r| &mut r.tournament)
+        .into_iter()
+        .filter(|message| {
+            matches!(
+                message,
+                NetMessage::BracketJoined { .. }
+                    | NetMessage::RoundPairing(_)
+                    | NetMessage::BracketStanding(_)
+            )
+        })
+        .collect()
+}
+
+/// The ratings the server confirmed since the last call, with the change of
+/// each ranked game, or `None` for a rating fetched.
+pub fn ratings(world: &mut World) -> Vec<(PlayerRating, Option<f64>)> {
+    read(world, |r| &mut r.ratings)
+        .into_iter()
+        .filter_map(|message| match message {
+            NetMessage::Rating(rating) => Some((rating, None)),
+            NetMessage::RankedResult { change, rating } => Some((rating, Some(change))),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The daily challenges the server handed out since the last call.
+pub fn daily_challenges(world: &mut World) -> Vec<DailyChallenge> {
+    read(world, |r| &mut r.daily)
+        .into_iter()
+        .filter_map(|message| match message {
+            NetMessage::Daily(daily) => Some(daily),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The session keys the server handed out since the last call.
+pub fn session_keys(world: &mut World) -> Vec<String> {
+    read(world, |r| &mut r.auth)
+        .into_iter()
+        .filter_map(|message| match message {
+            NetMessage::Authenticated { session_key } => Some(session_key),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The boards other players streamed since the last call, with their
+/// names.
+pub fn board_snapshots(world: &mut World) -> Vec<(String, BoardSnapshot)> {
+    read(world, |r| &mut r.snapshots)
+        .into_iter()
+        .filter_map(|message| match message {
+            NetMessage::BoardSnapshot { player, snapshot } => Some((player, snapshot)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The progress other players shared since the last call, with their
+/// names.
+pub fn board_summaries(world: &mut World) -> Vec<(String, BoardSummary)> {
+    read(world, |r| &mut r.summaries)
+        .into_iter()
+        .filter_map(|message| match message {
+            NetMessage::BoardSummary { player, summary } => Some((player, summary)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Everything about the network session kept in the world.
+#[derive(Default)]
+pub struct NetState {
+    inbox: Option<NetInbox>,
+    events: Option<Events<NetMessage>>,
+    readers: Option<Readers>,
+    roster: Option<Roster>,
+    chat: Option<ChatLog>,
+    stats: Option<NetStats>,
+    spectators: Option<Spectators>,
+    progress: Option<OpponentProgress>,
+    rooms: Option<RoomsPage>,
+    join: Option<JoinStatus>,
+    host: Option<Host>,
+    session: Option<Session>,
+    handshake: Option<Handshake>,
+    tournament: Option<Tournament>,
+    daily: Option<OfficialDaily>,
+}
+
+impl NetState {
+    /// Take the session out of `world`.
+    pub fn take(world: &mut World) -> NetState {
+        NetState {
+            inbox: world.remove_resource(),
+            events: world.remove_resource(),
+            readers: world.remove_resource(),
+            roster: world.remove_resource(),
+            chat: world.remove_resource(),
+            stats: world.remove_resource(),
+            spectators: world.remove_resource(),
+            progress: world.remove_resource(),
+            rooms: world.remove_resource(),
+            join: world.remove_resource(),
+            host: world.remove_resource(),
+            session: world.remove_resource(),
+            handshake: world.remove_resource(),
+            tournament: world.remove_resource(),
+            daily: world.remove_resource(),
+        }
+    }
+
+    /// Put the session back into `world`.
+    pub fn restore(self, world: &mut World) {
+        fn put<T: 'static>(world: &mut World, resource: Option<T>) {
+            if let Some(resource) = resource {
+                world.insert_resource(resource);
+            }
+        }
+        put(world, self.inbox);
+        put(world, self.events);
+        put(world, self.readers);
+        put(world, self.roster);
+        put(world, self.chat);
+        put(world, self.stats);
+        put(world, self.spectators);
+        put(world, self.progress);
+        put(world, self.rooms);
+        put(world, self.join);
+        put(world, self.host);
+        put(world, self.session);
+        put(world, self.handshake);
+        put(world, self.tournament);
+        put(world, self.daily);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Pile;
+
+    #[test]
+    fn received_messages_reach_every_system_once() {
+        let mut world = World::new();
+        receive(
+            &mut world,
+            NetMessage::Roster {
+                players: vec!["ann".into(), "bo".into()],
+            },
+        );
+        receive(
+            &mut world,
+            NetMessage::Chat {
+                from: "ann".into(),
+                text: "good luck".into(),
+            },
+        );
+        receive(
+            &mut world,
+            NetMessage::PlayerMove {
+                player: "bo".into(),
+                mv: Move::Draw,
+            },
+        );
+        // Nothing happens before the tick.
+        assert!(remote_moves(&mut world).is_empty());
+
+        inbox_system(&mut world);
+        roster_system(&mut world);
+        chat_system(&mut world);
+        assert_eq!(world.resource::<Roster>().unwrap().players, ["ann", "bo"]);
+        assert_eq!(
+            world.resource::<ChatLog>().unwrap().lines[0].text,
+            "good luck"
+        );
+        assert_eq!(remote_moves(&mut world), [Move::Draw]);
+
+        // The next tick brings nothing new, and the session survives a deal.
+        inbox_system(&mut world);
+        chat_system(&mut world);
+        assert!(remote_moves(&mut world).is_empty());
+        assert_eq!(world.resource::<ChatLog>().unwrap().lines.len(), 1);
+        let state = NetState::take(&mut world);
+        world.clear_resources();
+        state.restore(&mut world);
+        assert!(world.resource::<Roster>().is_some());
+
+        assert!(receive_text(&mut world, "not json").is_err());
+        receive_text(&mut world, r#"{"type":"roster","players":[]}"#).unwrap();
+        let stats = *world.resource::<NetStats>().unwrap();
+        assert_eq!((stats.messages_received, stats.dropped), (2, 1));
+        assert_eq!(stats.bytes_received, 38);
+
+        let json = NetMessage::PlayerMove {
+            player: "bo".into(),
+            mv: Move::Transfer {
+                from: Pile::Waste,
+                to: Pile::Tableau(2),
+                count: 1,
+            },
+        }
+        .to_json();
+        assert!(json.starts_with(r#"{"type":"player_move","#));
+        assert!(NetMessage::from_json(&json).is_ok());
+    }
+}
@@ -0,0 +1,707 @@
+// Where every card goes on the table.
+//
+// The layout system turns the cards' piles and pile indices into screen
+// positions for the current canvas size, storing them as `Position`
+// components. Rendering and hit-testing both read those components, so they
+// always agree about where a card is.
+//
+// The table is as many card widths across as the variant needs: stock and
+// waste on the left of the top row with the foundations lined up on the
+// right, and the tableau piles below (seven of everything but the stock in
+// Klondike). Tableau
+// piles fan downwards; when a long pile would run off the bottom of the
+// canvas its fan is compressed until it fits. The waste fans its top few
+// cards sideways, as in draw-three games. Mirrored layouts, for left-handed
+// players, swap the table left to right: the stock sits on the right and the
+// waste fans out to the left.
+//
+// Drawing order comes in layers: the table, then pile placeholders, then the
+// cards, the cards being dragged and finally overlays such as dialogs. Each
+// layer owns a range of `z` values, so picking cards up with `pick_up` lifts
+// them above everything on the table until they are dropped again. While
+// they are dragged, `drag_to` follows the pointer so renderers can draw a
+// ghost of the stack under it, and `drop_targets` asks the rules which piles
+// would take the stack if it were dropped now.
+//
+// Landscape and portrait screens get different presets: a tall, narrow
+// canvas has little room across, so its preset trims the margins and gaps
+// to keep the cards large and fans the tableau further down instead.
+
+use serde::{Deserialize, Serialize};
+
+use crate::dirty;
+use crate::ecs::{Entity, World};
+use crate::error::GameError;
+use crate::game::{FaceUp, Pile, PileIndex};
+use crate::rules;
+use crate::variant::{self, Table};
+
+/// Height of a card relative to its width.
+const CARD_ASPECT: f32 = 1.4;
+
+/// Component with a card's top-left corner on the canvas, in CSS pixels.
+/// `z` orders overlapping cards: higher is drawn later. It comes from the
+/// card's `RenderLayer`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+    pub z: u32,
+}
+
+/// What is drawn on top of what, from the bottom up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderLayer {
+    Table,
+    /// Pile outlines and empty-pile placeholders.
+    Piles,
+    Cards,
+    /// Cards picked up and following the pointer.
+    Dragged,
+    /// Dialogs, menus and other user interface above the board.
+    Overlay,
+}
+
+impl RenderLayer {
+    /// Bits of `z` used for the order within a layer.
+    const ORDER_BITS: u32 = 16;
+
+    /// The `z` of the `order`th thing drawn in this layer.
+    pub fn z(self, order: usize) -> u32 {
+        let order = order.min((1 << Self::ORDER_BITS) - 1) as u32;
+        (self as u32) << Self::ORDER_BITS | order
+    }
+
+    /// The layer a `z` value lies in.
+    pub fn of(z: u32) -> RenderLayer {
+        match z >> Self::ORDER_BITS {
+            0 => RenderLayer::Table,
+            1 => RenderLayer::Piles,
+            2 => RenderLayer::Cards,
+            3 => RenderLayer::Dragged,
+            _ => RenderLayer::Overlay,
+        }
+    }
+}
+
+/// Marker component for cards that have been picked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dragged;
+
+/// Resource with where the pointer dragging cards is, and where on the
+/// bottom dragged card it took hold of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DragPointer {
+    pub x: f32,
+    pub y: f32,
+    pub grab_x: f32,
+    pub grab_y: f32,
+}
+
+impl DragPointer {
+    /// How far the stack has been carried from where it lies.
+    pub fn offset(&self, resting: Position) -> (f32, f32) {
+        (
+            self.x - self.grab_x - resting.x,
+            self.y - self.grab_y - resting.y,
+        )
+    }
+}
+
+/// Resource with the size of the canvas the table is drawn on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CanvasSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for CanvasSize {
+    fn default() -> Self {
+        CanvasSize {
+            width: 800.0,
+            height: 600.0,
+        }
+    }
+}
+
+/// Resource with the tunable layout parameters. Offsets are fractions of
+/// the card size, so the table scales with the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutSettings {
+    /// Vertical step between face-up tableau cards, in card heights.
+    pub tableau_fan: f32,
+    /// Vertical step between face-down tableau cards, in card heights.
+    pub face_down_fan: f32,
+    /// How many of the top waste cards are fanned out.
+    pub waste_fan_cards: usize,
+    /// Horizontal step between fanned waste cards, in card widths.
+    pub waste_fan: f32,
+    /// Space around the table and between piles, in card widths.
+    pub margin: f32,
+    pub gap: f32,
+    /// Flip the table horizontally, putting the stock on the right.
+    pub mirrored: bool,
+}
+
+impl Default for LayoutSettings {
+    fn default() -> Self {
+        LayoutSettings {
+            tableau_fan: 0.25,
+            face_down_fan: 0.1,
+            waste_fan_cards: 3,
+            waste_fan: 0.2,
+            margin: 0.2,
+            gap: 0.15,
+            mirrored: false,
+        }
+    }
+}
+
+impl LayoutSettings {
+    /// The settings suiting a canvas of the given orientation.
+    pub fn preset(orientation: Orientation) -> Self {
+        match orientation {
+            Orientation::Landscape => LayoutSettings::default(),
+            Orientation::Portrait => LayoutSettings {
+                tableau_fan: 0.3,
+                face_down_fan: 0.12,
+                waste_fan: 0.25,
+                margin: 0.1,
+                gap: 0.08,
+                ..LayoutSettings::default()
+            },
+        }
+    }
+}
+
+/// Which way round the canvas is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+}
+
+impl Orientation {
+    pub fn of(canvas: CanvasSize) -> Orientation {
+        if canvas.height > canvas.width {
+            Orientation::Portrait
+        } else {
+            Orientation::Landscape
+        }
+    }
+}
+
+/// Card size and pile origins for a canvas size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    pub card_width: f32,
+    pub card_height: f32,
+    margin: f32,
+    gap: f32,
+    tableau_top: f32,
+    mirrored: bool,
+    columns: u8,
+    foundation_column: u8,
+}
+
+impl Metrics {
+    pub fn new(canvas: CanvasSize, settings: &LayoutSettings, table: &Table) -> Self {
+        // Stock and waste take two columns; the foundations need their own.
+        let column_count = table.tableau_piles.max(table.foundation_piles + 2);
+        let columns = f32::from(column_count);
+        // width = columns * w + (columns - 1) * gap * w + 2 * margin * w
+        let units = columns + (columns - 1.0) * settings.gap + 2.0 * settings.margin;
+        let mut card_width = canvas.width / units;
+        // Leave room for at least the top row and a short tableau pile.
+        let min_rows_height = (2.0 + settings.gap + 2.0 * settings.margin) * CARD_ASPECT;
+        card_width = card_width.min(canvas.height / min_rows_height).max(1.0);
+        let card_height = card_width * CARD_ASPECT;
+        let margin = settings.margin * card_width;
+        let gap = settings.gap * card_width;
+        Metrics {
+            card_width,
+            card_height,
+            margin,
+            gap,
+            tableau_top: margin + card_height + gap,
+            mirrored: settings.mirrored,
+            columns: column_count,
+            foundation_column: column_count - table.foundation_piles,
+        }
+    }
+
+    /// Left edge of table column `column`, counted from the stock's side.
+    pub fn column_x(&self, column: u8) -> f32 {
+        let column = if self.mirrored {
+            self.columns - 1 - column
+        } else {
+            column
+        };
+        self.margin + f32::from(column) * (self.card_width + self.gap)
+    }
+
+    /// Which way the waste fans: `1.0` to the right, `-1.0` when mirrored.
+    fn fan_direction(&self) -> f32 {
+        if self.mirrored { -1.0 } else { 1.0 }
+    }
+
+    /// Where the bottom card of `pile` sits.
+    pub fn pile_origin(&self, pile: Pile) -> (f32, f32) {
+        match pile {
+            Pile::Stock => (self.column_x(0), self.margin),
+            Pile::Waste => (self.column_x(1), self.margin),
+            Pile::Foundation(i) => (self.column_x(self.foundation_column + i), self.margin),
+            Pile::Tableau(i) => (self.column_x(i), self.tableau_top),
+        }
+    }
+}
+
+/// System writing a `Position` onto every card.
+pub fn layout_system(world: &mut World) {
+    let canvas = world.resource::<CanvasSize>().copied().unwrap_or_default();
+    let settings = world
+        .resource::<LayoutSettings>()
+        .copied()
+        .unwrap_or_default();
+    let metrics = Metrics::new(canvas, &settings, &variant::table(world));
+
+    let mut placed: Vec<(Entity, Position)> = Vec::new();
+    for pile in rules::all_piles(world) {
+        let cards = rules::pile_cards(world, pile);
+        let (x, y) = metrics.pile_origin(pile);
+        match pile {
+            Pile::Tableau(_) => {
+                let face_up: Vec<bool> = cards
+                    .iter()
+                    .map(|&e| world.get_component::<FaceUp>(e).is_some_and(|f| f.0))
+                    .collect();
+                let steps = tableau_steps(&face_up, &settings, &metrics, canvas.height - y);
+                let mut offset = 0.0;
+                for (i, &entity) in cards.iter().enumerate() {
+                    placed.push((entity, position(x, y + offset, entity, world)));
+                    offset += steps[i];
+                }
+            }
+            Pile::Waste => {
+                let fanned = cards.len().saturating_sub(settings.waste_fan_cards);
+                for (i, &entity) in cards.iter().enumerate() {
+                    let step = i.saturating_sub(fanned) as f32;
+                    let dx =
+                        metrics.fan_direction() * step * settings.waste_fan * metrics.card_width;
+                    placed.push((entity, position(x + dx, y, entity, world)));
+                }
+            }
+            Pile::Stock | Pile::Foundation(_) => {
+                for &entity in &cards {
+                    placed.push((entity, position(x, y, entity, world)));
+                }
+            }
+        }
+    }
+    for (entity, position) in placed {
+        world.add_component(entity, position);
+    }
+    dirty::track(world, (metrics.card_width, metrics.card_height));
+}
+
+/// Pick up the top `count` cards of `pile`, putting them on the dragged
+/// layer. Anything picked up before is dropped.
+pub fn pick_up(world: &mut World, pile: Pile, count: usize) -> Result<Vec<Entity>, GameError> {
+    let cards = rules::pile_cards(world, pile);
+    let not_enough = GameError::NotEnoughCards {
+        pile,
+        requested: count,
+    };
+    if count == 0 || count > cards.len() {
+        return Err(not_enough);
+    }
+    let picked = cards[cards.len() - count..].to_vec();
+    if !picked
+        .iter()
+        .all(|&e| world.get_component::<FaceUp>(e).is_some_and(|f| f.0))
+    {
+        return Err(not_enough);
+    }
+    drop_dragged(world);
+    for &entity in &picked {
+        world.add_component(entity, Dragged);
+    }
+    Ok(picked)
+}
+
+/// Put the dragged cards back on the card layer and return them.
+pub fn drop_dragged(world: &mut World) -> Vec<Entity> {
+    let dragged = dragged(world);
+    for &entity in &dragged {
+        world.remove_component::<Dragged>(entity);
+    }
+    world.remove_resource::<DragPointer>();
+    dragged
+}
+
+/// The cards being dragged, bottom card first.
+pub fn dragged(world: &World) -> Vec<Entity> {
+    let mut dragged: Vec<(PileIndex, Entity)> = world
+        .iter::<Dragged>()
+        .map(|(e, _)| {
+            (
+                world
+                    .get_component::<PileIndex>(e)
+                    .copied()
+                    .unwrap_or(PileIndex(0)),
+                e,
+            )
+        })
+        .collect();
+    dragged.sort();
+    dragged.into_iter().map(|(_, e)| e).collect()
+}
+
+/// Move the pointer dragging cards to `(x, y)`. The first call after
+/// `pick_up` takes hold of the stack where the pointer is. Does nothing
+/// when no cards are dragged.
+pub fn drag_to(world: &mut World, x: f32, y: f32) {
+    let Some(&first) = dragged(world).first() else {
+        return;
+    };
+    let pointer = match world.resource::<DragPointer>() {
+        Some(&pointer) => DragPointer { x, y, ..pointer },
+        None => {
+            let resting = world
+                .get_component::<Position>(first)
+                .copied()
+                .unwrap_or(Position { x, y, z: 0 });
+            DragPointer {
+                x,
+                y,
+                grab_x: x - resting.x,
+                grab_y: y - resting.y,
+            }
+        }
+    };
+    world.insert_resource(pointer);
+}
+
+/// Every pile the dragged cards could be dropped on, as the rules see it.
+pub fn drop_targets(world: &World) -> Vec<Pile> {
+    let dragged = dragged(world);
+    let Some(&from) = dragged
+        .first()
+        .and_then(|&first| world.get_component::<Pile>(first))
+    else {
+        return Vec::new();
+    };
+    rules::all_piles(world)
+        .into_iter()
+        .filter(|&to| {
+            to != from
+                && rules::validate(
+                    world,
+                    rules::Move::Transfer {
+                        from,
+                        to,
+                        count: dragged.len(),
+                    },
+                )
+                .is_ok()
+        })
+        .collect()
+}
+
+/// The card under the point `(x, y)`: of the cards laid out there, the one
+/// drawn last. Reads the `Position`s written by `layout_system`.
+pub fn card_at(world: &World, x: f32, y: f32) -> Option<Entity> {
+    card_under(world, x, y, RenderLayer::Dragged)
+}
+
+/// The topmost card under `(x, y)` in a layer up to `layer`.
+fn card_under(world: &World, x: f32, y: f32, layer: RenderLayer) -> Option<Entity> {
+    let metrics = metrics(world);
+    world
+        .iter::<Position>()
+        .filter(|(_, p)| {
+            RenderLayer::of(p.z) <= layer
+                && (p.x..p.x + metrics.card_width).contains(&x)
+                && (p.y..p.y + metrics.card_height).contains(&y)
+        })
+        .max_by_key(|(_, p)| p.z)
+        .map(|(entity, _)| entity)
+}
+
+/// The pile under `(x, y)`, including empty piles, for dropping cards onto.
+/// Tableau piles reach down to the bottom of the canvas. Dragged cards are
+/// looked through, since they are what is being dropped.
+pub fn pile_at(world: &World, x: f32, y: f32) -> Option<Pile> {
+    if let Some(entity) = card_under(world, x, y, RenderLayer::Cards) {
+        return world.get_component::<Pile>(entity).copied();
+    }
+    let metrics = metrics(world);
+    rules::all_piles(world).into_iter().find(|&pile| {
+        let (px, py) = metrics.pile_origin(pile);
+        let bottom = match pile {
+            Pile::Tableau(_) => f32::INFINITY,
+            _ => py + metrics.card_height,
+        };
+        (px..px + metrics.card_width).contains(&x) && (py..bottom).contains(&y)
+    })
+}
+
+pub(crate) fn metrics(world: &World) -> Metrics {
+    let canvas = world.resource::<CanvasSize>().copied().unwrap_or_default();
+    let settings = world
+        .resource::<LayoutSettings>()
+        .copied()
+        .unwrap_or_default();
+    Metrics::new(canvas, &settings, &variant::table(world))
+}
+
+/// A laid-out table, as sent to the frontend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableLayout {
+    pub card_width: f32,
+    pub card_height: f32,
+    pub cards: Vec<PlacedCard>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlacedCard {
+    pub entity: Entity,
+    #[serde(flatten)]
+    pub position: Position,
+}
+
+impl TableLayout {
+    /// Run the layout system and collect the result.
+    pub fn compute(world: &mut World) -> Self {
+        layout_system(world);
+        let metrics = metrics(world);
+        let mut cards: Vec<PlacedCard> = world
+            .iter::<Position>()
+            .map(|(entity, &position)| PlacedCard { entity, position })
+            .collect();
+        cards.sort_by_key(|c| c.entity);
+        TableLayout {
+            card_width: metrics.card_width,
+            card_height: metrics.card_height,
+            cards,
+        }
+    }
+}
+
+fn position(x: f32, y: f32, entity: Entity, world: &World) -> Position {
+    let index = world.get_component::<PileIndex>(entity).map_or(0, |i| i.0);
+    let layer = if world.get_component::<Dragged>(entity).is_some() {
+        RenderLayer::Dragged
+    } else {
+        RenderLayer::Cards
+    };
+    Position {
+        x,
+        y,
+        z: layer.z(index),
+    }
+}
+
+/// The vertical step below each card of a tableau pile, compressed so the
+/// pile fits into `available` pixels.
+fn tableau_steps(
+    face_up: &[bool],
+    settings: &LayoutSettings,
+    metrics: &Metrics,
+    available: f32,
+) -> Vec<f32> {
+    let mut steps: Vec<f32> = face_up
+        .iter()
+        .map(|&up| {
+            let fan = if up {
+                settings.tableau_fan
+            } else {
+                settings.face_down_fan
+            };
+            fan * metrics.card_height
+        })
+        .collect();
+    // Nothing is drawn below the top card.
+    if let Some(last) = steps.last_mut() {
+        *last = 0.0;
+    }
+    let needed: f32 = steps.iter().sum();
+    let room = (available - metrics.card_height - metrics.margin).max(0.0);
+    if needed > room {
+        let scale = room / needed;
+        for step in &mut steps {
+            *step *= scale;
+        }
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Card, Deck, Rank, Suit};
+
+    fn tableau_world(cards: usize) -> World {
+        let mut world = World::new();
+        world.spawn_batch((0..cards).map(|i| {
+            (
+                Card::new(Suit::Hearts, Rank::King),
+                Pile::Tableau(0),
+                PileIndex(i),
+                FaceUp(true),
+            )
+        }));
+        world
+    }
+
+    fn top_y(world: &World) -> f32 {
+        let top = rules::top_card(world, Pile::Tableau(0)).unwrap();
+        world.get_component::<Position>(top).unwrap().y
+    }
+
+    #[test]
+    fn tableau_fans_down_from_its_origin() {
+        let mut world = tableau_world(3);
+        layout_system(&mut world);
+        let metrics = metrics(&world);
+        let fan = LayoutSettings::default().tableau_fan * metrics.card_height;
+        let (_, y) = metrics.pile_origin(Pile::Tableau(0));
+        assert!((top_y(&world) - (y + 2.0 * fan)).abs() < 0.01);
+    }
+
+    #[test]
+    fn long_piles_are_compressed_to_fit() {
+        let mut world = tableau_world(30);
+        let canvas = CanvasSize {
+            width: 800.0,
+            height: 600.0,
+        };
+        world.insert_resource(canvas);
+        layout_system(&mut world);
+        let metrics = metrics(&world);
+        assert!(top_y(&world) + metrics.card_height <= canvas.height);
+    }
+
+    #[test]
+    fn mirrored_layout_hit_tests_where_it_draws() {
+        let mut world = World::new();
+        rules::deal(&mut world, &Deck::standard().cards);
+        world.insert_resource(LayoutSettings {
+            mirrored: true,
+            ..LayoutSettings::default()
+        });
+        layout_system(&mut world);
+        let metrics = metrics(&world);
+        let (stock_x, _) = metrics.pile_origin(Pile::Stock);
+        let (last_x, _) = metrics.pile_origin(Pile::Foundation(3));
+        assert!(stock_x > last_x, "the stock moves to the right");
+
+        let top = rules::top_card(&world, Pile::Tableau(0)).unwrap();
+        let p = *world.get_component::<Position>(top).unwrap();
+        assert_eq!(card_at(&world, p.x + 1.0, p.y + 1.0), Some(top));
+        assert_eq!(
+            pile_at(&world, stock_x + 1.0, metrics.card_height / 2.0),
+            Some(Pile::Stock)
+        );
+    }
+
+    #[test]
+    fn dragged_cards_are_drawn_above_the_table() {
+        let mut world = World::new();
+        rules::deal(&mut world, &Deck::standard().cards);
+        let picked = pick_up(&mut world, Pile::Tableau(0), 1).unwrap();
+        assert!(pick_up(&mut world, Pile::Tableau(1), 2).is_err());
+        layout_system(&mut world);
+
+        let dragged = *world.get_component::<Position>(picked[0]).unwrap();
+        assert_eq!(RenderLayer::of(dragged.z), RenderLayer::Dragged);
+        assert!(world.iter::<Position>().all(|(_, p)| p.z <= dragged.z));
+        assert_eq!(
+            pile_at(&world, dragged.x + 1.0, dragged.y + 1.0),
+            Some(Pile::Tableau(0))
+        );
+
+        assert_eq!(drop_dragged(&mut world), picked);
+        layout_system(&mut world);
+        let dropped = world.get_component::<Position>(picked[0]).unwrap();
+        assert_eq!(dropped.z, RenderLayer::Cards.z(0));
+    }
+
+    #[test]
+    fn dragged_stacks_follow_the_pointer_to_legal_targets() {
+        let mut world = World::new();
+        world.spawn_batch([
+            (
+                Card::new(Suit::Hearts, Rank::Queen),
+                Pile::Tableau(0),
+                PileIndex(0),
+                FaceUp(true),
+            ),
+            (
+                Card::new(Suit::Spades, Rank::King),
+                Pile::Tableau(1),
+                PileIndex(0),
+                FaceUp(true),
+            ),
+            (
+                Card::new(Suit::Clubs, Rank::King),
+                Pile::Tableau(2),
+                PileIndex(0),
+                FaceUp(true),
+            ),
+        ]);
+        layout_system(&mut world);
+        let picked = pick_up(&mut world, Pile::Tableau(0), 1).unwrap();
+        let resting = *world.get_component::<Position>(picked[0]).unwrap();
+        drag_to(&mut world, resting.x + 5.0, resting.y + 5.0);
+        drag_to(&mut world, 300.0, 400.0);
+        let pointer = *world.resource::<DragPointer>().unwrap();
+        assert_eq!(
+            pointer.offset(resting),
+            (295.0 - resting.x, 395.0 - resting.y)
+        );
+        // Both black Kings take the Queen; empty piles want a King.
+        assert_eq!(
+            drop_targets(&world),
+            vec![Pile::Tableau(1), Pile::Tableau(2)]
+        );
+
+        drop_dragged(&mut world);
+        assert!(world.resource::<DragPointer>().is_none());
+        assert!(drop_targets(&world).is_empty());
+    }
+
+    #[test]
+    fn portrait_preset_keeps_cards_large() {
+        let phone = CanvasSize {
+            width: 390.0,
+            height: 844.0,
+        };
+        assert_eq!(Orientation::of(phone), Orientation::Portrait);
+        let table = variant::table(&World::new());
+        let landscape = Metrics::new(phone, &LayoutSettings::default(), &table);
+        let portrait = Metrics::new(
+            phone,
+            &LayoutSettings::preset(Orientation::Portrait),
+            &table,
+        );
+        assert!(portrait.card_width > landscape.card_width);
+        let (x, _) = portrait.pile_origin(Pile::Tableau(6));
+        assert!(x + portrait.card_width <= phone.width);
+    }
+
+    #[test]
+    fn double_klondike_fits_across_the_canvas() {
+        let mut world = World::new();
+        world.insert_resource(Table::DOUBLE_KLONDIKE);
+        layout_system(&mut world);
+        let metrics = metrics(&world);
+        let (x, _) = metrics.pile_origin(Pile::Foundation(7));
+        assert!(x + metrics.card_width <= CanvasSize::default().width);
+        let (x, _) = metrics.pile_origin(Pile::Tableau(8));
+        assert!(x + metrics.card_width <= CanvasSize::default().width);
+    }
+}
@@ -0,0 +1,397 @@
+//! Leaderboard client.
+//!
+//! Finished games can be submitted together with their score and time, and
+//! rankings can be fetched one page at a time, either for a whole variant
+//! ("klondike") or for a single daily challenge ("klondike" on "2025-07-31").
+//!
+//! Two transports are supported:
+//!
+//! * the typed WebSocket protocol (`protocol::NetMessage::SubmitScore` and
+//!   `FetchRankings`), for games that already hold a connection, and
+//! * a plain REST endpoint, used by `LeaderboardClient` in the browser.
+//!
+//! Submissions are signed with the player's session token using
+//! HMAC-SHA256, so the server can reject scores that were altered in transit
+//! or forged without a valid session.
+
+use serde::{Deserialize, Serialize};
+
+use crate::signing;
+
+/// Number of entries requested per page when the caller does not say.
+pub const DEFAULT_PAGE_SIZE: u32 = 20;
+
+/// Largest page the client will ask for. Servers are free to return fewer.
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// The result of one finished game, ready to be submitted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreSubmission {
+    /// Variant identifier, e.g. `"klondike"`.
+    pub variant: String,
+    /// Date of the daily challenge (`YYYY-MM-DD`), or `None` for a free game.
+    pub daily: Option<String>,
+    pub score: i32,
+    /// Time taken to win, in milliseconds.
+    pub time_ms: u32,
+    pub moves: u32,
+}
+
+impl ScoreSubmission {
+    /// Sign the submission with the session token handed out at login.
+    pub fn sign(self, session_token: &str) -> SignedSubmission {
+        let signature = signature(&self, session_token);
+        SignedSubmission {
+            submission: self,
+            signature,
+        }
+    }
+}
+
+/// A `ScoreSubmission` together with its HMAC-SHA256 signature (hex encoded).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedSubmission {
+    pub submission: ScoreSubmission,
+    pub signature: String,
+}
+
+impl SignedSubmission {
+    /// Check that the signature matches the submission for `session_token`.
+    pub fn verify(&self, session_token: &str) -> bool {
+        signing::verify_hmac_hex(session_token, &payload(&self.submission), &self.signature)
+    }
+}
+
+/// Compute the hex encoded HMAC of the submission's JSON encoding.
+///
+/// Struct fields always serialize in declaration order, so client and server
+/// produce identical bytes for the same submission.
+fn signature(submission: &ScoreSubmission, session_token: &str) -> String {
+    signing::hmac_hex(session_token, &payload(submission))
+}
+
+fn payload(submission: &ScoreSubmission) -> Vec<u8> {
+    serde_json::to_vec(submission).expect("submissions always serialize")
+}
+
+/// Which rankings to fetch, and which page of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RankingsQuery {
+    pub variant: String,
+    /// Restrict the rankings to one daily challenge.
+    pub daily: Option<String>,
+    /// Zero-based page number.
+    pub page: u32,
+    pub page_size: u32,
+}
+
+impl RankingsQuery {
+    /// All-time rankings for a variant, starting at the first page.
+    pub fn variant(variant: &str) -> Self {
+        Self {
+            variant: variant.to_string(),
+            daily: None,
+            page: 0,
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    /// Rankings for the daily challenge played on `date` (`YYYY-MM-DD`).
+    pub fn daily(variant: &str, date: &str) -> Self {
+        Self {
+            daily: Some(date.to_string()),
+            ..Self::variant(variant)
+        }
+    }
+
+    /// Select a page. The page size is clamped to `1..=MAX_PAGE_SIZE`.
+    pub fn page(mut self, page: u32, page_size: u32) -> Self {
+        self.page = page;
+        self.page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        self
+    }
+
+    /// The REST URL for this query below `base_url`. The variant and date
+    /// are percent-encoded, so neither can add path segments or parameters.
+    pub fn url(&self, base_url: &str) -> String {
+        let base = base_url.trim_end_matches('/');
+        let variant = percent_encode(&self.variant);
+        let board = match &self.daily {
+            Some(date) => format!("{variant}/daily/{}", percent_encode(date)),
+            None => variant,
+        };
+        format!(
+            "{base}/leaderboard/{board}?page={}&page_size={}",
+            self.page, self.page_size
+        )
+    }
+}
+
+/// `text` with every byte but the unreserved characters of RFC 3986
+/// percent-encoded, fit for a path segment or a query value.
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(b).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// The REST URL that accepts `SignedSubmission`s below `base_url`.
+pub fn submit_url(base_url: &str) -> String {
+    format!("{}/leaderboard/scores", base_url.trim_end_matches('/'))
+}
+
+/// One row of a leaderboard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    /// 1-based position on the leaderboard.
+    pub rank: u32,
+    pub player: String,
+    pub score: i32,
+    pub time_ms: u32,
+}
+
+/// One page of rankings as returned by the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RankingsPage {
+    pub variant: String,
+    pub daily: Option<String>,
+    pub page: u32,
+    pub total_pages: u32,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl RankingsPage {
+    /// Whether another page follows this one.
+    pub fn has_next(&self) -> bool {
+        self.page.saturating_add(1) < self.total_pages
+    }
+}
+
+#[cfg(feature = "wasm")]
+pub use web::LeaderboardClient;
+#[cfg(feature = "wasm")]
+pub(crate) use web::fetch_text;
+
+/// Browser side of the leaderboard: REST requests made with `fetch` and
+/// exposed to JavaScript as Promises.
+#[cfg(feature = "wasm")]
+mod web {
+    use super::*;
+    use crate::protocol::NetMessage;
+    use js_sys::Promise;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_futures::{JsFuture, future_to_promise};
+    use web_sys::{Headers, Request, RequestInit, Response};
+
+    /// Talks to a leaderboard REST service at `base_url`.
+    #[wasm_bindgen]
+    pub struct LeaderboardClient {
+        base_url: String,
+        session_token: Option<String>,
+    }
+
+    #[wasm_bindgen]
+    impl LeaderboardClient {
+        #[wasm_bindgen(constructor)]
+        pub fn new(base_url: &str) -> LeaderboardClient {
+            LeaderboardClient {
+                base_url: base_url.to_string(),
+                session_token: None,
+            }
+        }
+
+        /// Remember the session token used to sign submissions.
+        pub fn set_session_token(&mut self, token: &str) {
+            self.session_token = Some(token.to_string());
+        }
+
+        /// Submit a finished game. The Promise resolves once the server has
+        /// accepted the score and rejects with an error message otherwise.
+        pub fn submit_score(
+            &self,
+            variant: &str,
+            daily: Option<String>,
+            score: i32,
+            time_ms: u32,
+            moves: u32,
+        ) -> Result<Promise, JsValue> {
+            let signed = self.signed(variant, daily, score, time_ms, moves)?;
+            let body = serde_json::to_string(&signed).expect("submissions always serialize");
+
+            let headers = Headers::new()?;
+            headers.set("Content-Type", "application/json")?;
+            let init = RequestInit::new();
+            init.set_method("POST");
+            init.set_headers(&headers);
+            init.set_body(&JsValue::from_str(&body));
+            let request = Request::new_with_str_and_init(&submit_url(&self.base_url), &init)?;
+
+            Ok(future_to_promise(async move {
+                fetch_text(request).await?;
+                Ok(JsValue::UNDEFINED)
+            }))
+        }
+
+        /// Build the `submit_score` protocol message instead of calling the
+        /// REST endpoint, for sending over an open `NetworkClient`.
+        pub fn submit_score_message(
+            &self,
+            variant: &str,
+            daily: Option<String>,
+            score: i32,
+            time_ms: u32,
+            moves: u32,
+        ) -> Result<String, JsValue> {
+            let signed = self.signed(variant, daily, score, time_ms, moves)?;
+            Ok(NetMessage::SubmitScore(signed).to_json())
+        }
+
+        /// Fetch one page of all-time rankings for `variant`.
+        pub fn fetch_rankings(
+            &self,
+            variant: &str,
+            page: u32,
+            page_size: u32,
+        ) -> Result<Promise, JsValue> {
+            self.fetch(RankingsQuery::variant(variant).page(page, page_size))
+        }
+
+        /// Fetch one page of the rankings for the daily challenge on `date`.
+        pub fn fetch_daily_rankings(
+            &self,
+            variant: &str,
+            date: &str,
+            page: u32,
+            page_size: u32,
+        ) -> Result<Promise, JsValue> {
+            self.fetch(RankingsQuery::daily(variant, date).page(page, page_size))
+        }
+    }
+
+    impl LeaderboardClient {
+        fn signed(
+            &self,
+            variant: &str,
+            daily: Option<String>,
+            score: i32,
+            time_ms: u32,
+            moves: u32,
+        ) -> Result<SignedSubmission, JsValue> {
+            let token = self
+                .session_token
+                .as_deref()
+                .ok_or_else(|| JsValue::from_str("Not signed in"))?;
+            let submission = ScoreSubmission {
+                variant: variant.to_string(),
+                daily,
+                score,
+                time_ms,
+                moves,
+            };
+            Ok(submission.sign(token))
+        }
+
+        /// Resolve to the page as a plain JavaScript object.
+        fn fetch(&self, query: RankingsQuery) -> Result<Promise, JsValue> {
+            let request = Request::new_with_str(&query.url(&self.base_url))?;
+            Ok(future_to_promise(async move {
+                let text = fetch_text(request).await?;
+                // Decode once in Rust so malformed responses are reported
+                // here rather than deep inside the UI code.
+                serde_json::from_str::<RankingsPage>(&text)
+                    .map_err(|e| JsValue::from_str(&format!("Invalid rankings: {e}")))?;
+                js_sys::JSON::parse(&text)
+            }))
+        }
+    }
+
+    /// Perform a request and return the body of a successful response.
+    pub(crate) async fn fetch_text(request: Request) -> Result<String, JsValue> {
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window"))?;
+        let response: Response = JsFuture::from(window.fetch_with_request(&request))
+            .await?
+            .dyn_into()?;
+        if !response.ok() {
+            return Err(JsValue::from_str(&format!("HTTP {}", response.status())));
+        }
+        let text = JsFuture::from(response.text()?).await?;
+        text.as_string()
+            .ok_or_else(|| JsValue::from_str("Response body is not text"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::NetMessage;
+
+    fn submission() -> ScoreSubmission {
+        ScoreSubmission {
+            variant: "klondike".to_string(),
+            daily: Some("2025-07-31".to_string()),
+            score: 640,
+            time_ms: 183_000,
+            moves: 112,
+        }
+    }
+
+    #[test]
+    fn signatures_detect_tampering() {
+        let mut signed = submission().sign("token");
+        assert!(signed.verify("token"));
+        assert!(!signed.verify("other token"));
+
+        signed.submission.score += 1;
+        assert!(!signed.verify("token"));
+
+        let mut garbled = submission().sign("token");
+        garbled.signature.replace_range(0..2, "zz");
+        assert!(!garbled.verify("token"));
+        garbled.signature.truncate(3);
+        assert!(!garbled.verify("token"));
+    }
+
+    #[test]
+    fn query_urls() {
+        let all = RankingsQuery::variant("klondike").page(2, 500);
+        assert_eq!(
+            all.url("https://example.com/api/"),
+            "https://example.com/api/leaderboard/klondike?page=2&page_size=100"
+        );
+        let daily = RankingsQuery::daily("klondike", "2025-07-31");
+        assert_eq!(
+            daily.url("https://example.com"),
+            "https://example.com/leaderboard/klondike/daily/2025-07-31?page=0&page_size=20"
+        );
+        let sneaky = RankingsQuery::daily("klondike/../admin", "2025-07-31?page=9&x=y#");
+        assert_eq!(
+            sneaky.url("https://example.com"),
+            "https://example.com/leaderboard/klondike%2F..%2Fadmin/daily/\
+             2025-07-31%3Fpage%3D9%26x%3Dy%23?page=0&page_size=20"
+        );
+    }
+
+    #[test]
+    fn the_last_page_has_no_next_even_at_the_limit() {
+        let page = RankingsPage {
+            variant: "klondike".to_string(),
+            daily: None,
+            page: u32::MAX,
+            total_pages: u32::MAX,
+            entries: Vec::new(),
+        };
+        assert!(!page.has_next());
+    }
+
+    #[test]
+    fn protocol_round_trip() {
+        let msg = NetMessage::SubmitScore(submission().sign("token"));
+        assert_eq!(NetMessage::from_json(&msg.to_json()).unwrap(), msg);
+    }
+}
@@ -1,34 +1,306 @@
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-mod ecs;
-mod game;
-mod network;
+pub mod achievements;
+pub mod animation;
+pub mod app;
+pub mod arena;
+pub mod audit;
+pub mod autoplay;
+pub mod bench;
+pub mod bitboard;
+#[cfg(feature = "networking")]
+pub mod channel;
+pub mod clock;
+pub mod compress;
+pub mod daily;
+pub mod dirty;
+pub mod easing;
+pub mod ecs;
+pub mod emote;
+pub mod endpoint;
+pub mod error;
+pub mod events;
+pub mod faces;
+#[cfg(feature = "wasm")]
+pub mod fullscreen;
+pub mod game;
+pub mod handshake;
+pub mod haptics;
+#[cfg(feature = "solver")]
+pub mod hint;
+#[cfg(feature = "networking")]
+pub mod host;
+#[cfg(feature = "networking")]
+pub mod inbox;
+pub mod layout;
+#[cfg(feature = "networking")]
+pub mod leaderboard;
+pub mod lifecycle;
+#[cfg(feature = "networking")]
+pub mod lobby;
+pub mod logging;
+pub mod memview;
+#[cfg(all(feature = "wasm", feature = "networking"))]
+pub mod network;
+pub mod options;
+#[cfg(feature = "renderers")]
+pub mod particles;
+pub mod progress;
+#[cfg(feature = "networking")]
+pub mod protocol;
+#[cfg(feature = "networking")]
+pub mod ratelimit;
+pub mod rating;
+#[cfg(feature = "renderers")]
+pub mod render_worker;
+pub mod replay;
+pub mod resolution;
+pub mod rng;
+#[cfg(feature = "networking")]
+pub mod rollback;
+pub mod rules;
+pub mod save;
+pub mod scoring;
+#[cfg(feature = "networking")]
+pub mod session;
+pub mod share;
+pub mod signing;
+pub mod slots;
+#[cfg(feature = "renderers")]
+pub mod snapshot;
+// Built either way: `rules` asks its `Position` whether the board is stuck.
+// Without the `solver` feature nothing calls the search, and the linker
+// leaves it out.
+pub mod solver;
+#[cfg(feature = "networking")]
+pub mod spectator;
+pub mod stats;
+pub mod storage;
+pub mod supermove;
+#[cfg(feature = "networking")]
+pub mod sync;
+pub mod theme;
+pub mod timeattack;
+pub mod tooltip;
+#[cfg(feature = "networking")]
+pub mod tournament;
+#[cfg(feature = "networking")]
+pub mod transport;
+pub mod variant;
+pub mod view;
+pub mod wakelock;
+#[cfg(feature = "solver")]
+pub mod worker;
 
-use ecs::{Entity, World};
-use game::{Card, Deck, Pile, FaceUp};
+use achievements::{Achievement, Achievements};
+use animation::AnimationStyle;
+#[cfg(feature = "networking")]
+use audit::MoveSigner;
+use audit::{AuditAction, AuditError, AuditLog};
+use autoplay::{AutoPlay, AutoPlayMode, AutoPlaySettings};
+use compress::SizeStats;
+use ecs::World;
+use error::GameError;
+use events::GameEvent;
+use faces::{FaceSource, SuitStyle};
+use game::{Deck, Pile};
+use haptics::{Haptics, Pulse};
+use layout::{CanvasSize, LayoutSettings, Orientation};
+use lifecycle::{Abandoned, Lifecycle};
+#[cfg(all(feature = "wasm", feature = "networking"))]
 use network::NetworkClient;
+use options::GameOptions;
+#[cfg(feature = "networking")]
+use protocol::NetMessage;
+use rand::RngCore;
+#[cfg(all(feature = "wasm", feature = "networking"))]
+use ratelimit::{Admission, MessageClass, RateLimiter};
+use replay::{ReplayFile, ReplayPlayback};
+use resolution::Resolution;
+use rng::GameRng;
+use rules::{History, Move, Paused, UndoPolicy};
+use save::SavedGame;
+use scoring::Score;
+use slots::{SaveSlots, SlotInfo};
+use stats::Statistics;
+#[cfg(feature = "networking")]
+use sync::SyncDoc;
+use theme::{Theme, ThemePreset};
+#[cfg(all(feature = "wasm", feature = "networking"))]
+use transport::{LongPoll, Transport};
+use wakelock::WakeLock;
+
+/// `localStorage` key under which achievement progress is kept.
+const ACHIEVEMENTS_KEY: &str = "solitaire.achievements";
+
+/// `localStorage` key under which play statistics are kept.
+const STATS_KEY: &str = "solitaire.stats";
+
+/// `localStorage` key under which the game in progress is kept.
+const AUTOSAVE_KEY: &str = "solitaire.autosave";
+
+/// `localStorage` key under which the index of the save slots is kept.
+const SLOTS_KEY: &str = "solitaire.slots";
 
 /// High level game wrapper exposed to JavaScript.
-/// This struct owns the ECS `World` and a deck of cards.
-#[wasm_bindgen]
+/// This struct owns the ECS `World` and a deck of cards. The world always
+/// holds a `GameRng` resource from which the seed of every deal is drawn, and
+/// an `AuditLog` of the moves played on the current deal.
+///
+/// Without the `wasm` feature the same type is usable from plain Rust, which
+/// is handy for servers, command line play-testing and native unit tests.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct SolitaireGame {
     world: World,
     deck: Deck,
+    // Achievements outlive individual games, so they live here rather than in
+    // the world that `setup_board` recreates. So do the statistics.
+    achievements: Achievements,
+    stats: Statistics,
+    // Deals rejected with `mulligan` in this session.
+    mulligans_used: u32,
+    // Key used to sign audit entries in ranked games.
+    audit_key: Option<String>,
+    // Signs the moves streamed to the server, with the same key.
+    #[cfg(feature = "networking")]
+    move_signer: Option<MoveSigner>,
+    // Carried over into every new deal. `options.seed` is the seed of the
+    // current deal; `next_seed`, when set, is used for the next one.
+    options: GameOptions,
+    next_seed: Option<u64>,
+    // The player's own options, put aside while a deal that brings options
+    // of its own, a daily challenge or a replay, is on the board. The next
+    // new deal brings them back.
+    own_options: Option<GameOptions>,
+    // The daily challenge on the board, for its deadline.
+    daily_challenge: Option<daily::DailyChallenge>,
+    // Revision of the last autosave.
+    save_revision: u64,
+    autoplay: AutoPlaySettings,
+    thoughtful: bool,
+    tooltips: bool,
+    reduced_motion: bool,
+    suits: SuitStyle,
+    canvas: CanvasSize,
+    layout: LayoutSettings,
+    // The laid out board in wasm memory, for `state_view`.
+    state_view: memview::StateBuffer,
+    resolution: Resolution,
+    faces: FaceSource,
+    animation: AnimationStyle,
+    theme: Theme,
+    haptics: Haptics,
+    wake_lock: WakeLock,
+    #[cfg(feature = "wasm")]
+    on_achievement: Option<js_sys::Function>,
+    #[cfg(feature = "wasm")]
+    on_no_moves_left: Option<js_sys::Function>,
+    #[cfg(feature = "wasm")]
+    on_game_event: Option<js_sys::Function>,
+    // Receives the events of each frame as one array, see `EventBatch`.
+    #[cfg(feature = "wasm")]
+    on_game_events: Option<js_sys::Function>,
+    #[cfg(feature = "wasm")]
+    event_batch: events::EventBatch,
+    #[cfg(all(feature = "wasm", feature = "networking"))]
+    on_emote: Option<js_sys::Function>,
     // Networking is optional. We create the socket lazily when the player
     // decides to join a multiplayer session.
-    network: Option<NetworkClient>,
+    #[cfg(all(feature = "wasm", feature = "networking"))]
+    network: Option<Box<dyn Transport>>,
+    // The logical channels of the connection, see `channel`.
+    #[cfg(feature = "networking")]
+    channels: channel::Mux,
+    // Budgets for what the page sends, whichever transport carries it; see
+    // `ratelimit`.
+    #[cfg(all(feature = "wasm", feature = "networking"))]
+    limiter: RateLimiter,
+    // Whether and as whom this board's progress is shared.
+    #[cfg(feature = "networking")]
+    progress: progress::Sharing,
+    // Whether the server's socket keeps failing, so long polling is used.
+    #[cfg(all(feature = "wasm", feature = "networking"))]
+    fallback: transport::Fallback,
+    // The servers `connect` accepts.
+    #[cfg(all(feature = "wasm", feature = "networking"))]
+    server_policy: endpoint::ServerPolicy,
+    #[cfg(feature = "wasm")]
+    fullscreen: Option<fullscreen::Fullscreen>,
+    // The element the theme's CSS properties are published on.
+    #[cfg(feature = "wasm")]
+    theme_root: Option<web_sys::HtmlElement>,
+    // Runs `solve_async` off the main thread, once the page provides one.
+    #[cfg(all(feature = "wasm", feature = "solver"))]
+    solver_worker: Option<worker::SolverWorker>,
 }
 
-#[wasm_bindgen]
+impl Default for SolitaireGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl SolitaireGame {
-    /// Create a new solitaire game with an empty ECS world and a full deck.
-    #[wasm_bindgen(constructor)]
+    /// Create a new game of classic Klondike with default options.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new() -> SolitaireGame {
-        SolitaireGame {
-            world: World::new(),
-            deck: Deck::standard(),
-            network: None,
-        }
+        Self::with_options(GameOptions::default())
+    }
+
+    /// Create a game from a JSON `GameOptions` object such as
+    /// `{ "variant": "double_klondike", "scoring": "vegas" }`. Fields that
+    /// are left out keep their defaults.
+    pub fn new_game(options_json: &str) -> Result<SolitaireGame, GameError> {
+        let options = serde_json::from_str(options_json)
+            .map_err(|e| GameError::InvalidOptions(e.to_string()))?;
+        Ok(Self::with_options(options))
+    }
+
+    /// Create a game from a deal code, as made by `deal_code`. Its first
+    /// deal is exactly the deal the code was made from.
+    pub fn from_deal_code(code: &str) -> Result<SolitaireGame, GameError> {
+        let options = GameOptions::from_code(code).map_err(GameError::InvalidOptions)?;
+        Ok(Self::with_options(options))
+    }
+
+    /// The options of the current game as JSON, with the seed of the current
+    /// deal. Feeding them to `new_game` recreates this deal.
+    pub fn options(&self) -> String {
+        serde_json::to_string(&self.options).expect("options always serialize")
+    }
+
+    /// A short code that recreates the current deal and its rules.
+    pub fn deal_code(&self) -> String {
+        self.options.to_code()
+    }
+
+    /// A one-line summary of the game for sharing, such as "I won Klondike
+    /// in 112 moves / 3:41 (score 640) · deal ...", with the deal code.
+    pub fn result_summary(&self) -> String {
+        share::result_summary(
+            &self.world,
+            clock::now_ms(),
+            self.options.variant,
+            &self.deal_code(),
+        )
+    }
+
+    /// Create a game whose shuffles are fully determined by `seed`.
+    pub fn with_seed(seed: u64) -> SolitaireGame {
+        Self::with_rng(GameRng::from_seed(seed))
+    }
+
+    /// The seed of the random number generator used for shuffling.
+    pub fn seed(&self) -> u64 {
+        self.rng().seed()
+    }
+
+    /// Restart the random sequence from `seed`. The next `setup_board` call
+    /// deals exactly the same board as any other game that used this seed.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng_mut().reseed(seed);
     }
 
     /// Draw a card from the deck. Returns `None` when the deck is empty.
@@ -39,51 +311,2267 @@ impl SolitaireGame {
             .map(|c| format!("{:?} of {:?}", c.rank, c.suit))
     }
 
+    /// Choose the game dealt by the next `setup_board`: `"klondike"` or
+    /// `"double_klondike"`.
+    pub fn set_variant(&mut self, variant: &str) -> Result<(), GameError> {
+        let options = self.own_options.as_mut().unwrap_or(&mut self.options);
+        options.variant = variant.parse().map_err(GameError::UnknownVariant)?;
+        Ok(())
+    }
+
+    /// The variant being played, e.g. `"klondike"`.
+    pub fn variant(&self) -> String {
+        self.options.variant.to_string()
+    }
+
     /// Set up a fresh solitaire board by shuffling the deck and dealing the
     /// cards into their initial piles.
     ///
-    /// This method demonstrates how to spawn entities and attach components in
-    /// our tiny ECS. It does not implement every solitaire rule, but it
-    /// prepares the tableau, foundations, stock and waste piles so that the
-    /// game logic can be built on top.
+    /// Each card becomes an entity with `Card`, `Pile`, `PileIndex` and
+    /// `FaceUp` components, laid out as a standard Klondike deal by
+    /// `rules::deal`.
+    ///
+    /// A game still in progress is abandoned first, and counted as such
+    /// once a move has been made in it.
     pub fn setup_board(&mut self) {
-        // Reset the ECS world and shuffle the deck so every game is different.
-        self.world = World::new();
-        self.deck.shuffle();
+        self.abandon_in_progress();
+        self.own_options_back();
+        self.start_deal();
+    }
+
+    /// Start a new game with a JSON `GameOptions` object, as `new_game`
+    /// takes, in place of the current one, which is abandoned if it is still
+    /// in progress.
+    pub fn start_game(&mut self, options_json: &str) -> Result<(), GameError> {
+        let options: GameOptions = serde_json::from_str(options_json)
+            .map_err(|e| GameError::InvalidOptions(e.to_string()))?;
+        self.abandon_in_progress();
+        self.own_options_back();
+        self.options = options;
+        self.next_seed = options.seed;
+        self.start_deal();
+        Ok(())
+    }
+
+    /// Deal the current deal again from the start. A game still in
+    /// progress is abandoned first.
+    pub fn reset(&mut self) {
+        self.abandon_in_progress();
+        self.next_seed = self.options.seed;
+        self.start_deal();
+    }
+
+    /// Give up the game in progress. It counts as lost, its clock stops and
+    /// no more moves are taken until the next deal.
+    pub fn abandon(&mut self) -> Result<(), GameError> {
+        let event = lifecycle::abandon(&mut self.world, clock::now_ms())
+            .ok_or(GameError::NoGameInProgress)?;
+        self.stop_autoplay();
+        self.dispatch(vec![event]);
+        Ok(())
+    }
+
+    /// Where the game is in its life: `"idle"` before the first deal, then
+    /// `"playing"`, `"paused"`, `"won"`, `"timed_out"`, `"abandoned"`, or
+    /// `"replay"` while a replay is loaded.
+    pub fn lifecycle(&self) -> String {
+        Lifecycle::of(&self.world).name().to_string()
+    }
+
+    /// The daily challenge of `date` (`YYYY-MM-DD`, the player's today) in
+    /// the current variant, as JSON: `{ date, seed, options, deadline_ms,
+    /// official }`. `official` is false when the server has not handed
+    /// out the challenge and it was derived locally.
+    pub fn daily_challenge(&self, date: &str) -> Result<String, GameError> {
+        let challenge = self.daily(date)?;
+        Ok(serde_json::to_string(&challenge).expect("challenges always serialize"))
+    }
+
+    /// Deal the daily challenge of `date`, with its options. They last as
+    /// long as the challenge: the next new deal is dealt with the player's
+    /// own again.
+    pub fn start_daily_challenge(&mut self, date: &str) -> Result<(), GameError> {
+        let challenge = self.daily(date)?;
+        self.abandon_in_progress();
+        self.own_options.get_or_insert(self.options);
+        self.options = challenge.game_options();
+        self.next_seed = self.options.seed;
+        self.daily_challenge = Some(challenge);
+        self.start_deal();
+        Ok(())
+    }
+
+    /// Reject the current deal and deal a new one without it counting as a
+    /// loss. Only allowed before the first move, up to the `mulligans` of
+    /// the options per session, and never in a daily challenge.
+    pub fn mulligan(&mut self) -> Result<(), GameError> {
+        if self.options.daily || self.options.mulligans == 0 {
+            return Err(GameError::MulliganNotAllowed);
+        }
+        if self.mulligans_left() == 0 {
+            return Err(GameError::NoMulligansLeft);
+        }
+        if self
+            .world
+            .resource::<History>()
+            .is_some_and(|h| h.moves() > 0)
+        {
+            return Err(GameError::IllegalMove(
+                "a deal can only be rejected before the first move",
+            ));
+        }
+        self.mulligans_used += 1;
+        self.stats.record_mulligan();
+        self.own_options_back();
+        self.start_deal();
+        Ok(())
+    }
+
+    /// Mulligans left in this session.
+    pub fn mulligans_left(&self) -> u32 {
+        if self.options.daily {
+            return 0;
+        }
+        self.options.mulligans.saturating_sub(self.mulligans_used)
+    }
+
+    /// Play statistics across all sessions as JSON:
+    /// `{ played, won, won_without_undo, abandoned, mulligans, best_time_ms,
+    /// rating }`.
+    pub fn statistics(&self) -> String {
+        self.stats.to_json()
+    }
+
+    /// The statistics to back up or move to another device, as JSON for
+    /// `import_stats_json`.
+    pub fn export_stats_json(&self) -> String {
+        self.stats.export()
+    }
 
-        // We will spawn an entity for each card in the deck and attach the
-        // relevant components.
-        for card in self.deck.cards.iter() {
-            // Create a new entity identifier.
-            let entity = self.world.spawn();
+    /// Merge statistics from `export_stats_json` into these: games are added
+    /// up and the best time kept. Importing the same export twice counts
+    /// its games twice.
+    pub fn import_stats_json(&mut self, json: &str) -> Result<(), GameError> {
+        let imported = Statistics::import(json).map_err(GameError::InvalidOptions)?;
+        self.stats.merge(&imported);
+        storage::save(STATS_KEY, &self.stats.to_json());
+        self.autosave();
+        Ok(())
+    }
+
+    /// Whether a game in progress was saved, by this session or an earlier
+    /// one.
+    pub fn has_autosave(&self) -> bool {
+        storage::load(AUTOSAVE_KEY).is_some()
+    }
+
+    /// Pick up the saved game in progress where it was left, abandoning the
+    /// one on the board if it is still in progress.
+    pub fn load_autosave(&mut self) -> Result<(), GameError> {
+        let saved = storage::load(AUTOSAVE_KEY).ok_or(GameError::NoSavedGame)?;
+        self.restore(&SavedGame::from_packed(&saved)?)
+    }
+
+    /// The save slots, most recently saved first, as a JSON array of
+    /// `{ name, variant, saved_ms, moves, cards_home, description }`.
+    pub fn save_slots(&self) -> String {
+        serde_json::to_string(&Self::slot_index().list()).expect("slot indexes always serialize")
+    }
+
+    /// Save the game in progress in the slot `name`, replacing what was in
+    /// it.
+    pub fn save_to_slot(&mut self, name: &str) -> Result<(), GameError> {
+        let name = slots::validate_name(name)?;
+        let saved = SavedGame::capture(
+            &self.world,
+            self.options,
+            self.save_revision,
+            clock::now_ms(),
+        );
+        let mut index = Self::slot_index();
+        index.put(SlotInfo::of(name, &self.world, &saved));
+        storage::save(&slots::slot_key(name), &saved.to_packed());
+        storage::save(SLOTS_KEY, &index.to_json());
+        Ok(())
+    }
+
+    /// Pick up the game saved in the slot `name`. It becomes the game in
+    /// progress, in place of one still in progress, which is abandoned; the
+    /// slot keeps it as it was saved.
+    pub fn load_from_slot(&mut self, name: &str) -> Result<(), GameError> {
+        let saved = storage::load(&slots::slot_key(name.trim())).ok_or(GameError::NoSavedGame)?;
+        self.restore(&SavedGame::from_packed(&saved)?)?;
+        self.autosave();
+        Ok(())
+    }
+
+    /// Empty the slot `name`, returning whether there was a game in it.
+    pub fn delete_slot(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        let mut index = Self::slot_index();
+        storage::remove(&slots::slot_key(name));
+        let removed = index.remove(name);
+        storage::save(SLOTS_KEY, &index.to_json());
+        removed
+    }
 
-            // Every entity gets a `Card` component storing its suit and rank.
-            self.world.add_component(entity, *card);
+    /// The ranked rating as JSON, `{ rating, games }`, or `null` before the
+    /// server has told it.
+    pub fn rating(&self) -> String {
+        serde_json::to_string(&self.stats.rating()).expect("ratings always serialize")
+    }
+
+    /// What a ranked game against an opponent rated `opponent` would do to
+    /// the rating, as JSON: `{ win, draw, loss }` in whole points, such as
+    /// `{ "win": 12, "draw": 2, "loss": -8 }`.
+    pub fn rating_preview(&self, opponent: f64) -> String {
+        let preview = rating::preview(&self.stats.rating().unwrap_or_default(), opponent);
+        serde_json::to_string(&preview).expect("previews always serialize")
+    }
+
+    /// Turn the top card of the stock onto the waste.
+    pub fn draw_from_stock(&mut self) -> Result<(), GameError> {
+        self.play_move(Move::Draw)
+    }
+
+    /// Turn the waste back over once the stock has run out.
+    pub fn recycle_waste(&mut self) -> Result<(), GameError> {
+        self.play_move(Move::Recycle)
+    }
 
-            // Cards start face down by default.
-            self.world.add_component(entity, FaceUp(false));
+    /// Move the top `count` cards of one pile onto another.
+    ///
+    /// Piles are named `"waste"`, `"foundation-0"` to `"foundation-3"` and
+    /// `"tableau-0"` to `"tableau-6"`.
+    pub fn move_cards(&mut self, from: &str, to: &str, count: usize) -> Result<(), GameError> {
+        let from: Pile = from.parse().map_err(GameError::UnknownPile)?;
+        let to: Pile = to.parse().map_err(GameError::UnknownPile)?;
+        self.play_move(Move::Transfer { from, to, count })
+    }
 
-            // Place the card into the stock pile. A real game would deal cards
-            // to the tableau here, but keeping it simple lets beginners focus
-            // on the ECS mechanics first.
-            self.world.add_component(entity, Pile::Stock);
+    /// Take back the most recent move. Auto-complete and solution playback
+    /// count as one move, and are stopped and taken back as a whole. Moves
+    /// cannot be taken back in co-op, where the other players built on them.
+    pub fn undo(&mut self) -> Result<(), GameError> {
+        #[cfg(feature = "networking")]
+        if rollback::is_active(&self.world) {
+            return Err(GameError::IllegalMove(
+                "moves cannot be taken back in co-op",
+            ));
         }
+        self.check_time();
+        let events = rules::undo(&mut self.world)?;
+        self.stop_autoplay();
+        self.record_audit(AuditAction::Undo);
+        self.dispatch(events);
+        Ok(())
     }
 
-    /// Connect to a multiplayer server using a WebSocket URL.
+    /// Pause the game: the clock stops and moves are refused until `resume`.
+    /// With `hide_cards` the renderer is asked to cover the board.
     ///
-    /// Returns an error if the connection could not be established.
-    pub fn connect(&mut self, url: &str) -> Result<(), JsValue> {
-        let client = NetworkClient::new(url)?;
-        self.network = Some(client);
+    /// Does nothing when the game is already paused or over.
+    pub fn pause(&mut self, hide_cards: bool) {
+        if self.is_paused() || self.is_won() || self.world.resource::<Abandoned>().is_some() {
+            return;
+        }
+        if let Some(clock) = self.world.resource_mut::<clock::GameClock>() {
+            clock.pause(clock::now_ms());
+        }
+        self.world.insert_resource(Paused { hide_cards });
+        self.dispatch(vec![GameEvent::Paused { hide_cards }]);
+    }
+
+    /// Carry on after `pause`.
+    pub fn resume(&mut self) {
+        if self.world.remove_resource::<Paused>().is_none() {
+            return;
+        }
+        if let Some(clock) = self.world.resource_mut::<clock::GameClock>() {
+            clock.resume(clock::now_ms());
+        }
+        self.dispatch(vec![GameEvent::Resumed]);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.world.resource::<Paused>().is_some()
+    }
+
+    /// Whether the board should currently be hidden from view.
+    pub fn cards_hidden(&self) -> bool {
+        self.world
+            .resource::<Paused>()
+            .is_some_and(|p| p.hide_cards)
+    }
+
+    /// Choose how undo works from now on: `limit` undos per game (`None`
+    /// for unlimited, `0` to disable undo as in Vegas or ranked play), each
+    /// costing `penalty` points. A negative penalty is refused.
+    pub fn set_undo_policy(&mut self, limit: Option<u32>, penalty: i32) -> Result<(), GameError> {
+        let penalty = u32::try_from(penalty).map_err(|_| {
+            GameError::InvalidOptions(format!("an undo penalty cannot be negative ({penalty})"))
+        })?;
+        self.options.undo = UndoPolicy { limit, penalty };
+        if let Some(own) = &mut self.own_options {
+            own.undo = self.options.undo;
+        }
+        self.world.insert_resource(self.options.undo);
         Ok(())
     }
 
-    /// Send a text message over the WebSocket if it is connected.
-    pub fn send(&self, msg: &str) -> Result<(), JsValue> {
-        match &self.network {
-            Some(net) => net.send(msg),
-            None => Err(JsValue::from_str("Not connected")),
+    /// Undos left in this game, or `None` when undo is unlimited.
+    pub fn undos_left(&self) -> Option<u32> {
+        let used = self.world.resource::<History>().map_or(0, History::undos);
+        self.options.undo.remaining(used)
+    }
+
+    /// How fast auto-complete and solution playback move cards, and whether
+    /// they animate at all or jump straight to the end.
+    pub fn set_autoplay_settings(&mut self, cards_per_second: f64, animate: bool) {
+        self.autoplay = AutoPlaySettings {
+            cards_per_second,
+            animate,
+            ..self.autoplay
+        };
+        self.world.insert_resource(self.autoplay);
+    }
+
+    /// With smart moves on, `click` only sends a card to a foundation when
+    /// no tableau card could still need it.
+    pub fn set_smart_moves(&mut self, smart: bool) {
+        self.autoplay.smart_moves = smart;
+        self.world.insert_resource(self.autoplay);
+    }
+
+    /// Handle a single click on a pile: the stock draws (or recycles), any
+    /// other pile sends its top card to a foundation if it may go there.
+    /// Returns whether a move was made.
+    pub fn click(&mut self, pile: &str) -> Result<bool, GameError> {
+        let pile: Pile = pile.parse().map_err(GameError::UnknownPile)?;
+        match autoplay::click_move(&self.world, pile, self.autoplay.smart_moves) {
+            Some(mv) => self.play_move(mv).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    /// The auto-play settings as JSON:
+    /// `{ cards_per_second, animate, smart_moves }`.
+    pub fn autoplay_settings(&self) -> String {
+        serde_json::to_string(&self.autoplay).expect("settings always serialize")
+    }
+
+    /// Whether auto-complete could finish the game now.
+    pub fn can_auto_complete(&self) -> bool {
+        autoplay::can_auto_complete(&self.world)
+    }
+
+    /// Start moving every card to the foundations, paced by `advance`.
+    pub fn start_auto_complete(&mut self) -> Result<(), GameError> {
+        if !self.can_auto_complete() {
+            return Err(GameError::IllegalMove(
+                "auto-complete needs every tableau card face up and the stock within reach",
+            ));
+        }
+        self.world
+            .insert_resource(AutoPlay::new(AutoPlayMode::AutoComplete));
+        Ok(())
+    }
+
+    pub fn stop_autoplay(&mut self) {
+        self.world.remove_resource::<AutoPlay>();
+    }
+
+    pub fn is_autoplaying(&self) -> bool {
+        self.world.resource::<AutoPlay>().is_some()
+    }
+
+    /// Let `dt_ms` milliseconds of auto-play pass and return the number of
+    /// moves played. Call once per frame; does nothing unless auto-play was
+    /// started, and stops by itself once the game is over. It is also where
+    /// a time attack notices its countdown running out, where messages from
+    /// the network are handled, even while paused, and where the frame's
+    /// events go out to `on_game_events`.
+    pub fn advance(&mut self, dt_ms: f64) -> u32 {
+        let played = self.play_due(dt_ms);
+        self.deliver_events();
+        // Nothing here reads the removals, and the particles, animations and
+        // emotes remove components every frame.
+        self.world.clear_trackers();
+        played
+    }
+
+    /// Switch thoughtful Klondike on or off: every face-down card is shown
+    /// to the player (greyed), though the rules still treat it as face down.
+    pub fn set_thoughtful(&mut self, thoughtful: bool) {
+        self.thoughtful = thoughtful;
+        if thoughtful {
+            self.world.insert_resource(view::Thoughtful);
+        } else {
+            self.world.remove_resource::<view::Thoughtful>();
+        }
+    }
+
+    /// The table as the player should see it, as JSON: every pile with its
+    /// cards bottom to top, each `face_up`, `revealed` or `hidden`. Hidden
+    /// cards carry no rank or suit.
+    pub fn board_state(&self) -> String {
+        serde_json::to_string(&view::BoardView::from_world(&self.world))
+            .expect("board views always serialize")
+    }
+
+    /// Tell the layout how big the canvas is, in CSS pixels. Call it
+    /// whenever the canvas is resized.
+    pub fn set_canvas_size(&mut self, width: f32, height: f32) {
+        self.canvas = CanvasSize { width, height };
+        self.world.insert_resource(self.canvas);
+        self.publish_theme();
+    }
+
+    /// Fit the table to a canvas resized to `width` by `height` CSS pixels:
+    /// the card size, pile positions and fans are recomputed, switching to
+    /// the portrait or landscape preset when the orientation changes, and
+    /// the cards glide over to their new places in the following `scene()`s.
+    pub fn handle_resize(&mut self, width: f32, height: f32) {
+        let canvas = CanvasSize { width, height };
+        let orientation = Orientation::of(canvas);
+        if orientation != Orientation::of(self.canvas) {
+            self.layout = LayoutSettings {
+                mirrored: self.layout.mirrored,
+                ..LayoutSettings::preset(orientation)
+            };
+            self.world.insert_resource(self.layout);
+        }
+        let before = animation::positions(&self.world);
+        self.set_canvas_size(width, height);
+        layout::layout_system(&mut self.world);
+        let easing = animation::style(&self.world).moves;
+        animation::start_slides(&mut self.world, &before, clock::now_ms(), easing);
+    }
+
+    /// Tell the renderer the screen's `devicePixelRatio`, for instance when
+    /// the window moves to another monitor.
+    pub fn set_device_pixel_ratio(&mut self, ratio: f32) {
+        self.resolution.device_pixel_ratio = ratio;
+        self.world.insert_resource(self.resolution);
+    }
+
+    /// Render at 1x whatever the screen, trading sharpness for speed on
+    /// low-end devices.
+    pub fn set_low_resolution(&mut self, low_resolution: bool) {
+        self.resolution.low_resolution = low_resolution;
+        self.world.insert_resource(self.resolution);
+    }
+
+    /// The size to give the canvas's backing store, in device pixels:
+    /// `[width, height]`. Pass it to `RenderWorker::resize`, or set the
+    /// canvas's `width` and `height` to it.
+    pub fn backing_store_size(&self) -> Vec<u32> {
+        let (width, height) = self.resolution.backing_store(self.canvas);
+        vec![width, height]
+    }
+
+    /// Turn the short vibrations on moves, refused moves and wins on or off.
+    /// They only happen on devices that can vibrate.
+    pub fn set_haptics(&mut self, enabled: bool) {
+        self.haptics.enabled = enabled;
+    }
+
+    /// Whether moves are felt as vibrations: turned on and supported.
+    pub fn haptics_active(&self) -> bool {
+        self.haptics.is_active()
+    }
+
+    /// Keep the screen from sleeping while a game is in progress, which is
+    /// the default, or let it sleep as usual.
+    pub fn set_wake_lock(&mut self, enabled: bool) {
+        self.wake_lock.set_enabled(enabled);
+    }
+
+    /// Whether the screen is meant to stay awake now: the lock is on and a
+    /// game is being played.
+    pub fn wake_lock_held(&self) -> bool {
+        self.wake_lock.is_held()
+    }
+
+    /// Use the animation style of a theme: `"classic"` or `"playful"`.
+    pub fn set_animation_theme(&mut self, theme: &str) -> Result<(), GameError> {
+        let theme = serde_json::from_value(serde_json::Value::from(theme))
+            .map_err(|_| GameError::InvalidOptions(format!("unknown theme \"{theme}\"")))?;
+        self.animation = AnimationStyle::theme(theme);
+        self.world.insert_resource(self.animation);
+        Ok(())
+    }
+
+    /// Play moves, flips and deals without animation, and skip the win
+    /// celebration. Starts on when the system asks for reduced motion.
+    pub fn set_reduced_motion(&mut self, reduced: bool) {
+        self.reduced_motion = reduced;
+        if reduced {
+            self.world.insert_resource(animation::ReducedMotion);
+        } else {
+            self.world.remove_resource::<animation::ReducedMotion>();
+        }
+    }
+
+    pub fn reduced_motion(&self) -> bool {
+        self.reduced_motion
+    }
+
+    /// Change the easing of some animations with a JSON object such as
+    /// `{ "moves": "back", "deal": "bounce" }`. The curves are `"linear"`,
+    /// `"ease_out_cubic"`, `"back"` and `"bounce"`; animations left out
+    /// keep theirs.
+    pub fn set_animation_style(&mut self, style_json: &str) -> Result<(), GameError> {
+        let mut style = serde_json::to_value(self.animation).expect("styles always serialize");
+        let changes: serde_json::Map<String, serde_json::Value> = serde_json::from_str(style_json)
+            .map_err(|e| GameError::InvalidOptions(e.to_string()))?;
+        style
+            .as_object_mut()
+            .expect("styles serialize as objects")
+            .extend(changes);
+        self.animation =
+            serde_json::from_value(style).map_err(|e| GameError::InvalidOptions(e.to_string()))?;
+        self.world.insert_resource(self.animation);
+        Ok(())
+    }
+
+    /// The animation style as JSON.
+    pub fn animation_style(&self) -> String {
+        serde_json::to_string(&self.animation).expect("styles always serialize")
+    }
+
+    /// Switch to one of the themes that come with the game: `"light"` or
+    /// `"dark"`.
+    pub fn set_theme(&mut self, theme: &str) -> Result<(), GameError> {
+        let preset: ThemePreset = serde_json::from_value(serde_json::Value::from(theme))
+            .map_err(|_| GameError::InvalidOptions(format!("unknown theme \"{theme}\"")))?;
+        self.use_theme(Theme::preset(preset));
+        Ok(())
+    }
+
+    /// Change some theme values with a JSON object such as
+    /// `{ "table_color": "#203040", "card_radius": 0.12 }`. Values left out
+    /// keep theirs. The table background is one of
+    /// `{ "kind": "solid" }`, `{ "kind": "gradient", "center": ..., "edge": ... }`,
+    /// `{ "kind": "texture", "url": ..., "tile_size": 64 }` and
+    /// `{ "kind": "felt", "grain": 0.4 }`, and the card back one of
+    /// `{ "design": "lattice" }`, `"stripes"`, `"dots"`, `"plain"` and
+    /// `{ "design": "image", "url": ... }`.
+    pub fn set_custom_theme(&mut self, theme_json: &str) -> Result<(), GameError> {
+        let mut theme = serde_json::to_value(&self.theme).expect("themes always serialize");
+        let changes: serde_json::Map<String, serde_json::Value> = serde_json::from_str(theme_json)
+            .map_err(|e| GameError::InvalidOptions(e.to_string()))?;
+        theme
+            .as_object_mut()
+            .expect("themes serialize as objects")
+            .extend(changes);
+        let theme =
+            serde_json::from_value(theme).map_err(|e| GameError::InvalidOptions(e.to_string()))?;
+        self.use_theme(theme);
+        Ok(())
+    }
+
+    /// The theme as JSON.
+    pub fn theme(&self) -> String {
+        serde_json::to_string(&self.theme).expect("themes always serialize")
+    }
+
+    /// The theme as a JSON object of CSS custom properties, such as
+    /// `{ "--solitaire-table-color": "#1f6b3a", ... }`.
+    pub fn theme_css(&self) -> String {
+        let properties: serde_json::Map<String, serde_json::Value> = self
+            .theme
+            .css_properties(layout::metrics(&self.world).card_width)
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.into()))
+            .collect();
+        serde_json::Value::Object(properties).to_string()
+    }
+
+    /// Load card faces from `{base_url}/{key}.png` images, or draw them in
+    /// code when `base_url` is not given.
+    pub fn set_card_images(&mut self, base_url: Option<String>) {
+        self.faces = match base_url {
+            Some(base_url) => FaceSource::Images { base_url },
+            None => FaceSource::Generated,
+        };
+    }
+
+    /// Tell suits apart by more than red and black: with `four_color`
+    /// diamonds are blue and clubs green, and with `badges` each corner index
+    /// gets a badge with the suit's initial. Tooltips name the colours.
+    /// Reload the atlas afterwards.
+    pub fn set_suit_style(&mut self, four_color: bool, badges: bool) {
+        self.suits = SuitStyle { four_color, badges };
+        self.world.insert_resource(self.suits);
+        dirty::invalidate(&mut self.world);
+    }
+
+    /// Every card face and the back as JSON, for the renderer to load:
+    /// `[{ key, card, source: "svg", svg } | { key, card, source: "url", url }]`.
+    /// The back follows the theme, so load it again after switching themes.
+    pub fn card_atlas(&self) -> String {
+        serde_json::to_string(&faces::atlas(
+            &self.faces,
+            &self.theme.card_back,
+            self.suits,
+        ))
+        .expect("atlases always serialize")
+    }
+
+    /// Change the fan offsets of the layout. `tableau_fan` and
+    /// `face_down_fan` are in card heights, `waste_fan` in card widths;
+    /// `waste_fan_cards` is how many waste cards are fanned out.
+    pub fn set_layout_settings(
+        &mut self,
+        tableau_fan: f32,
+        face_down_fan: f32,
+        waste_fan: f32,
+        waste_fan_cards: usize,
+    ) {
+        self.layout = LayoutSettings {
+            tableau_fan,
+            face_down_fan,
+            waste_fan,
+            waste_fan_cards,
+            ..self.layout
+        };
+        self.world.insert_resource(self.layout);
+    }
+
+    /// Mirror the table for left-handed play: stock and waste on the right,
+    /// foundations on the left.
+    pub fn set_mirrored(&mut self, mirrored: bool) {
+        self.layout.mirrored = mirrored;
+        self.world.insert_resource(self.layout);
+    }
+
+    /// The layout settings as JSON.
+    pub fn layout_settings(&self) -> String {
+        serde_json::to_string(&self.layout).expect("settings always serialize")
+    }
+
+    /// Lay out the table for the current canvas size and return it as JSON:
+    /// `{ card_width, card_height, cards: [{ entity, x, y, z }] }`.
+    pub fn layout(&mut self) -> String {
+        self.apply_fullscreen_resize();
+        serde_json::to_string(&layout::TableLayout::compute(&mut self.world))
+            .expect("layouts always serialize")
+    }
+
+    /// Lay out the table like `layout()`, but write it into the state view
+    /// instead of JSON, and return the view's generation. A renderer calls
+    /// this every frame and reads the cards straight out of wasm memory
+    /// through `state_view()`, fetching a new view only when the generation
+    /// differs from the one it last saw. Each card is 8 numbers, bottom
+    /// card first: `entity, card (0-51, or -1 when hidden), x, y, z,
+    /// face_up (0 or 1), pile, index in pile`, where the pile is 0 for the
+    /// stock, 1 for the waste, 16 + n for foundation n and 32 + n for
+    /// tableau pile n.
+    pub fn update_state_view(&mut self) -> u32 {
+        self.apply_fullscreen_resize();
+        layout::layout_system(&mut self.world);
+        self.state_view.update(&self.world);
+        self.state_view.generation()
+    }
+
+    /// Changes whenever views returned by `state_view()` may have become
+    /// invalid.
+    pub fn state_view_generation(&mut self) -> u32 {
+        self.state_view.generation()
+    }
+
+    /// What changed on the canvas since the last call, as JSON:
+    /// `{ full, rects: [{ x, y, width, height }], counters }`. Call it after
+    /// `layout()` and repaint only those rectangles, or everything when
+    /// `full` is set.
+    pub fn take_dirty_regions(&mut self) -> String {
+        serde_json::to_string(&dirty::take(&mut self.world)).expect("regions always serialize")
+    }
+
+    /// The card drawn at canvas point `(x, y)` by the last `layout()`.
+    pub fn card_at(&self, x: f32, y: f32) -> Option<u32> {
+        layout::card_at(&self.world, x, y)
+    }
+
+    /// The pile at canvas point `(x, y)`, even if it is empty, by name.
+    pub fn pile_at(&self, x: f32, y: f32) -> Option<String> {
+        layout::pile_at(&self.world, x, y).map(|pile| pile.to_string())
+    }
+
+    /// Pick up the top `count` cards of `pile` to drag them. They are laid
+    /// out above every other card until dropped.
+    pub fn pick_up(&mut self, pile: &str, count: usize) -> Result<(), GameError> {
+        let pile: Pile = pile.parse().map_err(GameError::UnknownPile)?;
+        layout::pick_up(&mut self.world, pile, count).map(|_| ())
+    }
+
+    /// Everything the player may know about the card at canvas point
+    /// `(x, y)`, as JSON, or `None` when there is no card: `{ entity, card,
+    /// visibility, pile, index, x, y, z, targets, movable }`.
+    pub fn card_info_at(&self, x: f32, y: f32) -> Option<String> {
+        tooltip::card_info_at(&self.world, x, y)
+            .map(|info| serde_json::to_string(&info).expect("card info always serializes"))
+    }
+
+    /// Report the pointer resting at canvas point `(x, y)`, for tooltips.
+    pub fn hover(&mut self, x: f32, y: f32) {
+        tooltip::hover(&mut self.world, Some((x, y)), clock::now_ms());
+    }
+
+    /// Report the pointer leaving the canvas.
+    pub fn clear_hover(&mut self) {
+        tooltip::hover(&mut self.world, None, clock::now_ms());
+    }
+
+    /// Have `scene()` carry a tooltip for the card under the pointer.
+    pub fn set_tooltips(&mut self, enabled: bool) {
+        self.tooltips = enabled;
+        if enabled {
+            self.world.insert_resource(tooltip::Tooltips);
+        } else {
+            self.world.remove_resource::<tooltip::Tooltips>();
+        }
+    }
+
+    /// Follow the pointer dragging the picked-up cards, at canvas point
+    /// `(x, y)`. From then on `scene()` carries a ghost of the stack under
+    /// the pointer and the piles that would take it.
+    pub fn drag_to(&mut self, x: f32, y: f32) {
+        layout::drag_to(&mut self.world, x, y);
+    }
+
+    /// The piles the picked-up cards could be dropped on, as a JSON array of
+    /// names such as `["tableau-3","foundation-0"]`.
+    pub fn drop_targets(&self) -> String {
+        let targets: Vec<String> = layout::drop_targets(&self.world)
+            .iter()
+            .map(Pile::to_string)
+            .collect();
+        serde_json::to_string(&targets).expect("pile names always serialize")
+    }
+
+    /// Drop the cards picked up with `pick_up` onto the pile `to`, moving
+    /// them there if the rules allow it. Either way they stop being dragged.
+    pub fn drop_on(&mut self, to: &str) -> Result<(), GameError> {
+        let to: Pile = to.parse().map_err(GameError::UnknownPile)?;
+        let dragged = layout::drop_dragged(&mut self.world);
+        let Some(&first) = dragged.first() else {
+            return Err(GameError::IllegalMove("no cards are being dragged"));
+        };
+        let from = *self
+            .world
+            .get_component::<Pile>(first)
+            .expect("dragged cards lie on a pile");
+        self.play_move(Move::Transfer {
+            from,
+            to,
+            count: dragged.len(),
+        })
+    }
+
+    /// Put dragged cards back where they came from.
+    pub fn cancel_drag(&mut self) {
+        layout::drop_dragged(&mut self.world);
+    }
+
+    /// Milliseconds left in a time attack, or `None` in other games.
+    pub fn time_left_ms(&self) -> Option<f64> {
+        let attack = self.world.resource::<timeattack::TimeAttack>()?;
+        Some(attack.remaining_ms(&self.world, clock::now_ms()))
+    }
+
+    /// Whether every card has reached the foundations.
+    pub fn is_won(&self) -> bool {
+        rules::is_won(&self.world)
+    }
+
+    /// Number of moves made in the current game.
+    pub fn moves(&self) -> u32 {
+        self.world.resource::<History>().map_or(0, History::moves)
+    }
+
+    /// Score of the current game, including the time bonus once it is won.
+    pub fn score(&self) -> i32 {
+        self.world.resource::<Score>().map_or(0, Score::points)
+    }
+
+    /// Sign the audit entries of the next deal with `key`, the per-session key
+    /// handed out by the ranked matchmaking server, and from now on stream
+    /// every action to the server signed with it, numbered from 0. Keys
+    /// arriving in an `authenticated` message are set on their own.
+    pub fn set_audit_key(&mut self, key: &str) {
+        self.audit_key = Some(key.to_string());
+        #[cfg(feature = "networking")]
+        {
+            self.move_signer = Some(MoveSigner::new(key.to_string()));
+        }
+    }
+
+    /// The audit log of the current deal as a JSON array.
+    pub fn audit_log(&self) -> String {
+        self.world
+            .resource::<AuditLog>()
+            .map_or_else(|| "[]".to_string(), AuditLog::to_json)
+    }
+
+    /// The audit log deflated for sending, see `compress::pack`.
+    /// `verify_audit_log` reads it as it is.
+    pub fn audit_log_packed(&self) -> String {
+        compress::pack(&self.audit_log())
+    }
+
+    /// What packing saves on the game in progress, as JSON:
+    /// `{ save, audit_log }`, each `{ json_bytes, packed_bytes, ratio }`.
+    pub fn size_stats(&self) -> String {
+        let saved = SavedGame::capture(
+            &self.world,
+            self.options,
+            self.save_revision,
+            clock::now_ms(),
+        );
+        serde_json::json!({
+            "save": SizeStats::of(&saved.to_json()),
+            "audit_log": SizeStats::of(&self.audit_log()),
+        })
+        .to_string()
+    }
+
+    /// The current deal as a replay file, see `replay`, or `None` before
+    /// the first deal.
+    pub fn export_replay(&self) -> Option<String> {
+        ReplayFile::record(&self.world, self.options, clock::now_ms()).map(|r| r.to_json())
+    }
+
+    /// Deal the game of a replay file, or of an audit log, again, ready to
+    /// be stepped through with `step_replay`. Its actions are not counted in
+    /// the statistics or saved. A game still in progress is abandoned first.
+    pub fn load_replay(&mut self, text: &str) -> Result<(), GameError> {
+        let replay = ReplayFile::from_json(text)?;
+        self.abandon_in_progress();
+        self.own_options.get_or_insert(self.options);
+        self.options = replay.header.options;
+        self.next_seed = Some(replay.header.seed);
+        // Not a new game: nothing is announced, so it is not counted.
+        self.deal_board();
+        self.world.insert_resource(ReplayPlayback::new(&replay));
+        dirty::invalidate(&mut self.world);
+        Ok(())
+    }
+
+    /// Play the next action of the loaded replay, animated like a move of
+    /// the player's. Returns whether there was one left.
+    pub fn step_replay(&mut self) -> Result<bool, GameError> {
+        let Some(action) = self
+            .world
+            .resource_mut::<ReplayPlayback>()
+            .and_then(ReplayPlayback::next_action)
+        else {
+            return Ok(false);
+        };
+        let events = action.play(&mut self.world)?;
+        let now = clock::now_ms();
+        if let Some(log) = self.world.resource_mut::<AuditLog>() {
+            log.record(action, now);
         }
+        animation::start_flips(&mut self.world, &events, now);
+        animation::start_moves(&mut self.world, &events, now);
+        Ok(true)
+    }
+
+    /// How far the loaded replay has been played, as JSON `{ step, steps }`,
+    /// or `None` when no replay is loaded.
+    pub fn replay_position(&self) -> Option<String> {
+        let (step, steps) = self.world.resource::<ReplayPlayback>()?.position();
+        Some(serde_json::json!({ "step": step, "steps": steps }).to_string())
+    }
+
+    /// Fingerprint of the current position, sent alongside the audit log so
+    /// the server can check where the replayed game should end.
+    pub fn state_digest(&self) -> String {
+        audit::state_digest(&self.world)
+    }
+
+    /// Check a received audit log, plain or packed: its hash chain, its
+    /// signatures (when `key` is given) and that replaying it ends in the
+    /// position `final_digest`.
+    pub fn verify_audit_log(
+        log_json: &str,
+        key: Option<String>,
+        final_digest: &str,
+    ) -> Result<(), AuditError> {
+        let log_json =
+            compress::unpack(log_json).map_err(|e| AuditError::Malformed(e.to_string()))?;
+        audit::verify(&log_json, key.as_deref(), final_digest)
+    }
+
+    /// Check the signed moves streamed during one deal, as a JSON array:
+    /// their signatures with `key`, that their nonces only go up, and that
+    /// replaying them ends in the position `final_digest`.
+    pub fn verify_signed_moves(
+        moves_json: &str,
+        key: &str,
+        final_digest: &str,
+    ) -> Result<(), AuditError> {
+        audit::verify_moves(moves_json, key, final_digest)
+    }
+
+    /// Estimated memory held by the ECS stores, as a JSON array of
+    /// `{ component, kind, len, bytes }` objects, largest first.
+    pub fn memory_usage(&self) -> String {
+        serde_json::to_string(&self.world.memory_usage()).expect("usage always serializes")
+    }
+
+    /// Give spare memory back after switching to a smaller board. Worth
+    /// calling in long-lived tabs.
+    pub fn shrink_to_fit(&mut self) {
+        self.world.shrink_to_fit();
+    }
+
+    /// Find a named entity, such as the pile entity `"tableau-3"`.
+    ///
+    /// Names stay the same across deals even though entity IDs do not.
+    pub fn find_entity(&self, name: &str) -> Option<u32> {
+        self.world.find_by_name(name)
+    }
+
+    /// Every known component of `entity` as a JSON object keyed by
+    /// component name, for the debug inspector.
+    pub fn inspect_entity(&self, entity: u32) -> String {
+        let components = game::component_registry().components_of(&self.world, entity);
+        serde_json::to_string(&components).expect("components always serialize")
+    }
+
+    /// Identifiers of every achievement unlocked so far.
+    pub fn unlocked_achievements(&self) -> Vec<String> {
+        self.achievements
+            .unlocked()
+            .iter()
+            .map(|a| a.id().to_string())
+            .collect()
+    }
+}
+
+/// Multiplayer entry points: the lobby, sessions, co-op, tournaments,
+/// spectating and sync. Only with the `networking` feature.
+#[cfg(feature = "networking")]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SolitaireGame {
+    /// Ask the server for today's official daily challenge in the current
+    /// variant. Until it answers, and without a server at all,
+    /// `daily_challenge` derives the challenge locally.
+    pub fn fetch_daily_challenge(&mut self) {
+        self.transmit(&NetMessage::FetchDaily {
+            variant: self.options.variant,
+        });
+    }
+
+    /// The won daily challenge on the board as a leaderboard submission, to
+    /// sign and send with `LeaderboardClient`, as JSON: `{ variant, daily,
+    /// score, time_ms, moves }`. Refused once the challenge's deadline has
+    /// passed, since the server takes no more scores for it.
+    pub fn daily_submission(&self) -> Result<String, GameError> {
+        let challenge = self
+            .daily_challenge
+            .as_ref()
+            .filter(|_| self.options.daily)
+            .ok_or(GameError::NoDailyResult)?;
+        let now = clock::now_ms();
+        if !challenge.is_open(now) {
+            return Err(GameError::DailyClosed);
+        }
+        if !self.is_won() {
+            return Err(GameError::NoDailyResult);
+        }
+        let time_ms = self
+            .world
+            .resource::<clock::GameClock>()
+            .map_or(0.0, |c| c.elapsed_ms(now));
+        let submission = leaderboard::ScoreSubmission {
+            variant: self.options.variant.to_string(),
+            daily: Some(challenge.date.clone()),
+            score: self.score(),
+            time_ms: time_ms as u32,
+            moves: self.moves(),
+        };
+        Ok(serde_json::to_string(&submission).expect("submissions always serialize"))
+    }
+
+    /// The autosave and statistics as a document to sync with
+    /// `sync::SyncClient`.
+    pub fn sync_document(&self) -> String {
+        serde_json::to_string(&self.local_sync_doc()).expect("documents always serialize")
+    }
+
+    /// Take over a document pulled by `sync::SyncClient`: its statistics
+    /// replace these and its game in progress is loaded. Returns whether it
+    /// was taken, which it is not when it is no newer than the local one.
+    pub fn apply_sync_document(&mut self, json: &str) -> Result<bool, GameError> {
+        let doc: SyncDoc =
+            serde_json::from_str(json).map_err(|e| GameError::InvalidOptions(e.to_string()))?;
+        if sync::resolve(&self.local_sync_doc(), Some(&doc)) != sync::Resolution::Pull {
+            return Ok(false);
+        }
+        // Given up here, before the pulled statistics take over.
+        if doc.autosave.is_some() {
+            self.abandon_in_progress();
+        }
+        self.stats = doc.statistics;
+        storage::save(STATS_KEY, &self.stats.to_json());
+        if let Some(saved) = &doc.autosave {
+            self.restore(saved)?;
+            storage::save(AUTOSAVE_KEY, &saved.to_packed());
+        }
+        self.save_revision = doc.revision;
+        Ok(true)
+    }
+
+    /// Handle a message as if the network had just received it, on the next
+    /// `advance`. Servers and tests use this in place of a WebSocket.
+    pub fn receive_message(&mut self, json: &str) -> Result<(), GameError> {
+        inbox::receive_text(&mut self.world, json)
+            .map_err(|e| GameError::InvalidOptions(e.to_string()))
+    }
+
+    /// How the protocol handshake with the server went, as JSON: `{ "state":
+    /// "idle" | "sent" }`, `{ "state": "agreed", "agreement": { protocol,
+    /// encoding, features, peer_version } }` or `{ "state": "incompatible",
+    /// "error": { "reason": "version" | "encoding", ... } }`.
+    pub fn handshake(&self) -> String {
+        let handshake = self.world.resource::<handshake::Handshake>();
+        serde_json::to_string(&handshake.cloned().unwrap_or_default())
+            .expect("handshakes always serialize")
+    }
+
+    /// The token this client resumes its session with after a reconnect, if
+    /// the server handed one out.
+    pub fn resume_token(&mut self) -> Option<String> {
+        session::session_mut(&mut self.world).resume_token.clone()
+    }
+
+    /// Forget the resume token, so the next connection starts a fresh
+    /// session.
+    pub fn forget_session(&mut self) {
+        session::forget(&mut self.world);
+    }
+
+    /// Ask the server for one page of the open rooms matching `filter_json`:
+    /// `{ variant, mode, region, hide_full, page, page_size }`, every field
+    /// optional. The answer shows up in `rooms()` after a later `advance`.
+    pub fn list_rooms(&mut self, filter_json: &str) -> Result<(), GameError> {
+        let filter: lobby::RoomFilter = serde_json::from_str(filter_json)
+            .map_err(|e| GameError::InvalidOptions(e.to_string()))?;
+        let (page, page_size) = (filter.page, filter.page_size);
+        self.transmit(&NetMessage::ListRooms(filter.page(page, page_size)));
+        Ok(())
+    }
+
+    /// The latest page of rooms as JSON: `{ page, total_pages, rooms }`,
+    /// each room `{ id, name, variant, mode, players, max_players, region }`.
+    /// Empty until the server has answered `list_rooms`.
+    pub fn rooms(&self) -> String {
+        let page = self.world.resource::<lobby::RoomsPage>().cloned();
+        serde_json::to_string(&page.unwrap_or_default()).expect("rooms always serialize")
+    }
+
+    /// Join the room listed with `room` as its id. See `join_status` for how
+    /// it went.
+    pub fn join_room(&mut self, room: &str) {
+        self.world.insert_resource(lobby::JoinStatus::Pending);
+        self.transmit(&NetMessage::JoinRoom {
+            room: room.to_string(),
+        });
+    }
+
+    /// Invite others into the room this player is in. Returns a URL fragment
+    /// such as `#invite=K7Q2M9XD` to share, valid for a day.
+    pub fn create_invite(&mut self) -> Result<String, GameError> {
+        let Some(lobby::JoinStatus::Joined { room }) = self.world.resource::<lobby::JoinStatus>()
+        else {
+            return Err(GameError::NotInRoom);
+        };
+        let invite = lobby::Invite::new(room.id.clone(), clock::now_ms());
+        let fragment = invite.fragment();
+        self.transmit(&NetMessage::CreateInvite(invite));
+        Ok(fragment)
+    }
+
+    /// Join the room of an invite, given as a token, a fragment or a whole
+    /// link. See `join_status` for how it went.
+    pub fn join_by_invite(&mut self, invite: &str) -> Result<(), GameError> {
+        let token = lobby::parse_invite(invite).ok_or(GameError::InvalidInvite)?;
+        self.world.insert_resource(lobby::JoinStatus::Pending);
+        self.transmit(&NetMessage::JoinByInvite { token });
+        Ok(())
+    }
+
+    /// How joining a room went, as JSON: `{ "state": "pending" }`,
+    /// `{ "state": "joined", "room": {...} }` or `{ "state": "failed",
+    /// "reason": "unknown_invite" | "invite_expired" | "room_full" |
+    /// "room_gone" }`. `null` before any join.
+    pub fn join_status(&self) -> String {
+        let status = self.world.resource::<lobby::JoinStatus>();
+        serde_json::to_string(&status).expect("join status always serializes")
+    }
+
+    /// Enter the tournament `bracket`. The game events `BracketJoined`,
+    /// `RoundPaired` and `StandingUpdated` tell the page how it goes, and
+    /// `bracket()` has the details.
+    pub fn join_bracket(&mut self, bracket: &str) {
+        self.world
+            .insert_resource(tournament::Tournament::new(bracket.to_string()));
+        self.transmit(&NetMessage::JoinBracket {
+            bracket: bracket.to_string(),
+        });
+    }
+
+    /// Deal the seed of the bracket's current round, once it is paired.
+    pub fn start_round(&mut self) -> Result<(), GameError> {
+        let pairing = self.pairing()?;
+        self.next_seed = Some(pairing.seed);
+        self.setup_board();
+        Ok(())
+    }
+
+    /// Send how the current round went, once played: whether the game on
+    /// its seed was won, with its score, moves and time. A round's result
+    /// can only be sent once.
+    pub fn submit_round_result(&mut self) -> Result<(), GameError> {
+        let pairing = self.pairing()?;
+        if self.options.seed != Some(pairing.seed) {
+            return Err(GameError::NoPairing);
+        }
+        let time_ms = self
+            .world
+            .resource::<clock::GameClock>()
+            .map_or(0.0, |c| c.elapsed_ms(clock::now_ms()));
+        let result = tournament::RoundResult {
+            bracket: pairing.bracket,
+            round: pairing.round,
+            seed: pairing.seed,
+            won: self.is_won(),
+            score: self.score(),
+            moves: self.moves(),
+            time_ms: time_ms as u64,
+        };
+        self.transmit(&NetMessage::SubmitResult(result));
+        if let Some(tournament) = self.world.resource_mut::<tournament::Tournament>() {
+            tournament.submitted = true;
+        }
+        Ok(())
+    }
+
+    /// Ask the server where the bracket stands. The answer shows up in
+    /// `bracket()` after a later `advance`, with a `StandingUpdated` event.
+    pub fn fetch_standing(&mut self) -> Result<(), GameError> {
+        let tournament = self
+            .world
+            .resource::<tournament::Tournament>()
+            .ok_or(GameError::NotInBracket)?;
+        let bracket = tournament.bracket.clone();
+        self.transmit(&NetMessage::FetchStanding { bracket });
+        Ok(())
+    }
+
+    /// The bracket this player is in as JSON: `{ bracket, joined, players,
+    /// pairing, submitted, standing }` with the pairing `{ round, opponent,
+    /// seed }` and the standing `{ round, standings, champion }`, each
+    /// standing `{ player, wins, losses, eliminated }`. `null` before
+    /// `join_bracket`.
+    pub fn bracket(&self) -> String {
+        let tournament = self.world.resource::<tournament::Tournament>();
+        serde_json::to_string(&tournament).expect("brackets always serialize")
+    }
+
+    /// Start again on the current deal as a co-op game, played as `player`
+    /// together with the others in the session who start it too. From then on
+    /// moves are played a few ticks after they are made, on every board at
+    /// once, and sent to the others as they are made.
+    pub fn start_coop(&mut self, player: &str) {
+        self.next_seed = self.options.seed;
+        self.start_deal();
+        let seed = self.options.seed.expect("dealing sets the seed");
+        rollback::start(&mut self.world, player.to_string(), seed, clock::now_ms());
+    }
+
+    /// Whether this is a co-op game. It ends with the deal.
+    pub fn is_coop(&self) -> bool {
+        rollback::is_active(&self.world)
+    }
+
+    /// This board as a message for the others in the session to watch:
+    /// send it every so often with `send`.
+    pub fn board_snapshot(&mut self, player: &str) -> String {
+        NetMessage::BoardSnapshot {
+            player: player.to_string(),
+            snapshot: spectator::BoardSnapshot::capture(&mut self.world, clock::now_ms()),
+        }
+        .to_json()
+    }
+
+    /// Share how far this board has come with the others in the session, as
+    /// `player`: a small summary goes out every second or so while it
+    /// changes. Much cheaper than streaming `board_snapshot`s.
+    pub fn share_progress(&mut self, player: &str) {
+        self.progress.set_player(Some(player.to_string()));
+    }
+
+    pub fn stop_sharing_progress(&mut self) {
+        self.progress.set_player(None);
+    }
+
+    /// The progress the others share, for a progress panel, as JSON:
+    /// `[{ player, foundations, face_down, stock, cards, cards_home }]` with
+    /// the cards on each foundation, the face-down cards in each tableau
+    /// pile, the cards left in the stock, in the deal and on the
+    /// foundations.
+    pub fn opponent_progress(&self) -> String {
+        serde_json::to_string(&progress::panel(&self.world)).expect("progress always serializes")
+    }
+
+    /// The players whose boards can be watched, as a JSON array of names.
+    pub fn spectated_players(&self) -> String {
+        serde_json::to_string(&spectator::players(&self.world)).expect("names always serialize")
+    }
+
+    /// `player`'s board drawn `width` by `height` pixels, moving smoothly
+    /// between the snapshots received: `{ card_width, card_height, cards }`
+    /// with cards as in `scene()`. Call once per frame while watching.
+    pub fn spectator_scene(&self, player: &str, width: f32, height: f32) -> Option<String> {
+        let size = CanvasSize { width, height };
+        spectator::scene(&self.world, player, clock::now_ms(), size)
+            .map(|scene| serde_json::to_string(&scene).expect("scenes always serialize"))
+    }
+
+    /// Traffic counters of the multiplayer session as JSON: `{ messages_sent,
+    /// bytes_sent, messages_received, bytes_received, dropped, reconnects }`.
+    pub fn net_stats(&self) -> String {
+        let stats = self.world.resource::<inbox::NetStats>();
+        serde_json::to_string(&stats.copied().unwrap_or_default()).expect("stats always serialize")
+    }
+
+    /// The players in the multiplayer session as a JSON array of names.
+    pub fn roster(&self) -> String {
+        let roster = self.world.resource::<inbox::Roster>();
+        serde_json::to_string(&roster.map_or(&[][..], |r| &r.players[..]))
+            .expect("names always serialize")
+    }
+
+    /// Send a quick reaction to the others in the session: `"wave"`,
+    /// `"thumbs_up"`, `"laugh"`, `"wow"`, `"oops"` or `"good_game"`. Emotes
+    /// sent too quickly one after another are dropped.
+    pub fn send_emote(&mut self, id: &str) -> Result<(), GameError> {
+        let emote = emote::Emote::from_id(id)
+            .ok_or_else(|| GameError::InvalidOptions(format!("unknown emote \"{id}\"")))?;
+        self.transmit(&NetMessage::Emote {
+            from: String::new(),
+            emote,
+        });
+        Ok(())
+    }
+
+    /// The latest chat as JSON: `[{ from, text }]`, oldest first.
+    pub fn chat_log(&self) -> String {
+        let log = self.world.resource::<inbox::ChatLog>();
+        serde_json::to_string(&log.map(|l| &l.lines)).expect("chat always serializes")
+    }
+}
+
+/// Entry points built on the solver. Only with the `solver` feature.
+#[cfg(feature = "solver")]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SolitaireGame {
+    /// The whole winning line from the current position as a JSON array of
+    /// moves, or `None` when the solver cannot prove the position winnable.
+    pub fn show_solution(&mut self) -> Option<String> {
+        let moves = hint::solution(&mut self.world, solver::DEFAULT_NODE_LIMIT)?;
+        Some(serde_json::to_string(&moves).expect("moves always serialize"))
+    }
+
+    /// Play the next move of the winning line, for a guided finish.
+    pub fn play_solution_step(&mut self) -> Result<(), GameError> {
+        if self.is_paused() {
+            return Err(GameError::Paused);
+        }
+        let mv = hint::next_move(&mut self.world, solver::DEFAULT_NODE_LIMIT)
+            .ok_or(GameError::NoSolution)?;
+        self.play_move(mv)
+    }
+
+    /// Start playing the solver's winning line, paced by `advance`.
+    pub fn start_solution_playback(&mut self) -> Result<(), GameError> {
+        hint::solution(&mut self.world, solver::DEFAULT_NODE_LIMIT).ok_or(GameError::NoSolution)?;
+        self.world
+            .insert_resource(AutoPlay::new(AutoPlayMode::Solution));
+        Ok(())
+    }
+
+    /// Whether the game can still be won from the board as it is now:
+    /// `"winnable"`, `"dead"`, or `"unknown"` when the solver could not
+    /// decide within `timeout_ms` milliseconds.
+    pub fn is_current_position_winnable(&self, timeout_ms: f64) -> String {
+        let deadline = clock::now_ms() + timeout_ms.max(0.0);
+        let here = solver::Position::from_world(&self.world);
+        // The node limit keeps the probe bounded where there is no clock.
+        solver::solve_until(&here, 10 * solver::DEFAULT_NODE_LIMIT, deadline)
+            .verdict()
+            .to_string()
+    }
+
+    /// The current board as JSON, in the form `SolverWorker::solve` expects.
+    pub fn solver_position(&self) -> String {
+        serde_json::to_string(&solver::Position::from_world(&self.world))
+            .expect("positions always serialize")
+    }
+}
+
+/// Entry points for renderers beyond `layout()`. Only with the `renderers`
+/// feature.
+#[cfg(feature = "renderers")]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SolitaireGame {
+    /// Lay out the table and return everything needed to paint it as JSON,
+    /// including the dirty regions, for a renderer such as `RenderWorker`.
+    pub fn scene(&mut self) -> String {
+        self.apply_fullscreen_resize();
+        serde_json::to_string(&render_worker::Scene::capture(&mut self.world))
+            .expect("scenes always serialize")
+    }
+
+    /// The board as it is laid out now, drawn as a standalone SVG document,
+    /// for sharing a finished game or a puzzling position.
+    pub fn board_svg(&mut self) -> String {
+        snapshot::board_svg(&mut self.world)
+    }
+}
+
+impl SolitaireGame {
+    /// Create a game played with `options`. With a seed, the first deal is
+    /// the deal of that seed and the ones after it follow deterministically.
+    pub fn with_options(options: GameOptions) -> SolitaireGame {
+        let rng = options
+            .seed
+            .map_or_else(GameRng::from_entropy, GameRng::from_seed);
+        let mut game = Self::with_rng(rng);
+        game.next_seed = options.seed;
+        game.options = options;
+        game
+    }
+
+    fn with_rng(rng: GameRng) -> SolitaireGame {
+        logging::init();
+        let mut world = World::new();
+        world.insert_resource(rng);
+        let achievements = storage::load(ACHIEVEMENTS_KEY)
+            .and_then(|json| Achievements::from_json(&json).ok())
+            .unwrap_or_default();
+        let stats = storage::load(STATS_KEY)
+            .and_then(|json| Statistics::from_json(&json).ok())
+            .unwrap_or_default();
+        let save_revision = storage::load(AUTOSAVE_KEY)
+            .and_then(|text| SavedGame::from_packed(&text).ok())
+            .map_or(0, |saved| saved.revision);
+        SolitaireGame {
+            world,
+            deck: Deck::standard(),
+            achievements,
+            stats,
+            mulligans_used: 0,
+            audit_key: None,
+            #[cfg(feature = "networking")]
+            move_signer: None,
+            options: GameOptions::default(),
+            next_seed: None,
+            own_options: None,
+            daily_challenge: None,
+            save_revision,
+            autoplay: AutoPlaySettings::default(),
+            thoughtful: false,
+            tooltips: false,
+            reduced_motion: animation::prefers_reduced_motion(),
+            suits: SuitStyle::default(),
+            canvas: CanvasSize::default(),
+            layout: LayoutSettings::default(),
+            state_view: memview::StateBuffer::new(),
+            resolution: Resolution::detect(),
+            faces: FaceSource::default(),
+            animation: AnimationStyle::default(),
+            theme: Theme::default(),
+            haptics: Haptics::default(),
+            wake_lock: WakeLock::default(),
+            #[cfg(feature = "wasm")]
+            on_achievement: None,
+            #[cfg(feature = "wasm")]
+            on_no_moves_left: None,
+            #[cfg(feature = "wasm")]
+            on_game_event: None,
+            #[cfg(feature = "wasm")]
+            on_game_events: None,
+            #[cfg(feature = "wasm")]
+            event_batch: events::EventBatch::default(),
+            #[cfg(all(feature = "wasm", feature = "networking"))]
+            on_emote: None,
+            #[cfg(all(feature = "wasm", feature = "networking"))]
+            network: None,
+            #[cfg(feature = "networking")]
+            channels: channel::Mux::default(),
+            #[cfg(all(feature = "wasm", feature = "networking"))]
+            limiter: RateLimiter::default(),
+            #[cfg(feature = "networking")]
+            progress: progress::Sharing::default(),
+            #[cfg(all(feature = "wasm", feature = "networking"))]
+            fallback: transport::Fallback::default(),
+            #[cfg(all(feature = "wasm", feature = "networking"))]
+            server_policy: endpoint::ServerPolicy::default(),
+            #[cfg(feature = "wasm")]
+            fullscreen: None,
+            #[cfg(feature = "wasm")]
+            theme_root: None,
+            #[cfg(all(feature = "wasm", feature = "solver"))]
+            solver_worker: None,
+        }
+    }
+
+    /// Achievement progress across all games.
+    pub fn achievements(&self) -> &Achievements {
+        &self.achievements
+    }
+
+    /// The ECS world holding the current deal.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Validate and play `mv`, as the JavaScript entry points do.
+    pub fn play_move(&mut self, mv: Move) -> Result<(), GameError> {
+        self.check_time();
+        // In co-op the move is played on its tick, by `process_network`.
+        #[cfg(feature = "networking")]
+        if rollback::is_active(&self.world) {
+            rules::validate(&self.world, mv)?;
+            if let Some(input) = rollback::schedule(&mut self.world, mv) {
+                self.transmit(&NetMessage::CoopMove(input));
+            }
+            return Ok(());
+        }
+        let events = rules::apply(&mut self.world, mv).inspect_err(|e| {
+            log::debug!(target: "rules", mv:?, error:% = e; "move refused");
+            self.haptics.play(Pulse::for_error(e));
+        })?;
+        log::debug!(target: "rules", mv:?, events = events.len(); "move played");
+        self.record_audit(AuditAction::Move(mv));
+        self.dispatch(events);
+        Ok(())
+    }
+
+    /// Play `mv` as part of the previous move, so a single undo takes both
+    /// back. In co-op, where there is no undo, it is scheduled like any move.
+    pub fn play_follow_up(&mut self, mv: Move) -> Result<(), GameError> {
+        #[cfg(feature = "networking")]
+        if rollback::is_active(&self.world) {
+            return self.play_move(mv);
+        }
+        self.check_time();
+        let events = rules::apply_follow_up(&mut self.world, mv)?;
+        self.record_audit(AuditAction::FollowUp(mv));
+        self.dispatch(events);
+        Ok(())
+    }
+
+    fn daily(&self, date: &str) -> Result<daily::DailyChallenge, GameError> {
+        daily::challenge(&self.world, date, self.options.variant)
+            .ok_or_else(|| GameError::InvalidOptions(format!("\"{date}\" is not a date")))
+    }
+
+    /// End a time attack whose countdown has run out.
+    fn check_time(&mut self) {
+        if let Some(event) = timeattack::check(&mut self.world, clock::now_ms()) {
+            self.dispatch(vec![event]);
+        }
+    }
+
+    fn record_audit(&mut self, action: AuditAction) {
+        let now = clock::now_ms();
+        if let Some(log) = self.world.resource_mut::<AuditLog>() {
+            log.record(action, now);
+        }
+        #[cfg(feature = "networking")]
+        if let Some(signer) = &mut self.move_signer {
+            let signed = signer.sign(self.options.seed.unwrap_or(0), action, now);
+            self.transmit(&NetMessage::SignedMove(signed));
+        }
+    }
+
+    /// The auto-play moves due after `dt_ms`, see `advance`.
+    fn play_due(&mut self, dt_ms: f64) -> u32 {
+        #[cfg(feature = "networking")]
+        self.process_network();
+        if self.is_paused() {
+            return 0;
+        }
+        self.check_time();
+        let Some(mut auto) = self.world.remove_resource::<AutoPlay>() else {
+            return 0;
+        };
+        let due = auto.due(dt_ms, &self.autoplay);
+        let mut played = 0;
+        let mut finished = false;
+        while (played as usize) < due && !finished {
+            let mv = match auto.mode {
+                AutoPlayMode::AutoComplete => auto.auto_complete_move(&self.world),
+                #[cfg(feature = "solver")]
+                AutoPlayMode::Solution => {
+                    hint::next_move(&mut self.world, solver::DEFAULT_NODE_LIMIT)
+                }
+            };
+            // Everything after the first move belongs to the same undo step.
+            let result = mv.map(|mv| {
+                if auto.started {
+                    self.play_follow_up(mv)
+                } else {
+                    self.play_move(mv)
+                }
+            });
+            match result {
+                Some(Ok(())) => {
+                    played += 1;
+                    auto.started = true;
+                }
+                Some(Err(_)) | None => finished = true,
+            }
+            finished |= self.is_won();
+            // A co-op move only lands on its tick: asking for the next one
+            // now would get the same move again, and forever.
+            #[cfg(feature = "networking")]
+            if rollback::is_active(&self.world) {
+                break;
+            }
+        }
+        if !finished {
+            self.world.insert_resource(auto);
+        }
+        played
+    }
+
+    /// Forward events produced by the rules to the subsystems that react to
+    /// them.
+    fn dispatch(&mut self, events: Vec<GameEvent>) {
+        let now = clock::now_ms();
+        animation::start_flips(&mut self.world, &events, now);
+        animation::start_moves(&mut self.world, &events, now);
+        self.haptics.play(Pulse::for_events(&events));
+        let mut earned = Vec::new();
+        let mut stats_changed = false;
+        for event in &events {
+            earned.extend(self.achievements.handle(event, now));
+            stats_changed |= self.stats.handle(event);
+            share::handle(&mut self.world, event);
+            self.wake_lock.handle(event);
+            match event {
+                GameEvent::GameStarted { .. } => animation::start_deal(&mut self.world, now),
+                GameEvent::NoMovesLeft => self.notify_no_moves_left(),
+                #[cfg(feature = "renderers")]
+                GameEvent::GameWon { .. } => {
+                    particles::celebrate(&mut self.world, now, self.options.seed.unwrap_or(0))
+                }
+                _ => {}
+            }
+            self.notify_event(event);
+        }
+        if stats_changed {
+            storage::save(STATS_KEY, &self.stats.to_json());
+        }
+        self.autosave();
+        if !earned.is_empty() {
+            storage::save(ACHIEVEMENTS_KEY, &self.achievements.to_json());
+            for achievement in earned {
+                self.notify_achievement(achievement);
+            }
+        }
+    }
+
+    /// Deal a new board and announce it.
+    fn start_deal(&mut self) {
+        let seed = self.deal_board();
+        self.dispatch(vec![GameEvent::GameStarted { seed }]);
+    }
+
+    /// Deal with the player's own options again, after a daily challenge or
+    /// a replay.
+    fn own_options_back(&mut self) {
+        if let Some(own) = self.own_options.take() {
+            self.options = own;
+        }
+        self.daily_challenge = None;
+    }
+
+    /// Abandon the game on the board if it is still in progress. A deal put
+    /// aside before any move was made was never really played, and is not.
+    fn abandon_in_progress(&mut self) {
+        if self
+            .world
+            .resource::<History>()
+            .is_none_or(|h| h.moves() == 0)
+        {
+            return;
+        }
+        if let Some(event) = lifecycle::abandon(&mut self.world, clock::now_ms()) {
+            self.dispatch(vec![event]);
+        }
+    }
+
+    /// Deal a new board, as `setup_board` does, without announcing it.
+    /// Returns the seed of the deal.
+    fn deal_board(&mut self) -> u64 {
+        // Reset the ECS world but carry the random number generator over, so a
+        // seeded game keeps producing its sequence of deals. The card
+        // entities stay: dealing just moves them, so renderer state bound to
+        // them carries over too.
+        let mut rng = self
+            .world
+            .remove_resource::<GameRng>()
+            .unwrap_or_else(GameRng::from_entropy);
+        #[cfg(feature = "networking")]
+        let net = inbox::NetState::take(&mut self.world);
+        self.world.clear_resources();
+        #[cfg(feature = "networking")]
+        net.restore(&mut self.world);
+        layout::drop_dragged(&mut self.world);
+
+        // Each deal gets its own seed so it can be recreated on its own, for
+        // example by a server replaying the audit log.
+        let seed = self.next_seed.take().unwrap_or_else(|| rng.next_u64());
+        self.options.seed = Some(seed);
+        self.deck = self.options.table().deck();
+        self.deck.shuffle(&mut GameRng::from_seed(seed));
+        self.world.insert_resource(rng);
+        self.options.insert_into(&mut self.world);
+        self.world
+            .insert_resource(AuditLog::new(seed, self.audit_key.clone()));
+        self.world
+            .insert_resource(clock::GameClock::start(clock::now_ms()));
+        self.world.insert_resource(self.autoplay);
+        self.world.insert_resource(self.canvas);
+        self.world.insert_resource(self.layout);
+        self.world.insert_resource(self.resolution);
+        self.world.insert_resource(self.animation);
+        self.world.insert_resource(self.theme.clone());
+        self.world.insert_resource(self.suits);
+        self.set_thoughtful(self.thoughtful);
+        self.set_tooltips(self.tooltips);
+        self.set_reduced_motion(self.reduced_motion);
+
+        rules::deal(&mut self.world, &self.deck.cards);
+        seed
+    }
+
+    /// Save the game in progress under the next revision.
+    fn autosave(&mut self) {
+        self.save_revision += 1;
+        let saved = SavedGame::capture(
+            &self.world,
+            self.options,
+            self.save_revision,
+            clock::now_ms(),
+        );
+        storage::save(AUTOSAVE_KEY, &saved.to_packed());
+    }
+
+    /// Deal the saved game again and replay it. Revisions never go back, so
+    /// the autosave after it still counts as the newer one.
+    fn restore(&mut self, saved: &SavedGame) -> Result<(), GameError> {
+        self.abandon_in_progress();
+        self.options = saved.options;
+        self.next_seed = saved.seed();
+        // Not a new game: nothing is announced, so it is not counted again.
+        self.deal_board();
+        saved.replay(&mut self.world, clock::now_ms())?;
+        dirty::invalidate(&mut self.world);
+        self.save_revision = self.save_revision.max(saved.revision);
+        Ok(())
+    }
+
+    fn slot_index() -> SaveSlots {
+        storage::load(SLOTS_KEY)
+            .and_then(|json| SaveSlots::from_json(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn use_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.world.insert_resource(self.theme.clone());
+        dirty::invalidate(&mut self.world);
+        self.publish_theme();
+    }
+
+    /// Bring the CSS properties on the theme root up to date.
+    #[cfg(feature = "wasm")]
+    fn publish_theme(&self) {
+        if let Some(root) = &self.theme_root {
+            let card_width = layout::metrics(&self.world).card_width;
+            let _ = theme::apply(&self.theme, card_width, root);
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn publish_theme(&self) {}
+
+    /// Fit the table to the element that entered or left fullscreen mode.
+    #[cfg(feature = "wasm")]
+    fn apply_fullscreen_resize(&mut self) {
+        let resize = self.fullscreen.as_ref().and_then(|f| f.take_resize());
+        if let Some((width, height)) = resize {
+            self.handle_resize(width, height);
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn apply_fullscreen_resize(&mut self) {}
+
+    #[cfg(feature = "wasm")]
+    fn notify_achievement(&self, achievement: Achievement) {
+        if let Some(callback) = &self.on_achievement {
+            let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(achievement.id()));
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn notify_achievement(&self, _achievement: Achievement) {}
+
+    #[cfg(feature = "wasm")]
+    fn notify_no_moves_left(&self) {
+        if let Some(callback) = &self.on_no_moves_left {
+            let _ = callback.call0(&JsValue::NULL);
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn notify_no_moves_left(&self) {}
+
+    #[cfg(feature = "wasm")]
+    fn notify_event(&mut self, event: &GameEvent) {
+        if let Some(callback) = &self.on_game_event {
+            let json = serde_json::to_string(event).expect("events always serialize");
+            let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(&json));
+        }
+        if self.on_game_events.is_some() {
+            self.event_batch.push(event.clone());
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn notify_event(&mut self, _event: &GameEvent) {}
+
+    /// Hand the events batched since the last call to `on_game_events`, and
+    /// return how many there were.
+    #[cfg(feature = "wasm")]
+    fn deliver_events(&mut self) -> usize {
+        let count = self.event_batch.len();
+        if let (Some(callback), Some(json)) = (&self.on_game_events, self.event_batch.take_json()) {
+            let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(&json));
+        }
+        count
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn deliver_events(&mut self) -> usize {
+        0
+    }
+
+    fn rng(&self) -> &GameRng {
+        self.world
+            .resource::<GameRng>()
+            .expect("the world always holds a GameRng")
+    }
+
+    fn rng_mut(&mut self) -> &mut GameRng {
+        self.world
+            .resource_mut::<GameRng>()
+            .expect("the world always holds a GameRng")
+    }
+}
+
+#[cfg(feature = "networking")]
+impl SolitaireGame {
+    /// Hand what the network received to the systems that react to it, play
+    /// the moves other players made, and play the co-op ticks that are due.
+    fn process_network(&mut self) {
+        self.receive_network();
+        inbox::inbox_system(&mut self.world);
+        for hello in inbox::hellos(&mut self.world) {
+            match handshake::receive(&mut self.world, &hello) {
+                Ok(agreement) => self.channels.set_framed(agreement.has("channels")),
+                Err(_) => self.disconnect(),
+            }
+        }
+        for message in inbox::session_messages(&mut self.world) {
+            if let Some(reply) = session::handle(&mut self.world, message) {
+                self.transmit(&reply);
+            }
+        }
+        self.open_session();
+        inbox::roster_system(&mut self.world);
+        inbox::chat_system(&mut self.world);
+        inbox::lobby_system(&mut self.world);
+        for message in inbox::tournament_messages(&mut self.world) {
+            if let Some(event) = tournament::handle(&mut self.world, message) {
+                self.dispatch(vec![event]);
+            }
+        }
+        self.migrate_host();
+        let now = clock::now_ms();
+        for (from, emote) in inbox::emotes(&mut self.world) {
+            self.notify_emote(&from, emote);
+            emote::show(&mut self.world, from, emote, now);
+        }
+        for (player, snapshot) in inbox::board_snapshots(&mut self.world) {
+            spectator::receive(&mut self.world, player, snapshot, now);
+        }
+        for (player, summary) in inbox::board_summaries(&mut self.world) {
+            progress::receive(&mut self.world, player, summary);
+        }
+        if let Some(summary) = self.progress.due(&self.world, now) {
+            self.transmit(&summary);
+        }
+        for mv in inbox::remote_moves(&mut self.world) {
+            // Not the player's own moves, so they stay out of the audit log.
+            if let Ok(events) = rules::apply(&mut self.world, mv) {
+                log::debug!(target: "rules", mv:?, events = events.len(); "remote move played");
+                self.dispatch(events);
+            }
+        }
+        for challenge in inbox::daily_challenges(&mut self.world) {
+            daily::receive(&mut self.world, challenge);
+        }
+        for key in inbox::session_keys(&mut self.world) {
+            self.set_audit_key(&key);
+            self.transmit(&NetMessage::FetchRating);
+        }
+        for (rating, change) in inbox::ratings(&mut self.world) {
+            let known = self.stats.rating().unwrap_or_default();
+            let change = change.unwrap_or(rating.rating - known.rating);
+            self.dispatch(vec![GameEvent::RatingChanged { rating, change }]);
+        }
+        let mut rolled_back = false;
+        for input in inbox::coop_inputs(&mut self.world) {
+            rolled_back |= rollback::receive(&mut self.world, input);
+        }
+        if rolled_back {
+            dirty::invalidate(&mut self.world);
+        }
+        let events = rollback::step(&mut self.world, now);
+        if !events.is_empty() {
+            self.dispatch(events);
+        }
+    }
+
+    /// The match of the bracket's current round, while its result is yet to
+    /// be sent.
+    fn pairing(&self) -> Result<tournament::Pairing, GameError> {
+        let tournament = self
+            .world
+            .resource::<tournament::Tournament>()
+            .ok_or(GameError::NotInBracket)?;
+        match &tournament.pairing {
+            Some(pairing) if !tournament.submitted => Ok(pairing.clone()),
+            _ => Err(GameError::NoPairing),
+        }
+    }
+
+    /// Hand the room over to a new host when the old one left, and take over
+    /// the timeline a new host sends.
+    fn migrate_host(&mut self) {
+        if let Some(migration) = host::host_system(&mut self.world) {
+            let ours = self
+                .world
+                .resource::<rollback::Coop>()
+                .is_some_and(|c| c.player == migration.to);
+            if ours && let Some(timeline) = rollback::timeline(&self.world) {
+                self.transmit(&NetMessage::HostSnapshot {
+                    host: migration.to.clone(),
+                    epoch: migration.epoch,
+                    timeline,
+                });
+            }
+            self.dispatch(vec![GameEvent::HostMigrated {
+                from: migration.from,
+                to: migration.to,
+            }]);
+        }
+        for (player, epoch, timeline) in inbox::host_snapshots(&mut self.world) {
+            if host::is_authority(&self.world, &player, epoch)
+                && rollback::adopt(&mut self.world, timeline)
+            {
+                dirty::invalidate(&mut self.world);
+            }
+        }
+    }
+
+    /// Send `message` to the others in the session, if connected.
+    #[cfg(feature = "wasm")]
+    fn transmit(&mut self, message: &NetMessage) {
+        if self.network.is_some() {
+            let _ = self.send(&message.to_json());
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn transmit(&mut self, _message: &NetMessage) {}
+
+    #[cfg(feature = "wasm")]
+    fn receive_network(&mut self) {
+        self.watch_transport();
+        self.channels.tick();
+        for frame in self.limiter.release(clock::now_ms()) {
+            let dropped = self
+                .channels
+                .push(channel::Channel::of_frame(&frame), &frame);
+            inbox::stats_mut(&mut self.world).dropped += dropped as u64;
+        }
+        self.flush_channels();
+        let Some(network) = &self.network else {
+            return;
+        };
+        for frame in network.take_received() {
+            for text in self.channels.receive(&frame) {
+                // Frames that are not protocol messages are counted as dropped.
+                let _ = inbox::receive_text(&mut self.world, &text);
+            }
+        }
+    }
+
+    /// Send what the channels let out now.
+    #[cfg(feature = "wasm")]
+    fn flush_channels(&mut self) {
+        let Some(network) = &self.network else {
+            return;
+        };
+        for frame in self.channels.drain() {
+            let stats = inbox::stats_mut(&mut self.world);
+            if network.send(&frame).is_ok() {
+                stats.messages_sent += 1;
+                stats.bytes_sent += frame.len() as u64;
+            } else {
+                stats.dropped += 1;
+            }
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn receive_network(&mut self) {}
+
+    /// Notice a connection that never got through, and once the server's
+    /// socket has failed too often, reconnect with long polling.
+    #[cfg(feature = "wasm")]
+    fn watch_transport(&mut self) {
+        let Some(network) = &self.network else {
+            return;
+        };
+        if !network.has_failed() {
+            return;
+        }
+        let socket = network.is_socket();
+        self.network = None;
+        if socket {
+            self.fallback.socket_failed();
+            if self.fallback.use_long_poll()
+                && let Some(url) = self.fallback.url().map(str::to_string)
+                && self.connect(&url).is_ok()
+            {
+                inbox::stats_mut(&mut self.world).reconnects += 1;
+            }
+        }
+    }
+
+    /// Greet the server and present the resume token once a new connection
+    /// has opened.
+    #[cfg(feature = "wasm")]
+    fn open_session(&mut self) {
+        if !self.network.as_ref().is_some_and(|n| n.is_open()) {
+            return;
+        }
+        if let Some(hello) = handshake::take_hello(&mut self.world) {
+            self.transmit(&NetMessage::Hello(hello));
+        }
+        if let Some(resume) = session::take_resume(&mut self.world) {
+            self.transmit(&resume);
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn open_session(&mut self) {}
+
+    /// Stop talking on a connection to a server we cannot understand.
+    #[cfg(feature = "wasm")]
+    fn disconnect(&mut self) {
+        if let Some(network) = self.network.take() {
+            network.close();
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn disconnect(&mut self) {}
+
+    #[cfg(feature = "wasm")]
+    fn notify_emote(&self, from: &str, emote: emote::Emote) {
+        if let Some(callback) = &self.on_emote {
+            let id = serde_json::to_value(emote).expect("emotes always serialize");
+            let _ = callback.call2(
+                &JsValue::NULL,
+                &JsValue::from_str(from),
+                &JsValue::from_str(id.as_str().expect("emotes serialize as strings")),
+            );
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn notify_emote(&self, _from: &str, _emote: emote::Emote) {}
+
+    fn local_sync_doc(&self) -> SyncDoc {
+        let autosave =
+            storage::load(AUTOSAVE_KEY).and_then(|text| SavedGame::from_packed(&text).ok());
+        SyncDoc {
+            revision: self.save_revision,
+            saved_ms: autosave.as_ref().map_or(0.0, |saved| saved.saved_ms),
+            autosave,
+            statistics: self.stats.clone(),
+        }
+    }
+}
+
+/// Entry points that need the browser, and so only exist when the `wasm`
+/// feature is enabled.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl SolitaireGame {
+    /// Register a callback invoked with the achievement id (for example
+    /// `"speed_win"`) whenever an achievement is unlocked.
+    pub fn on_achievement_unlocked(&mut self, callback: &js_sys::Function) {
+        self.on_achievement = Some(callback.clone());
+    }
+
+    /// Register a callback invoked when a move leaves no way forward, so the
+    /// page can offer a new game.
+    pub fn on_no_moves_left(&mut self, callback: &js_sys::Function) {
+        self.on_no_moves_left = Some(callback.clone());
+    }
+
+    /// Register a callback invoked with every game event as JSON, for
+    /// example `{"Combo":{"streak":3,"multiplier":3}}`, so the page can
+    /// animate or celebrate it.
+    pub fn on_game_event(&mut self, callback: &js_sys::Function) {
+        self.on_game_event = Some(callback.clone());
+    }
+
+    /// Register a callback invoked at most once a frame, from `advance`,
+    /// with every game event since the last time as one JSON array, such as
+    /// `[{"CardMoved":{...}},"StockRecycled"]`. A deal or an auto-complete
+    /// then costs one call into the page instead of one per event.
+    pub fn on_game_events(&mut self, callback: &js_sys::Function) {
+        self.on_game_events = Some(callback.clone());
+    }
+
+    /// Hand the events batched so far to `on_game_events` now, rather than
+    /// at the next `advance`, and return how many there were. For pages
+    /// that do not call `advance` every frame.
+    pub fn flush_events(&mut self) -> usize {
+        self.deliver_events()
+    }
+
+    /// The current deal as a replay file in a `Blob`, for a download link.
+    pub fn export_replay_blob(&self) -> Result<web_sys::Blob, JsValue> {
+        let replay = ReplayFile::record(&self.world, self.options, clock::now_ms())
+            .ok_or_else(|| JsValue::from_str("no game has been dealt"))?;
+        replay::to_blob(&replay)
+    }
+
+    /// Copy the deal code to the clipboard. The promise settles once it is
+    /// written, and rejects where the clipboard is unavailable.
+    pub fn copy_deal_code(&self) -> js_sys::Promise {
+        share::copy_text(&self.deal_code())
+    }
+
+    /// Copy `result_summary()` to the clipboard, like `copy_deal_code`.
+    pub fn copy_result_summary(&self) -> js_sys::Promise {
+        share::copy_text(&self.result_summary())
+    }
+
+    /// Show `element`, usually the canvas or its container, fullscreen. The
+    /// table is fitted to its new size, and back again when fullscreen mode
+    /// ends, before the next `layout()` or `scene()`. Call it from a user
+    /// gesture such as a click.
+    pub fn request_fullscreen(&mut self, element: &web_sys::Element) -> Result<(), JsValue> {
+        self.fullscreen = Some(fullscreen::Fullscreen::request(element)?);
+        Ok(())
+    }
+
+    /// Publish the theme as CSS custom properties on `element`, usually the
+    /// table's container, and keep them up to date as the theme or the card
+    /// size changes.
+    pub fn set_theme_root(&mut self, element: &web_sys::HtmlElement) -> Result<(), JsValue> {
+        let card_width = layout::metrics(&self.world).card_width;
+        theme::apply(&self.theme, card_width, element)?;
+        self.theme_root = Some(element.clone());
+        Ok(())
+    }
+
+    /// Leave fullscreen mode.
+    pub fn exit_fullscreen(&self) {
+        if let Some(fullscreen) = &self.fullscreen {
+            fullscreen.exit();
+        }
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen.as_ref().is_some_and(|f| f.is_active())
+    }
+
+    /// The cards written by `update_state_view()`, as a `Float32Array`
+    /// over wasm memory. Nothing is copied, so the array reads the numbers
+    /// of the latest update, until `state_view_generation()` changes.
+    pub fn state_view(&self) -> js_sys::Float32Array {
+        // SAFETY: the array aliases the buffer without borrowing it. It is
+        // read by JavaScript between calls into the module only, and the
+        // generation tells the page when the buffer moved or the memory
+        // under the array was detached.
+        unsafe { js_sys::Float32Array::view(self.state_view.as_slice()) }
+    }
+}
+
+/// Multiplayer entry points that talk to the browser's WebSocket API, so
+/// they need both the `wasm` and the `networking` features.
+#[cfg(all(feature = "wasm", feature = "networking"))]
+#[wasm_bindgen]
+impl SolitaireGame {
+    /// Connect to a multiplayer server using a WebSocket URL. After the
+    /// socket to a server has failed a few times, connections to it use HTTP
+    /// long polling instead; see `transport`.
+    ///
+    /// Returns an error if the URL is malformed or not allowed by the
+    /// server policy, if the connection could not be established, or if one
+    /// is open or opening already.
+    pub fn connect(&mut self, url: &str) -> Result<(), GameError> {
+        self.server_policy
+            .check(url)
+            .map_err(GameError::InvalidServerUrl)?;
+        if self
+            .network
+            .as_ref()
+            .is_some_and(|n| n.is_open() || n.is_connecting())
+        {
+            return Err(GameError::AlreadyConnected);
+        }
+        self.fallback.connecting(url);
+        let client: Box<dyn Transport> = if self.fallback.use_long_poll() {
+            Box::new(LongPoll::new(&transport::long_poll_url(url)))
+        } else {
+            let client = NetworkClient::new(url).map_err(|e| {
+                GameError::ConnectionFailed(e.as_string().unwrap_or_else(|| format!("{e:?}")))
+            })?;
+            Box::new(client)
+        };
+        if self.network.replace(client).is_some() {
+            inbox::stats_mut(&mut self.world).reconnects += 1;
+        }
+        self.channels = channel::Mux::default();
+        handshake::connected(&mut self.world);
+        session::connected(&mut self.world);
+        Ok(())
+    }
+
+    /// Restrict the servers `connect` accepts, with a JSON object
+    /// `{ secure_only, allowed_hosts }`: `secure_only` refuses `ws://`, and
+    /// a non-empty `allowed_hosts` list, of host names or `*.domain`
+    /// patterns, refuses any other host. See `endpoint`.
+    pub fn set_server_policy(&mut self, policy_json: &str) -> Result<(), GameError> {
+        self.server_policy = serde_json::from_str(policy_json)
+            .map_err(|e| GameError::InvalidOptions(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Register a callback invoked with the sender's name and the emote id
+    /// whenever another player sends an emote.
+    pub fn on_emote(&mut self, callback: &js_sys::Function) {
+        self.on_emote = Some(callback.clone());
+    }
+
+    /// Send a text message over the WebSocket if it is connected.
+    ///
+    /// The message goes out on its logical channel (see `channel`): moves at
+    /// once, chat and telemetry a few per tick behind them. Chat and lobby
+    /// requests over their budget (see `ratelimit`) are dropped and counted
+    /// as such; a board snapshot over it waits for the budget to come back.
+    pub fn send(&mut self, msg: &str) -> Result<(), GameError> {
+        if let Some(error) = handshake::incompatible(&self.world) {
+            return Err(GameError::Incompatible(error.clone()));
+        }
+        if self.network.is_none() {
+            inbox::stats_mut(&mut self.world).dropped += 1;
+            return Err(GameError::NotConnected);
+        }
+        if log::log_enabled!(target: "network", log::Level::Debug) {
+            let kind = logging::message_type(msg);
+            log::debug!(target: "network", kind:%, bytes = msg.len(); "message sent");
+            log::trace!(target: "network", frame = msg; "sent frame");
+        }
+        // Ahead of the channels, so no message they number is held back or
+        // dropped, which would leave the peer waiting for it.
+        match self
+            .limiter
+            .admit(MessageClass::of_frame(msg), msg, clock::now_ms())
+        {
+            Admission::Send => {}
+            // Sent by `receive_network` once the budget allows.
+            Admission::Held => return Ok(()),
+            Admission::Drop => {
+                inbox::stats_mut(&mut self.world).dropped += 1;
+                return Ok(());
+            }
+        }
+        let dropped = self.channels.push(channel::Channel::of_frame(msg), msg);
+        inbox::stats_mut(&mut self.world).dropped += dropped as u64;
+        self.flush_channels();
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "wasm", feature = "solver"))]
+#[wasm_bindgen]
+impl SolitaireGame {
+    /// Run `solve_async` on a worker started from `script_url`, a module
+    /// script calling `start_solver_worker`. Returns `false`, leaving the
+    /// solver on the main thread, where workers are unavailable.
+    pub fn use_solver_worker(&mut self, script_url: &str) -> bool {
+        self.solver_worker = worker::SolverWorker::new(script_url).ok();
+        self.solver_worker.is_some()
+    }
+
+    /// Solve the board as it is now, giving up after `timeout_ms`
+    /// milliseconds, without blocking the frame loop. The search runs on the
+    /// worker given to `use_solver_worker`, or else a few milliseconds at a
+    /// time between frames. Resolves with the outcome as JSON, such as
+    /// `{"result":"winnable","moves":[...]}`.
+    pub fn solve_async(&self, timeout_ms: f64) -> js_sys::Promise {
+        worker::solve_async(
+            self.solver_worker.as_ref(),
+            solver::Position::from_world(&self.world),
+            10 * solver::DEFAULT_NODE_LIMIT,
+            timeout_ms,
+        )
+    }
+}
+
+#[cfg(all(feature = "wasm", feature = "renderers"))]
+#[wasm_bindgen]
+impl SolitaireGame {
+    /// A PNG snapshot of the board, at the screen's resolution: a promise of
+    /// a `Blob`, ready for `URL.createObjectURL` or `navigator.share`.
+    pub fn export_png(&mut self) -> js_sys::Promise {
+        snapshot::export_png(
+            self.board_svg(),
+            self.canvas.width,
+            self.canvas.height,
+            self.resolution.pixel_ratio(),
+        )
     }
 }
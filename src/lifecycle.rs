@@ -0,0 +1,177 @@
+// Where a game is in its life, from the deal to its end.
+//
+// Nothing keeps the state in a field of its own: it is read off the world,
+// from the resources the rest of the game already keeps. There is no board
+// before the first deal, a replay being stepped through has its playback,
+// a won board has every card home, a time attack out of time has `TimeUp`
+// and a paused one `Paused`. The one state with a marker of its own is
+// `Abandoned`, put there when the player gives a game up. Like `TimeUp` it
+// stops any further move or undo; the next deal clears it with every other
+// resource.
+//
+// A game counts as played from its deal, so giving it up changes nothing in
+// the played and won counts: the `GameAbandoned` event only has the
+// statistics note that it ended that way.
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::GameClock;
+use crate::ecs::World;
+use crate::events::GameEvent;
+use crate::replay::ReplayPlayback;
+use crate::rules::{self, History, Paused};
+use crate::timeattack::TimeUp;
+
+/// Resource present once the game has been given up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Abandoned;
+
+/// The state of the game on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lifecycle {
+    /// Nothing has been dealt yet.
+    Idle,
+    Playing,
+    Paused,
+    Won,
+    /// The countdown of a time attack ran out.
+    TimedOut,
+    Abandoned,
+    /// A replay is being stepped through; it is not a game of the player's.
+    Replay,
+}
+
+impl Lifecycle {
+    pub fn of(world: &World) -> Self {
+        if world.resource::<History>().is_none() {
+            Lifecycle::Idle
+        } else if world.resource::<ReplayPlayback>().is_some() {
+            Lifecycle::Replay
+        } else if rules::is_won(world) {
+            Lifecycle::Won
+        } else if world.resource::<Abandoned>().is_some() {
+            Lifecycle::Abandoned
+        } else if world.resource::<TimeUp>().is_some() {
+            Lifecycle::TimedOut
+        } else if world.resource::<Paused>().is_some() {
+            Lifecycle::Paused
+        } else {
+            Lifecycle::Playing
+        }
+    }
+
+    /// Whether a game is still going, paused or not.
+    pub fn in_progress(self) -> bool {
+        matches!(self, Lifecycle::Playing | Lifecycle::Paused)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lifecycle::Idle => "idle",
+            Lifecycle::Playing => "playing",
+            Lifecycle::Paused => "paused",
+            Lifecycle::Won => "won",
+            Lifecycle::TimedOut => "timed_out",
+            Lifecycle::Abandoned => "abandoned",
+            Lifecycle::Replay => "replay",
+        }
+    }
+}
+
+/// Give up the game in progress at time `now`: its clock stops, no move is
+/// accepted any more, and the returned event says how far it got. `None`
+/// when no game is in progress.
+pub fn abandon(world: &mut World, now: f64) -> Option<GameEvent> {
+    if !Lifecycle::of(world).in_progress() {
+        return None;
+    }
+    world.insert_resource(Abandoned);
+    let clock = world.resource_mut::<GameClock>().map(|clock| {
+        clock.pause(now);
+        clock.elapsed_ms(now)
+    });
+    Some(GameEvent::GameAbandoned {
+        moves: world.resource::<History>().map_or(0, History::moves),
+        time_ms: clock.unwrap_or(0.0) as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolitaireGame;
+    use crate::error::GameError;
+    use crate::rules::Move;
+    use crate::stats::Statistics;
+
+    #[test]
+    fn abandoned_games_take_no_more_moves() {
+        let mut world = World::new();
+        assert_eq!(Lifecycle::of(&world), Lifecycle::Idle);
+        assert_eq!(abandon(&mut world, 0.0), None);
+
+        rules::deal_from_seed(&mut world, 5);
+        world.insert_resource(GameClock::start(1_000.0));
+        assert_eq!(Lifecycle::of(&world), Lifecycle::Playing);
+        rules::apply(&mut world, Move::Draw).unwrap();
+        world.insert_resource(Paused { hide_cards: false });
+        assert_eq!(Lifecycle::of(&world), Lifecycle::Paused);
+        world.remove_resource::<Paused>();
+
+        assert_eq!(
+            abandon(&mut world, 4_000.0),
+            Some(GameEvent::GameAbandoned {
+                moves: 1,
+                time_ms: 3_000
+            })
+        );
+        assert_eq!(Lifecycle::of(&world).name(), "abandoned");
+        assert_eq!(abandon(&mut world, 5_000.0), None);
+        assert_eq!(
+            rules::apply(&mut world, Move::Draw),
+            Err(GameError::Abandoned)
+        );
+        assert_eq!(rules::undo(&mut world), Err(GameError::Abandoned));
+    }
+
+    #[test]
+    fn starting_over_gives_up_the_game_in_progress() {
+        let mut game = SolitaireGame::new();
+        assert_eq!(game.lifecycle(), "idle");
+        assert_eq!(game.abandon(), Err(GameError::NoGameInProgress));
+
+        game.start_game(r#"{"seed":7}"#).unwrap();
+        let board = game.board_state();
+        game.draw_from_stock().unwrap();
+        game.reset();
+        assert_eq!(game.board_state(), board);
+        game.abandon().unwrap();
+        assert_eq!(game.lifecycle(), "abandoned");
+        assert_eq!(game.draw_from_stock(), Err(GameError::Abandoned));
+        game.start_game(r#"{"seed":8}"#).unwrap();
+        assert_eq!(game.lifecycle(), "playing");
+
+        let stats = Statistics::from_json(&game.statistics()).unwrap();
+        assert_eq!((stats.played(), stats.abandoned()), (3, 2));
+
+        // Nothing was played on these deals, so nothing is given up.
+        game.reset();
+        #[cfg(feature = "networking")]
+        game.start_coop("ann");
+        let stats = Statistics::from_json(&game.statistics()).unwrap();
+        assert_eq!(stats.abandoned(), 2);
+    }
+
+    #[test]
+    fn loading_over_a_game_in_progress_gives_it_up() {
+        let mut game = SolitaireGame::new();
+        game.start_game(r#"{"seed":7}"#).unwrap();
+        let replay = game.export_replay().unwrap();
+        game.draw_from_stock().unwrap();
+        game.load_replay(&replay).unwrap();
+        assert_eq!(game.lifecycle(), "replay");
+        let stats = Statistics::from_json(&game.statistics()).unwrap();
+        assert_eq!((stats.played(), stats.abandoned()), (1, 1));
+    }
+}
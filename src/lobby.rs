@@ -0,0 +1,294 @@
+//! Browsing the public rooms of the game server.
+//!
+//! Players looking for a game send a `RoomFilter` in a
+//! `protocol::NetMessage::ListRooms` message and get back one `RoomsPage` of
+//! the open rooms that match it, each with its variant, mode, players and
+//! region, then join one with `JoinRoom` instead of typing in a code. The
+//! latest page received is kept in the world as a resource.
+//!
+//! Players already in a room can also invite friends with an `Invite`: a
+//! short token, shared as a URL fragment such as `#invite=K7Q2M9XD`, that
+//! the server resolves to the room until it expires. Whether a join worked,
+//! and why not if it did not, is kept in the `JoinStatus` resource.
+//!
+//! The filtering and paging live here too, in `RoomFilter::select`, as does
+//! `resolve` for invites, so a native server can answer with exactly the
+//! rules the client expects.
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::leaderboard::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::rng::GameRng;
+use crate::variant::Variant;
+
+/// How long an invite can be used, in milliseconds.
+pub const INVITE_TTL_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+/// Characters in an invite token.
+const TOKEN_LENGTH: usize = 8;
+/// Letters and digits of invite tokens, without look-alikes such as `O`
+/// and `0`.
+const TOKEN_ALPHABET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// How the players of a room play together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomMode {
+    /// Everyone plays the same deal on their own board, fastest wins.
+    Race,
+    /// Everyone plays on one shared board, see `rollback`.
+    Coop,
+}
+
+/// A room on the server, as listed in the lobby.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub id: String,
+    pub name: String,
+    pub variant: Variant,
+    pub mode: RoomMode,
+    pub players: u32,
+    pub max_players: u32,
+    /// Where the room is hosted, such as `"eu-west"`.
+    pub region: String,
+}
+
+impl RoomInfo {
+    pub fn is_full(&self) -> bool {
+        self.players >= self.max_players
+    }
+}
+
+/// Which rooms to list, and which page of them. Unset fields match every
+/// room.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomFilter {
+    pub variant: Option<Variant>,
+    pub mode: Option<RoomMode>,
+    pub region: Option<String>,
+    /// Leave out rooms with no seat free.
+    pub hide_full: bool,
+    /// Zero-based page number.
+    pub page: u32,
+    pub page_size: u32,
+}
+
+impl Default for RoomFilter {
+    fn default() -> Self {
+        Self {
+            variant: None,
+            mode: None,
+            region: None,
+            hide_full: true,
+            page: 0,
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+impl RoomFilter {
+    /// Select a page. The page size is clamped to `1..=MAX_PAGE_SIZE`.
+    pub fn page(mut self, page: u32, page_size: u32) -> Self {
+        self.page = page;
+        self.page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        self
+    }
+
+    pub fn matches(&self, room: &RoomInfo) -> bool {
+        self.variant.is_none_or(|v| v == room.variant)
+            && self.mode.is_none_or(|m| m == room.mode)
+            && self.region.as_ref().is_none_or(|r| *r == room.region)
+            && !(self.hide_full && room.is_full())
+    }
+
+    /// The requested page of the `rooms` that match, in the order given.
+    pub fn select(&self, rooms: &[RoomInfo]) -> RoomsPage {
+        let page_size = self.page_size.clamp(1, MAX_PAGE_SIZE) as usize;
+        let matching: Vec<&RoomInfo> = rooms.iter().filter(|r| self.matches(r)).collect();
+        RoomsPage {
+            page: self.page,
+            total_pages: matching.len().div_ceil(page_size) as u32,
+            rooms: matching
+                .into_iter()
+                .skip(self.page as usize * page_size)
+                .take(page_size)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// One page of rooms as returned by the server, and the latest one received
+/// as a resource.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomsPage {
+    pub page: u32,
+    pub total_pages: u32,
+    pub rooms: Vec<RoomInfo>,
+}
+
+/// A way into a room, handed out by one of its players.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Invite {
+    pub token: String,
+    pub room: String,
+    /// Milliseconds since the Unix epoch after which the invite is refused.
+    pub expires_ms: f64,
+}
+
+impl Invite {
+    /// A new invite to `room`, valid for `INVITE_TTL_MS` from `now_ms`.
+    pub fn new(room: String, now_ms: f64) -> Self {
+        let mut rng = GameRng::from_entropy();
+        let token = (0..TOKEN_LENGTH)
+            .map(|_| {
+                let at = rng.next_u32() as usize % TOKEN_ALPHABET.len();
+                char::from(TOKEN_ALPHABET[at])
+            })
+            .collect();
+        Invite {
+            token,
+            room,
+            expires_ms: now_ms + INVITE_TTL_MS,
+        }
+    }
+
+    /// The invite as a URL fragment, to append to the game's address.
+    pub fn fragment(&self) -> String {
+        format!("#invite={}", self.token)
+    }
+}
+
+/// The token of an invite given as a token, a fragment or a whole URL, if
+/// it is one.
+pub fn parse_invite(text: &str) -> Option<String> {
+    let text = text.trim();
+    let token = text.rsplit_once("invite=").map_or(text, |(_, t)| t);
+    let token = token.to_ascii_uppercase();
+    (token.len() == TOKEN_LENGTH && token.bytes().all(|b| TOKEN_ALPHABET.contains(&b)))
+        .then_some(token)
+}
+
+/// Why the server did not let a player in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinError {
+    /// No invite was ever made with the token.
+    UnknownInvite,
+    InviteExpired,
+    RoomFull,
+    /// The room has closed since it was listed or the invite made.
+    RoomGone,
+}
+
+/// On the server: the room `token` lets a player into at `now_ms`.
+pub fn resolve<'a>(
+    invites: &[Invite],
+    rooms: &'a [RoomInfo],
+    token: &str,
+    now_ms: f64,
+) -> Result<&'a RoomInfo, JoinError> {
+    let invite = invites
+        .iter()
+        .find(|i| i.token == token)
+        .ok_or(JoinError::UnknownInvite)?;
+    if now_ms > invite.expires_ms {
+        return Err(JoinError::InviteExpired);
+    }
+    let room = rooms
+        .iter()
+        .find(|r| r.id == invite.room)
+        .ok_or(JoinError::RoomGone)?;
+    if room.is_full() {
+        return Err(JoinError::RoomFull);
+    }
+    Ok(room)
+}
+
+/// Resource with how asking to join a room went.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JoinStatus {
+    /// Waiting for the server to answer.
+    Pending,
+    Joined {
+        room: RoomInfo,
+    },
+    Failed {
+        reason: JoinError,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(id: &str, mode: RoomMode, players: u32, region: &str) -> RoomInfo {
+        RoomInfo {
+            id: id.into(),
+            name: format!("Room {id}"),
+            variant: Variant::Klondike,
+            mode,
+            players,
+            max_players: 4,
+            region: region.into(),
+        }
+    }
+
+    #[test]
+    fn filters_pick_open_rooms_one_page_at_a_time() {
+        let rooms = [
+            room("a", RoomMode::Race, 1, "eu-west"),
+            room("b", RoomMode::Coop, 2, "eu-west"),
+            room("c", RoomMode::Race, 4, "eu-west"),
+            room("d", RoomMode::Race, 3, "us-east"),
+            room("e", RoomMode::Race, 0, "eu-west"),
+        ];
+        let filter: RoomFilter =
+            serde_json::from_str(r#"{ "mode": "race", "region": "eu-west" }"#).unwrap();
+        let page = filter.clone().page(0, 1).select(&rooms);
+        assert_eq!((page.total_pages, page.rooms[0].id.as_str()), (2, "a"));
+        let page = filter.clone().page(1, 1).select(&rooms);
+        assert_eq!(page.rooms[0].id, "e");
+
+        let all = RoomFilter {
+            hide_full: false,
+            ..RoomFilter::default()
+        };
+        assert_eq!(all.select(&rooms).rooms.len(), 5);
+        assert_eq!(RoomFilter::default().page(0, 0).page_size, 1);
+    }
+
+    #[test]
+    fn invites_lead_into_their_room_until_they_expire() {
+        let invite = Invite::new("b".into(), 0.0);
+        let fragment = invite.fragment();
+        assert_eq!(fragment.len(), "#invite=".len() + TOKEN_LENGTH);
+        let link = format!("https://example.com/play{fragment}");
+        let token = parse_invite(&link).unwrap();
+        assert_eq!(parse_invite(&token.to_lowercase()).as_ref(), Some(&token));
+        assert_eq!(parse_invite("#invite=nope"), None);
+
+        let mut rooms = vec![room("b", RoomMode::Coop, 2, "eu-west")];
+        let invites = [invite];
+        assert_eq!(resolve(&invites, &rooms, &token, 1.0).unwrap().id, "b");
+        assert_eq!(
+            resolve(&invites, &rooms, &token, INVITE_TTL_MS + 1.0),
+            Err(JoinError::InviteExpired)
+        );
+        assert_eq!(
+            resolve(&invites, &rooms, "ZZZZZZZZ", 1.0),
+            Err(JoinError::UnknownInvite)
+        );
+        rooms[0].players = 4;
+        assert_eq!(
+            resolve(&invites, &rooms, &token, 1.0),
+            Err(JoinError::RoomFull)
+        );
+        assert_eq!(
+            resolve(&invites, &[], &token, 1.0),
+            Err(JoinError::RoomGone)
+        );
+    }
+}
@@ -0,0 +1,242 @@
+// Log output, and how much of it each module gives.
+//
+// The game logs through the `log` crate. `init` installs a logger that
+// writes to the browser console, with `console.error`, `warn`, `info`,
+// `log` and `debug` by level so the developer tools can filter them, or to
+// standard error natively. An embedder that installs a logger of its own
+// first keeps it: the entries go there, and the levels set here are not
+// used.
+//
+// Levels are set per module, by its name in the source tree: `"network"`
+// for the protocol traffic, `"rules"` for the moves played. A module without
+// a level of its own uses the default, set under the name `"*"`. Everything
+// starts at `warn`, so nothing but trouble is written until someone asks.
+//
+// Entries carry key-value pairs, written after the message as `key=value`:
+// a move says what it was and how many events it raised, a protocol message
+// whether it was sent or received, its type and its size. At `trace` the
+// protocol frames are written whole.
+
+use std::sync::{OnceLock, RwLock};
+
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::error::GameError;
+
+/// The prefix of the targets `log` gives this crate's modules.
+const CRATE_PREFIX: &str = concat!(env!("CARGO_CRATE_NAME"), "::");
+
+/// The level of each module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Levels {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Levels {
+    pub const fn new() -> Self {
+        Levels {
+            default: LevelFilter::Warn,
+            modules: Vec::new(),
+        }
+    }
+
+    /// Set the level of `module`, or the default when it is `"*"`.
+    pub fn set(&mut self, module: &str, level: LevelFilter) {
+        if module == "*" {
+            self.default = level;
+        } else if let Some(entry) = self.modules.iter_mut().find(|(m, _)| m == module) {
+            entry.1 = level;
+        } else {
+            self.modules.push((module.to_string(), level));
+        }
+    }
+
+    /// The level entries of `target` are written at: that of the innermost
+    /// module it is in with a level of its own, else the default.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        let name = module_name(target);
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                name.strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// The most any module writes.
+    pub fn max(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static LEVELS: RwLock<Levels> = RwLock::new(Levels::new());
+static INSTALLED: OnceLock<bool> = OnceLock::new();
+static LOGGER: ConsoleLogger = ConsoleLogger;
+
+/// Install the console logger, unless a logger is installed already.
+/// Returns whether the console logger is the one in use.
+pub fn init() -> bool {
+    *INSTALLED.get_or_init(|| {
+        let installed = log::set_logger(&LOGGER).is_ok();
+        if installed {
+            log::set_max_level(levels().max());
+        }
+        installed
+    })
+}
+
+/// Set the level of `module` (`"*"` for the default) to `level`: `"off"`,
+/// `"error"`, `"warn"`, `"info"`, `"debug"` or `"trace"`.
+pub fn set_level(module: &str, level: &str) -> Result<(), GameError> {
+    let level: LevelFilter = level
+        .parse()
+        .map_err(|_| GameError::InvalidOptions(format!("unknown log level \"{level}\"")))?;
+    let max = {
+        let mut levels = LEVELS.write().unwrap_or_else(|e| e.into_inner());
+        levels.set(module, level);
+        levels.max()
+    };
+    if init() {
+        log::set_max_level(max);
+    }
+    Ok(())
+}
+
+/// The levels currently set.
+pub fn levels() -> Levels {
+    LEVELS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// The type of a protocol message from its JSON, for log entries.
+pub fn message_type(json: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(json)
+        .ok()
+        .and_then(|value| value.get("type")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| "?".to_string())
+}
+
+/// A log entry as one line: `[LEVEL module] message key=value ...`.
+pub fn format_record(record: &Record) -> String {
+    let mut line = format!(
+        "[{} {}] {}",
+        record.level(),
+        module_name(record.target()),
+        record.args()
+    );
+    let _ = record.key_values().visit(&mut Pairs(&mut line));
+    line
+}
+
+/// `target` without this crate's prefix.
+fn module_name(target: &str) -> &str {
+    target.strip_prefix(CRATE_PREFIX).unwrap_or(target)
+}
+
+struct Pairs<'a>(&'a mut String);
+
+impl<'kvs> VisitSource<'kvs> for Pairs<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push_str(&format!(" {key}={value}"));
+        Ok(())
+    }
+}
+
+struct ConsoleLogger;
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let levels = LEVELS.read().unwrap_or_else(|e| e.into_inner());
+        metadata.level() <= levels.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            write(record.level(), &format_record(record));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn write(level: Level, line: &str) {
+    use web_sys::console;
+    let line = wasm_bindgen::JsValue::from_str(line);
+    match level {
+        Level::Error => console::error_1(&line),
+        Level::Warn => console::warn_1(&line),
+        Level::Info => console::info_1(&line),
+        Level::Debug => console::log_1(&line),
+        Level::Trace => console::debug_1(&line),
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+fn write(_level: Level, line: &str) {
+    eprintln!("{line}");
+}
+
+#[cfg(feature = "wasm")]
+pub use web::set_log_level;
+
+#[cfg(feature = "wasm")]
+mod web {
+    use wasm_bindgen::prelude::*;
+
+    use super::*;
+
+    /// Set how much `module` logs to the console, for example
+    /// `set_log_level("network", "debug")`. `"*"` sets the level of every
+    /// module without one of its own; levels go from `"off"` through
+    /// `"error"`, `"warn"`, `"info"` and `"debug"` to `"trace"`.
+    #[wasm_bindgen]
+    pub fn set_log_level(module: &str, level: &str) -> Result<(), GameError> {
+        set_level(module, level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modules_log_at_their_own_level_with_their_pairs() {
+        let mut levels = Levels::new();
+        levels.set("network", LevelFilter::Debug);
+        levels.set("network::relay", LevelFilter::Off);
+        levels.set("*", LevelFilter::Error);
+        let network = concat!(env!("CARGO_CRATE_NAME"), "::network");
+        assert_eq!(levels.level_for(network), LevelFilter::Debug);
+        assert_eq!(levels.level_for("network::relay::x"), LevelFilter::Off);
+        assert_eq!(levels.level_for("networking"), LevelFilter::Error);
+        assert_eq!(levels.level_for("rules"), LevelFilter::Error);
+        assert_eq!(levels.max(), LevelFilter::Debug);
+
+        let pairs: [(&str, &dyn kv::ToValue); 2] = [("type", &"chat"), ("bytes", &42)];
+        let line = format_record(
+            &Record::builder()
+                .args(format_args!("sent"))
+                .level(Level::Debug)
+                .target(network)
+                .key_values(&pairs)
+                .build(),
+        );
+        assert_eq!(line, "[DEBUG network] sent type=chat bytes=42");
+        assert_eq!(message_type(r#"{"type":"chat","text":"hi"}"#), "chat");
+        assert_eq!(message_type("not json"), "?");
+        assert!(set_level("rules", "loud").is_err());
+    }
+}
@@ -1,3 +1,21 @@
+// A tiny headless entry point for play-testing the game logic natively.
+//
+// Build it without the browser bindings:
+//     cargo run --no-default-features
+// The same library code that powers the WebAssembly build runs here, which
+// makes it easy to poke at the rules from a terminal or a server process.
+
+use eda3_ecs_wasm_game_soli_vanilla_codex_20250731::SolitaireGame;
+
 fn main() {
-    println!("Hello, world!");
+    let mut game = SolitaireGame::new();
+    game.setup_board();
+
+    // Draw a handful of cards so there is something to look at.
+    for _ in 0..3 {
+        match game.draw_card() {
+            Some(card) => println!("Drew {card}"),
+            None => println!("The deck is empty"),
+        }
+    }
 }
@@ -0,0 +1,208 @@
+// The laid out board as plain numbers in wasm memory.
+//
+// `layout()` hands the renderer JSON, which a renderer drawing every frame
+// pays for twice: once to write it and once to parse it. A `StateBuffer`
+// keeps the same information packed in a vector of `f32`, `STRIDE` numbers
+// per card, which JavaScript reads through a `Float32Array` over the wasm
+// memory itself. Refreshing it rewrites the numbers in place; nothing is
+// serialized or copied across.
+//
+// Such a view only stays valid while the vector stays where it is and the
+// wasm memory does not grow, which detaches every view over it. Either bumps
+// the buffer's generation, so the renderer keeps its view until the
+// generation it last saw changes, and then asks for a new one.
+//
+// Cards the player may not see are written without their identity, as in
+// `view`.
+
+use crate::ecs::World;
+use crate::game::{Card, FaceUp, Pile, PileIndex};
+use crate::layout::Position;
+use crate::view::Thoughtful;
+
+/// Numbers per card.
+pub const STRIDE: usize = 8;
+
+/// Where each number lies in a card's record.
+pub mod field {
+    /// The card's entity.
+    pub const ENTITY: usize = 0;
+    /// `Card::to_index`, or -1 for a face-down card the player may not see.
+    pub const CARD: usize = 1;
+    pub const X: usize = 2;
+    pub const Y: usize = 3;
+    pub const Z: usize = 4;
+    /// 1 when face up, 0 when face down.
+    pub const FACE_UP: usize = 5;
+    /// The pile, see `super::pile_code`.
+    pub const PILE: usize = 6;
+    /// The card's place in its pile, from the bottom.
+    pub const INDEX: usize = 7;
+}
+
+/// The number standing for `pile`: 0 for the stock, 1 for the waste,
+/// 16 and up for the foundations and 32 and up for the tableau piles.
+pub fn pile_code(pile: Pile) -> u8 {
+    match pile {
+        Pile::Stock => 0,
+        Pile::Waste => 1,
+        Pile::Foundation(i) => 16 + i,
+        Pile::Tableau(i) => 32 + i,
+    }
+}
+
+/// The board's cards, packed for a typed-array view.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StateBuffer {
+    data: Vec<f32>,
+    generation: u32,
+    memory_pages: usize,
+}
+
+impl StateBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write the cards of `world`, as the layout system last placed them,
+    /// drawn bottom first.
+    pub fn update(&mut self, world: &World) {
+        let before = self.data.as_ptr();
+        let thoughtful = world.resource::<Thoughtful>().is_some();
+        let mut cards: Vec<(&Position, [f32; STRIDE])> = world
+            .iter::<Position>()
+            .filter_map(|(entity, position)| {
+                let card = world.get_component::<Card>(entity)?;
+                let pile = world.get_component::<Pile>(entity)?;
+                let face_up = world.get_component::<FaceUp>(entity).is_some_and(|f| f.0);
+                let index = world.get_component::<PileIndex>(entity).map_or(0, |i| i.0);
+                let shown = face_up || thoughtful;
+                Some((
+                    position,
+                    [
+                        entity as f32,
+                        if shown {
+                            f32::from(card.to_index())
+                        } else {
+                            -1.0
+                        },
+                        position.x,
+                        position.y,
+                        position.z as f32,
+                        f32::from(u8::from(face_up)),
+                        f32::from(pile_code(*pile)),
+                        index as f32,
+                    ],
+                ))
+            })
+            .collect();
+        cards.sort_by_key(|(position, record)| (position.z, record[field::ENTITY] as u32));
+        self.data.clear();
+        for (_, record) in cards {
+            self.data.extend_from_slice(&record);
+        }
+        if self.data.as_ptr() != before {
+            self.generation = self.generation.wrapping_add(1);
+        }
+        self.check_memory();
+    }
+
+    /// The records, `STRIDE` numbers per card.
+    pub fn as_slice(&self) -> &[f32] {
+        &self.data
+    }
+
+    /// Number of cards written.
+    pub fn cards(&self) -> usize {
+        self.data.len() / STRIDE
+    }
+
+    /// Changes whenever views made before may no longer be valid.
+    pub fn generation(&mut self) -> u32 {
+        self.check_memory();
+        self.generation
+    }
+
+    fn check_memory(&mut self) {
+        let pages = memory_pages();
+        if pages != self.memory_pages {
+            self.memory_pages = pages;
+            self.generation = self.generation.wrapping_add(1);
+        }
+    }
+}
+
+/// Pages of wasm memory, which only ever grows. Native memory never moves
+/// under a slice, so there it is always 0.
+fn memory_pages() -> usize {
+    #[cfg(target_arch = "wasm32")]
+    {
+        core::arch::wasm32::memory_size::<0>()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout;
+    use crate::rules::{self, Move};
+
+    #[test]
+    fn records_follow_the_layout_and_hide_face_down_cards() {
+        let mut world = World::new();
+        rules::deal_from_seed(&mut world, 4);
+        layout::layout_system(&mut world);
+        let mut buffer = StateBuffer::new();
+        buffer.update(&world);
+        let generation = buffer.generation();
+        assert_eq!(buffer.cards(), 52);
+
+        let records: Vec<&[f32]> = buffer.as_slice().chunks(STRIDE).collect();
+        assert!(records.windows(2).all(|w| w[0][field::Z] <= w[1][field::Z]));
+        for record in &records {
+            let entity = record[field::ENTITY] as u32;
+            let position = world.get_component::<Position>(entity).unwrap();
+            assert_eq!(
+                (record[field::X], record[field::Y]),
+                (position.x, position.y)
+            );
+            let face_up = world.get_component::<FaceUp>(entity).unwrap().0;
+            assert_eq!(record[field::FACE_UP] == 1.0, face_up);
+            assert_eq!(record[field::CARD] < 0.0, !face_up);
+        }
+        let tableau = |i: u8| f32::from(pile_code(Pile::Tableau(i)));
+        assert_eq!(
+            records
+                .iter()
+                .filter(|r| r[field::PILE] == tableau(6))
+                .count(),
+            7
+        );
+
+        // Refreshing in place keeps views valid.
+        rules::apply(&mut world, Move::Draw).unwrap();
+        layout::layout_system(&mut world);
+        buffer.update(&world);
+        assert_eq!(buffer.generation(), generation);
+        let waste = f32::from(pile_code(Pile::Waste));
+        let drawn = buffer
+            .as_slice()
+            .chunks(STRIDE)
+            .find(|r| r[field::PILE] == waste)
+            .unwrap();
+        assert!(drawn[field::CARD] >= 0.0);
+
+        world.insert_resource(Thoughtful);
+        buffer.update(&world);
+        assert!(
+            buffer
+                .as_slice()
+                .chunks(STRIDE)
+                .all(|r| r[field::CARD] >= 0.0)
+        );
+    }
+}
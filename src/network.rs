@@ -5,17 +5,47 @@
 //! using `wasm-bindgen`. The client wraps the browser's `WebSocket` API and
 //! allows sending text messages and registering callbacks for incoming
 //! messages or connection events.
+//!
+//! Every text frame is also queued for the game itself, which collects them
+//! with `take_received` once per tick, decodes and counts them, and hands
+//! them to its systems through the ECS (see `inbox`). To the game the client
+//! is one `transport::Transport`; where sockets are blocked it falls back to
+//! long polling.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::{JsCast, closure::Closure};
 use web_sys::{BinaryType, ErrorEvent, Event, MessageEvent, WebSocket};
 
+use crate::clock;
+use crate::ratelimit::{Admission, MessageClass, RateLimiter};
+use crate::transport::Transport;
+
 /// A very small wrapper around `WebSocket` so that we can use it from Rust
 /// and expose it to JavaScript through WebAssembly.
 #[wasm_bindgen]
 pub struct NetworkClient {
     /// The underlying WebSocket handle provided by the browser.
     ws: WebSocket,
+    /// Text frames waiting for the game to pick them up. The listener
+    /// below fills it while the game is busy elsewhere, hence the shared
+    /// `Rc<RefCell<..>>`.
+    received: Rc<RefCell<Vec<String>>>,
+    /// The listener queueing messages. We keep it here instead of calling
+    /// `forget`, so it is freed together with the client.
+    _queue: Closure<dyn FnMut(MessageEvent)>,
+    /// Set once the socket closes without having opened, which is how a
+    /// blocked connection shows up.
+    failed: Rc<Cell<bool>>,
+    /// The listener setting `failed`, kept alive like `_queue`.
+    _watch: Closure<dyn FnMut(Event)>,
+    /// Budgets for chat and other messages that can wait, so a page that
+    /// sends too much through `send` does not get the session banned. The
+    /// game keeps budgets of its own, in front of whichever transport it
+    /// uses.
+    limiter: RefCell<RateLimiter>,
 }
 
 #[wasm_bindgen]
@@ -28,12 +58,61 @@ impl NetworkClient {
     pub fn new(url: &str) -> Result<NetworkClient, JsValue> {
         let ws = WebSocket::new(url)?;
         ws.set_binary_type(BinaryType::Arraybuffer);
-        Ok(NetworkClient { ws })
+
+        // A listener added with `add_event_listener` runs next to whatever
+        // `on_message` installs, so JavaScript keeps seeing every message.
+        let received: Rc<RefCell<Vec<String>>> = Rc::default();
+        let queue = {
+            let received = Rc::clone(&received);
+            Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+                // Binary frames are not part of our protocol and are left to
+                // the JavaScript callback.
+                if let Some(text) = e.data().as_string() {
+                    received.borrow_mut().push(text);
+                }
+            })
+        };
+        ws.add_event_listener_with_callback("message", queue.as_ref().unchecked_ref())?;
+
+        // The `close` event fires for failed connections too; a socket that
+        // closes before it ever reached `OPEN` never got through.
+        let failed: Rc<Cell<bool>> = Rc::default();
+        let watch = {
+            let failed = Rc::clone(&failed);
+            let opened = Rc::new(Cell::new(false));
+            Closure::<dyn FnMut(Event)>::new(move |e: Event| {
+                if e.type_() == "open" {
+                    opened.set(true);
+                } else if !opened.get() {
+                    failed.set(true);
+                }
+            })
+        };
+        ws.add_event_listener_with_callback("open", watch.as_ref().unchecked_ref())?;
+        ws.add_event_listener_with_callback("close", watch.as_ref().unchecked_ref())?;
+        Ok(NetworkClient {
+            ws,
+            received,
+            _queue: queue,
+            failed,
+            _watch: watch,
+            limiter: RefCell::default(),
+        })
     }
 
     /// Send a UTF-8 text message to the server.
+    ///
+    /// Chat, board snapshots and lobby requests count against a budget (see
+    /// `ratelimit`). Chat and lobby requests over it are refused with an
+    /// error; a snapshot over it is held back and sent later, unless a newer
+    /// one replaces it first.
     pub fn send(&self, msg: &str) -> Result<(), JsValue> {
-        self.ws.send_with_str(msg)
+        let class = MessageClass::of_frame(msg);
+        match self.limiter.borrow_mut().admit(class, msg, clock::now_ms()) {
+            Admission::Send => self.ws.send_with_str(msg),
+            Admission::Held => Ok(()),
+            Admission::Drop => Err(JsValue::from_str("Rate limited")),
+        }
     }
 
     /// Set a callback that is invoked whenever a message is received.
@@ -72,3 +151,35 @@ impl NetworkClient {
         cb.forget();
     }
 }
+
+impl Transport for NetworkClient {
+    /// Send a frame the game has already budgeted for.
+    fn send(&self, text: &str) -> Result<(), JsValue> {
+        self.ws.send_with_str(text)
+    }
+
+    fn take_received(&self) -> Vec<String> {
+        self.received.take()
+    }
+
+    /// Whether the socket has finished connecting and can send.
+    fn is_open(&self) -> bool {
+        self.ws.ready_state() == WebSocket::OPEN
+    }
+
+    fn is_connecting(&self) -> bool {
+        self.ws.ready_state() == WebSocket::CONNECTING
+    }
+
+    fn has_failed(&self) -> bool {
+        self.failed.get()
+    }
+
+    fn close(&self) {
+        let _ = self.ws.close();
+    }
+
+    fn is_socket(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,293 @@
+// Everything chosen before a game starts.
+//
+// `GameOptions` gathers the variant and the house rules in one value that
+// can be handed to `SolitaireGame::with_options`, saved as JSON, or shared as
+// a short *deal code* that recreates exactly the same game elsewhere:
+//
+//     double_klondike.3.2.keep.vegas.1.-.0.-.12345
+//
+// The fields are, in order: variant, draw count, redeal limit, what a
+// recycle does to the waste (`keep` its order or `shuffle` it), scoring
+// mode (with `+combo` when streaks are scored), foundation base rank (1 for
+// an Ace up to 13 for a King, with `+suits` when every foundation is kept for
+// one suit), undo limit, undo penalty, time attack limit
+// in milliseconds and deal seed. `-` stands for "no limit" or "no seed".
+//
+// Settings of the session rather than of the deal, such as how many deals
+// may be rejected as mulligans, are not part of the code.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::game::Rank;
+use crate::rules::UndoPolicy;
+use crate::scoring::{ComboRules, ScoringMode};
+use crate::timeattack::TimeAttack;
+use crate::variant::{Table, Variant};
+
+/// The options a game is played with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameOptions {
+    pub variant: Variant,
+    /// Cards turned per draw, or `None` for the variant's usual count.
+    pub draw_count: Option<u8>,
+    /// How often the waste may be recycled, or `None` for no limit.
+    pub redeal_limit: Option<u32>,
+    /// Shuffle the waste when it is turned back into the stock.
+    pub reshuffle_on_recycle: bool,
+    pub scoring: ScoringMode,
+    /// Multiply the points of quick streaks of foundation plays.
+    pub combos: bool,
+    /// The rank every foundation starts with.
+    pub foundation_base: Rank,
+    /// Keep each foundation for one suit, Spades first.
+    pub fixed_foundation_suits: bool,
+    pub undo: UndoPolicy,
+    /// Play a time attack against this countdown.
+    pub time_limit_ms: Option<u32>,
+    /// Seed of the deal itself, as recorded in the audit log. `None` deals
+    /// a random board.
+    pub seed: Option<u64>,
+    /// Deals the player may reject per session without losing them.
+    pub mulligans: u32,
+    /// Whether this is a daily challenge, where everyone plays the same deal
+    /// and mulligans are off.
+    pub daily: bool,
+}
+
+impl Default for GameOptions {
+    /// Classic Klondike: draw one, unlimited redeals, standard scoring and
+    /// free undo.
+    fn default() -> Self {
+        GameOptions {
+            variant: Variant::Klondike,
+            draw_count: None,
+            redeal_limit: None,
+            reshuffle_on_recycle: false,
+            scoring: ScoringMode::Standard,
+            combos: false,
+            foundation_base: Rank::Ace,
+            fixed_foundation_suits: false,
+            undo: UndoPolicy::UNLIMITED,
+            time_limit_ms: None,
+            seed: None,
+            mulligans: 0,
+            daily: false,
+        }
+    }
+}
+
+impl GameOptions {
+    /// The variant's table with these options' house rules.
+    pub fn table(&self) -> Table {
+        let table = self.variant.table();
+        Table {
+            draw_count: self.draw_count.unwrap_or(table.draw_count).max(1),
+            redeal_limit: self.redeal_limit,
+            foundation_base: self.foundation_base,
+            reshuffle_on_recycle: self.reshuffle_on_recycle,
+            fixed_foundation_suits: self.fixed_foundation_suits,
+            ..table
+        }
+    }
+
+    /// Insert the resources `rules::deal` and `rules::apply` read.
+    pub fn insert_into(&self, world: &mut World) {
+        world.insert_resource(self.table());
+        world.insert_resource(self.undo);
+        world.insert_resource(self.scoring);
+        world.insert_resource(self.scoring.time_bonus(&self.table()));
+        if self.combos {
+            world.insert_resource(ComboRules::default());
+        }
+        if let Some(limit) = self.time_limit_ms {
+            world.insert_resource(TimeAttack::new(f64::from(limit)));
+        }
+    }
+
+    /// The deal code for these options.
+    pub fn to_code(&self) -> String {
+        fn or_dash<T: Display>(value: Option<T>) -> String {
+            value.map_or("-".to_string(), |v| v.to_string())
+        }
+        let scoring = match self.scoring {
+            ScoringMode::Standard => "standard",
+            ScoringMode::Vegas => "vegas",
+            ScoringMode::None => "none",
+        };
+        let combos = if self.combos { "+combo" } else { "" };
+        let suits = if self.fixed_foundation_suits {
+            "+suits"
+        } else {
+            ""
+        };
+        format!(
+            "{}.{}.{}.{}.{}{}.{}{}.{}.{}.{}.{}",
+            self.variant,
+            self.table().draw_count,
+            or_dash(self.redeal_limit),
+            if self.reshuffle_on_recycle {
+                "shuffle"
+            } else {
+                "keep"
+            },
+            scoring,
+            combos,
+            self.foundation_base.value(),
+            suits,
+            or_dash(self.undo.limit),
+            self.undo.penalty,
+            or_dash(self.time_limit_ms),
+            or_dash(self.seed),
+        )
+    }
+
+    /// Read options back from a deal code made by `to_code`.
+    pub fn from_code(code: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid deal code \"{code}\"");
+        let fields: Vec<&str> = code.trim().split('.').collect();
+        let [
+            variant,
+            draw,
+            redeals,
+            recycle,
+            scoring,
+            base,
+            limit,
+            penalty,
+            time,
+            seed,
+        ] = fields[..]
+        else {
+            return Err(invalid());
+        };
+        fn number<T: FromStr>(field: &str) -> Option<T> {
+            field.parse().ok()
+        }
+        fn optional<T: FromStr>(field: &str) -> Option<Option<T>> {
+            if field == "-" {
+                Some(None)
+            } else {
+                number(field).map(Some)
+            }
+        }
+        let reshuffle_on_recycle = match recycle {
+            "keep" => false,
+            "shuffle" => true,
+            _ => return Err(invalid()),
+        };
+        let (scoring, combos) = match scoring.strip_suffix("+combo") {
+            Some(scoring) => (scoring, true),
+            None => (scoring, false),
+        };
+        let scoring = match scoring {
+            "standard" => ScoringMode::Standard,
+            "vegas" => ScoringMode::Vegas,
+            "none" => ScoringMode::None,
+            _ => return Err(invalid()),
+        };
+        let (base, fixed_foundation_suits) = match base.strip_suffix("+suits") {
+            Some(base) => (base, true),
+            None => (base, false),
+        };
+        let base: u8 = number(base).ok_or_else(invalid)?;
+        let foundation_base = *Rank::ALL
+            .get(usize::from(base).wrapping_sub(1))
+            .ok_or_else(invalid)?;
+        Ok(GameOptions {
+            variant: variant.parse()?,
+            draw_count: Some(number(draw).ok_or_else(invalid)?),
+            redeal_limit: optional(redeals).ok_or_else(invalid)?,
+            reshuffle_on_recycle,
+            scoring,
+            combos,
+            foundation_base,
+            fixed_foundation_suits,
+            undo: UndoPolicy {
+                limit: optional(limit).ok_or_else(invalid)?,
+                penalty: number(penalty).ok_or_else(invalid)?,
+            },
+            time_limit_ms: optional(time).ok_or_else(invalid)?,
+            seed: optional(seed).ok_or_else(invalid)?,
+            ..GameOptions::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolitaireGame;
+    use crate::error::GameError;
+
+    #[test]
+    fn deal_codes_round_trip() {
+        let options = GameOptions {
+            variant: Variant::DoubleKlondike,
+            draw_count: Some(3),
+            redeal_limit: Some(2),
+            reshuffle_on_recycle: true,
+            scoring: ScoringMode::Vegas,
+            combos: true,
+            foundation_base: Rank::Ace,
+            fixed_foundation_suits: true,
+            undo: UndoPolicy::UNLIMITED,
+            time_limit_ms: Some(300_000),
+            seed: Some(12345),
+            ..GameOptions::default()
+        };
+        let code = options.to_code();
+        assert_eq!(
+            code,
+            "double_klondike.3.2.shuffle.vegas+combo.1+suits.-.0.300000.12345"
+        );
+        assert_eq!(GameOptions::from_code(&code), Ok(options));
+        assert!(GameOptions::from_code("klondike.1.-.keep.vegas.14.-.0.-.-").is_err());
+        assert!(GameOptions::from_code("klondike").is_err());
+        assert!(GameOptions::from_code("klondike.1.-.keep.vegas.1.-.5.-.-").is_ok());
+        assert!(GameOptions::from_code("klondike.1.-.keep.vegas.1.-.-5.-.-").is_err());
+        assert!(SolitaireGame::new_game(r#"{"undo":{"limit":null,"penalty":-5}}"#).is_err());
+        assert!(SolitaireGame::new().set_undo_policy(None, -5).is_err());
+    }
+
+    #[test]
+    fn a_deal_code_recreates_the_deal() {
+        let mut game = SolitaireGame::new_game(r#"{"variant":"double_klondike"}"#).unwrap();
+        game.setup_board();
+        game.setup_board();
+        let mut copy = SolitaireGame::from_deal_code(&game.deal_code()).unwrap();
+        copy.setup_board();
+        assert_eq!(copy.board_state(), game.board_state());
+        assert_eq!(copy.deal_code(), game.deal_code());
+    }
+
+    #[test]
+    fn mulligans_are_limited_and_off_in_daily_challenges() {
+        let mut game = SolitaireGame::new_game(r#"{"mulligans":1}"#).unwrap();
+        game.setup_board();
+        game.mulligan().unwrap();
+        assert_eq!(game.mulligan(), Err(GameError::NoMulligansLeft));
+
+        let mut daily = SolitaireGame::new_game(r#"{"mulligans":1,"daily":true}"#).unwrap();
+        daily.setup_board();
+        assert_eq!(daily.mulligan(), Err(GameError::MulliganNotAllowed));
+
+        let mut game = SolitaireGame::new_game(r#"{"mulligans":1}"#).unwrap();
+        game.setup_board();
+        game.draw_from_stock().unwrap();
+        assert!(matches!(game.mulligan(), Err(GameError::IllegalMove(_))));
+    }
+
+    #[test]
+    fn missing_json_fields_keep_their_defaults() {
+        let options: GameOptions =
+            serde_json::from_str(r#"{"variant":"double_klondike"}"#).unwrap();
+        assert_eq!(options.table().draw_count, 3);
+        assert_eq!(options.scoring, ScoringMode::Standard);
+        assert_eq!(options.seed, None);
+    }
+}
@@ -0,0 +1,280 @@
+// A small particle system, for the win celebration.
+//
+// Particles are ordinary entities: a `Particle` saying where it is and what
+// it looks like, a `Velocity`, a `Gravity` pulling it down, a `Lifetime` and,
+// for anything that should bounce off the bottom of the table, a `Bounce`.
+// `particle_system` moves them along and retires them when their time is up.
+// The world never reuses entity ids, so a retired particle simply loses its
+// components. Renderers draw the particles from the scene like everything
+// else.
+//
+// Winning sets off the classic cascade: card after card leaps off the
+// foundations, Kings first, and bounces across the table, while confetti
+// rains down from the top.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::animation;
+use crate::ecs::{Entity, World};
+use crate::game::{Card, Pile};
+use crate::layout::{CanvasSize, Position};
+use crate::rng::GameRng;
+use crate::rules;
+use crate::variant;
+
+/// Average time between two cards leaving the foundations.
+const CASCADE_STEP_MS: f64 = 150.0;
+/// How long a cascading card stays on the table.
+const CARD_LIFETIME_MS: f64 = 4_000.0;
+const CONFETTI_PIECES: usize = 80;
+const CONFETTI_LIFETIME_MS: f64 = 3_000.0;
+
+/// What a particle looks like.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParticleKind {
+    /// A copy of a card's face.
+    Card { card: Card },
+    /// A scrap of paper of the given hue, in degrees.
+    Confetti { hue: u16 },
+}
+
+/// Component of a particle: what it looks like and where it is, in CSS
+/// pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Particle {
+    #[serde(flatten)]
+    pub kind: ParticleKind,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Component with a particle's speed, in pixels per millisecond.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Velocity {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Component with a particle's downward acceleration, in pixels per
+/// millisecond squared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gravity(pub f32);
+
+/// Component with the game time a particle appears at and the time it goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lifetime {
+    pub from_ms: f64,
+    pub until_ms: f64,
+}
+
+impl Lifetime {
+    pub fn is_alive(&self, now_ms: f64) -> bool {
+        (self.from_ms..self.until_ms).contains(&now_ms)
+    }
+}
+
+/// Component making a particle bounce when it reaches `floor`, keeping
+/// `damping` of its speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounce {
+    pub floor: f32,
+    pub damping: f32,
+}
+
+/// Resource with the time `particle_system` last ran at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LastStep(f64);
+
+/// Start the win celebration at `now_ms`. `seed` makes the particles' paths
+/// random without touching the game's own `GameRng`. With reduced motion
+/// there is no celebration.
+pub fn celebrate(world: &mut World, now_ms: f64, seed: u64) {
+    if animation::reduced_motion(world) {
+        return;
+    }
+    let mut rng = GameRng::from_seed(seed);
+    let canvas = world.resource::<CanvasSize>().copied().unwrap_or_default();
+    let foundations: Vec<Vec<Entity>> = (0..variant::table(world).foundation_piles)
+        .map(|f| rules::pile_cards(world, Pile::Foundation(f)))
+        .collect();
+    let height = foundations.iter().map(Vec::len).max().unwrap_or(0);
+    // The style's easing spaces the cards out over the whole cascade.
+    let easing = animation::style(world).win;
+    let total = foundations.iter().map(Vec::len).sum::<usize>().max(1) as f64;
+    let cascade_ms = total * CASCADE_STEP_MS;
+
+    let mut cards = Vec::new();
+    let mut launched = 0.0;
+    for depth in 1..=height {
+        for pile in &foundations {
+            let Some(&entity) = pile.len().checked_sub(depth).map(|i| &pile[i]) else {
+                continue;
+            };
+            let Some(&card) = world.get_component::<Card>(entity) else {
+                continue;
+            };
+            let at = world
+                .get_component::<Position>(entity)
+                .copied()
+                .unwrap_or(Position {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0,
+                });
+            let from_ms = now_ms + cascade_ms * f64::from(easing.apply((launched / total) as f32));
+            launched += 1.0;
+            cards.push((
+                Particle {
+                    kind: ParticleKind::Card { card },
+                    x: at.x,
+                    y: at.y,
+                },
+                Velocity {
+                    x: rng.gen_range(-0.5..0.5),
+                    y: rng.gen_range(-0.4..0.0),
+                },
+                Gravity(0.002),
+                Lifetime {
+                    from_ms,
+                    until_ms: from_ms + CARD_LIFETIME_MS,
+                },
+                Bounce {
+                    floor: canvas.height * 0.8,
+                    damping: 0.7,
+                },
+            ));
+        }
+    }
+    world.spawn_batch(cards);
+
+    let confetti: Vec<_> = (0..CONFETTI_PIECES)
+        .map(|_| {
+            let from_ms = now_ms + rng.gen_range(0.0..1_000.0);
+            (
+                Particle {
+                    kind: ParticleKind::Confetti {
+                        hue: rng.gen_range(0..360),
+                    },
+                    x: rng.gen_range(0.0..canvas.width.max(1.0)),
+                    y: 0.0,
+                },
+                Velocity {
+                    x: rng.gen_range(-0.05..0.05),
+                    y: rng.gen_range(0.05..0.15),
+                },
+                Gravity(0.0001),
+                Lifetime {
+                    from_ms,
+                    until_ms: from_ms + CONFETTI_LIFETIME_MS,
+                },
+            )
+        })
+        .collect();
+    world.spawn_batch(confetti);
+}
+
+/// Move every live particle on to `now_ms` and retire the expired ones.
+pub fn particle_system(world: &mut World, now_ms: f64) {
+    let last = world.resource::<LastStep>().map_or(now_ms, |l| l.0);
+    world.insert_resource(LastStep(now_ms));
+    let dt = (now_ms - last).max(0.0) as f32;
+
+    let particles: Vec<(Entity, Lifetime)> =
+        world.iter::<Lifetime>().map(|(e, &l)| (e, l)).collect();
+    for (entity, lifetime) in particles {
+        if now_ms >= lifetime.until_ms {
+            world.remove_component::<Particle>(entity);
+            world.remove_component::<Velocity>(entity);
+            world.remove_component::<Gravity>(entity);
+            world.remove_component::<Lifetime>(entity);
+            world.remove_component::<Bounce>(entity);
+            continue;
+        }
+        if !lifetime.is_alive(now_ms) {
+            continue;
+        }
+        let gravity = world.get_component::<Gravity>(entity).map_or(0.0, |g| g.0);
+        let bounce = world.get_component::<Bounce>(entity).copied();
+        let Some(mut velocity) = world.get_component::<Velocity>(entity).copied() else {
+            continue;
+        };
+        velocity.y += gravity * dt;
+        if let Some(particle) = world.get_component_mut::<Particle>(entity) {
+            particle.x += velocity.x * dt;
+            particle.y += velocity.y * dt;
+            if let Some(bounce) = bounce
+                && particle.y > bounce.floor
+                && velocity.y > 0.0
+            {
+                particle.y = bounce.floor;
+                velocity.y = -velocity.y * bounce.damping;
+            }
+        }
+        world.add_component(entity, velocity);
+    }
+}
+
+/// The particles showing at `now_ms`, in the order they should be drawn.
+pub fn visible(world: &World, now_ms: f64) -> Vec<Particle> {
+    world
+        .iter::<Particle>()
+        .filter(|(entity, _)| {
+            world
+                .get_component::<Lifetime>(*entity)
+                .is_some_and(|l| l.is_alive(now_ms))
+        })
+        .map(|(_, &particle)| particle)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{FaceUp, PileIndex, Rank, Suit};
+
+    #[test]
+    fn cascading_cards_fall_bounce_and_expire() {
+        let mut world = World::new();
+        world.spawn_batch([
+            (
+                Card::new(Suit::Hearts, Rank::Ace),
+                Pile::Foundation(0),
+                PileIndex(0),
+                FaceUp(true),
+            ),
+            (
+                Card::new(Suit::Hearts, Rank::Two),
+                Pile::Foundation(0),
+                PileIndex(1),
+                FaceUp(true),
+            ),
+        ]);
+        celebrate(&mut world, 0.0, 1);
+        particle_system(&mut world, 0.0);
+        let shown = visible(&world, 0.0);
+        // The Two leaves first; the Ace waits its turn.
+        assert!(shown.iter().any(|p| p.kind
+            == ParticleKind::Card {
+                card: Card::new(Suit::Hearts, Rank::Two)
+            }));
+        assert!(shown.iter().all(|p| !matches!(
+            p.kind,
+            ParticleKind::Card { card } if card.rank == Rank::Ace
+        )));
+
+        // Cards bounce off the floor; confetti just falls through.
+        let floor = CanvasSize::default().height * 0.8;
+        for t in 1..=100 {
+            particle_system(&mut world, f64::from(t) * 30.0);
+            let shown = visible(&world, f64::from(t) * 30.0);
+            let mut cards = shown
+                .iter()
+                .filter(|p| matches!(p.kind, ParticleKind::Card { .. }));
+            assert!(cards.all(|p| p.y <= floor));
+        }
+        particle_system(&mut world, 10_000.0);
+        assert_eq!(world.iter::<Particle>().count(), 0);
+    }
+}
@@ -0,0 +1,176 @@
+// Following opponents' progress without watching their boards.
+//
+// Streaming whole boards for `spectator` is more than a small progress panel
+// needs. A `BoardSummary` says just how far a board has come: the cards on
+// each foundation, the face-down cards left in each tableau pile and the
+// cards left in the stock. Players share theirs with `Sharing`, which sends a
+// fresh summary at most every `SUMMARY_INTERVAL_MS` when it has changed, and
+// every `REFRESH_MS` regardless, so players joining late catch up. The
+// summaries received are kept by player name in `OpponentProgress`.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::game::{Card, FaceUp, Pile};
+#[cfg(feature = "networking")]
+use crate::protocol::NetMessage;
+use crate::rules;
+use crate::variant;
+
+/// Least time between two summaries.
+pub const SUMMARY_INTERVAL_MS: f64 = 1_000.0;
+/// Longest time between two summaries, changed or not.
+pub const REFRESH_MS: f64 = 5_000.0;
+
+/// How far a board has come.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardSummary {
+    /// Cards on each foundation.
+    pub foundations: Vec<u8>,
+    /// Face-down cards in each tableau pile.
+    pub face_down: Vec<u8>,
+    /// Cards left in the stock.
+    pub stock: u8,
+    /// Cards in the deal.
+    pub cards: u8,
+}
+
+impl BoardSummary {
+    pub fn of(world: &World) -> Self {
+        let table = variant::table(world);
+        let count = |pile| rules::pile_cards(world, pile).len() as u8;
+        let face_down = (0..table.tableau_piles)
+            .map(|t| {
+                rules::pile_cards(world, Pile::Tableau(t))
+                    .into_iter()
+                    .filter(|&e| !world.get_component::<FaceUp>(e).is_some_and(|f| f.0))
+                    .count() as u8
+            })
+            .collect();
+        BoardSummary {
+            foundations: (0..table.foundation_piles)
+                .map(|f| count(Pile::Foundation(f)))
+                .collect(),
+            face_down,
+            stock: count(Pile::Stock),
+            cards: world.iter::<Card>().count() as u8,
+        }
+    }
+
+    /// Cards on the foundations.
+    pub fn cards_home(&self) -> u32 {
+        self.foundations.iter().map(|&n| u32::from(n)).sum()
+    }
+}
+
+/// A player's summary as shown in the progress panel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerProgress {
+    pub player: String,
+    #[serde(flatten)]
+    pub summary: BoardSummary,
+    pub cards_home: u32,
+}
+
+/// Resource with the latest summary from each opponent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpponentProgress(BTreeMap<String, BoardSummary>);
+
+/// File a summary from `player`.
+pub fn receive(world: &mut World, player: String, summary: BoardSummary) {
+    if world.resource::<OpponentProgress>().is_none() {
+        world.insert_resource(OpponentProgress::default());
+    }
+    let progress = world
+        .resource_mut::<OpponentProgress>()
+        .expect("inserted above");
+    progress.0.insert(player, summary);
+}
+
+/// Every opponent's progress, by name.
+pub fn panel(world: &World) -> Vec<PlayerProgress> {
+    world
+        .resource::<OpponentProgress>()
+        .map(|p| {
+            p.0.iter()
+                .map(|(player, summary)| PlayerProgress {
+                    player: player.clone(),
+                    summary: summary.clone(),
+                    cards_home: summary.cards_home(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Sharing this board's progress with the others in the session.
+#[cfg(feature = "networking")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sharing {
+    /// The name to share it under, while sharing.
+    player: Option<String>,
+    last: Option<(BoardSummary, f64)>,
+}
+
+#[cfg(feature = "networking")]
+impl Sharing {
+    /// Share as `player` from now on, or stop with `None`.
+    pub fn set_player(&mut self, player: Option<String>) {
+        self.player = player;
+        self.last = None;
+    }
+
+    /// The summary to send at `now_ms`, if one is due.
+    pub fn due(&mut self, world: &World, now_ms: f64) -> Option<NetMessage> {
+        let player = self.player.clone()?;
+        let since = self.last.as_ref().map(|(_, sent_ms)| now_ms - sent_ms);
+        if since.is_some_and(|ms| ms < SUMMARY_INTERVAL_MS) {
+            return None;
+        }
+        let summary = BoardSummary::of(world);
+        let unchanged = self.last.as_ref().is_some_and(|(last, _)| *last == summary);
+        if unchanged && since.is_some_and(|ms| ms < REFRESH_MS) {
+            return None;
+        }
+        self.last = Some((summary.clone(), now_ms));
+        Some(NetMessage::BoardSummary { player, summary })
+    }
+}
+
+#[cfg(all(test, feature = "networking"))]
+mod tests {
+    use super::*;
+    use crate::rules::Move;
+
+    #[test]
+    fn summaries_go_out_when_the_board_changes() {
+        let mut world = World::new();
+        rules::deal_from_seed(&mut world, 3);
+        let summary = BoardSummary::of(&world);
+        assert_eq!(summary.face_down, [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!((summary.stock, summary.cards), (24, 52));
+        assert_eq!(summary.cards_home(), 0);
+
+        let mut sharing = Sharing::default();
+        assert!(sharing.due(&world, 0.0).is_none());
+        sharing.set_player(Some("ann".into()));
+        assert!(sharing.due(&world, 0.0).is_some());
+        // Nothing changed: only the refresh goes out.
+        assert!(sharing.due(&world, 2_000.0).is_none());
+        assert!(sharing.due(&world, REFRESH_MS).is_some());
+        rules::apply(&mut world, Move::Draw).unwrap();
+        assert!(sharing.due(&world, REFRESH_MS + 500.0).is_none());
+        let Some(NetMessage::BoardSummary { player, summary }) =
+            sharing.due(&world, REFRESH_MS + SUMMARY_INTERVAL_MS)
+        else {
+            panic!("the draw is shared");
+        };
+        assert_eq!(summary.stock, 23);
+
+        receive(&mut world, player, summary);
+        let panel = panel(&world);
+        assert_eq!((panel[0].player.as_str(), panel[0].cards_home), ("ann", 0));
+    }
+}
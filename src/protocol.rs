@@ -0,0 +1,161 @@
+//! Typed messages exchanged with the game server.
+//!
+//! Messages travel as JSON text frames over the WebSocket managed by
+//! `network::NetworkClient`. Each message carries a `"type"` field naming the
+//! variant, for example:
+//!
+//! ```json
+//! { "type": "fetch_rankings", "variant": "klondike", "daily": null, "page": 0, "page_size": 20 }
+//! ```
+//!
+//! Before anything else, both sides exchange a `Hello` to agree on a
+//! version, see `handshake`.
+//!
+//! Keeping the protocol in its own module, free of any browser types, lets a
+//! native server share exactly the same definitions.
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::SignedMove;
+use crate::daily::DailyChallenge;
+use crate::emote::Emote;
+use crate::handshake::Hello;
+use crate::leaderboard::{RankingsPage, RankingsQuery, SignedSubmission};
+use crate::lobby::{Invite, JoinError, RoomFilter, RoomInfo, RoomsPage};
+use crate::progress::BoardSummary;
+use crate::rating::PlayerRating;
+use crate::rollback::{Input, Timeline};
+use crate::rules::Move;
+use crate::spectator::BoardSnapshot;
+use crate::tournament::{BracketStanding, Pairing, RoundResult};
+use crate::variant::Variant;
+
+/// Every message understood by the client and the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NetMessage {
+    /// Either way, first on every connection: what the side speaks.
+    Hello(Hello),
+    /// Server → client: a new session, which a later connection can take up
+    /// again with `Resume` and this token.
+    SessionStarted { resume_token: String },
+    /// Client → server: put this connection back into the session of the
+    /// token.
+    Resume { token: String },
+    /// Server → client: the session was taken up again.
+    Resumed,
+    /// Server → client: the token is unknown or has run out.
+    ResumeFailed,
+    /// Client → server: send what happened in the match after `since_tick`.
+    RequestCatchUp { since_tick: u64 },
+    /// Server → client: the state of the match, answering `RequestCatchUp`.
+    CatchUp {
+        roster: Vec<String>,
+        timeline: Option<Timeline>,
+    },
+    /// Client → server: record a finished game on the leaderboard.
+    SubmitScore(SignedSubmission),
+    /// Server → client: the session is authenticated; ranked moves are
+    /// signed with `session_key` from now on.
+    Authenticated { session_key: String },
+    /// Client → server: an action of a ranked game, as it is made.
+    SignedMove(SignedMove),
+    /// Client → server: ask for the sender's ranked rating.
+    FetchRating,
+    /// Server → client: the sender's ranked rating, answering `FetchRating`.
+    Rating(PlayerRating),
+    /// Server → client: the sender's ranked game was scored, changing the
+    /// rating by `change`.
+    RankedResult { change: f64, rating: PlayerRating },
+    /// Client → server: ask for today's daily challenge in `variant`.
+    FetchDaily { variant: Variant },
+    /// Server → client: the official daily challenge, answering
+    /// `FetchDaily`.
+    Daily(DailyChallenge),
+    /// Client → server: ask for one page of rankings.
+    FetchRankings(RankingsQuery),
+    /// Server → client: the page requested by `FetchRankings`.
+    Rankings(RankingsPage),
+    /// Client → server: ask for one page of the open rooms.
+    ListRooms(RoomFilter),
+    /// Server → client: the page requested by `ListRooms`.
+    Rooms(RoomsPage),
+    /// Client → server: join the room with this id.
+    JoinRoom { room: String },
+    /// Client → server: let whoever has this invite into the sender's room.
+    CreateInvite(Invite),
+    /// Client → server: join the room of an invite.
+    JoinByInvite { token: String },
+    /// Server → client: the sender is now in `room`.
+    Joined { room: RoomInfo },
+    /// Server → client: the sender could not join.
+    JoinFailed { reason: JoinError },
+    /// Client → server: enter the tournament `bracket`.
+    JoinBracket { bracket: String },
+    /// Server → client: the sender is in `bracket`, with `players`.
+    BracketJoined {
+        bracket: String,
+        players: Vec<String>,
+    },
+    /// Server → client: the sender's match in the next round.
+    RoundPairing(Pairing),
+    /// Client → server: how the sender's round went.
+    SubmitResult(RoundResult),
+    /// Client → server: ask where `bracket` stands.
+    FetchStanding { bracket: String },
+    /// Server → client: where a bracket stands, on request and whenever it
+    /// changes.
+    BracketStanding(BracketStanding),
+    /// Server → client: a move another player made on the shared deal.
+    PlayerMove {
+        player: String,
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    /// Server → client: the players in the session, whenever it changes.
+    Roster { players: Vec<String> },
+    /// Either way: a player's board, for others to watch.
+    BoardSnapshot {
+        player: String,
+        snapshot: BoardSnapshot,
+    },
+    /// Either way: how far a player's board has come, for a progress panel.
+    BoardSummary {
+        player: String,
+        summary: BoardSummary,
+    },
+    /// Either way: a line of chat.
+    Chat { from: String, text: String },
+    /// Either way: a quick reaction. The server fills in `from`.
+    Emote { from: String, emote: Emote },
+    /// Either way: an opaque message setting up a voice call between `from`
+    /// and `to`, relayed by the server.
+    VoiceSignal {
+        from: String,
+        to: String,
+        data: String,
+    },
+    /// Client → server: a measurement for the server's dashboards.
+    Telemetry { name: String, value: f64 },
+    /// Either way: a move in a co-op game, with the tick it is played on.
+    CoopMove(Input),
+    /// Either way: the co-op game as the host of the room sees it, sent when
+    /// it takes over.
+    HostSnapshot {
+        host: String,
+        epoch: u32,
+        timeline: Timeline,
+    },
+}
+
+impl NetMessage {
+    /// Encode the message as a JSON text frame.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("protocol messages always serialize")
+    }
+
+    /// Decode a JSON text frame received from the network.
+    pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+}
@@ -0,0 +1,230 @@
+//! Keeping the client from flooding the server.
+//!
+//! A buggy page that sends chat in a loop, or streams its board every frame,
+//! would get the whole session thrown off the server for flooding. Outgoing
+//! messages are therefore sorted into classes, and every class but the
+//! critical one — moves, handshakes, submissions — has a token bucket: a
+//! burst it may send at once and a rate at which the allowance comes back.
+//!
+//! Messages wrapped in a `channel::Frame` are judged by the message inside.
+//!
+//! What goes over the budget depends on the class. Chat, emotes and lobby requests
+//! are dropped. Board snapshots are coalesced instead: only the newest one is
+//! held back, replacing any older one still waiting, and sent by `release`
+//! as soon as the budget allows, since watchers only care about the latest
+//! board anyway.
+
+use std::collections::HashMap;
+
+use crate::channel::Frame;
+use crate::protocol::NetMessage;
+
+/// What a message is for, as far as the budget goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageClass {
+    /// Never limited.
+    Critical,
+    Chat,
+    /// Quick reactions, see `emote`.
+    Emote,
+    /// Boards streamed for spectators.
+    Snapshot,
+    /// Listing rooms and making invites.
+    Lobby,
+}
+
+/// What to do with messages over the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Excess {
+    Drop,
+    /// Hold the newest back until there is budget again.
+    Coalesce,
+}
+
+/// The allowance of one class.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    /// Messages that may go out at once.
+    pub burst: f64,
+    /// Messages per second the allowance grows back by.
+    pub per_second: f64,
+    pub excess: Excess,
+}
+
+impl MessageClass {
+    pub fn of(message: &NetMessage) -> Self {
+        match message {
+            NetMessage::Chat { .. } => MessageClass::Chat,
+            NetMessage::Emote { .. } => MessageClass::Emote,
+            NetMessage::BoardSnapshot { .. } => MessageClass::Snapshot,
+            NetMessage::ListRooms(_) | NetMessage::CreateInvite(_) => MessageClass::Lobby,
+            _ => MessageClass::Critical,
+        }
+    }
+
+    /// The class of a text frame. Frames that are not protocol messages
+    /// pass as critical: the server is the judge of those.
+    pub fn of_frame(text: &str) -> Self {
+        let framed = serde_json::from_str::<Frame>(text).ok();
+        let text = framed.as_ref().map_or(text, |f| &f.msg);
+        NetMessage::from_json(text).map_or(MessageClass::Critical, |m| Self::of(&m))
+    }
+
+    pub fn budget(self) -> Option<Budget> {
+        match self {
+            MessageClass::Critical => None,
+            MessageClass::Chat => Some(Budget {
+                burst: 5.0,
+                per_second: 1.0,
+                excess: Excess::Drop,
+            }),
+            MessageClass::Emote => Some(Budget {
+                burst: 3.0,
+                per_second: 0.5,
+                excess: Excess::Drop,
+            }),
+            MessageClass::Snapshot => Some(Budget {
+                burst: 2.0,
+                per_second: 4.0,
+                excess: Excess::Coalesce,
+            }),
+            MessageClass::Lobby => Some(Budget {
+                burst: 3.0,
+                per_second: 0.5,
+                excess: Excess::Drop,
+            }),
+        }
+    }
+}
+
+/// What became of a message handed to the limiter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Send,
+    Drop,
+    /// Held back, to go out with `release`.
+    Held,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bucket {
+    tokens: f64,
+    last_ms: f64,
+}
+
+impl Bucket {
+    fn refill(&mut self, budget: &Budget, now_ms: f64) {
+        let elapsed = (now_ms - self.last_ms).max(0.0) / 1000.0;
+        self.tokens = (self.tokens + elapsed * budget.per_second).min(budget.burst);
+        self.last_ms = now_ms;
+    }
+
+    fn take(&mut self) -> bool {
+        let available = self.tokens >= 1.0;
+        if available {
+            self.tokens -= 1.0;
+        }
+        available
+    }
+}
+
+/// The buckets of every class, and the frames held back.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    buckets: HashMap<MessageClass, Bucket>,
+    held: HashMap<MessageClass, String>,
+}
+
+impl RateLimiter {
+    /// Decide whether `frame` of `class` may go out at `now_ms`.
+    pub fn admit(&mut self, class: MessageClass, frame: &str, now_ms: f64) -> Admission {
+        let Some(budget) = class.budget() else {
+            return Admission::Send;
+        };
+        // Something older is still waiting; this one takes its place.
+        if budget.excess == Excess::Coalesce && self.held.contains_key(&class) {
+            self.held.insert(class, frame.to_string());
+            return Admission::Held;
+        }
+        if self.bucket(class, &budget, now_ms).take() {
+            return Admission::Send;
+        }
+        match budget.excess {
+            Excess::Drop => Admission::Drop,
+            Excess::Coalesce => {
+                self.held.insert(class, frame.to_string());
+                Admission::Held
+            }
+        }
+    }
+
+    /// The held-back frames the budget allows out at `now_ms`.
+    pub fn release(&mut self, now_ms: f64) -> Vec<String> {
+        let classes: Vec<MessageClass> = self.held.keys().copied().collect();
+        let mut frames = Vec::new();
+        for class in classes {
+            let Some(budget) = class.budget() else {
+                continue;
+            };
+            if self.bucket(class, &budget, now_ms).take()
+                && let Some(frame) = self.held.remove(&class)
+            {
+                frames.push(frame);
+            }
+        }
+        frames
+    }
+
+    fn bucket(&mut self, class: MessageClass, budget: &Budget, now_ms: f64) -> &mut Bucket {
+        let bucket = self.buckets.entry(class).or_insert(Bucket {
+            tokens: budget.burst,
+            last_ms: now_ms,
+        });
+        bucket.refill(budget, now_ms);
+        bucket
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_floods_are_dropped_and_snapshots_coalesced() {
+        let mut limiter = RateLimiter::default();
+        let chat = NetMessage::Chat {
+            from: "ann".into(),
+            text: "hi".into(),
+        }
+        .to_json();
+        assert_eq!(MessageClass::of_frame(&chat), MessageClass::Chat);
+        let sent = (0..8)
+            .filter(|_| limiter.admit(MessageClass::Chat, &chat, 0.0) == Admission::Send)
+            .count();
+        assert_eq!(sent, 5);
+        // A second later there is room for one more.
+        assert_eq!(
+            limiter.admit(MessageClass::Chat, &chat, 1_000.0),
+            Admission::Send
+        );
+        assert_eq!(
+            limiter.admit(MessageClass::Chat, &chat, 1_000.0),
+            Admission::Drop
+        );
+        // Moves are never held up.
+        assert_eq!(
+            limiter.admit(MessageClass::Critical, "{}", 1_000.0),
+            Admission::Send
+        );
+        assert_eq!(MessageClass::of_frame("not json"), MessageClass::Critical);
+        let framed = serde_json::json!({ "ch": "chat", "seq": 0, "msg": chat }).to_string();
+        assert_eq!(MessageClass::of_frame(&framed), MessageClass::Chat);
+
+        for frame in ["a", "b", "c", "d"] {
+            limiter.admit(MessageClass::Snapshot, frame, 0.0);
+        }
+        assert!(limiter.release(0.0).is_empty());
+        assert_eq!(limiter.release(250.0), ["d"]);
+        assert!(limiter.release(500.0).is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+// The player's rating in ranked play.
+//
+// Ratings are Elo ratings kept by the server. The client asks for its own
+// with `protocol::NetMessage::FetchRating` once the session is
+// authenticated, and after every ranked game the server confirms the new
+// one with `RankedResult`. The latest rating is saved with the
+// `Statistics`, so it shows before the server has answered too.
+//
+// The server has the last word, but the client computes the same numbers to
+// show what is at stake before a game: `preview` gives the points a win, a
+// draw or a loss against an opponent would bring, such as "+12 if you win".
+
+use serde::{Deserialize, Serialize};
+
+/// The rating new players start with.
+pub const INITIAL_RATING: f64 = 1500.0;
+/// Ranked games after which a rating counts as settled.
+pub const PROVISIONAL_GAMES: u32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlayerRating {
+    pub rating: f64,
+    /// Ranked games played.
+    pub games: u32,
+}
+
+impl Default for PlayerRating {
+    fn default() -> Self {
+        PlayerRating {
+            rating: INITIAL_RATING,
+            games: 0,
+        }
+    }
+}
+
+impl PlayerRating {
+    /// How far a single game moves the rating: a lot while it is
+    /// provisional, less once it has settled.
+    pub fn k_factor(&self) -> f64 {
+        if self.games < PROVISIONAL_GAMES {
+            40.0
+        } else {
+            20.0
+        }
+    }
+
+    /// The change a game against `opponent` brings for `score`: 1 for a
+    /// win, 0.5 for a draw, 0 for a loss.
+    pub fn change(&self, opponent: f64, score: f64) -> f64 {
+        self.k_factor() * (score - expected_score(self.rating, opponent))
+    }
+}
+
+/// The score a player rated `ours` is expected to make against one rated
+/// `theirs`, from 0 to 1.
+pub fn expected_score(ours: f64, theirs: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((theirs - ours) / 400.0))
+}
+
+/// What a game against an opponent would do to the rating, in whole points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preview {
+    pub win: i32,
+    pub draw: i32,
+    pub loss: i32,
+}
+
+pub fn preview(rating: &PlayerRating, opponent: f64) -> Preview {
+    let points = |score| rating.change(opponent, score).round() as i32;
+    Preview {
+        win: points(1.0),
+        draw: points(0.5),
+        loss: points(0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_weigh_the_opponent() {
+        let new = PlayerRating::default();
+        assert_eq!(expected_score(1500.0, 1500.0), 0.5);
+        assert_eq!(
+            preview(&new, 1500.0),
+            Preview {
+                win: 20,
+                draw: 0,
+                loss: -20
+            }
+        );
+
+        let settled = PlayerRating {
+            rating: 1600.0,
+            games: 100,
+        };
+        let against_weaker = preview(&settled, 1400.0);
+        assert_eq!((against_weaker.win, against_weaker.loss), (5, -15));
+    }
+}
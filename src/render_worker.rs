@@ -0,0 +1,396 @@
+//! Painting the table in a Web Worker, on an `OffscreenCanvas`.
+//!
+//! The page keeps the game on the main thread and hands its canvas over to a
+//! worker once. From then on it only streams *scenes*: everything needed to
+//! paint one frame, as JSON. The worker paints them in its own time, so heavy
+//! drawing never blocks input handling:
+//!
+//! ```json
+//! { "kind": "scene", "card_width": 80.0, "card_height": 112.0, "pixel_ratio": 2.0,
+//!   "background": { "color": "#1f6b3a", "kind": "solid" },
+//!   "cards": [{ "entity": 3, "x": 16.0, "y": 16.0, "z": 131072, "card": null, "visibility": "hidden" }],
+//!   "dirty": { "full": false, "rects": [...], "counters": true },
+//!   "score": 15, "moves": 4, "hide_cards": false }
+//! ```
+//!
+//! Like the solver worker, the render worker is this same wasm module,
+//! started by a small script with the painting code:
+//!
+//! ```ignore
+//! // render-worker.js
+//! import init, { start_render_worker } from "./solitaire.js";
+//! await init();
+//! start_render_worker((canvas, scene) => paint(canvas.getContext("2d"), JSON.parse(scene)));
+//! ```
+//!
+//! and driven from the page, once per frame:
+//!
+//! ```ignore
+//! const renderer = new RenderWorker("./render-worker.js", canvasElement);
+//! renderer.send_scene(game.scene());
+//! ```
+//!
+//! The worker scales its drawing by `pixel_ratio`, e.g. with
+//! `ctx.setTransform(ratio, 0, 0, ratio, 0, 0)`, onto a canvas resized to
+//! `SolitaireGame::backing_store_size()`, and draws the score and move
+//! counter text at that scale so it stays sharp. It paints the `background`
+//! first: the colour, then a `gradient` or the `texture` tiles over it, e.g.
+//! with `ctx.createRadialGradient` or `ctx.createPattern`.
+//!
+//! Hidden cards travel without their identity, as in `BoardView`, so the
+//! worker cannot give away a face-down card either.
+
+use serde::{Deserialize, Serialize};
+
+use crate::animation::{self, Flip, FlipFrame, Slide};
+use crate::clock;
+use crate::dirty::{self, DirtyRegions, Rect};
+use crate::ecs::{Entity, World};
+use crate::emote::{self, EmoteView};
+use crate::game::{Card, FaceUp};
+use crate::layout::{self, DragPointer, Position, TableLayout};
+use crate::particles::{self, Particle};
+use crate::resolution::Resolution;
+use crate::rules::{self, History, Paused};
+use crate::scoring::Score;
+use crate::theme::{self, TableBackground};
+use crate::tooltip::{self, Tooltip};
+use crate::view::{self, CardView, Thoughtful};
+
+/// One card as it should be painted.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SceneCard {
+    pub entity: Entity,
+    #[serde(flatten)]
+    pub position: Position,
+    #[serde(flatten)]
+    pub view: CardView,
+    /// How to draw the card while it is being turned over.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flip: Option<FlipFrame>,
+}
+
+/// Opacity of the ghost drawn under the pointer during a drag.
+const GHOST_OPACITY: f32 = 0.6;
+
+/// A drag in progress, as it should be painted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DragScene {
+    /// Copies of the dragged cards under the pointer, to draw above
+    /// everything else at `opacity`.
+    pub ghost: Vec<SceneCard>,
+    pub opacity: f32,
+    /// The piles that would take the cards, to highlight.
+    pub targets: Vec<DropTarget>,
+}
+
+/// A pile the dragged cards can be dropped on, and where a highlight goes:
+/// over its top card, or its outline when it is empty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DropTarget {
+    pub pile: String,
+    #[serde(flatten)]
+    pub rect: Rect,
+}
+
+/// Everything needed to paint one frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub card_width: f32,
+    pub card_height: f32,
+    /// Backing-store pixels per CSS pixel to draw at.
+    pub pixel_ratio: f32,
+    /// The table beneath the piles, painted first.
+    pub background: TableBackground,
+    /// Cards in drawing order.
+    pub cards: Vec<SceneCard>,
+    pub dirty: DirtyRegions,
+    pub score: i32,
+    pub moves: u32,
+    /// Cover the board, as during a pause that hides the cards.
+    pub hide_cards: bool,
+    /// The drag in progress, once the pointer has moved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drag: Option<DragScene>,
+    /// A tooltip for the card under the pointer, when tooltips are on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tooltip: Option<Tooltip>,
+    /// Emotes other players sent, to draw over the table.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emotes: Vec<EmoteView>,
+    /// Particles to draw over everything else, such as the win celebration.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub particles: Vec<Particle>,
+}
+
+impl Scene {
+    /// Lay out the table and capture it, along with what changed since the
+    /// last capture. Cards being turned over are always dirty, and so is the
+    /// whole canvas while cards slide or particles and emotes fly about. Sliding cards
+    /// are captured where they are drawn, on their way to their places.
+    pub fn capture(world: &mut World) -> Self {
+        let now = clock::now_ms();
+        animation::animation_system(world, now);
+        particles::particle_system(world, now);
+        let particles = particles::visible(world, now);
+        emote::emote_system(world, now);
+        let emotes = emote::visible(world, now);
+        let layout = TableLayout::compute(world);
+        let thoughtful = world.resource::<Thoughtful>().is_some();
+        let resolution = world.resource::<Resolution>().copied().unwrap_or_default();
+        let mut sliding = false;
+        let mut cards: Vec<SceneCard> = layout
+            .cards
+            .into_iter()
+            .map(|placed| {
+                let card = world.get_component::<Card>(placed.entity).copied();
+                let face_up = world
+                    .get_component::<FaceUp>(placed.entity)
+                    .is_some_and(|f| f.0);
+                let mut position = placed.position;
+                if let Some((dx, dy)) = world
+                    .get_component::<Slide>(placed.entity)
+                    .and_then(|slide| slide.offset(now))
+                {
+                    position.x += dx;
+                    position.y += dy;
+                    sliding = true;
+                }
+                position.x = resolution.snap(position.x);
+                position.y = resolution.snap(position.y);
+                SceneCard {
+                    entity: placed.entity,
+                    position,
+                    view: view::view_of(card, face_up, thoughtful),
+                    flip: world
+                        .get_component::<Flip>(placed.entity)
+                        .and_then(|flip| flip.frame(now)),
+                }
+            })
+            .collect();
+        cards.sort_by_key(|c| c.position.z);
+        let mut dirty = dirty::take(world);
+        dirty
+            .rects
+            .extend(cards.iter().filter(|c| c.flip.is_some()).map(|c| Rect {
+                x: c.position.x,
+                y: c.position.y,
+                width: layout.card_width,
+                height: layout.card_height,
+            }));
+        let drag = drag_scene(world, &cards, (layout.card_width, layout.card_height));
+        let (tooltip, tooltip_changed) = tooltip::frame(world, now);
+        dirty.full |= sliding
+            || tooltip_changed
+            || drag.is_some()
+            || !particles.is_empty()
+            || !emotes.is_empty();
+        Scene {
+            card_width: layout.card_width,
+            card_height: layout.card_height,
+            pixel_ratio: resolution.pixel_ratio(),
+            background: theme::current(world).table_background(),
+            cards,
+            dirty,
+            score: world.resource::<Score>().map_or(0, Score::points),
+            moves: world.resource::<History>().map_or(0, History::moves),
+            hide_cards: world.resource::<Paused>().is_some_and(|p| p.hide_cards),
+            drag,
+            tooltip,
+            emotes,
+            particles,
+        }
+    }
+}
+
+/// The ghost and drop targets of the drag in progress, if the pointer has
+/// taken hold of the dragged cards.
+fn drag_scene(world: &World, cards: &[SceneCard], card_size: (f32, f32)) -> Option<DragScene> {
+    let pointer = world.resource::<DragPointer>()?;
+    let dragged = layout::dragged(world);
+    let mut ghost: Vec<SceneCard> = cards
+        .iter()
+        .filter(|c| dragged.contains(&c.entity))
+        .copied()
+        .collect();
+    let (dx, dy) = pointer.offset(ghost.first()?.position);
+    for card in &mut ghost {
+        card.position.x += dx;
+        card.position.y += dy;
+        card.flip = None;
+    }
+    let (width, height) = card_size;
+    let metrics = layout::metrics(world);
+    let targets = layout::drop_targets(world)
+        .into_iter()
+        .map(|pile| {
+            let (x, y) = rules::top_card(world, pile)
+                .and_then(|top| world.get_component::<Position>(top))
+                .map_or_else(|| metrics.pile_origin(pile), |p| (p.x, p.y));
+            DropTarget {
+                pile: pile.to_string(),
+                rect: Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                },
+            }
+        })
+        .collect();
+    Some(DragScene {
+        ghost,
+        opacity: GHOST_OPACITY,
+        targets,
+    })
+}
+
+/// A message from the page to the render worker, besides the initial
+/// canvas hand-over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RenderMessage {
+    Scene(Box<Scene>),
+    /// The canvas changed size, in device pixels.
+    Resize {
+        width: u32,
+        height: u32,
+    },
+}
+
+#[cfg(feature = "wasm")]
+mod web {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use js_sys::{Array, Function, Object, Reflect};
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::prelude::*;
+    use web_sys::{
+        DedicatedWorkerGlobalScope, HtmlCanvasElement, MessageEvent, OffscreenCanvas, Worker,
+        WorkerOptions, WorkerType,
+    };
+
+    use super::*;
+
+    /// Worker side: keep the canvas handed over by the page and call
+    /// `paint(canvas, scene_json)` for every scene it streams.
+    ///
+    /// Call once from the worker script after the module has loaded.
+    #[wasm_bindgen]
+    pub fn start_render_worker(paint: Function) -> Result<(), JsValue> {
+        let scope: DedicatedWorkerGlobalScope = js_sys::global().dyn_into()?;
+        let canvas: Rc<RefCell<Option<OffscreenCanvas>>> = Rc::default();
+        let onmessage = Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+            let data = e.data();
+            let Some(text) = data.as_string() else {
+                if let Ok(handed_over) = Reflect::get(&data, &JsValue::from_str("canvas")) {
+                    *canvas.borrow_mut() = handed_over.dyn_into().ok();
+                }
+                return;
+            };
+            let Some(target) = canvas.borrow().clone() else {
+                return;
+            };
+            match serde_json::from_str(&text) {
+                Ok(RenderMessage::Scene(_)) => {
+                    let _ = paint.call2(&JsValue::NULL, &target, &JsValue::from_str(&text));
+                }
+                Ok(RenderMessage::Resize { width, height }) => {
+                    target.set_width(width);
+                    target.set_height(height);
+                }
+                Err(_) => {}
+            }
+        });
+        scope.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        onmessage.forget();
+        Ok(())
+    }
+
+    /// Main-thread handle to a render worker.
+    #[wasm_bindgen]
+    pub struct RenderWorker {
+        worker: Worker,
+    }
+
+    #[wasm_bindgen]
+    impl RenderWorker {
+        /// Start a worker from `script_url`, a module script that calls
+        /// `start_render_worker`, and hand `canvas` over to it. The page
+        /// cannot draw on the canvas afterwards.
+        #[wasm_bindgen(constructor)]
+        pub fn new(script_url: &str, canvas: &HtmlCanvasElement) -> Result<RenderWorker, JsValue> {
+            let options = WorkerOptions::new();
+            options.set_type(WorkerType::Module);
+            let worker = Worker::new_with_options(script_url, &options)?;
+
+            let offscreen = canvas.transfer_control_to_offscreen()?;
+            let message = Object::new();
+            Reflect::set(&message, &JsValue::from_str("canvas"), &offscreen)?;
+            worker.post_message_with_transfer(&message, &Array::of1(&offscreen))?;
+            Ok(RenderWorker { worker })
+        }
+
+        /// Stream a scene, as returned by `SolitaireGame::scene`.
+        pub fn send_scene(&self, scene_json: &str) -> Result<(), JsValue> {
+            let scene: Scene =
+                serde_json::from_str(scene_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+            self.post(&RenderMessage::Scene(Box::new(scene)))
+        }
+
+        /// Tell the worker the canvas is now `width` by `height` device
+        /// pixels.
+        pub fn resize(&self, width: u32, height: u32) -> Result<(), JsValue> {
+            self.post(&RenderMessage::Resize { width, height })
+        }
+
+        /// Stop the worker.
+        pub fn terminate(&self) {
+            self.worker.terminate();
+        }
+    }
+
+    impl RenderWorker {
+        fn post(&self, message: &RenderMessage) -> Result<(), JsValue> {
+            let json = serde_json::to_string(message).expect("render messages always serialize");
+            self.worker.post_message(&JsValue::from_str(&json))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Deck;
+    use crate::view::Visibility;
+
+    #[test]
+    fn scenes_hide_face_down_cards_and_stream_as_json() {
+        let mut world = World::new();
+        rules::deal(&mut world, &Deck::standard().cards);
+        let scene = Scene::capture(&mut world);
+        assert_eq!(scene.cards.len(), 52);
+        assert!(scene.dirty.full);
+        assert!(
+            scene
+                .cards
+                .windows(2)
+                .all(|w| w[0].position.z <= w[1].position.z)
+        );
+        let hidden = scene
+            .cards
+            .iter()
+            .filter(|c| c.view.visibility == Visibility::Hidden);
+        assert!(hidden.clone().count() > 0);
+        assert!(hidden.clone().all(|c| c.view.card.is_none()));
+
+        let message = RenderMessage::Scene(Box::new(scene));
+        let json = serde_json::to_string(&message).unwrap();
+        assert!(json.starts_with(r#"{"kind":"scene","#));
+        assert_eq!(
+            serde_json::from_str::<RenderMessage>(&json).unwrap(),
+            message
+        );
+    }
+}
@@ -0,0 +1,311 @@
+// Replay files: a finished or unfinished game in a file of its own.
+//
+// A replay starts with a header saying what made it and how to deal the game
+// again: the format version, the version of the crate that recorded it, the
+// variant, the seed and the rest of the options. The actions the player made
+// follow, each with the time it was made, as the `AuditLog` recorded them.
+//
+// Files are read through `migrate`, which brings a file of any earlier
+// format up to `REPLAY_VERSION` before it is decoded, so a replay recorded by
+// an old client still plays on a new one. Version 0 is the bare audit log
+// exported by `SolitaireGame::audit_log` before there were replay files; it
+// has no header, and is dealt with default options and the seed of its
+// entries. Files from a newer format than this crate knows are refused.
+//
+// Playing a replay back deals the game again and steps through the actions
+// with `ReplayPlayback`, a world resource, so dealing another game ends the
+// playback.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::audit::{AuditAction, AuditEntry, AuditLog};
+use crate::compress;
+use crate::ecs::World;
+use crate::error::GameError;
+use crate::handshake::CRATE_VERSION;
+use crate::options::GameOptions;
+use crate::variant::Variant;
+
+/// Names the format in every header.
+pub const REPLAY_FORMAT: &str = "solitaire-replay";
+
+/// Version of the format `ReplayFile::to_json` writes.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// MIME type of exported replay files.
+pub const REPLAY_MIME: &str = "application/json";
+
+/// What a replay file says about itself and its deal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub format: String,
+    pub version: u32,
+    /// Version of the crate that recorded it, for bug reports.
+    pub crate_version: String,
+    pub variant: Variant,
+    pub seed: u64,
+    /// The options of the deal; their `seed` is `seed`.
+    pub options: GameOptions,
+    /// Milliseconds since the Unix epoch when the file was made.
+    pub recorded_ms: f64,
+}
+
+/// One action of the game and when it was made.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReplayStep {
+    pub action: AuditAction,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_ms: f64,
+}
+
+/// A game as a portable replay file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFile {
+    pub header: ReplayHeader,
+    pub steps: Vec<ReplayStep>,
+}
+
+impl ReplayFile {
+    /// The replay of the game of `world`, dealt with `options`, at `now_ms`.
+    /// `None` when the deal has no audit log to take the actions from.
+    pub fn record(world: &World, options: GameOptions, now_ms: f64) -> Option<Self> {
+        let log = world.resource::<AuditLog>()?;
+        Some(ReplayFile {
+            header: ReplayHeader {
+                format: REPLAY_FORMAT.to_string(),
+                version: REPLAY_VERSION,
+                crate_version: CRATE_VERSION.to_string(),
+                variant: options.variant,
+                seed: log.seed(),
+                options: GameOptions {
+                    seed: Some(log.seed()),
+                    ..options
+                },
+                recorded_ms: now_ms,
+            },
+            steps: log
+                .entries()
+                .iter()
+                .map(|e| ReplayStep {
+                    action: e.action,
+                    timestamp_ms: e.timestamp_ms,
+                })
+                .collect(),
+        })
+    }
+
+    /// How long the game went on, from the first action to the last.
+    pub fn duration_ms(&self) -> f64 {
+        match (self.steps.first(), self.steps.last()) {
+            (Some(first), Some(last)) => last.timestamp_ms - first.timestamp_ms,
+            _ => 0.0,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("replays always serialize")
+    }
+
+    /// Read a replay file of any format up to `REPLAY_VERSION`, plain or
+    /// packed by `compress::pack`.
+    pub fn from_json(text: &str) -> Result<Self, GameError> {
+        let json = compress::unpack(text)?;
+        let value: Value = serde_json::from_str(&json).map_err(invalid)?;
+        serde_json::from_value(migrate(value)?).map_err(invalid)
+    }
+}
+
+/// Bring a replay file of any known format up to `REPLAY_VERSION`.
+pub fn migrate(mut value: Value) -> Result<Value, GameError> {
+    loop {
+        let version = version_of(&value)?;
+        value = match version {
+            0 => from_audit_log(value)?,
+            REPLAY_VERSION => return Ok(value),
+            newer => {
+                return Err(GameError::InvalidOptions(format!(
+                    "replay recorded by a newer version (format {newer})"
+                )));
+            }
+        };
+    }
+}
+
+fn version_of(value: &Value) -> Result<u32, GameError> {
+    if value.is_array() {
+        return Ok(0);
+    }
+    let header = value
+        .get("header")
+        .ok_or_else(|| GameError::InvalidOptions("not a replay file".to_string()))?;
+    if header.get("format").and_then(Value::as_str) != Some(REPLAY_FORMAT) {
+        return Err(GameError::InvalidOptions("not a replay file".to_string()));
+    }
+    header
+        .get("version")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .ok_or_else(|| GameError::InvalidOptions("replay without a version".to_string()))
+}
+
+/// Version 0 to 1: wrap a bare audit log in a header.
+fn from_audit_log(value: Value) -> Result<Value, GameError> {
+    let entries: Vec<AuditEntry> = serde_json::from_value(value).map_err(invalid)?;
+    let seed = entries
+        .first()
+        .map(|e| e.seed)
+        .ok_or_else(|| GameError::InvalidOptions("the audit log is empty".to_string()))?;
+    let options = GameOptions {
+        seed: Some(seed),
+        ..GameOptions::default()
+    };
+    let replay = ReplayFile {
+        header: ReplayHeader {
+            format: REPLAY_FORMAT.to_string(),
+            version: 1,
+            crate_version: CRATE_VERSION.to_string(),
+            variant: options.variant,
+            seed,
+            options,
+            recorded_ms: entries.last().map_or(0.0, |e| e.timestamp_ms),
+        },
+        steps: entries
+            .iter()
+            .map(|e| ReplayStep {
+                action: e.action,
+                timestamp_ms: e.timestamp_ms,
+            })
+            .collect(),
+    };
+    serde_json::to_value(replay).map_err(invalid)
+}
+
+fn invalid(e: serde_json::Error) -> GameError {
+    GameError::InvalidOptions(e.to_string())
+}
+
+/// Resource present while a replay is played back on the world.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayPlayback {
+    steps: Vec<ReplayStep>,
+    next: usize,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: &ReplayFile) -> Self {
+        ReplayPlayback {
+            steps: replay.steps.clone(),
+            next: 0,
+        }
+    }
+
+    /// Take the action to play next, if any is left.
+    pub fn next_action(&mut self) -> Option<AuditAction> {
+        let step = self.steps.get(self.next)?;
+        self.next += 1;
+        Some(step.action)
+    }
+
+    /// How many actions have been played, and how many there are.
+    pub fn position(&self) -> (usize, usize) {
+        (self.next, self.steps.len())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.steps.len()
+    }
+}
+
+#[cfg(feature = "wasm")]
+pub use web::to_blob;
+
+#[cfg(feature = "wasm")]
+mod web {
+    use wasm_bindgen::prelude::*;
+    use web_sys::{Blob, BlobPropertyBag};
+
+    use super::{REPLAY_MIME, ReplayFile};
+
+    /// The replay as a `Blob`, ready for `URL.createObjectURL` and a
+    /// download link.
+    pub fn to_blob(replay: &ReplayFile) -> Result<Blob, JsValue> {
+        let parts = js_sys::Array::of1(&JsValue::from_str(&replay.to_json()));
+        let options = BlobPropertyBag::new();
+        options.set_type(REPLAY_MIME);
+        Blob::new_with_str_sequence_and_options(&parts, &options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::state_digest;
+    use crate::rules::{self, Move};
+
+    fn played(seed: u64) -> World {
+        let mut world = World::new();
+        rules::deal_from_seed(&mut world, seed);
+        world.insert_resource(AuditLog::new(seed, None));
+        for (i, action) in [
+            AuditAction::Move(Move::Draw),
+            AuditAction::Move(Move::Draw),
+            AuditAction::Undo,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            action.play(&mut world).unwrap();
+            world
+                .resource_mut::<AuditLog>()
+                .unwrap()
+                .record(action, 1000.0 * i as f64);
+        }
+        world
+    }
+
+    #[test]
+    fn replays_and_old_audit_logs_play_back_to_the_same_position() {
+        let world = played(9);
+        let replay = ReplayFile::record(&world, GameOptions::default(), 5000.0).unwrap();
+        assert_eq!(replay.header.seed, 9);
+        assert_eq!(replay.duration_ms(), 2000.0);
+        assert_eq!(ReplayFile::from_json(&replay.to_json()).unwrap(), replay);
+        let packed = compress::pack(&replay.to_json());
+        assert_eq!(ReplayFile::from_json(&packed).unwrap(), replay);
+
+        let log = world.resource::<AuditLog>().unwrap().to_json();
+        let migrated = ReplayFile::from_json(&log).unwrap();
+        assert_eq!(migrated.header.version, REPLAY_VERSION);
+        assert_eq!(migrated.steps, replay.steps);
+
+        let mut restored = World::new();
+        rules::deal_from_seed(&mut restored, migrated.header.seed);
+        let mut playback = ReplayPlayback::new(&migrated);
+        while let Some(action) = playback.next_action() {
+            action.play(&mut restored).unwrap();
+        }
+        assert!(playback.is_finished());
+        assert_eq!(state_digest(&restored), state_digest(&world));
+
+        let newer = replay.to_json().replace(r#""version":1"#, r#""version":7"#);
+        assert!(ReplayFile::from_json(&newer).is_err());
+        assert!(ReplayFile::from_json(r#"{"header":{"format":"other"}}"#).is_err());
+    }
+
+    #[test]
+    fn watching_a_replay_leaves_the_players_options_alone() {
+        use crate::SolitaireGame;
+
+        let mut recorder =
+            SolitaireGame::new_game(r#"{"variant":"double_klondike","seed":3}"#).unwrap();
+        recorder.setup_board();
+        let replay = recorder.export_replay().unwrap();
+
+        let mut game = SolitaireGame::new();
+        game.load_replay(&replay).unwrap();
+        assert_eq!(game.variant(), "double_klondike");
+        game.setup_board();
+        assert_eq!(game.variant(), "klondike");
+    }
+}
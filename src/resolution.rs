@@ -0,0 +1,109 @@
+// Rendering resolution on high-DPI screens.
+//
+// Everything else measures the canvas in CSS pixels. A canvas whose backing
+// store has only that many pixels looks blurry on a screen with a
+// `devicePixelRatio` of 2 or 3, so renderers make the backing store
+// `pixel_ratio` times larger and scale their drawing by the same factor; the
+// scene carries the ratio along. Card positions in the scene are snapped to
+// whole device pixels, keeping card edges and the score and timer text
+// crisp.
+//
+// The sharpness costs fill rate. Low-end devices can opt into 1x rendering,
+// which draws at CSS resolution whatever the screen.
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::CanvasSize;
+
+/// Ratios above this gain little sharpness for a lot of pixels.
+const MAX_PIXEL_RATIO: f32 = 3.0;
+
+/// Resource with the screen's pixel density and the player's choice.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Resolution {
+    /// Device pixels per CSS pixel, as reported by the browser.
+    pub device_pixel_ratio: f32,
+    /// Render at 1x regardless of the screen, for performance.
+    pub low_resolution: bool,
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Resolution {
+            device_pixel_ratio: 1.0,
+            low_resolution: false,
+        }
+    }
+}
+
+impl Resolution {
+    /// The screen's resolution, as far as it can be told.
+    pub fn detect() -> Self {
+        Resolution {
+            device_pixel_ratio: device_pixel_ratio(),
+            ..Resolution::default()
+        }
+    }
+
+    /// Backing-store pixels per CSS pixel to render at.
+    pub fn pixel_ratio(&self) -> f32 {
+        if self.low_resolution || !self.device_pixel_ratio.is_finite() {
+            return 1.0;
+        }
+        self.device_pixel_ratio.clamp(1.0, MAX_PIXEL_RATIO)
+    }
+
+    /// The size of the backing store for `canvas`, in device pixels.
+    pub fn backing_store(&self, canvas: CanvasSize) -> (u32, u32) {
+        let ratio = self.pixel_ratio();
+        (
+            (canvas.width * ratio).round() as u32,
+            (canvas.height * ratio).round() as u32,
+        )
+    }
+
+    /// Round a length in CSS pixels to a whole number of device pixels.
+    pub fn snap(&self, css_pixels: f32) -> f32 {
+        let ratio = self.pixel_ratio();
+        (css_pixels * ratio).round() / ratio
+    }
+}
+
+/// `window.devicePixelRatio`, or 1 where there is no window.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn device_pixel_ratio() -> f32 {
+    web_sys::window().map_or(1.0, |w| w.device_pixel_ratio() as f32)
+}
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+fn device_pixel_ratio() -> f32 {
+    1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retina_screens_get_a_larger_backing_store_unless_opted_out() {
+        let canvas = CanvasSize {
+            width: 400.5,
+            height: 300.0,
+        };
+        let mut resolution = Resolution {
+            device_pixel_ratio: 2.0,
+            ..Resolution::default()
+        };
+        assert_eq!(resolution.backing_store(canvas), (801, 600));
+        assert_eq!(resolution.snap(10.3), 10.5);
+
+        resolution.low_resolution = true;
+        assert_eq!(resolution.backing_store(canvas), (401, 300));
+        assert_eq!(resolution.snap(10.3), 10.0);
+
+        resolution.device_pixel_ratio = 8.0;
+        resolution.low_resolution = false;
+        assert_eq!(resolution.pixel_ratio(), MAX_PIXEL_RATIO);
+    }
+}
@@ -0,0 +1,94 @@
+// Deterministic random number generation for the game.
+//
+// Shuffling straight from `thread_rng()` makes every deal unrepeatable, which
+// is a problem for tests, for sharing a deal with a friend and for multiplayer
+// matches where every player must receive exactly the same shuffle. Instead
+// the game keeps a `GameRng` as a resource in the ECS `World`. It is always
+// created from a known seed, so the same seed produces the same sequence of
+// shuffles on every platform.
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+/// Seedable random number generator stored as a `World` resource.
+///
+/// `GameRng` implements `RngCore`, so it can be handed to any `rand` API such
+/// as `SliceRandom::shuffle`.
+#[derive(Debug, Clone)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    /// Create a generator that will always produce the same values for the
+    /// same `seed`.
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Create a generator from a freshly picked random seed.
+    ///
+    /// The seed is still recorded, so even "random" games can be replayed
+    /// later by passing `seed()` back into `from_seed`.
+    pub fn from_entropy() -> Self {
+        Self::from_seed(rand::thread_rng().r#gen())
+    }
+
+    /// The seed this generator was created from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restart the sequence from a new seed.
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::from_seed(seed);
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Deck;
+
+    #[test]
+    fn same_seed_same_shuffle() {
+        let mut a = Deck::standard();
+        let mut b = Deck::standard();
+        a.shuffle(&mut GameRng::from_seed(42));
+        b.shuffle(&mut GameRng::from_seed(42));
+        assert_eq!(a.cards, b.cards);
+
+        let mut c = Deck::standard();
+        c.shuffle(&mut GameRng::from_seed(43));
+        assert_ne!(a.cards, c.cards);
+    }
+
+    #[test]
+    fn entropy_seed_is_replayable() {
+        let mut first = GameRng::from_entropy();
+        let mut again = GameRng::from_seed(first.seed());
+        assert_eq!(first.next_u64(), again.next_u64());
+    }
+}
@@ -0,0 +1,274 @@
+// Rollback netcode for co-op games.
+//
+// In co-op, players share one deal and all play on it. Every move is an
+// `Input` stamped with the tick it takes effect on. A player's own moves are
+// scheduled `INPUT_DELAY_TICKS` ahead, which is usually long enough for them
+// to reach the other players before that tick comes. Every side plays the
+// inputs of a tick in the same order, by player name, so all boards pass
+// through the same positions.
+//
+// An input that arrives after its tick has been played is not lost. The
+// board is rolled back to the start of the deal, which its seed recreates
+// exactly, and re-simulated with every input up to the current tick, so it
+// ends up where the other boards are. Inputs the rules turn down, such as two
+// players moving the same card on one tick, are skipped on every side alike.
+//
+// When the host of the room changes, the new host's `Timeline` is the one
+// everybody agrees on; `adopt` takes it over.
+//
+// Ticks are `TICK_MS` long and counted from when co-op started on this side.
+// Co-op lasts for one deal: the `Coop` resource goes with the board.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::events::GameEvent;
+use crate::rules::{self, Move};
+use crate::scoring::Combo;
+
+/// Length of one tick, in milliseconds.
+pub const TICK_MS: f64 = 50.0;
+/// How many ticks after it is made a player's own move is played.
+pub const INPUT_DELAY_TICKS: u64 = 3;
+
+/// A move by one player, played on `tick`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Input {
+    pub tick: u64,
+    pub player: String,
+    #[serde(rename = "move")]
+    pub mv: Move,
+}
+
+/// Everything that decides a co-op board: the deal and the inputs played on
+/// it up to `tick`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timeline {
+    pub seed: u64,
+    pub tick: u64,
+    pub inputs: Vec<Input>,
+}
+
+/// Resource with the co-op game on this board.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coop {
+    /// The name this side plays under.
+    pub player: String,
+    seed: u64,
+    started_ms: f64,
+    /// The last tick played.
+    tick: u64,
+    /// Every input so far, in the order they are played.
+    inputs: Vec<Input>,
+}
+
+impl Coop {
+    /// The last tick played.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    fn insert(&mut self, input: Input) {
+        // Inputs of one player on one tick keep the order they were made in.
+        let at = self
+            .inputs
+            .partition_point(|i| (i.tick, &i.player) <= (input.tick, &input.player));
+        self.inputs.insert(at, input);
+    }
+}
+
+/// Start co-op as `player` on the deal of `seed`, which must just have been
+/// dealt.
+pub fn start(world: &mut World, player: String, seed: u64, now_ms: f64) {
+    world.insert_resource(Coop {
+        player,
+        seed,
+        started_ms: now_ms,
+        tick: 0,
+        inputs: Vec::new(),
+    });
+}
+
+pub fn is_active(world: &World) -> bool {
+    world.resource::<Coop>().is_some()
+}
+
+/// Schedule a move of this side's player. Returns the input to send to the
+/// others, or `None` outside co-op.
+pub fn schedule(world: &mut World, mv: Move) -> Option<Input> {
+    let coop = world.resource_mut::<Coop>()?;
+    let input = Input {
+        tick: coop.tick + INPUT_DELAY_TICKS,
+        player: coop.player.clone(),
+        mv,
+    };
+    coop.insert(input.clone());
+    Some(input)
+}
+
+/// File an input from another player. Returns whether the board had to be
+/// rolled back for it.
+pub fn receive(world: &mut World, input: Input) -> bool {
+    let Some(mut coop) = world.remove_resource::<Coop>() else {
+        return false;
+    };
+    let late = input.tick <= coop.tick;
+    coop.insert(input);
+    if late {
+        resimulate(world, &coop);
+    }
+    world.insert_resource(coop);
+    late
+}
+
+/// Play every tick up to `now_ms`, returning the events of the inputs played.
+pub fn step(world: &mut World, now_ms: f64) -> Vec<GameEvent> {
+    let Some(mut coop) = world.remove_resource::<Coop>() else {
+        return Vec::new();
+    };
+    let target = ((now_ms - coop.started_ms) / TICK_MS).max(0.0) as u64;
+    let mut events = Vec::new();
+    if target > coop.tick {
+        for input in coop
+            .inputs
+            .iter()
+            .filter(|i| i.tick > coop.tick && i.tick <= target)
+        {
+            if let Ok(played) = rules::apply(world, input.mv) {
+                events.extend(played);
+            }
+        }
+        coop.tick = target;
+    }
+    world.insert_resource(coop);
+    events
+}
+
+/// The co-op game on this board, for the others to adopt.
+pub fn timeline(world: &World) -> Option<Timeline> {
+    let coop = world.resource::<Coop>()?;
+    Some(Timeline {
+        seed: coop.seed,
+        tick: coop.tick,
+        inputs: coop.inputs.clone(),
+    })
+}
+
+/// Take over the host's `timeline`: its inputs up to its tick replace ours,
+/// and ours after it are kept. Returns whether the board was re-simulated;
+/// a timeline of another deal is ignored.
+pub fn adopt(world: &mut World, timeline: Timeline) -> bool {
+    let Some(mut coop) = world.remove_resource::<Coop>() else {
+        return false;
+    };
+    let same_deal = coop.seed == timeline.seed;
+    if same_deal {
+        let later: Vec<Input> = coop
+            .inputs
+            .drain(..)
+            .filter(|i| i.tick > timeline.tick && !timeline.inputs.contains(i))
+            .collect();
+        coop.inputs = timeline.inputs;
+        for input in later {
+            coop.insert(input);
+        }
+        resimulate(world, &coop);
+    }
+    world.insert_resource(coop);
+    same_deal
+}
+
+/// Deal the board again and play every input up to the current tick.
+fn resimulate(world: &mut World, coop: &Coop) {
+    world.remove_resource::<Combo>();
+    rules::deal_from_seed(world, coop.seed);
+    for input in coop.inputs.iter().take_while(|i| i.tick <= coop.tick) {
+        let _ = rules::apply(world, input.mv);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::state_digest;
+    use crate::game::Pile;
+
+    const SEED: u64 = 11;
+
+    fn board(player: &str) -> World {
+        let mut world = World::new();
+        rules::deal_from_seed(&mut world, SEED);
+        start(&mut world, player.into(), SEED, 0.0);
+        world
+    }
+
+    #[test]
+    fn late_inputs_roll_the_board_back_into_agreement() {
+        let mut ann = board("ann");
+        let mut bo = board("bo");
+
+        // Ann draws, and Ann's board plays it three ticks later.
+        let draw = schedule(&mut ann, Move::Draw).unwrap();
+        assert_eq!(draw.tick, INPUT_DELAY_TICKS);
+        assert!(step(&mut ann, 2.0 * TICK_MS).is_empty());
+        assert!(!step(&mut ann, 5.0 * TICK_MS).is_empty());
+
+        // Bo's board got there first and draws for Bo meanwhile.
+        step(&mut bo, 5.0 * TICK_MS);
+        let own = schedule(&mut bo, Move::Draw).unwrap();
+        assert_eq!(own.tick, 5 + INPUT_DELAY_TICKS);
+
+        // Ann's draw reaches Bo late and is played in the past; Bo's reaches
+        // Ann in time.
+        assert!(receive(&mut bo, draw));
+        assert!(!receive(&mut ann, own));
+        assert_eq!(state_digest(&ann), state_digest(&bo));
+
+        step(&mut ann, 10.0 * TICK_MS);
+        step(&mut bo, 10.0 * TICK_MS);
+        assert_eq!(state_digest(&ann), state_digest(&bo));
+        assert_eq!(rules::pile_cards(&bo, Pile::Waste).len(), 2);
+        assert_eq!(bo.resource::<Coop>().unwrap().inputs().len(), 2);
+    }
+
+    #[test]
+    fn co_op_moves_are_checked_and_wait_for_their_tick() {
+        let mut game = crate::SolitaireGame::with_seed(7);
+        game.setup_board();
+        game.start_coop("ann");
+        let stock = rules::pile_cards(game.world(), crate::game::Pile::Stock).len();
+        let scheduled = |game: &crate::SolitaireGame| {
+            game.world()
+                .resource::<Coop>()
+                .map_or(0, |coop| coop.inputs().len())
+        };
+
+        assert!(game.play_move(Move::Recycle).is_err());
+        assert_eq!(scheduled(&game), 0);
+        game.play_move(Move::Draw).unwrap();
+        game.play_follow_up(Move::Draw).unwrap();
+        assert_eq!(scheduled(&game), 2);
+        assert_eq!(
+            rules::pile_cards(game.world(), crate::game::Pile::Stock).len(),
+            stock
+        );
+        assert!(game.undo().is_err());
+    }
+
+    #[cfg(feature = "solver")]
+    #[test]
+    fn unanimated_autoplay_waits_for_each_move_to_land() {
+        let (seed, _) = crate::solver::find_winnable_deal(1, 20, 20_000).unwrap();
+        let mut game = crate::SolitaireGame::with_seed(seed);
+        game.setup_board();
+        game.start_coop("ann");
+        game.set_autoplay_settings(10.0, false);
+        game.start_solution_playback().unwrap();
+        assert_eq!(game.advance(16.0), 1);
+        assert!(game.is_autoplaying());
+    }
+}
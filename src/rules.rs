@@ -0,0 +1,1213 @@
+// The rules of Klondike solitaire, written as plain functions over the ECS
+// `World`.
+//
+// Every card is an entity carrying `Card`, `Pile`, `PileIndex` and `FaceUp`
+// components. A move simply rewrites those components. Before changing a
+// card we remember its previous state, which gives us undo for free: undoing
+// a move puts the remembered components back.
+//
+// The undo stack can be taken out of the world as a `SavedHistory` and put
+// back later, so a saved game keeps what each move may take back.
+//
+// Moves the player did not make themselves, such as the cards auto-complete
+// plays after they asked for it, can be applied as *follow-ups*: they join
+// the undo step of the move before them, so one undo takes back the whole
+// action.
+//
+// Functions here never talk to JavaScript. They return `GameEvent`s that the
+// caller can forward to whoever is interested.
+
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::arena::{Span, StackArena};
+use crate::clock::{self, GameClock};
+use crate::ecs::{Entity, World};
+use crate::error::GameError;
+use crate::events::GameEvent;
+use crate::game::{Card, FaceUp, Pile, PileAnchor, PileIndex, Rank};
+use crate::lifecycle::Abandoned;
+use crate::rng::GameRng;
+use crate::scoring::{Combo, ComboRules, Score, ScoreReason, ScoringMode, TimeBonus};
+use crate::solver::Position;
+use crate::timeattack::{self, TimeUp};
+use crate::variant;
+
+/// A single player action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Move {
+    /// Turn the top card of the stock onto the waste, or the top three in
+    /// draw-three games.
+    Draw,
+    /// Turn the whole waste back over to form a new stock.
+    Recycle,
+    /// Move the top `count` cards of `from` onto `to`.
+    Transfer { from: Pile, to: Pile, count: usize },
+}
+
+/// The state of one card before a move touched it.
+#[derive(Debug, Clone, Copy)]
+struct CardState {
+    entity: Entity,
+    pile: Pile,
+    index: PileIndex,
+    face_up: FaceUp,
+}
+
+/// Everything needed to take back one move.
+#[derive(Debug)]
+struct Record {
+    /// The previous state of every card the move changed, in `History::cards`.
+    cards: Span,
+    /// Points the move added to the score.
+    points: i32,
+    /// Whether the move turned the waste back into the stock.
+    recycle: bool,
+    /// The combo streak before the move, when combos are scored.
+    combo: Option<Combo>,
+    /// Whether the move followed from the one before and is undone with it.
+    follows: bool,
+}
+
+/// Resource holding the undo stack and the number of moves made.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Record>,
+    // The cards of every record, so a move costs no allocation of its own.
+    cards: StackArena<CardState>,
+    moves: u32,
+    undos: u32,
+    recycles: u32,
+}
+
+impl History {
+    /// Number of moves made in this game. Undone moves still count.
+    pub fn moves(&self) -> u32 {
+        self.moves
+    }
+
+    /// Whether there is a move that can be taken back.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Number of moves taken back in this game.
+    pub fn undos(&self) -> u32 {
+        self.undos
+    }
+
+    /// Number of times the waste has been turned back into the stock.
+    /// Undone recycles do not count.
+    pub fn recycles(&self) -> u32 {
+        self.recycles
+    }
+}
+
+/// The undo stack and counters of a `History`, and the score they go with,
+/// as saved with a game. Cards are named by where they lie when it is taken,
+/// which is where they lie again once the saved game has been replayed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedHistory {
+    undo: Vec<SavedRecord>,
+    moves: u32,
+    undos: u32,
+    recycles: u32,
+    score: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedRecord {
+    cards: Vec<SavedCardState>,
+    points: i32,
+    recycle: bool,
+    combo: Option<Combo>,
+    follows: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct SavedCardState {
+    /// Where the card lies now.
+    at: (Pile, PileIndex),
+    pile: Pile,
+    index: PileIndex,
+    face_up: FaceUp,
+}
+
+impl SavedHistory {
+    /// Take the undo stack of `world`, or `None` before the first move.
+    pub fn capture(world: &World) -> Option<Self> {
+        let history = world.resource::<History>()?;
+        let undo = history
+            .undo
+            .iter()
+            .map(|record| {
+                let cards = history
+                    .cards
+                    .get(record.cards)
+                    .iter()
+                    .map(|state| {
+                        Some(SavedCardState {
+                            at: (
+                                *world.get_component::<Pile>(state.entity)?,
+                                *world.get_component::<PileIndex>(state.entity)?,
+                            ),
+                            pile: state.pile,
+                            index: state.index,
+                            face_up: state.face_up,
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                Some(SavedRecord {
+                    cards,
+                    points: record.points,
+                    recycle: record.recycle,
+                    combo: record.combo,
+                    follows: record.follows,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(SavedHistory {
+            undo,
+            moves: history.moves,
+            undos: history.undos,
+            recycles: history.recycles,
+            score: world.resource::<Score>().map_or(0, Score::points),
+        })
+    }
+
+    /// Put the undo stack and score back on `world`, which must hold the
+    /// position it was taken in.
+    pub fn restore(&self, world: &mut World) -> Result<(), GameError> {
+        let lying_at: HashMap<(Pile, PileIndex), Entity> = world
+            .iter::<Card>()
+            .filter_map(|(entity, _)| {
+                let pile = *world.get_component::<Pile>(entity)?;
+                let index = *world.get_component::<PileIndex>(entity)?;
+                Some(((pile, index), entity))
+            })
+            .collect();
+        let mut cards = StackArena::new();
+        let undo = self
+            .undo
+            .iter()
+            .map(|record| {
+                let start = cards.top();
+                for state in &record.cards {
+                    cards.push(CardState {
+                        entity: *lying_at.get(&state.at)?,
+                        pile: state.pile,
+                        index: state.index,
+                        face_up: state.face_up,
+                    });
+                }
+                Some(Record {
+                    cards: cards.span_from(start),
+                    points: record.points,
+                    recycle: record.recycle,
+                    combo: record.combo,
+                    follows: record.follows,
+                })
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| {
+                GameError::InvalidOptions("the saved undo history does not fit the board".into())
+            })?;
+        world.insert_resource(History {
+            undo,
+            cards,
+            moves: self.moves,
+            undos: self.undos,
+            recycles: self.recycles,
+        });
+        let score = score_mut(world);
+        score.add(self.score - score.points());
+        Ok(())
+    }
+}
+
+/// Resource deciding how often moves may be taken back, and at what cost.
+///
+/// Without this resource undo is unlimited and free.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoPolicy {
+    /// Maximum number of undos per game; `None` means unlimited and
+    /// `Some(0)` disables undo, as in Vegas or ranked games.
+    pub limit: Option<u32>,
+    /// Points taken off the score for every undo. Never negative, so undo
+    /// cannot be used to earn points.
+    pub penalty: u32,
+}
+
+impl UndoPolicy {
+    pub const UNLIMITED: UndoPolicy = UndoPolicy {
+        limit: None,
+        penalty: 0,
+    };
+
+    pub const DISABLED: UndoPolicy = UndoPolicy {
+        limit: Some(0),
+        penalty: 0,
+    };
+
+    /// Undos still allowed after `used` of them, or `None` when unlimited.
+    pub fn remaining(&self, used: u32) -> Option<u32> {
+        self.limit.map(|limit| limit.saturating_sub(used))
+    }
+}
+
+/// Spawn one entity per card and deal them into the Klondike layout.
+///
+/// Cards are taken from the end of `cards`, like drawing from the top of a
+/// face-down deck. Tableau pile `n` receives `n + 1` cards of which only the
+/// top one is face up; everything left over forms the stock. The number of
+/// piles comes from the world's `Table`.
+///
+/// Every pile also gets an entity of its own, carrying a `PileAnchor` and
+/// named after the pile, e.g. `"foundation-2"`.
+///
+/// Dealing again into the same world reuses its card and pile entities, so
+/// whatever a renderer bound to an entity survives from one game to the
+/// next. Cards missing from the new deck are taken off the table.
+pub fn deal(world: &mut World, cards: &[Card]) {
+    let table = variant::table(world);
+    let mut remaining = cards.iter().rev();
+    let mut layout = Vec::with_capacity(cards.len());
+
+    // Deal row by row, just like at a real table.
+    let mut heights = vec![0usize; usize::from(table.tableau_piles)];
+    for row in 0..table.tableau_piles {
+        for col in row..table.tableau_piles {
+            let Some(&card) = remaining.next() else { break };
+            let height = &mut heights[col as usize];
+            layout.push((
+                card,
+                Pile::Tableau(col),
+                PileIndex(*height),
+                FaceUp(row == col),
+            ));
+            *height += 1;
+        }
+    }
+
+    // `remaining` runs from the top of the deck downwards, while pile indices
+    // count up from the bottom, so reverse it for the stock.
+    let stock: Vec<&Card> = remaining.collect();
+    for (index, &card) in stock.into_iter().rev().enumerate() {
+        layout.push((card, Pile::Stock, PileIndex(index), FaceUp(false)));
+    }
+
+    let mut pooled: Vec<(Entity, Card)> = world.iter::<Card>().map(|(e, &c)| (e, c)).collect();
+    let mut fresh = Vec::with_capacity(layout.len());
+    for (card, pile, index, face_up) in layout {
+        match pooled.iter().position(|&(_, c)| c == card) {
+            Some(at) => {
+                let entity = pooled.swap_remove(at).0;
+                world.add_component(entity, pile);
+                world.add_component(entity, index);
+                world.add_component(entity, face_up);
+            }
+            None => fresh.push((card, pile, index, face_up)),
+        }
+    }
+    world.spawn_batch(fresh);
+    for (entity, _) in pooled {
+        world.remove_component::<Card>(entity);
+        world.remove_component::<Pile>(entity);
+        world.remove_component::<PileIndex>(entity);
+        world.remove_component::<FaceUp>(entity);
+    }
+
+    let piles = table.piles();
+    let stale: Vec<Entity> = world
+        .iter::<PileAnchor>()
+        .filter(|(_, anchor)| !piles.contains(&anchor.0))
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in stale {
+        world.remove_component::<PileAnchor>(entity);
+    }
+    for pile in piles {
+        if world.find_by_name(&pile.to_string()).is_none() {
+            let anchor = world.spawn();
+            world.add_component(anchor, PileAnchor(pile));
+            world.name(anchor, &pile.to_string());
+        }
+    }
+
+    let scoring = world.resource::<ScoringMode>().copied().unwrap_or_default();
+    world.insert_resource(History::default());
+    world.insert_resource(scoring.starting_score(table.decks));
+}
+
+/// Shuffle a fresh deck for the world's `Table` with `seed` and deal it.
+///
+/// The same seed always produces the same board, which is what lets a server
+/// re-simulate a game from its seed and the list of moves.
+pub fn deal_from_seed(world: &mut World, seed: u64) {
+    let mut deck = variant::table(world).deck();
+    deck.shuffle(&mut GameRng::from_seed(seed));
+    deal(world, &deck.cards);
+}
+
+/// Every pile on the table: stock, waste, the foundations, then the tableau.
+pub fn all_piles(world: &World) -> Vec<Pile> {
+    variant::table(world).piles()
+}
+
+/// The cards in `pile`, from the bottom card to the top card.
+pub fn pile_cards(world: &World, pile: Pile) -> Vec<Entity> {
+    let mut cards: Vec<(PileIndex, Entity)> = world
+        .iter::<Pile>()
+        .filter(|(_, p)| **p == pile)
+        .map(|(entity, _)| (index_of(world, entity), entity))
+        .collect();
+    cards.sort();
+    cards.into_iter().map(|(_, entity)| entity).collect()
+}
+
+/// The top card of `pile`, if it has one.
+pub fn top_card(world: &World, pile: Pile) -> Option<Entity> {
+    pile_cards(world, pile).last().copied()
+}
+
+fn card_of(world: &World, entity: Entity) -> Card {
+    *world
+        .get_component::<Card>(entity)
+        .expect("every card entity has a Card")
+}
+
+fn index_of(world: &World, entity: Entity) -> PileIndex {
+    world
+        .get_component::<PileIndex>(entity)
+        .copied()
+        .unwrap_or(PileIndex(0))
+}
+
+fn is_face_up(world: &World, entity: Entity) -> bool {
+    world.get_component::<FaceUp>(entity).is_some_and(|f| f.0)
+}
+
+/// Resource present while the game is paused. No move or undo is accepted
+/// until it is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Paused {
+    /// Whether the board should be hidden from view meanwhile.
+    pub hide_cards: bool,
+}
+
+/// Check whether `mv` is allowed in the current position.
+pub fn validate(world: &World, mv: Move) -> Result<(), GameError> {
+    if world.resource::<Paused>().is_some() {
+        return Err(GameError::Paused);
+    }
+    if world.resource::<TimeUp>().is_some() {
+        return Err(GameError::TimeUp);
+    }
+    if world.resource::<Abandoned>().is_some() {
+        return Err(GameError::Abandoned);
+    }
+    match mv {
+        Move::Draw => {
+            if pile_cards(world, Pile::Stock).is_empty() {
+                return Err(GameError::IllegalMove("the stock is empty"));
+            }
+        }
+        Move::Recycle => {
+            if !pile_cards(world, Pile::Stock).is_empty() {
+                return Err(GameError::IllegalMove("the stock still has cards"));
+            }
+            if pile_cards(world, Pile::Waste).is_empty() {
+                return Err(GameError::IllegalMove("the waste is empty"));
+            }
+            let recycles = world.resource::<History>().map_or(0, History::recycles);
+            if variant::table(world)
+                .redeal_limit
+                .is_some_and(|limit| recycles >= limit)
+            {
+                return Err(GameError::IllegalMove("no redeals left"));
+            }
+        }
+        Move::Transfer { from, to, count } => validate_transfer(world, from, to, count)?,
+    }
+    Ok(())
+}
+
+fn validate_transfer(world: &World, from: Pile, to: Pile, count: usize) -> Result<(), GameError> {
+    let table = variant::table(world);
+    if !table.contains(from) || !table.contains(to) {
+        return Err(GameError::IllegalMove("that pile does not exist"));
+    }
+    if from == to {
+        return Err(GameError::IllegalMove(
+            "cards must move to a different pile",
+        ));
+    }
+    if from == Pile::Stock {
+        return Err(GameError::IllegalMove("draw from the stock instead"));
+    }
+    // An Ace could otherwise hop between empty foundations and score each
+    // time it lands.
+    if matches!((from, to), (Pile::Foundation(_), Pile::Foundation(_))) {
+        return Err(GameError::IllegalMove(
+            "cards cannot move from one foundation to another",
+        ));
+    }
+    if count == 0 {
+        return Err(GameError::IllegalMove("at least one card must move"));
+    }
+    if count > 1 && !matches!(from, Pile::Tableau(_)) {
+        return Err(GameError::IllegalMove(
+            "only tableau piles can move several cards",
+        ));
+    }
+
+    let source = pile_cards(world, from);
+    if count > source.len() {
+        return Err(GameError::NotEnoughCards {
+            pile: from,
+            requested: count,
+        });
+    }
+    let moving = &source[source.len() - count..];
+    if !moving.iter().all(|&e| is_face_up(world, e)) {
+        return Err(GameError::NotEnoughCards {
+            pile: from,
+            requested: count,
+        });
+    }
+
+    let card = card_of(world, moving[0]);
+    let target_top = top_card(world, to).map(|e| card_of(world, e));
+    match to {
+        Pile::Stock | Pile::Waste => Err(GameError::IllegalMove("cards cannot be placed there")),
+        Pile::Foundation(foundation) => match target_top {
+            None if table
+                .foundation_suit(foundation)
+                .is_some_and(|suit| suit != card.suit) =>
+            {
+                Err(GameError::IllegalMove(
+                    "that foundation is kept for another suit",
+                ))
+            }
+            None if card.rank == table.foundation_base => Ok(()),
+            None if table.foundation_base == Rank::Ace => {
+                Err(GameError::IllegalMove("foundations start with an Ace"))
+            }
+            None => Err(GameError::IllegalMove(
+                "foundations start with the base rank",
+            )),
+            Some(top) if top.suit == card.suit && top.rank.next_wrapping() == card.rank => Ok(()),
+            Some(_) => Err(GameError::IllegalMove(
+                "foundations build up in suit from Ace to King",
+            )),
+        },
+        Pile::Tableau(_) => match target_top {
+            None if card.rank == Rank::King => Ok(()),
+            None => Err(GameError::IllegalMove(
+                "only a King may fill an empty tableau pile",
+            )),
+            Some(top)
+                if top.suit.is_red() != card.suit.is_red()
+                    && top.rank.value() == card.rank.value() + 1 =>
+            {
+                Ok(())
+            }
+            Some(_) => Err(GameError::IllegalMove(
+                "tableau piles build down in alternating colours",
+            )),
+        },
+    }
+}
+
+/// Validate and perform `mv`, returning the events it produced.
+///
+/// Turning over the card exposed on a tableau pile is part of the same move,
+/// so a single undo restores both.
+pub fn apply(world: &mut World, mv: Move) -> Result<Vec<GameEvent>, GameError> {
+    validate(world, mv)?;
+
+    // Borrowed from the history while the cards move; nothing below fails.
+    let mut record = std::mem::take(&mut history_mut(world).cards);
+    let start = record.top();
+    let mut events = Vec::new();
+    // The card that earned the move's points, and the one turned over.
+    let mut moved = None;
+    let mut flipped = None;
+    match mv {
+        Move::Draw => {
+            let stock = pile_cards(world, Pile::Stock);
+            let count = usize::from(variant::table(world).draw_count.max(1)).min(stock.len());
+            let base = pile_cards(world, Pile::Waste).len();
+            // Cards are turned one at a time, so the top of the stock ends up
+            // lowest on the waste.
+            for (offset, &card) in stock.iter().rev().take(count).enumerate() {
+                place(world, card, Pile::Waste, base + offset, true, &mut record);
+                events.push(GameEvent::CardMoved {
+                    card,
+                    from: Pile::Stock,
+                    to: Pile::Waste,
+                });
+                events.push(GameEvent::CardFlipped {
+                    card,
+                    face_up: true,
+                });
+            }
+        }
+        Move::Recycle => {
+            // The top of the waste ends up at the bottom of the new stock.
+            let mut stock: Vec<Entity> = pile_cards(world, Pile::Waste).into_iter().rev().collect();
+            if variant::table(world).reshuffle_on_recycle {
+                let recycles = world.resource::<History>().map_or(0, History::recycles);
+                let seed = reshuffle_seed(stock.iter().map(|&e| card_of(world, e)), recycles);
+                stock.shuffle(&mut GameRng::from_seed(seed));
+            }
+            for (index, &card) in stock.iter().enumerate() {
+                place(world, card, Pile::Stock, index, false, &mut record);
+                events.push(GameEvent::CardMoved {
+                    card,
+                    from: Pile::Waste,
+                    to: Pile::Stock,
+                });
+            }
+            events.push(GameEvent::StockRecycled);
+        }
+        Move::Transfer { from, to, count } => {
+            let source = pile_cards(world, from);
+            let base = pile_cards(world, to).len();
+            let run = &source[source.len() - count..];
+            moved = run.first().copied();
+            for (offset, &card) in run.iter().enumerate() {
+                place(world, card, to, base + offset, true, &mut record);
+                events.push(GameEvent::CardMoved { card, from, to });
+            }
+
+            if let (Pile::Tableau(_), Some(exposed)) = (from, top_card(world, from))
+                && !is_face_up(world, exposed)
+            {
+                let index = index_of(world, exposed).0;
+                place(world, exposed, from, index, true, &mut record);
+                flipped = Some(exposed);
+                events.push(GameEvent::CardFlipped {
+                    card: exposed,
+                    face_up: true,
+                });
+            }
+
+            if let Pile::Foundation(foundation) = to
+                && pile_cards(world, to).len() == 13
+            {
+                events.push(GameEvent::FoundationCompleted { foundation });
+            }
+        }
+    }
+
+    let moves = history_mut(world).moves + 1;
+    let undos = history_mut(world).undos;
+    let scoring = world.resource::<ScoringMode>().copied().unwrap_or_default();
+    let mut scores: Vec<(ScoreReason, i32, Option<Entity>)> = scoring
+        .move_scores(mv, flipped.is_some())
+        .into_iter()
+        .map(|(reason, points)| {
+            let card = if reason == ScoreReason::Flip {
+                flipped
+            } else {
+                moved
+            };
+            (reason, points, card)
+        })
+        .collect();
+    let combo = world.resource::<ComboRules>().copied().map(|rules| {
+        let at_ms = world
+            .resource::<GameClock>()
+            .map_or(0.0, |c| c.elapsed_ms(clock::now_ms()));
+        let combo = world.resource::<Combo>().copied().unwrap_or_default();
+        let mut next = combo;
+        let multiplier = next.advance(mv, at_ms, &rules);
+        if next.streak() > 1 {
+            let points: i32 = scores.iter().map(|&(_, points, _)| points).sum();
+            scores.push((ScoreReason::Combo, points * (multiplier as i32 - 1), moved));
+            events.push(GameEvent::Combo {
+                streak: next.streak(),
+                multiplier,
+            });
+        }
+        world.insert_resource(next);
+        combo
+    });
+    let won = is_won(world);
+    if won {
+        let time_ms = world
+            .resource::<GameClock>()
+            .map_or(0.0, |c| c.elapsed_ms(clock::now_ms()));
+        let time_bonus = world
+            .resource::<TimeBonus>()
+            .copied()
+            .unwrap_or_else(|| scoring.time_bonus(&variant::table(world)))
+            .award(time_ms);
+        scores.push((ScoreReason::TimeBonus, time_bonus as i32, None));
+        events.push(GameEvent::GameWon {
+            moves,
+            undos,
+            time_ms: time_ms as u64,
+            time_bonus,
+        });
+        events.extend(timeattack::cleared(world, clock::now_ms()));
+    }
+    let score = score_mut(world);
+    let mut points = 0;
+    for (reason, amount, card) in scores {
+        // Report what actually changed, which the floor may have cut short.
+        let amount = score.add(amount);
+        if amount != 0 {
+            points += amount;
+            events.push(GameEvent::ScoreDelta {
+                reason,
+                amount,
+                card,
+            });
+        }
+    }
+
+    let history = history_mut(world);
+    let recycle = mv == Move::Recycle;
+    let cards = record.span_from(start);
+    history.cards = record;
+    history.undo.push(Record {
+        cards,
+        points,
+        recycle,
+        combo,
+        follows: false,
+    });
+    history.moves = moves;
+    history.recycles += u32::from(recycle);
+    // A cheap scan: a stuck board only needs a move list, not a search.
+    if !won && Position::from_world(world).is_stuck() {
+        events.push(GameEvent::NoMovesLeft);
+    }
+    Ok(events)
+}
+
+/// Validate and perform `mv` as a consequence of the previous move, so that
+/// both are taken back by a single undo.
+pub fn apply_follow_up(world: &mut World, mv: Move) -> Result<Vec<GameEvent>, GameError> {
+    let events = apply(world, mv)?;
+    let history = history_mut(world);
+    if let [.., _, last] = history.undo.as_mut_slice() {
+        last.follows = true;
+    }
+    Ok(events)
+}
+
+/// Take back the most recent move, along with its follow-ups.
+pub fn undo(world: &mut World) -> Result<Vec<GameEvent>, GameError> {
+    if world.resource::<Paused>().is_some() {
+        return Err(GameError::Paused);
+    }
+    if world.resource::<TimeUp>().is_some() {
+        return Err(GameError::TimeUp);
+    }
+    if world.resource::<Abandoned>().is_some() {
+        return Err(GameError::Abandoned);
+    }
+    let policy = world.resource::<UndoPolicy>().copied().unwrap_or_default();
+    let history = history_mut(world);
+    match policy.remaining(history.undos) {
+        Some(0) if policy.limit == Some(0) => return Err(GameError::UndoNotAllowed),
+        Some(0) => return Err(GameError::UndoLimitReached),
+        _ => {}
+    }
+    let mut records = vec![history.undo.pop().ok_or(GameError::NothingToUndo)?];
+    while records.last().is_some_and(|r| r.follows)
+        && let Some(record) = history.undo.pop()
+    {
+        records.push(record);
+    }
+    history.undos += 1;
+    let mut points = 0;
+    for record in &records {
+        history.recycles -= u32::from(record.recycle);
+        points += record.points;
+    }
+    if let Some(combo) = records.last().and_then(|r| r.combo) {
+        world.insert_resource(combo);
+    }
+    let score = score_mut(world);
+    let mut events = vec![GameEvent::UndoPerformed];
+    for (reason, amount) in [
+        (ScoreReason::Undo, -points),
+        (
+            ScoreReason::UndoPenalty,
+            -i32::try_from(policy.penalty).unwrap_or(i32::MAX),
+        ),
+    ] {
+        let amount = score.add(amount);
+        if amount != 0 {
+            events.push(GameEvent::ScoreDelta {
+                reason,
+                amount,
+                card: None,
+            });
+        }
+    }
+    // Restore in reverse so a card touched twice ends in its oldest state.
+    let mut cards = std::mem::take(&mut history_mut(world).cards);
+    for record in &records {
+        for state in cards.get(record.cards).iter().rev() {
+            world.add_component(state.entity, state.pile);
+            world.add_component(state.entity, state.index);
+            world.add_component(state.entity, state.face_up);
+        }
+    }
+    // The records were the newest, so the oldest of them is released last.
+    if let Some(oldest) = records.last() {
+        cards.release(oldest.cards);
+    }
+    history_mut(world).cards = cards;
+    Ok(events)
+}
+
+/// The seed for reshuffling a recycled stock. It only depends on the cards,
+/// in the order a plain recycle would leave them, and on how many recycles
+/// came before, so replaying the same moves always gives the same stock.
+pub(crate) fn reshuffle_seed(cards: impl Iterator<Item = Card>, recycles: u32) -> u64 {
+    // FNV-1a: stable across platforms and compiler versions, unlike the
+    // standard library's hasher.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let bytes = cards
+        .flat_map(|card| [card.suit as u8, card.rank.value()])
+        .chain(recycles.to_le_bytes());
+    for byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Whether every card has reached the foundations.
+pub fn is_won(world: &World) -> bool {
+    world.iter::<Card>().count() > 0
+        && world
+            .iter::<Pile>()
+            .all(|(_, pile)| matches!(pile, Pile::Foundation(_)))
+}
+
+/// Move a card, remembering where it was for undo.
+fn place(
+    world: &mut World,
+    entity: Entity,
+    pile: Pile,
+    index: usize,
+    face_up: bool,
+    record: &mut StackArena<CardState>,
+) {
+    record.push(CardState {
+        entity,
+        pile: *world
+            .get_component::<Pile>(entity)
+            .expect("cards have a Pile"),
+        index: index_of(world, entity),
+        face_up: FaceUp(is_face_up(world, entity)),
+    });
+    world.add_component(entity, pile);
+    world.add_component(entity, PileIndex(index));
+    world.add_component(entity, FaceUp(face_up));
+}
+
+fn score_mut(world: &mut World) -> &mut Score {
+    if world.resource::<Score>().is_none() {
+        world.insert_resource(Score::default());
+    }
+    world.resource_mut::<Score>().expect("inserted above")
+}
+
+fn history_mut(world: &mut World) -> &mut History {
+    if world.resource::<History>().is_none() {
+        world.insert_resource(History::default());
+    }
+    world.resource_mut::<History>().expect("inserted above")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Deck, Suit, TABLEAU_PILES};
+    use crate::variant::Table;
+
+    /// Deal an unshuffled deck. The last card of `Deck::standard()` is the
+    /// King of Spades, so tableau 0 receives it face up.
+    fn ordered_world() -> World {
+        let mut world = World::new();
+        deal(&mut world, &Deck::standard().cards);
+        world
+    }
+
+    fn card_at(world: &World, pile: Pile) -> Card {
+        card_of(world, top_card(world, pile).unwrap())
+    }
+
+    #[test]
+    fn deal_layout() {
+        let world = ordered_world();
+        for i in 0..TABLEAU_PILES {
+            let cards = pile_cards(&world, Pile::Tableau(i));
+            assert_eq!(cards.len(), i as usize + 1);
+            let face_up: Vec<bool> = cards.iter().map(|&e| is_face_up(&world, e)).collect();
+            assert_eq!(face_up.iter().filter(|f| **f).count(), 1);
+            assert!(face_up.last().unwrap());
+        }
+        assert_eq!(pile_cards(&world, Pile::Stock).len(), 52 - 28);
+
+        let anchor = world.find_by_name("foundation-2").unwrap();
+        assert_eq!(
+            world.get_component::<PileAnchor>(anchor),
+            Some(&PileAnchor(Pile::Foundation(2)))
+        );
+        assert_eq!(
+            card_at(&world, Pile::Tableau(0)),
+            Card::new(Suit::Spades, Rank::King)
+        );
+    }
+
+    #[test]
+    fn dealing_again_reuses_the_card_entities() {
+        let mut world = ordered_world();
+        world.insert_resource(Table::DOUBLE_KLONDIKE);
+        deal(&mut world, &Deck::with_decks(2).cards);
+        assert_eq!(world.iter::<Card>().count(), 104);
+
+        world.insert_resource(Table::KLONDIKE);
+        let mut deck = Deck::standard();
+        deck.shuffle(&mut GameRng::from_seed(7));
+        let before: Vec<(Entity, Card)> = world.iter::<Card>().map(|(e, &c)| (e, c)).collect();
+        deal(&mut world, &deck.cards);
+        let after: Vec<(Entity, Card)> = world.iter::<Card>().map(|(e, &c)| (e, c)).collect();
+        assert_eq!(after.len(), 52);
+        assert!(after.iter().all(|card| before.contains(card)));
+        assert_eq!(world.iter::<Pile>().count(), 52);
+        assert_eq!(world.iter::<PileAnchor>().count(), all_piles(&world).len());
+        assert_eq!(pile_cards(&world, Pile::Tableau(6)).len(), 7);
+    }
+
+    #[test]
+    fn draw_recycle_and_undo() {
+        let mut world = ordered_world();
+        let top = card_at(&world, Pile::Stock);
+        let events = apply(&mut world, Move::Draw).unwrap();
+        assert!(matches!(
+            events[0],
+            GameEvent::CardMoved {
+                to: Pile::Waste,
+                ..
+            }
+        ));
+        assert_eq!(card_at(&world, Pile::Waste), top);
+
+        assert!(apply(&mut world, Move::Recycle).is_err());
+        while apply(&mut world, Move::Draw).is_ok() {}
+        apply(&mut world, Move::Recycle).unwrap();
+        assert_eq!(card_at(&world, Pile::Stock), top);
+
+        undo(&mut world).unwrap();
+        assert!(pile_cards(&world, Pile::Stock).is_empty());
+        assert_eq!(history_mut(&mut world).moves(), 25);
+    }
+
+    #[test]
+    fn follow_ups_are_undone_with_their_move() {
+        let mut world = ordered_world();
+        apply(&mut world, Move::Draw).unwrap();
+        apply(&mut world, Move::Draw).unwrap();
+        apply_follow_up(&mut world, Move::Draw).unwrap();
+        apply_follow_up(&mut world, Move::Draw).unwrap();
+
+        undo(&mut world).unwrap();
+        assert_eq!(pile_cards(&world, Pile::Waste).len(), 1);
+        assert_eq!(history_mut(&mut world).undos(), 1);
+        undo(&mut world).unwrap();
+        assert!(pile_cards(&world, Pile::Waste).is_empty());
+        assert_eq!(undo(&mut world), Err(GameError::NothingToUndo));
+    }
+
+    #[test]
+    fn paused_board_refuses_moves() {
+        let mut world = ordered_world();
+        world.insert_resource(Paused { hide_cards: true });
+        assert_eq!(apply(&mut world, Move::Draw), Err(GameError::Paused));
+        world.remove_resource::<Paused>();
+        apply(&mut world, Move::Draw).unwrap();
+    }
+
+    #[test]
+    fn undo_policy_limits_and_penalises() {
+        let mut world = ordered_world();
+        world.insert_resource(UndoPolicy {
+            limit: Some(1),
+            penalty: 2,
+        });
+        world.resource_mut::<Score>().unwrap().add(10);
+        apply(&mut world, Move::Draw).unwrap();
+        apply(&mut world, Move::Draw).unwrap();
+        undo(&mut world).unwrap();
+        assert_eq!(undo(&mut world), Err(GameError::UndoLimitReached));
+        assert_eq!(history_mut(&mut world).undos(), 1);
+        assert_eq!(world.resource::<Score>().unwrap().points(), 8);
+
+        world.insert_resource(UndoPolicy::DISABLED);
+        assert_eq!(undo(&mut world), Err(GameError::UndoNotAllowed));
+    }
+
+    #[test]
+    fn redeal_limit_and_foundation_base() {
+        let mut world = World::new();
+        world.insert_resource(Table {
+            redeal_limit: Some(1),
+            foundation_base: Rank::King,
+            ..Table::KLONDIKE
+        });
+        deal(&mut world, &Deck::standard().cards);
+        // The King of Spades now starts a foundation.
+        apply(
+            &mut world,
+            Move::Transfer {
+                from: Pile::Tableau(0),
+                to: Pile::Foundation(0),
+                count: 1,
+            },
+        )
+        .unwrap();
+
+        while apply(&mut world, Move::Draw).is_ok() {}
+        apply(&mut world, Move::Recycle).unwrap();
+        while apply(&mut world, Move::Draw).is_ok() {}
+        assert_eq!(
+            apply(&mut world, Move::Recycle),
+            Err(GameError::IllegalMove("no redeals left"))
+        );
+        while history_mut(&mut world).recycles() == 1 {
+            undo(&mut world).unwrap();
+        }
+        assert!(validate(&world, Move::Recycle).is_ok());
+    }
+
+    #[test]
+    fn reports_when_no_moves_are_left() {
+        let mut world = World::new();
+        world.insert_resource(Table {
+            redeal_limit: Some(0),
+            ..Table::KLONDIKE
+        });
+        // A Two of Spades buries its own Ace; the stock holds a useless Five.
+        world.spawn_batch([
+            (
+                Card::new(Suit::Spades, Rank::Ace),
+                Pile::Tableau(0),
+                PileIndex(0),
+                FaceUp(false),
+            ),
+            (
+                Card::new(Suit::Spades, Rank::Two),
+                Pile::Tableau(0),
+                PileIndex(1),
+                FaceUp(true),
+            ),
+            (
+                Card::new(Suit::Hearts, Rank::Five),
+                Pile::Stock,
+                PileIndex(0),
+                FaceUp(false),
+            ),
+        ]);
+        let events = apply(&mut world, Move::Draw).unwrap();
+        assert_eq!(events.last(), Some(&GameEvent::NoMovesLeft));
+        undo(&mut world).unwrap();
+        world.insert_resource(Table::KLONDIKE);
+        let events = apply(&mut world, Move::Draw).unwrap();
+        assert_eq!(events.last(), Some(&GameEvent::NoMovesLeft));
+    }
+
+    #[test]
+    fn reshuffled_recycles_are_reproducible() {
+        let mut world = World::new();
+        world.insert_resource(Table {
+            reshuffle_on_recycle: true,
+            ..Table::KLONDIKE
+        });
+        deal(&mut world, &Deck::standard().cards);
+        while apply(&mut world, Move::Draw).is_ok() {}
+        let before = Position::from_world(&world);
+        apply(&mut world, Move::Recycle).unwrap();
+        let recycled = Position::from_world(&world);
+
+        // The solver's copy of the rule shuffles the same way.
+        let mut expected = before.clone();
+        expected.apply(Move::Recycle);
+        assert_eq!(recycled, expected);
+
+        let mut kept = before;
+        kept.table.reshuffle_on_recycle = false;
+        kept.apply(Move::Recycle);
+        assert_ne!(recycled.stock, kept.stock);
+    }
+
+    #[test]
+    fn transfer_rules() {
+        let mut world = ordered_world();
+        // The King of Spades on tableau 0 cannot start a foundation.
+        let to_foundation = Move::Transfer {
+            from: Pile::Tableau(0),
+            to: Pile::Foundation(0),
+            count: 1,
+        };
+        assert!(matches!(
+            apply(&mut world, to_foundation),
+            Err(GameError::IllegalMove(_))
+        ));
+        let too_many = Move::Transfer {
+            from: Pile::Tableau(3),
+            to: Pile::Tableau(0),
+            count: 2,
+        };
+        assert!(matches!(
+            validate(&world, too_many),
+            Err(GameError::NotEnoughCards { .. })
+        ));
+        assert_eq!(undo(&mut world), Err(GameError::NothingToUndo));
+    }
+
+    #[test]
+    fn aces_do_not_hop_between_foundations() {
+        let mut world = World::new();
+        world.spawn_batch([(
+            Card::new(Suit::Hearts, Rank::Ace),
+            Pile::Tableau(0),
+            PileIndex(0),
+            FaceUp(true),
+        )]);
+        let home = Move::Transfer {
+            from: Pile::Tableau(0),
+            to: Pile::Foundation(0),
+            count: 1,
+        };
+        apply(&mut world, home).unwrap();
+        let points = world.resource::<Score>().unwrap().points();
+        let hop = Move::Transfer {
+            from: Pile::Foundation(0),
+            to: Pile::Foundation(1),
+            count: 1,
+        };
+        assert!(matches!(
+            apply(&mut world, hop),
+            Err(GameError::IllegalMove(_))
+        ));
+        assert_eq!(world.resource::<Score>().unwrap().points(), points);
+        assert_eq!(ScoringMode::Standard.move_points(hop, false), 0);
+        assert!(ScoringMode::Vegas.move_scores(hop, false).is_empty());
+    }
+
+    #[test]
+    fn each_variant_pays_its_own_time_bonus() {
+        use crate::options::GameOptions;
+        use crate::variant::Variant;
+
+        for (variant, bonus) in [
+            (Variant::Klondike, 7_000),
+            (Variant::DoubleKlondike, 14_000),
+        ] {
+            let mut world = World::new();
+            GameOptions {
+                variant,
+                ..GameOptions::default()
+            }
+            .insert_into(&mut world);
+            // Won a hundred seconds in.
+            world.insert_resource(GameClock::start(clock::now_ms() - 100_500.0));
+            world.spawn_batch([(
+                Card::new(Suit::Hearts, Rank::Ace),
+                Pile::Waste,
+                PileIndex(0),
+                FaceUp(true),
+            )]);
+            let events = apply(
+                &mut world,
+                Move::Transfer {
+                    from: Pile::Waste,
+                    to: Pile::Foundation(0),
+                    count: 1,
+                },
+            )
+            .unwrap();
+            assert!(
+                events.iter().any(|e| matches!(
+                    e,
+                    GameEvent::GameWon { time_bonus, .. } if *time_bonus == bonus
+                )),
+                "{variant}"
+            );
+        }
+    }
+
+    #[test]
+    fn moving_exposes_and_flips() {
+        let mut world = World::new();
+        // Tableau 1 holds a face-down Two under a face-up Queen of Hearts.
+        world.spawn_batch([
+            (
+                Card::new(Suit::Clubs, Rank::Two),
+                Pile::Tableau(1),
+                PileIndex(0),
+                FaceUp(false),
+            ),
+            (
+                Card::new(Suit::Hearts, Rank::Queen),
+                Pile::Tableau(1),
+                PileIndex(1),
+                FaceUp(true),
+            ),
+            (
+                Card::new(Suit::Spades, Rank::King),
+                Pile::Tableau(0),
+                PileIndex(0),
+                FaceUp(true),
+            ),
+        ]);
+
+        let events = apply(
+            &mut world,
+            Move::Transfer {
+                from: Pile::Tableau(1),
+                to: Pile::Tableau(0),
+                count: 1,
+            },
+        )
+        .unwrap();
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, GameEvent::CardFlipped { face_up: true, .. }))
+        );
+        let exposed = top_card(&world, Pile::Tableau(1)).unwrap();
+        assert!(is_face_up(&world, exposed));
+        assert_eq!(world.resource::<Score>().unwrap().points(), 5);
+        assert!(events.contains(&GameEvent::ScoreDelta {
+            reason: ScoreReason::Flip,
+            amount: 5,
+            card: Some(exposed),
+        }));
+
+        let events = undo(&mut world).unwrap();
+        assert!(!is_face_up(&world, exposed));
+        assert_eq!(
+            events[1..],
+            [GameEvent::ScoreDelta {
+                reason: ScoreReason::Undo,
+                amount: -5,
+                card: None,
+            }]
+        );
+        assert_eq!(world.resource::<Score>().unwrap().points(), 0);
+        assert_eq!(card_at(&world, Pile::Tableau(1)).rank, Rank::Queen);
+    }
+}
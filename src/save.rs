@@ -0,0 +1,194 @@
+// Saving the game in progress and picking it up again.
+//
+// A game is saved as what recreates it: its options, seed included, the
+// actions the player made as recorded in the `AuditLog`, and how long it had
+// been played. Loading deals the seed again and replays the actions, so a
+// save stays small and can only ever hold a position the rules reach.
+//
+// The game in progress is saved after every action and every new deal, as
+// the *autosave*. Every save carries a revision one higher than the one
+// before it, so `sync` can tell which of two saves is the newer.
+//
+// A save also carries the undo stack as it was, so a reload does not take
+// away the moves the player could take back. Replaying rebuilds the stack
+// too, but scores moves at the time of the replay rather than when they were
+// made, and saves from before the stack was saved rely on that. Saves are
+// stored packed (see `compress`), since long games replay hundreds of
+// actions.
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::clock::GameClock;
+use crate::compress;
+use crate::ecs::World;
+use crate::error::GameError;
+use crate::options::GameOptions;
+use crate::rules::SavedHistory;
+
+/// A game in progress, as saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub revision: u64,
+    /// Milliseconds since the Unix epoch when it was saved.
+    pub saved_ms: f64,
+    /// The options of the deal, with its seed.
+    pub options: GameOptions,
+    pub actions: Vec<AuditAction>,
+    /// Milliseconds of play so far.
+    pub elapsed_ms: f64,
+    /// The undo stack, see `rules::SavedHistory`.
+    #[serde(default)]
+    pub history: Option<SavedHistory>,
+}
+
+impl SavedGame {
+    /// Save the game of `world`, dealt with `options`, at `now_ms`.
+    pub fn capture(world: &World, options: GameOptions, revision: u64, now_ms: f64) -> Self {
+        SavedGame {
+            revision,
+            saved_ms: now_ms,
+            options,
+            actions: world
+                .resource::<AuditLog>()
+                .map(|log| log.entries().iter().map(|e| e.action).collect())
+                .unwrap_or_default(),
+            elapsed_ms: world
+                .resource::<GameClock>()
+                .map_or(0.0, |clock| clock.elapsed_ms(now_ms)),
+            history: SavedHistory::capture(world),
+        }
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.options.seed
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("saves always serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, GameError> {
+        serde_json::from_str(json).map_err(|e| GameError::InvalidOptions(e.to_string()))
+    }
+
+    /// The save deflated for storage, see `compress::pack`.
+    pub fn to_packed(&self) -> String {
+        compress::pack(&self.to_json())
+    }
+
+    /// Read a save made by `to_packed`, or one stored as plain JSON.
+    pub fn from_packed(text: &str) -> Result<Self, GameError> {
+        Self::from_json(&compress::unpack(text)?)
+    }
+
+    /// Play the saved actions again on `world`, freshly dealt from the
+    /// save's options, put the saved undo stack back and set its clock
+    /// running from the saved time.
+    pub fn replay(&self, world: &mut World, now_ms: f64) -> Result<(), GameError> {
+        for &action in &self.actions {
+            action.play(world)?;
+            if let Some(log) = world.resource_mut::<AuditLog>() {
+                log.record(action, now_ms);
+            }
+        }
+        if let Some(history) = &self.history {
+            history.restore(world)?;
+        }
+        world.insert_resource(GameClock::start(now_ms - self.elapsed_ms));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::state_digest;
+    use crate::rules::{self, History, Move};
+
+    #[test]
+    fn saves_replay_to_the_same_position() {
+        let options = GameOptions {
+            seed: Some(5),
+            ..GameOptions::default()
+        };
+        let mut world = World::new();
+        rules::deal_from_seed(&mut world, 5);
+        world.insert_resource(AuditLog::new(5, None));
+        world.insert_resource(GameClock::start(0.0));
+        for action in [
+            AuditAction::Move(Move::Draw),
+            AuditAction::Move(Move::Draw),
+            AuditAction::Undo,
+        ] {
+            action.play(&mut world).unwrap();
+            world
+                .resource_mut::<AuditLog>()
+                .unwrap()
+                .record(action, 0.0);
+        }
+        let saved = SavedGame::capture(&world, options, 3, 30_000.0);
+        let saved = SavedGame::from_json(&saved.to_json()).unwrap();
+        assert_eq!(SavedGame::from_packed(&saved.to_packed()).unwrap(), saved);
+        assert_eq!((saved.actions.len(), saved.elapsed_ms), (3, 30_000.0));
+
+        let mut restored = World::new();
+        rules::deal_from_seed(&mut restored, saved.seed().unwrap());
+        restored.insert_resource(AuditLog::new(5, None));
+        saved.replay(&mut restored, 100_000.0).unwrap();
+        assert_eq!(state_digest(&restored), state_digest(&world));
+        let clock = restored.resource::<GameClock>().unwrap();
+        assert_eq!(clock.elapsed_ms(100_000.0), 30_000.0);
+        assert_eq!(restored.resource::<AuditLog>().unwrap().entries().len(), 3);
+    }
+
+    #[test]
+    fn the_undo_stack_survives_a_reload() {
+        let options = GameOptions {
+            seed: Some(8),
+            ..GameOptions::default()
+        };
+        let mut world = World::new();
+        rules::deal_from_seed(&mut world, 8);
+        world.insert_resource(AuditLog::new(8, None));
+        for action in [
+            AuditAction::Move(Move::Draw),
+            AuditAction::Move(Move::Draw),
+            AuditAction::FollowUp(Move::Draw),
+        ] {
+            action.play(&mut world).unwrap();
+            world
+                .resource_mut::<AuditLog>()
+                .unwrap()
+                .record(action, 0.0);
+        }
+        let saved = SavedGame::capture(&world, options, 1, 0.0);
+        assert!(saved.history.is_some());
+        let old_format = SavedGame {
+            history: None,
+            ..saved.clone()
+        };
+
+        for saved in [saved, old_format] {
+            let mut restored = World::new();
+            rules::deal_from_seed(&mut restored, 8);
+            restored.insert_resource(AuditLog::new(8, None));
+            SavedGame::from_json(&saved.to_json())
+                .unwrap()
+                .replay(&mut restored, 0.0)
+                .unwrap();
+            let mut original = World::new();
+            rules::deal_from_seed(&mut original, 8);
+            saved.replay(&mut original, 0.0).unwrap();
+
+            // The draw and its follow-up go back together, then the first.
+            for world in [&mut original, &mut restored] {
+                rules::undo(world).unwrap();
+            }
+            assert_eq!(state_digest(&restored), state_digest(&original));
+            rules::undo(&mut restored).unwrap();
+            assert!(rules::undo(&mut restored).is_err());
+            assert_eq!(restored.resource::<History>().unwrap().undos(), 2);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+// Points for moves and the time bonus for winning quickly.
+//
+// Scoring follows the classic "standard" rules of desktop Klondike:
+//
+// | action                          | points |
+// |---------------------------------|--------|
+// | waste to tableau                |     +5 |
+// | waste or tableau to foundation  |    +10 |
+// | turning over a tableau card     |     +5 |
+// | foundation back to tableau      |    -15 |
+// | recycling the waste             |   -100 |
+//
+// The score never drops below zero. On winning, a time bonus of
+// 700,000 / seconds is added, but only for games lasting more than 30
+// seconds. That is Klondike's bonus: every variant's `Table` carries its
+// own, and Double Klondike, with twice the cards to play, allows twice the
+// time for the same bonus.
+//
+// Vegas scoring instead treats the game as a bet: the player starts $52 down
+// per deck, wins $5 for every card on a foundation (and loses it again when
+// the card comes back), and can finish in the red. Games can also be played
+// without a score at all. The `ScoringMode` resource picks the rules.
+//
+// On top of either mode, combo scoring rewards streaks in the style of
+// TriPeaks: consecutive foundation plays, each made within a short window of
+// the last and with no trip to the stock in between, multiply their points
+// by the length of the streak.
+//
+// `rules::apply` does the bookkeeping, remembering the points of every move
+// so an undo takes them back, and reports every change of score as a
+// `ScoreDelta` event naming its reason and the card involved.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::Pile;
+use crate::rules::Move;
+use crate::variant::Table;
+
+/// Resource choosing how the game is scored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoringMode {
+    #[default]
+    Standard,
+    Vegas,
+    None,
+}
+
+impl ScoringMode {
+    /// Points for playing `mv`. `flipped` tells whether the move turned over
+    /// a tableau card.
+    pub fn move_points(self, mv: Move, flipped: bool) -> i32 {
+        self.move_scores(mv, flipped)
+            .iter()
+            .map(|&(_, points)| points)
+            .sum()
+    }
+
+    /// The points for playing `mv`, broken down by what earned them.
+    pub fn move_scores(self, mv: Move, flipped: bool) -> Vec<(ScoreReason, i32)> {
+        match self {
+            ScoringMode::Standard => move_scores(mv, flipped),
+            ScoringMode::Vegas => match mv {
+                Move::Transfer {
+                    from: Pile::Foundation(_),
+                    to: Pile::Foundation(_),
+                    ..
+                } => Vec::new(),
+                Move::Transfer {
+                    to: Pile::Foundation(_),
+                    ..
+                } => vec![(ScoreReason::ToFoundation, 5)],
+                Move::Transfer {
+                    from: Pile::Foundation(_),
+                    ..
+                } => vec![(ScoreReason::FromFoundation, -5)],
+                _ => Vec::new(),
+            },
+            ScoringMode::None => Vec::new(),
+        }
+    }
+
+    /// The score of a fresh deal of `decks` decks.
+    pub fn starting_score(self, decks: u8) -> Score {
+        match self {
+            ScoringMode::Vegas => Score {
+                points: -52 * i32::from(decks),
+                floor: None,
+            },
+            ScoringMode::Standard | ScoringMode::None => Score::default(),
+        }
+    }
+
+    /// The bonus for winning fast at `table`. Only standard scoring has
+    /// one.
+    pub fn time_bonus(self, table: &Table) -> TimeBonus {
+        match self {
+            ScoringMode::Standard => table.time_bonus,
+            ScoringMode::Vegas | ScoringMode::None => TimeBonus::NONE,
+        }
+    }
+}
+
+/// What a change of score was for, as reported by `GameEvent::ScoreDelta`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreReason {
+    WasteToTableau,
+    ToFoundation,
+    FromFoundation,
+    Flip,
+    Recycle,
+    /// The extra points of a combo streak.
+    Combo,
+    TimeBonus,
+    /// Points of an undone move being taken back.
+    Undo,
+    UndoPenalty,
+}
+
+/// Resource switching on combo scoring.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComboRules {
+    /// Longest pause between two foundation plays of one streak.
+    pub window_ms: f64,
+    /// The multiplier stops growing here.
+    pub max_multiplier: u32,
+}
+
+impl Default for ComboRules {
+    fn default() -> Self {
+        ComboRules {
+            window_ms: 3_000.0,
+            max_multiplier: 5,
+        }
+    }
+}
+
+/// Resource tracking the current streak of foundation plays.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Combo {
+    streak: u32,
+    // Game time of the last foundation play.
+    last_ms: f64,
+}
+
+impl Combo {
+    /// Foundation plays in the current streak.
+    pub fn streak(&self) -> u32 {
+        self.streak
+    }
+
+    /// Count `mv`, played at game time `at_ms`, towards the streak and
+    /// return the multiplier for its points.
+    pub(crate) fn advance(&mut self, mv: Move, at_ms: f64, rules: &ComboRules) -> u32 {
+        match mv {
+            Move::Draw | Move::Recycle => {
+                self.streak = 0;
+                1
+            }
+            Move::Transfer {
+                to: Pile::Foundation(_),
+                ..
+            } => {
+                let within = at_ms - self.last_ms <= rules.window_ms;
+                self.streak = if self.streak > 0 && within {
+                    self.streak + 1
+                } else {
+                    1
+                };
+                self.last_ms = at_ms;
+                self.streak.min(rules.max_multiplier.max(1))
+            }
+            Move::Transfer { .. } => 1,
+        }
+    }
+}
+
+/// Resource holding the score of the current game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Score {
+    points: i32,
+    // The lowest the score can go, if anywhere.
+    floor: Option<i32>,
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Score {
+            points: 0,
+            floor: Some(0),
+        }
+    }
+}
+
+impl Score {
+    pub fn points(&self) -> i32 {
+        self.points
+    }
+
+    /// Add `delta` points, stopping at zero unless the scoring mode allows a
+    /// negative score. Returns the change actually made, which is what
+    /// undoing has to take back.
+    pub(crate) fn add(&mut self, delta: i32) -> i32 {
+        let before = self.points;
+        self.points += delta;
+        if let Some(floor) = self.floor {
+            self.points = self.points.max(floor);
+        }
+        self.points - before
+    }
+}
+
+/// Resource configuring the bonus awarded for a fast win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TimeBonus {
+    /// Divided by the game's length in seconds.
+    pub points: u32,
+    /// Games this short or shorter get no bonus at all.
+    pub min_seconds: u32,
+}
+
+impl Default for TimeBonus {
+    fn default() -> Self {
+        TimeBonus::STANDARD
+    }
+}
+
+impl TimeBonus {
+    /// The classic bonus of desktop Klondike.
+    pub const STANDARD: TimeBonus = TimeBonus {
+        points: 700_000,
+        min_seconds: 30,
+    };
+
+    /// No bonus, for variants that do not reward speed.
+    pub const NONE: TimeBonus = TimeBonus {
+        points: 0,
+        min_seconds: 0,
+    };
+
+    /// The bonus for a game won after `elapsed_ms` milliseconds.
+    pub fn award(&self, elapsed_ms: f64) -> u32 {
+        let seconds = (elapsed_ms / 1000.0).floor() as u32;
+        if seconds > self.min_seconds {
+            self.points / seconds
+        } else {
+            0
+        }
+    }
+}
+
+/// Points for playing `mv`. `flipped` tells whether the move turned over a
+/// tableau card.
+pub fn move_points(mv: Move, flipped: bool) -> i32 {
+    ScoringMode::Standard.move_points(mv, flipped)
+}
+
+fn move_scores(mv: Move, flipped: bool) -> Vec<(ScoreReason, i32)> {
+    let mut scores = Vec::new();
+    match mv {
+        Move::Draw => {}
+        Move::Recycle => scores.push((ScoreReason::Recycle, -100)),
+        Move::Transfer { from, to, .. } => match (from, to) {
+            (Pile::Waste, Pile::Tableau(_)) => scores.push((ScoreReason::WasteToTableau, 5)),
+            // The rules refuse this; it earns nothing in any case.
+            (Pile::Foundation(_), Pile::Foundation(_)) => {}
+            (_, Pile::Foundation(_)) => scores.push((ScoreReason::ToFoundation, 10)),
+            (Pile::Foundation(_), Pile::Tableau(_)) => {
+                scores.push((ScoreReason::FromFoundation, -15))
+            }
+            _ => {}
+        },
+    }
+    if flipped {
+        scores.push((ScoreReason::Flip, 5));
+    }
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_bonus_needs_more_than_thirty_seconds() {
+        let bonus = TimeBonus::default();
+        assert_eq!(bonus.award(30_999.0), 0);
+        assert_eq!(bonus.award(31_000.0), 22_580);
+        assert_eq!(bonus.award(700_000.0), 1_000);
+        assert_eq!(TimeBonus::NONE.award(60_000.0), 0);
+    }
+
+    #[test]
+    fn score_stops_at_zero() {
+        let mut score = Score::default();
+        assert_eq!(score.add(15), 15);
+        assert_eq!(score.add(-100), -15);
+        assert_eq!(score.points(), 0);
+        let flip_to_foundation = Move::Transfer {
+            from: Pile::Tableau(2),
+            to: Pile::Foundation(0),
+            count: 1,
+        };
+        assert_eq!(move_points(flip_to_foundation, true), 15);
+        assert_eq!(
+            ScoringMode::Standard.move_scores(flip_to_foundation, true),
+            [(ScoreReason::ToFoundation, 10), (ScoreReason::Flip, 5)]
+        );
+    }
+
+    #[test]
+    fn combos_need_quick_plays_without_draws() {
+        let rules = ComboRules::default();
+        let play = Move::Transfer {
+            from: Pile::Waste,
+            to: Pile::Foundation(0),
+            count: 1,
+        };
+        let mut combo = Combo::default();
+        assert_eq!(combo.advance(play, 0.0, &rules), 1);
+        assert_eq!(combo.advance(play, 1_000.0, &rules), 2);
+        assert_eq!(combo.advance(play, 5_000.0, &rules), 1);
+        assert_eq!(combo.advance(play, 6_000.0, &rules), 2);
+        assert_eq!(combo.advance(Move::Draw, 6_500.0, &rules), 1);
+        assert_eq!(combo.advance(play, 7_000.0, &rules), 1);
+        for t in 1..10 {
+            combo.advance(play, 7_000.0 + f64::from(t), &rules);
+        }
+        assert_eq!(combo.streak(), 10);
+        assert_eq!(combo.advance(play, 7_100.0, &rules), 5);
+    }
+
+    #[test]
+    fn vegas_scores_foundation_cards_and_can_go_negative() {
+        let mut score = ScoringMode::Vegas.starting_score(1);
+        let to_foundation = Move::Transfer {
+            from: Pile::Waste,
+            to: Pile::Foundation(0),
+            count: 1,
+        };
+        score.add(ScoringMode::Vegas.move_points(to_foundation, true));
+        assert_eq!(score.points(), -47);
+        assert_eq!(ScoringMode::Vegas.move_points(Move::Recycle, false), 0);
+        assert_eq!(ScoringMode::None.move_points(to_foundation, true), 0);
+    }
+}
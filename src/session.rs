@@ -0,0 +1,136 @@
+// Picking a match up again after the connection drops.
+//
+// When the server accepts a connection it hands out a resume token in a
+// `SessionStarted` message. The token is kept, in storage too so a reload
+// keeps it, and presented in a `Resume` message as soon as a later
+// connection opens, so the server can put the client back into its match
+// rather than start a fresh session.
+//
+// Whatever happened while the client was away is then caught up on: once
+// the server confirms with `Resumed`, the client asks for a `CatchUp` from the
+// last co-op tick it played and takes over the roster and co-op timeline it
+// brings. A refused token is forgotten.
+
+use crate::dirty;
+use crate::ecs::World;
+use crate::inbox::Roster;
+use crate::protocol::NetMessage;
+use crate::rollback;
+use crate::storage;
+
+/// Storage key of the resume token.
+pub const RESUME_TOKEN_KEY: &str = "solitaire.resume_token";
+
+/// Resource with the token to resume the session with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    pub resume_token: Option<String>,
+    /// Whether a `Resume` is waiting for the connection to open.
+    pub resume_pending: bool,
+}
+
+/// The `Session` of `world`, loaded from storage the first time.
+pub fn session_mut(world: &mut World) -> &mut Session {
+    if world.resource::<Session>().is_none() {
+        let resume_token = storage::load(RESUME_TOKEN_KEY).filter(|t| !t.is_empty());
+        world.insert_resource(Session {
+            resume_token,
+            resume_pending: false,
+        });
+    }
+    world.resource_mut::<Session>().expect("inserted above")
+}
+
+/// A new connection was made: resume on it once it opens, if there is a
+/// token.
+pub fn connected(world: &mut World) {
+    let session = session_mut(world);
+    session.resume_pending = session.resume_token.is_some();
+}
+
+/// The `Resume` to send now that the connection is open, if one is due.
+pub fn take_resume(world: &mut World) -> Option<NetMessage> {
+    let session = session_mut(world);
+    if !std::mem::take(&mut session.resume_pending) {
+        return None;
+    }
+    let token = session.resume_token.clone()?;
+    Some(NetMessage::Resume { token })
+}
+
+/// Forget the resume token, here and in storage.
+pub fn forget(world: &mut World) {
+    *session_mut(world) = Session::default();
+    storage::save(RESUME_TOKEN_KEY, "");
+}
+
+/// React to a session message from the server, returning the reply to
+/// send, if any.
+pub fn handle(world: &mut World, message: NetMessage) -> Option<NetMessage> {
+    match message {
+        NetMessage::SessionStarted { resume_token } => {
+            storage::save(RESUME_TOKEN_KEY, &resume_token);
+            session_mut(world).resume_token = Some(resume_token);
+            None
+        }
+        NetMessage::Resumed => {
+            let since_tick = world.resource::<rollback::Coop>().map_or(0, |c| c.tick());
+            Some(NetMessage::RequestCatchUp { since_tick })
+        }
+        NetMessage::ResumeFailed => {
+            forget(world);
+            None
+        }
+        NetMessage::CatchUp { roster, timeline } => {
+            world.insert_resource(Roster { players: roster });
+            if let Some(timeline) = timeline
+                && rollback::adopt(world, timeline)
+            {
+                dirty::invalidate(world);
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_dropped_connection_resumes_with_its_token_and_catches_up() {
+        let mut world = World::new();
+        // The first connection has nothing to resume.
+        connected(&mut world);
+        assert_eq!(take_resume(&mut world), None);
+
+        let started = NetMessage::SessionStarted {
+            resume_token: "r-42".into(),
+        };
+        assert_eq!(handle(&mut world, started), None);
+        connected(&mut world);
+        assert_eq!(
+            take_resume(&mut world),
+            Some(NetMessage::Resume {
+                token: "r-42".into()
+            })
+        );
+        assert_eq!(take_resume(&mut world), None);
+
+        assert_eq!(
+            handle(&mut world, NetMessage::Resumed),
+            Some(NetMessage::RequestCatchUp { since_tick: 0 })
+        );
+        let catch_up = NetMessage::CatchUp {
+            roster: vec!["ann".into()],
+            timeline: None,
+        };
+        handle(&mut world, catch_up);
+        assert_eq!(world.resource::<Roster>().unwrap().players, ["ann"]);
+
+        handle(&mut world, NetMessage::ResumeFailed);
+        connected(&mut world);
+        assert_eq!(take_resume(&mut world), None);
+    }
+}
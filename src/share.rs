@@ -0,0 +1,119 @@
+// Sharing deals and results as text.
+//
+// A deal is shared as its deal code, which recreates it exactly. A result
+// is a one-line summary such as "I won Klondike in 112 moves / 3:41 (score
+// 640)" followed by the code, so whoever reads it can try the same deal.
+// The moves and time of a win are kept from its `GameWon` event: the game
+// clock carries on running afterwards.
+//
+// In the browser both go through `navigator.clipboard` with `copy_text`.
+
+use crate::clock::GameClock;
+use crate::ecs::World;
+use crate::events::GameEvent;
+use crate::rules::History;
+use crate::scoring::Score;
+use crate::variant::Variant;
+
+/// Resource with how the current game was won.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Won {
+    moves: u32,
+    time_ms: u64,
+}
+
+/// Remember the result of a win.
+pub fn handle(world: &mut World, event: &GameEvent) {
+    if let GameEvent::GameWon { moves, time_ms, .. } = *event {
+        world.insert_resource(Won { moves, time_ms });
+    }
+}
+
+/// A one-line summary of the game so far, or of the win, at `now_ms`.
+pub fn result_summary(world: &World, now_ms: f64, variant: Variant, deal_code: &str) -> String {
+    let score = world.resource::<Score>().map_or(0, Score::points);
+    match world.resource::<Won>() {
+        Some(won) => format!(
+            "I won {} in {} moves / {} (score {score}) · deal {deal_code}",
+            variant.name(),
+            won.moves,
+            format_time(won.time_ms as f64),
+        ),
+        None => format!(
+            "Playing {}: {} moves / {} so far (score {score}) · deal {deal_code}",
+            variant.name(),
+            world.resource::<History>().map_or(0, History::moves),
+            format_time(
+                world
+                    .resource::<GameClock>()
+                    .map_or(0.0, |c| c.elapsed_ms(now_ms))
+            ),
+        ),
+    }
+}
+
+/// `ms` as minutes and seconds, "3:41", with hours in front once there
+/// are any, "1:02:09".
+pub fn format_time(ms: f64) -> String {
+    let seconds = (ms / 1000.0).max(0.0) as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+#[cfg(feature = "wasm")]
+pub use web::copy_text;
+
+#[cfg(feature = "wasm")]
+mod web {
+    use js_sys::{Promise, Reflect};
+    use wasm_bindgen::JsValue;
+
+    /// Write `text` to the clipboard. The promise rejects where the
+    /// clipboard cannot be written, as on pages not served securely.
+    pub fn copy_text(text: &str) -> Promise {
+        let navigator = web_sys::window().map(|w| w.navigator());
+        match navigator {
+            Some(navigator)
+                if Reflect::get(&navigator, &JsValue::from_str("clipboard"))
+                    .is_ok_and(|c| !c.is_undefined()) =>
+            {
+                navigator.clipboard().write_text(text)
+            }
+            _ => Promise::reject(&JsValue::from_str("No clipboard")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summaries_carry_moves_time_and_the_deal() {
+        let mut world = World::new();
+        world.insert_resource(GameClock::start(0.0));
+        assert_eq!(
+            result_summary(&world, 130_500.0, Variant::Klondike, "code"),
+            "Playing Klondike: 0 moves / 2:10 so far (score 0) · deal code"
+        );
+
+        handle(
+            &mut world,
+            &GameEvent::GameWon {
+                moves: 112,
+                undos: 0,
+                time_ms: 221_000,
+                time_bonus: 0,
+            },
+        );
+        assert_eq!(
+            result_summary(&world, 900_000.0, Variant::DoubleKlondike, "code"),
+            "I won Double Klondike in 112 moves / 3:41 (score 0) · deal code"
+        );
+        assert_eq!(format_time(3_729_000.0), "1:02:09");
+    }
+}
@@ -0,0 +1,47 @@
+// Hashing and signing helpers shared by the leaderboard and the audit log.
+//
+// Both use SHA-256 based primitives and exchange digests as lowercase hex
+// strings, which survive JSON and JavaScript without any escaping issues.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+/// HMAC-SHA256 of `data` keyed with `key`, hex encoded.
+pub fn hmac_hex(key: &str, data: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    hex(&mac.finalize().into_bytes())
+}
+
+/// Whether `signature` is the hex encoded HMAC-SHA256 of `data` keyed with
+/// `key`. The MACs are compared in constant time, so how long a check takes
+/// says nothing about how close a forgery came.
+pub fn verify_hmac_hex(key: &str, data: &[u8], signature: &str) -> bool {
+    let Some(expected) = unhex(signature) else {
+        return false;
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// SHA-256 of `data`, hex encoded.
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
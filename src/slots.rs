@@ -0,0 +1,148 @@
+// Named save slots, for keeping several games in progress.
+//
+// Each slot holds a `SavedGame` under a key of its own, `slot_key`, and the
+// index of the slots is kept under one more key, so the list can be shown
+// without loading every game. The index has what a save screen shows of a
+// slot: its variant, when it was saved and a short description of the board
+// to stand in for a thumbnail, such as "Klondike, 12 of 52 cards home,
+// 40 moves".
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::error::GameError;
+use crate::progress::BoardSummary;
+use crate::rules::History;
+use crate::save::SavedGame;
+use crate::variant::Variant;
+
+/// What the index keeps of a slot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlotInfo {
+    pub name: String,
+    pub variant: Variant,
+    /// Milliseconds since the Unix epoch when it was saved.
+    pub saved_ms: f64,
+    pub moves: u32,
+    pub cards_home: u32,
+    pub description: String,
+}
+
+impl SlotInfo {
+    /// The index entry for saving the game of `world` as `saved` in `name`.
+    pub fn of(name: &str, world: &World, saved: &SavedGame) -> Self {
+        let summary = BoardSummary::of(world);
+        let moves = world.resource::<History>().map_or(0, History::moves);
+        let variant = saved.options.variant;
+        SlotInfo {
+            name: name.to_string(),
+            variant,
+            saved_ms: saved.saved_ms,
+            moves,
+            cards_home: summary.cards_home(),
+            description: format!(
+                "{}, {} of {} cards home, {moves} {}",
+                variant.name(),
+                summary.cards_home(),
+                summary.cards,
+                if moves == 1 { "move" } else { "moves" }
+            ),
+        }
+    }
+}
+
+/// The index of the slots.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SaveSlots {
+    slots: Vec<SlotInfo>,
+}
+
+impl SaveSlots {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("slot indexes always serialize")
+    }
+
+    /// The slots, most recently saved first.
+    pub fn list(&self) -> Vec<&SlotInfo> {
+        let mut slots: Vec<_> = self.slots.iter().collect();
+        slots.sort_by(|a, b| b.saved_ms.total_cmp(&a.saved_ms));
+        slots
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SlotInfo> {
+        self.slots.iter().find(|slot| slot.name == name)
+    }
+
+    /// Add `info` to the index, in place of the slot of the same name.
+    pub fn put(&mut self, info: SlotInfo) {
+        self.remove(&info.name);
+        self.slots.push(info);
+    }
+
+    /// Take the slot `name` out of the index, returning whether it was in.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.slots.len();
+        self.slots.retain(|slot| slot.name != name);
+        self.slots.len() != before
+    }
+}
+
+/// The storage key of the slot `name`.
+pub fn slot_key(name: &str) -> String {
+    format!("solitaire.slot.{name}")
+}
+
+/// Check that `name` can name a slot: it must not be blank.
+pub fn validate_name(name: &str) -> Result<&str, GameError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(GameError::InvalidOptions(
+            "save slots need a name".to_string(),
+        ));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::GameOptions;
+    use crate::rules::{self, Move};
+
+    #[test]
+    fn slots_are_described_and_listed_newest_first() {
+        let mut world = World::new();
+        rules::deal_from_seed(&mut world, 3);
+        rules::apply(&mut world, Move::Draw).unwrap();
+        let saved = |saved_ms| SavedGame {
+            revision: 1,
+            saved_ms,
+            options: GameOptions::default(),
+            actions: Vec::new(),
+            elapsed_ms: 0.0,
+            history: None,
+        };
+
+        let mut slots = SaveSlots::default();
+        slots.put(SlotInfo::of("morning", &world, &saved(1_000.0)));
+        slots.put(SlotInfo::of("evening", &world, &saved(3_000.0)));
+        slots.put(SlotInfo::of("morning", &world, &saved(5_000.0)));
+        let slots = SaveSlots::from_json(&slots.to_json()).unwrap();
+        let names: Vec<_> = slots.list().iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["morning", "evening"]);
+        assert_eq!(
+            slots.get("evening").unwrap().description,
+            "Klondike, 0 of 52 cards home, 1 move"
+        );
+
+        let mut slots = slots;
+        assert!(slots.remove("evening") && !slots.remove("evening"));
+        assert_eq!(validate_name("  lunch "), Ok("lunch"));
+        assert!(validate_name(" ").is_err());
+    }
+}
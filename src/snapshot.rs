@@ -0,0 +1,162 @@
+// Snapshots of the board, for sharing.
+//
+// `board_svg` draws the table as it is laid out right now into a single SVG
+// document, in the colours of the current theme: the table background, an
+// outline for every pile, the cards from the bottom up with the faces from
+// `faces` and the score and move counter. Face-down cards show their backs,
+// and so does every card while a pause hides the board, so a snapshot never
+// gives more away than the screen does.
+//
+// In the browser, `export_png` turns that drawing into a PNG: it decodes the
+// SVG as an image, paints it onto an `OffscreenCanvas` at the screen's pixel
+// ratio and encodes the canvas as a `Blob`. Images decoded that way do not
+// load anything else, so texture backgrounds and back images only show in
+// the PNG when they are `data:` URLs, as the felt is.
+
+use crate::ecs::World;
+use crate::faces;
+use crate::game::{Card, FaceUp};
+use crate::layout::{self, CanvasSize, TableLayout};
+use crate::rules::{self, History, Paused};
+use crate::scoring::Score;
+use crate::theme::{self, Background, TableBackground};
+
+/// The board as a standalone SVG document, `CanvasSize` large.
+pub fn board_svg(world: &mut World) -> String {
+    let canvas = world.resource::<CanvasSize>().copied().unwrap_or_default();
+    let mut cards = TableLayout::compute(world).cards;
+    cards.sort_by_key(|c| c.position.z);
+    let metrics = layout::metrics(world);
+    let (width, height) = (metrics.card_width, metrics.card_height);
+    let hidden = world.resource::<Paused>().is_some_and(|p| p.hide_cards);
+    let theme = theme::current(world);
+    let suits = faces::suit_style(world);
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {w} {h}" width="{w}" height="{h}">{}{}"#,
+        faces::back_defs(&theme.card_back),
+        table_svg(&theme.table_background(), canvas),
+        w = canvas.width,
+        h = canvas.height,
+    );
+    for pile in rules::all_piles(world) {
+        let (x, y) = metrics.pile_origin(pile);
+        svg.push_str(&format!(
+            r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" rx="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+            width * theme.card_radius,
+            theme.line_color,
+        ));
+    }
+    let scale = (width / faces::WIDTH, height / faces::HEIGHT);
+    for placed in cards {
+        let face_up = world
+            .get_component::<FaceUp>(placed.entity)
+            .is_some_and(|f| f.0);
+        let content = match world.get_component::<Card>(placed.entity) {
+            Some(&card) if face_up && !hidden => faces::card_content(card, suits),
+            _ => faces::back_content(&theme.card_back),
+        };
+        svg.push_str(&format!(
+            r#"<g transform="translate({} {}) scale({} {})">{content}</g>"#,
+            placed.position.x, placed.position.y, scale.0, scale.1
+        ));
+    }
+    svg.push_str(&format!(
+        r#"<text x="{}" y="{}" fill="{}" font-family="{}" font-size="{}">Score {} · Moves {}</text></svg>"#,
+        metrics.card_width * 0.2,
+        canvas.height - metrics.card_width * 0.2,
+        theme.text_color,
+        theme.font_family,
+        (metrics.card_width * 0.25).max(12.0),
+        world.resource::<Score>().map_or(0, Score::points),
+        world.resource::<History>().map_or(0, History::moves),
+    ));
+    svg
+}
+
+/// The table background, covering the whole canvas.
+fn table_svg(table: &TableBackground, canvas: CanvasSize) -> String {
+    let (w, h) = (canvas.width, canvas.height);
+    let mut svg = format!(r#"<rect width="{w}" height="{h}" fill="{}"/>"#, table.color);
+    let paint = match &table.background {
+        Background::Solid | Background::Felt { .. } => return svg,
+        Background::Gradient { center, edge } => format!(
+            r#"<radialGradient id="table"><stop offset="0" stop-color="{center}"/><stop offset="1" stop-color="{edge}"/></radialGradient>"#
+        ),
+        Background::Texture { url, tile_size: t } => format!(
+            r#"<pattern id="table" patternUnits="userSpaceOnUse" width="{t}" height="{t}"><image href="{}" width="{t}" height="{t}"/></pattern>"#,
+            faces::escape_attribute(url)
+        ),
+    };
+    svg.push_str(&format!(
+        r#"<defs>{paint}</defs><rect width="{w}" height="{h}" fill="url(#table)"/>"#
+    ));
+    svg
+}
+
+#[cfg(feature = "wasm")]
+pub use web::export_png;
+
+#[cfg(feature = "wasm")]
+mod web {
+    use js_sys::Promise;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_futures::{JsFuture, future_to_promise};
+    use web_sys::{HtmlImageElement, OffscreenCanvas, OffscreenCanvasRenderingContext2d};
+
+    /// Paint `svg`, `width` by `height` CSS pixels, at `pixel_ratio` and
+    /// resolve to the PNG as a `Blob`. Needs a page, for decoding the SVG.
+    pub fn export_png(svg: String, width: f32, height: f32, pixel_ratio: f32) -> Promise {
+        future_to_promise(async move {
+            let image = HtmlImageElement::new()?;
+            let loaded = Promise::new(&mut |resolve, reject| {
+                image.set_onload(Some(&resolve));
+                image.set_onerror(Some(&reject));
+            });
+            let encoded = js_sys::encode_uri_component(&svg);
+            image.set_src(&format!("data:image/svg+xml;charset=utf-8,{encoded}"));
+            JsFuture::from(loaded).await?;
+
+            let (w, h) = (
+                (width * pixel_ratio).round(),
+                (height * pixel_ratio).round(),
+            );
+            let canvas = OffscreenCanvas::new(w as u32, h as u32)?;
+            let context: OffscreenCanvasRenderingContext2d = canvas
+                .get_context("2d")?
+                .ok_or_else(|| JsValue::from_str("No 2D context"))?
+                .dyn_into()?;
+            context.draw_image_with_html_image_element_and_dw_and_dh(
+                &image,
+                0.0,
+                0.0,
+                f64::from(w),
+                f64::from(h),
+            )?;
+            JsFuture::from(canvas.convert_to_blob()?).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Deck;
+
+    #[test]
+    fn snapshots_show_what_the_player_sees() {
+        let mut world = World::new();
+        rules::deal(&mut world, &Deck::standard().cards);
+        let svg = board_svg(&mut world);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        assert_eq!(svg.matches("<g transform=\"translate(").count(), 52);
+        // The 24 stock cards and the 21 covered tableau cards.
+        assert_eq!(svg.matches("url(#lattice)").count(), 45);
+        // The felt of the default theme, tiled beneath the piles.
+        assert!(svg.contains(r#"<pattern id="table""#) && svg.contains(r#"fill="url(#table)""#));
+
+        world.insert_resource(Paused { hide_cards: true });
+        assert_eq!(board_svg(&mut world).matches("url(#lattice)").count(), 52);
+    }
+}
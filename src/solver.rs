@@ -0,0 +1,667 @@
+// A bounded Klondike solver.
+//
+// Searching the ECS `World` directly would be far too slow: every move
+// generation would walk the component stores. Instead the solver copies the
+// board into a small `Position` value of plain vectors, then runs a
+// depth-first search over it, remembering every position it has seen so
+// draw/recycle cycles cannot trap it.
+//
+// Positions travel to the worker as JSON, with every card written as its
+// `Card::to_index`.
+//
+// Single-deck boards are packed into a `Bitboard` for the search itself,
+// which generates the same moves without allocating a position per node.
+// The moves still to try at every node on the search stack share one
+// `StackArena`, released as the search backtracks.
+//
+// The search is bounded by a node budget. Running out of budget yields
+// `Outcome::Unknown`; only a search that tried everything reports `Dead`.
+//
+// The solver knows nothing about the browser. On the web it is meant to run
+// inside a Web Worker (see `worker.rs`) so a long search never stalls the
+// frame loop.
+
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use rand::RngCore;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::arena::{Span, StackArena};
+use crate::bitboard::Bitboard;
+use crate::clock;
+use crate::ecs::World;
+use crate::game::{Card, FaceUp, Pile, Rank, card_index};
+use crate::rng::GameRng;
+use crate::rules::{self, Move};
+use crate::variant::{self, Table};
+
+/// Nodes explored by default before the solver gives up.
+pub const DEFAULT_NODE_LIMIT: usize = 200_000;
+
+/// One tableau pile, split into its face-down and face-up parts. Both run
+/// from the bottom card to the top card.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TableauPile {
+    #[serde(with = "card_index::vec")]
+    pub hidden: Vec<Card>,
+    #[serde(with = "card_index::vec")]
+    pub face_up: Vec<Card>,
+}
+
+/// A compact copy of the board for searching. Every pile runs from the bottom
+/// card to the top card.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Position {
+    #[serde(with = "card_index::vec")]
+    pub stock: Vec<Card>,
+    #[serde(with = "card_index::vec")]
+    pub waste: Vec<Card>,
+    #[serde(with = "card_index::nested")]
+    pub foundations: Vec<Vec<Card>>,
+    pub tableau: Vec<TableauPile>,
+    /// The table's house rules: draw count, redeal limit and foundation
+    /// base.
+    #[serde(default)]
+    pub table: Table,
+    /// Recycles made so far, counted against the redeal limit.
+    #[serde(default)]
+    pub recycles: u32,
+}
+
+/// What the solver found out about a position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Outcome {
+    /// The moves win the game when played in order.
+    Winnable { moves: Vec<Move> },
+    /// The search ran out of budget before reaching a verdict.
+    Unknown,
+    /// No sequence of moves wins from here.
+    Dead,
+}
+
+impl Outcome {
+    /// The verdict alone: `"winnable"`, `"unknown"` or `"dead"`.
+    pub fn verdict(&self) -> &'static str {
+        match self {
+            Outcome::Winnable { .. } => "winnable",
+            Outcome::Unknown => "unknown",
+            Outcome::Dead => "dead",
+        }
+    }
+}
+
+impl Position {
+    /// An empty Klondike board.
+    pub fn empty() -> Self {
+        Position::empty_table(&Table::KLONDIKE)
+    }
+
+    /// An empty board with the piles of `table`.
+    pub fn empty_table(table: &Table) -> Self {
+        Position {
+            stock: Vec::new(),
+            waste: Vec::new(),
+            foundations: vec![Vec::new(); usize::from(table.foundation_piles)],
+            tableau: vec![TableauPile::default(); usize::from(table.tableau_piles)],
+            table: *table,
+            recycles: 0,
+        }
+    }
+
+    /// Copy the board out of a world dealt by `rules::deal`.
+    pub fn from_world(world: &World) -> Self {
+        let mut position = Position::empty_table(&variant::table(world));
+        position.recycles = world
+            .resource::<rules::History>()
+            .map_or(0, rules::History::recycles);
+        let card = |entity| {
+            *world
+                .get_component::<Card>(entity)
+                .expect("every card entity has a Card")
+        };
+        position.stock = rules::pile_cards(world, Pile::Stock)
+            .into_iter()
+            .map(card)
+            .collect();
+        position.waste = rules::pile_cards(world, Pile::Waste)
+            .into_iter()
+            .map(card)
+            .collect();
+        for (i, foundation) in position.foundations.iter_mut().enumerate() {
+            *foundation = rules::pile_cards(world, Pile::Foundation(i as u8))
+                .into_iter()
+                .map(card)
+                .collect();
+        }
+        for (i, pile) in position.tableau.iter_mut().enumerate() {
+            for entity in rules::pile_cards(world, Pile::Tableau(i as u8)) {
+                if world.get_component::<FaceUp>(entity).is_some_and(|f| f.0) {
+                    pile.face_up.push(card(entity));
+                } else {
+                    pile.hidden.push(card(entity));
+                }
+            }
+        }
+        position
+    }
+
+    /// The opening position of the deal shuffled with `deal_seed`.
+    pub fn from_deal_seed(deal_seed: u64) -> Self {
+        let mut world = World::new();
+        rules::deal_from_seed(&mut world, deal_seed);
+        Position::from_world(&world)
+    }
+
+    /// Whether every card has reached the foundations.
+    pub fn is_won(&self) -> bool {
+        self.stock.is_empty()
+            && self.waste.is_empty()
+            && self
+                .tableau
+                .iter()
+                .all(|p| p.hidden.is_empty() && p.face_up.is_empty())
+    }
+
+    /// Every move worth trying here, most promising first.
+    ///
+    /// Moving the first or second card of a foundation (an Ace or a Two,
+    /// usually) can never hurt, so when one is available it is the only move
+    /// returned.
+    pub fn moves(&self) -> Vec<Move> {
+        let mut to_foundation = Vec::new();
+        let mut exposing = Vec::new();
+        let mut others = Vec::new();
+
+        let sources = std::iter::once((Pile::Waste, self.waste.last().copied())).chain(
+            self.tableau
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (Pile::Tableau(i as u8), p.face_up.last().copied())),
+        );
+        for (from, top) in sources {
+            let Some(card) = top else { continue };
+            if let Some(f) = self.foundation_for(card) {
+                let mv = Move::Transfer {
+                    from,
+                    to: Pile::Foundation(f),
+                    count: 1,
+                };
+                let base = self.table.foundation_base;
+                if card.rank == base || card.rank == base.next_wrapping() {
+                    return vec![mv];
+                }
+                to_foundation.push(mv);
+            }
+        }
+
+        for (i, pile) in self.tableau.iter().enumerate() {
+            let len = pile.face_up.len();
+            for count in 1..=len {
+                let card = pile.face_up[len - count];
+                let clears = count == len;
+                // Shifting a whole pile onto an empty pile changes nothing.
+                if clears && pile.hidden.is_empty() && card.rank == Rank::King {
+                    continue;
+                }
+                for (j, target) in self.tableau.iter().enumerate() {
+                    if i != j && fits_tableau(card, target) {
+                        let mv = Move::Transfer {
+                            from: Pile::Tableau(i as u8),
+                            to: Pile::Tableau(j as u8),
+                            count,
+                        };
+                        if clears && !pile.hidden.is_empty() {
+                            exposing.push(mv);
+                        } else {
+                            others.push(mv);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(&card) = self.waste.last() {
+            for (j, target) in self.tableau.iter().enumerate() {
+                if fits_tableau(card, target) {
+                    others.push(Move::Transfer {
+                        from: Pile::Waste,
+                        to: Pile::Tableau(j as u8),
+                        count: 1,
+                    });
+                }
+            }
+        }
+
+        if !self.stock.is_empty() {
+            others.push(Move::Draw);
+        } else if !self.waste.is_empty()
+            && self
+                .table
+                .redeal_limit
+                .is_none_or(|limit| self.recycles < limit)
+        {
+            others.push(Move::Recycle);
+        }
+
+        // Taking cards back off the foundations is rarely right, so it comes
+        // last.
+        for (f, foundation) in self.foundations.iter().enumerate() {
+            let Some(&card) = foundation.last() else {
+                continue;
+            };
+            for (j, target) in self.tableau.iter().enumerate() {
+                if fits_tableau(card, target) {
+                    others.push(Move::Transfer {
+                        from: Pile::Foundation(f as u8),
+                        to: Pile::Tableau(j as u8),
+                        count: 1,
+                    });
+                }
+            }
+        }
+
+        to_foundation.extend(exposing);
+        to_foundation.extend(others);
+        to_foundation
+    }
+
+    /// Whether the player is stuck: no card can move anywhere, and no card
+    /// still to come from the stock or waste could be played either. Drawing
+    /// and recycling only count as moves when they can turn up such a card.
+    pub fn is_stuck(&self) -> bool {
+        let playable = |card: Card| {
+            self.foundation_for(card).is_some()
+                || self.tableau.iter().any(|t| fits_tableau(card, t))
+        };
+        if self
+            .moves()
+            .iter()
+            .any(|mv| !matches!(mv, Move::Draw | Move::Recycle))
+        {
+            return false;
+        }
+        let can_recycle = self
+            .table
+            .redeal_limit
+            .is_none_or(|limit| self.recycles < limit);
+        // Without another recycle, only the stock is still to come.
+        let mut upcoming: Vec<Card> = self.stock.clone();
+        if can_recycle {
+            upcoming.extend_from_slice(&self.waste);
+        }
+        !upcoming.into_iter().any(playable)
+    }
+
+    /// Play a move returned by `moves`.
+    pub fn apply(&mut self, mv: Move) {
+        match mv {
+            Move::Draw => {
+                assert!(!self.stock.is_empty(), "draw needs a stock card");
+                let count = usize::from(self.table.draw_count.max(1));
+                let start = self.stock.len().saturating_sub(count);
+                self.waste.extend(self.stock.drain(start..).rev());
+            }
+            Move::Recycle => {
+                self.stock = self.waste.drain(..).rev().collect();
+                if self.table.reshuffle_on_recycle {
+                    let seed = rules::reshuffle_seed(self.stock.iter().copied(), self.recycles);
+                    self.stock.shuffle(&mut GameRng::from_seed(seed));
+                }
+                self.recycles += 1;
+            }
+            Move::Transfer { from, to, count } => {
+                let cards = self.take(from, count);
+                match to {
+                    Pile::Foundation(f) => self.foundations[f as usize].extend(cards),
+                    Pile::Tableau(t) => self.tableau[t as usize].face_up.extend(cards),
+                    Pile::Stock | Pile::Waste => unreachable!("cards never move onto {to}"),
+                }
+            }
+        }
+    }
+
+    fn take(&mut self, from: Pile, count: usize) -> Vec<Card> {
+        let source = match from {
+            Pile::Waste => &mut self.waste,
+            Pile::Foundation(f) => &mut self.foundations[f as usize],
+            Pile::Tableau(t) => &mut self.tableau[t as usize].face_up,
+            Pile::Stock => unreachable!("the stock is only drawn from"),
+        };
+        let cards = source.split_off(source.len() - count);
+        if let Pile::Tableau(t) = from {
+            let pile = &mut self.tableau[t as usize];
+            if pile.face_up.is_empty()
+                && let Some(card) = pile.hidden.pop()
+            {
+                pile.face_up.push(card);
+            }
+        }
+        cards
+    }
+
+    /// The foundation `card` can be played to, if any.
+    fn foundation_for(&self, card: Card) -> Option<u8> {
+        let fits = |(f, foundation): &(usize, &Vec<Card>)| match foundation.last() {
+            None => {
+                card.rank == self.table.foundation_base
+                    && self
+                        .table
+                        .foundation_suit(*f as u8)
+                        .is_none_or(|suit| suit == card.suit)
+            }
+            Some(top) => top.suit == card.suit && top.rank.next_wrapping() == card.rank,
+        };
+        // Base cards go to the first empty foundation they may start, like
+        // `rules` expects.
+        self.foundations
+            .iter()
+            .enumerate()
+            .find(fits)
+            .map(|(f, _)| f as u8)
+    }
+}
+
+/// A board the search can explore.
+trait Node: Clone {
+    /// What is remembered of a position once it has been seen.
+    type Key: Eq + Hash;
+
+    fn key(&self) -> Self::Key;
+    fn push_moves(&self, arena: &mut StackArena<Move>);
+    fn apply(&mut self, mv: Move);
+    fn is_won(&self) -> bool;
+}
+
+impl Node for Position {
+    type Key = u64;
+
+    fn key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn push_moves(&self, arena: &mut StackArena<Move>) {
+        arena.extend(Position::moves(self));
+    }
+
+    fn apply(&mut self, mv: Move) {
+        Position::apply(self, mv)
+    }
+
+    fn is_won(&self) -> bool {
+        Position::is_won(self)
+    }
+}
+
+impl Node for Bitboard {
+    type Key = Bitboard;
+
+    fn key(&self) -> Bitboard {
+        *self
+    }
+
+    fn push_moves(&self, arena: &mut StackArena<Move>) {
+        self.moves_into(arena);
+    }
+
+    fn apply(&mut self, mv: Move) {
+        Bitboard::apply(self, mv)
+    }
+
+    fn is_won(&self) -> bool {
+        Bitboard::is_won(self)
+    }
+}
+
+fn fits_tableau(card: Card, target: &TableauPile) -> bool {
+    match target.face_up.last() {
+        None => target.hidden.is_empty() && card.rank == Rank::King,
+        Some(top) => {
+            top.suit.is_red() != card.suit.is_red() && top.rank.value() == card.rank.value() + 1
+        }
+    }
+}
+
+/// A position on the search stack, with the moves still to try from it.
+struct Frame<N> {
+    position: N,
+    /// Every move from `position`, in `Run::moves`.
+    moves: Span,
+    next: usize,
+}
+
+/// Search for a winning line from `start`, exploring at most `node_limit`
+/// positions.
+pub fn solve(start: &Position, node_limit: usize) -> Outcome {
+    Search::new(start, node_limit)
+        .run(None)
+        .expect("a search without a pause runs to the end")
+}
+
+/// Like `solve`, but also gives up once `clock::now_ms()` passes
+/// `deadline_ms`, for probes that must answer within a time budget.
+pub fn solve_until(start: &Position, node_limit: usize, deadline_ms: f64) -> Outcome {
+    Search::new(start, node_limit)
+        .run(Some(deadline_ms))
+        .unwrap_or(Outcome::Unknown)
+}
+
+/// Nodes explored between two looks at the clock.
+const CLOCK_INTERVAL: usize = 256;
+
+/// A search that can stop and carry on later, so it can run a slice at a
+/// time between frames where there is no worker to run it.
+pub struct Search(Searching);
+
+enum Searching {
+    Packed(Run<Bitboard>),
+    Plain(Run<Position>),
+}
+
+impl Search {
+    /// Get ready to search from `start`, exploring at most `node_limit`
+    /// positions in all.
+    pub fn new(start: &Position, node_limit: usize) -> Self {
+        Search(match Bitboard::from_position(start) {
+            Some(board) => Searching::Packed(Run::new(&board, node_limit)),
+            None => Searching::Plain(Run::new(start, node_limit)),
+        })
+    }
+
+    /// Search on until there is an outcome, or until `clock::now_ms()`
+    /// passes `pause_ms`. `None` means the search paused and can be resumed
+    /// with another call.
+    pub fn step_until(&mut self, pause_ms: f64) -> Option<Outcome> {
+        self.run(Some(pause_ms))
+    }
+
+    fn run(&mut self, pause_ms: Option<f64>) -> Option<Outcome> {
+        match &mut self.0 {
+            Searching::Packed(run) => run.resume(pause_ms),
+            Searching::Plain(run) => run.resume(pause_ms),
+        }
+    }
+}
+
+/// The state of a search over one kind of `Node`.
+struct Run<N: Node> {
+    seen: HashSet<N::Key>,
+    path: Vec<Move>,
+    stack: Vec<Frame<N>>,
+    moves: StackArena<Move>,
+    nodes: usize,
+    node_limit: usize,
+    outcome: Option<Outcome>,
+}
+
+impl<N: Node> Run<N> {
+    fn new(start: &N, node_limit: usize) -> Self {
+        let won = start.is_won();
+        let mut run = Run {
+            seen: HashSet::from([start.key()]),
+            path: Vec::new(),
+            stack: Vec::new(),
+            moves: StackArena::new(),
+            nodes: 0,
+            node_limit,
+            outcome: won.then(|| Outcome::Winnable { moves: Vec::new() }),
+        };
+        if !won {
+            run.push(start.clone());
+        }
+        run
+    }
+
+    fn push(&mut self, position: N) {
+        let start = self.moves.top();
+        position.push_moves(&mut self.moves);
+        self.stack.push(Frame {
+            position,
+            moves: self.moves.span_from(start),
+            next: 0,
+        });
+    }
+
+    fn resume(&mut self, pause_ms: Option<f64>) -> Option<Outcome> {
+        if self.outcome.is_none() {
+            self.outcome = Some(self.explore(pause_ms)?);
+        }
+        self.outcome.clone()
+    }
+
+    fn explore(&mut self, pause_ms: Option<f64>) -> Option<Outcome> {
+        // An explicit stack rather than recursion: winning lines run to a few
+        // hundred moves, which is too deep for the wasm call stack.
+        while let Some(frame) = self.stack.last_mut() {
+            let Some(&mv) = self.moves.get(frame.moves).get(frame.next) else {
+                self.moves.release(frame.moves);
+                self.stack.pop();
+                self.path.pop();
+                continue;
+            };
+            frame.next += 1;
+
+            let mut next = frame.position.clone();
+            next.apply(mv);
+            if !self.seen.insert(next.key()) {
+                continue;
+            }
+            self.path.push(mv);
+            if next.is_won() {
+                return Some(Outcome::Winnable {
+                    moves: std::mem::take(&mut self.path),
+                });
+            }
+            self.nodes += 1;
+            if self.nodes >= self.node_limit {
+                return Some(Outcome::Unknown);
+            }
+            self.push(next);
+            if let Some(pause) = pause_ms
+                && self.nodes.is_multiple_of(CLOCK_INTERVAL)
+                && clock::now_ms() >= pause
+            {
+                return None;
+            }
+        }
+        Some(Outcome::Dead)
+    }
+}
+
+/// Try up to `attempts` game seeds, starting at `first_seed`, and return the
+/// first one whose opening deal the solver can win, with the winning moves.
+///
+/// The seed is a game seed: `SolitaireGame::set_seed(seed)` followed by
+/// `setup_board()` deals exactly the board that was solved.
+pub fn find_winnable_deal(
+    first_seed: u64,
+    attempts: u32,
+    node_limit: usize,
+) -> Option<(u64, Vec<Move>)> {
+    (0..attempts).find_map(|i| {
+        let seed = first_seed.wrapping_add(u64::from(i));
+        let deal_seed = GameRng::from_seed(seed).next_u64();
+        match solve(&Position::from_deal_seed(deal_seed), node_limit) {
+            Outcome::Winnable { moves } => Some((seed, moves)),
+            Outcome::Unknown | Outcome::Dead => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolitaireGame;
+    use crate::game::{Deck, Suit};
+
+    /// Everything on the foundations except the Kings, which sit face up on
+    /// the tableau.
+    fn almost_won() -> Position {
+        let mut position = Position::empty();
+        let deck = Deck::standard();
+        for (f, suit) in deck.cards.chunks(13).enumerate() {
+            position.foundations[f] = suit[..12].to_vec();
+            position.tableau[f].face_up.push(suit[12]);
+        }
+        position
+    }
+
+    #[test]
+    fn solves_an_almost_won_position() {
+        let Outcome::Winnable { moves } = solve(&almost_won(), 100) else {
+            panic!("expected a win");
+        };
+        assert_eq!(moves.len(), 4);
+    }
+
+    #[test]
+    fn reports_dead_positions() {
+        let mut position = Position::empty();
+        // The Two of Spades can go nowhere and buries the only Ace.
+        position.tableau[0] = TableauPile {
+            hidden: vec![Card::new(Suit::Spades, Rank::Ace)],
+            face_up: vec![Card::new(Suit::Spades, Rank::Two)],
+        };
+        assert_eq!(solve(&position, 100), Outcome::Dead);
+        assert_eq!(solve(&almost_won(), 1), Outcome::Unknown);
+        // A deadline in the past still lets the first batch of nodes run.
+        assert_eq!(solve_until(&position, 100, 0.0).verdict(), "dead");
+    }
+
+    #[test]
+    fn a_paused_search_carries_on_where_it_stopped() {
+        let position = Position::from_deal_seed(1);
+        let mut search = Search::new(&position, 20_000);
+        // A pause long gone stops the search at every look at the clock.
+        let mut slices = 1;
+        let outcome = loop {
+            match search.step_until(0.0) {
+                Some(outcome) => break outcome,
+                None => slices += 1,
+            }
+        };
+        assert!(slices > 1);
+        assert_eq!(outcome, solve(&position, 20_000));
+        assert_eq!(search.step_until(0.0), Some(outcome));
+    }
+
+    #[test]
+    fn found_solutions_replay_in_the_game() {
+        let (seed, moves) = find_winnable_deal(1, 20, 20_000).expect("a winnable deal");
+        let mut game = SolitaireGame::with_seed(seed);
+        game.setup_board();
+        assert_eq!(
+            Position::from_world(game.world()),
+            Position::from_deal_seed(GameRng::from_seed(seed).next_u64())
+        );
+        for mv in moves {
+            game.play_move(mv).unwrap();
+        }
+        assert!(game.is_won());
+    }
+}
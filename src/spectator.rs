@@ -0,0 +1,248 @@
+// Watching other players' boards.
+//
+// Players in a session can stream their boards to each other as
+// `BoardSnapshot`s: every card where it lies on the sender's table, scaled to
+// fractions of the table's size so the watching side can draw it at any size,
+// stamped with the sender's clock. Hidden cards travel without their
+// identity, as in `BoardView`.
+//
+// Snapshots arrive now and then and at uneven intervals. Drawing each one as
+// it comes in would make cards teleport, so the watching side keeps a short
+// buffer per player and draws the board as it was `INTERPOLATION_DELAY_MS`
+// in the past, between the two snapshots around that moment, with every card
+// moved part of the way from one to the other. The sender's clock is mapped
+// onto ours by the smallest difference seen between sending and arrival.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{Entity, World};
+use crate::game::{Card, FaceUp, card_index};
+use crate::layout::{CanvasSize, Position, TableLayout};
+use crate::view::{self, CardView, Thoughtful, Visibility};
+
+/// How far behind the latest snapshot spectators watch, so there is usually
+/// a newer snapshot to move towards.
+pub const INTERPOLATION_DELAY_MS: f64 = 150.0;
+/// Snapshots kept per player.
+const BUFFER: usize = 32;
+
+/// One card on a streamed board. `x` and `y` are fractions of the table's
+/// width and height, and the card travels as its `Card::to_index`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RemoteCard {
+    /// The card's entity on the sender's side, to follow it between
+    /// snapshots.
+    pub entity: Entity,
+    pub x: f32,
+    pub y: f32,
+    pub z: u32,
+    #[serde(with = "card_index::option", default)]
+    pub card: Option<Card>,
+    pub visibility: Visibility,
+}
+
+impl RemoteCard {
+    pub fn view(&self) -> CardView {
+        CardView {
+            card: self.card,
+            visibility: self.visibility,
+        }
+    }
+}
+
+/// A player's board at one moment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoardSnapshot {
+    /// When it was taken, by the sender's clock.
+    pub sent_ms: f64,
+    /// Card size as fractions of the table's width and height.
+    pub card_width: f32,
+    pub card_height: f32,
+    pub cards: Vec<RemoteCard>,
+}
+
+impl BoardSnapshot {
+    /// Capture the board of `world` at `now_ms`.
+    pub fn capture(world: &mut World, now_ms: f64) -> Self {
+        let layout = TableLayout::compute(world);
+        let canvas = world.resource::<CanvasSize>().copied().unwrap_or_default();
+        let thoughtful = world.resource::<Thoughtful>().is_some();
+        let cards = layout
+            .cards
+            .into_iter()
+            .map(|placed| {
+                let card = world.get_component::<Card>(placed.entity).copied();
+                let face_up = world
+                    .get_component::<FaceUp>(placed.entity)
+                    .is_some_and(|f| f.0);
+                let view = view::view_of(card, face_up, thoughtful);
+                RemoteCard {
+                    entity: placed.entity,
+                    x: placed.position.x / canvas.width,
+                    y: placed.position.y / canvas.height,
+                    z: placed.position.z,
+                    card: view.card,
+                    visibility: view.visibility,
+                }
+            })
+            .collect();
+        BoardSnapshot {
+            sent_ms: now_ms,
+            card_width: layout.card_width / canvas.width,
+            card_height: layout.card_height / canvas.height,
+            cards,
+        }
+    }
+}
+
+/// The snapshots received from one player.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Buffer {
+    snapshots: VecDeque<BoardSnapshot>,
+    /// Our clock minus theirs, plus the quickest delivery seen.
+    offset_ms: Option<f64>,
+}
+
+/// Resource with the boards being watched, by player name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spectators(BTreeMap<String, Buffer>);
+
+/// A watched board as it should be painted, in pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpectatorCard {
+    pub entity: Entity,
+    #[serde(flatten)]
+    pub position: Position,
+    #[serde(flatten)]
+    pub view: CardView,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpectatorScene {
+    pub card_width: f32,
+    pub card_height: f32,
+    /// Cards in drawing order.
+    pub cards: Vec<SpectatorCard>,
+}
+
+/// File a snapshot from `player` that arrived at `now_ms`.
+pub fn receive(world: &mut World, player: String, snapshot: BoardSnapshot, now_ms: f64) {
+    if world.resource::<Spectators>().is_none() {
+        world.insert_resource(Spectators::default());
+    }
+    let spectators = world.resource_mut::<Spectators>().expect("inserted above");
+    let buffer = spectators.0.entry(player).or_default();
+    let offset = now_ms - snapshot.sent_ms;
+    buffer.offset_ms = Some(buffer.offset_ms.map_or(offset, |o| o.min(offset)));
+    // Snapshots overtaken by newer ones on the way are of no use.
+    if buffer
+        .snapshots
+        .back()
+        .is_some_and(|last| last.sent_ms >= snapshot.sent_ms)
+    {
+        return;
+    }
+    buffer.snapshots.push_back(snapshot);
+    if buffer.snapshots.len() > BUFFER {
+        buffer.snapshots.pop_front();
+    }
+}
+
+/// The players whose boards can be watched.
+pub fn players(world: &World) -> Vec<String> {
+    world
+        .resource::<Spectators>()
+        .map(|s| s.0.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// `player`'s board as of `INTERPOLATION_DELAY_MS` before `now_ms`, drawn
+/// `size` large.
+pub fn scene(world: &World, player: &str, now_ms: f64, size: CanvasSize) -> Option<SpectatorScene> {
+    let buffer = world.resource::<Spectators>()?.0.get(player)?;
+    let at = now_ms - buffer.offset_ms? - INTERPOLATION_DELAY_MS;
+    let newer = buffer
+        .snapshots
+        .iter()
+        .position(|s| s.sent_ms > at)
+        .unwrap_or(buffer.snapshots.len() - 1);
+    let to = &buffer.snapshots[newer];
+    let (from, t) = match newer.checked_sub(1).map(|i| &buffer.snapshots[i]) {
+        Some(from) if to.sent_ms > from.sent_ms => (
+            from,
+            (((at - from.sent_ms) / (to.sent_ms - from.sent_ms)) as f32).clamp(0.0, 1.0),
+        ),
+        _ => (to, 1.0),
+    };
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    let mut cards: Vec<SpectatorCard> = to
+        .cards
+        .iter()
+        .map(|card| {
+            let before = from.cards.iter().find(|c| c.entity == card.entity);
+            let (x, y) =
+                before.map_or((card.x, card.y), |b| (lerp(b.x, card.x), lerp(b.y, card.y)));
+            // Faces turn over halfway.
+            let shown = before.filter(|_| t < 0.5).unwrap_or(card);
+            SpectatorCard {
+                entity: card.entity,
+                position: Position {
+                    x: x * size.width,
+                    y: y * size.height,
+                    z: shown.z,
+                },
+                view: shown.view(),
+            }
+        })
+        .collect();
+    cards.sort_by_key(|c| c.position.z);
+    Some(SpectatorScene {
+        card_width: lerp(from.card_width, to.card_width) * size.width,
+        card_height: lerp(from.card_height, to.card_height) * size.height,
+        cards,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Deck, Pile};
+    use crate::rules::{self, Move};
+
+    #[test]
+    fn watched_cards_glide_between_snapshots() {
+        let mut remote = World::new();
+        rules::deal(&mut remote, &Deck::standard().cards);
+        let first = BoardSnapshot::capture(&mut remote, 1_000.0);
+        rules::apply(&mut remote, Move::Draw).unwrap();
+        let drawn = rules::top_card(&remote, Pile::Waste).unwrap();
+        let second = BoardSnapshot::capture(&mut remote, 1_200.0);
+        assert!(second.cards.iter().all(|c| (0.0..1.0).contains(&c.x)));
+
+        // Our clock runs 5 seconds ahead of theirs, with 50ms delivery.
+        let mut world = World::new();
+        receive(&mut world, "ann".into(), first.clone(), 6_050.0);
+        receive(&mut world, "ann".into(), second.clone(), 6_250.0);
+        receive(&mut world, "ann".into(), first.clone(), 6_300.0);
+        assert_eq!(players(&world), ["ann"]);
+
+        let size = CanvasSize::default();
+        let at = |now: f64| {
+            let scene = scene(&world, "ann", now, size).unwrap();
+            scene.cards.into_iter().find(|c| c.entity == drawn).unwrap()
+        };
+        let x = |snapshot: &BoardSnapshot| {
+            snapshot.cards.iter().find(|c| c.entity == drawn).unwrap().x * size.width
+        };
+        // Halfway between the snapshots, the drawn card is halfway across.
+        let midway = at(5_050.0 + 1_100.0 + INTERPOLATION_DELAY_MS);
+        assert!((midway.position.x - (x(&first) + x(&second)) / 2.0).abs() < 0.01);
+        assert!(midway.view.card.is_some());
+        // Past the last snapshot, it rests where it was last seen.
+        let resting = at(10_000.0);
+        assert!((resting.position.x - x(&second)).abs() < 0.01);
+        assert!(scene(&world, "bo", 10_000.0, size).is_none());
+    }
+}
@@ -0,0 +1,301 @@
+// Play statistics kept between sessions: games played, won and lost.
+//
+// A game counts as played as soon as it is dealt, so walking away from a
+// deal counts as a loss. A deal is won once at most: taking the winning move
+// back and playing it again does not win it twice. Games given up outright are counted too, among the
+// losses. The one exception is a *mulligan*: rejecting a deal
+// before making a move, which the options may allow a few times per session.
+// The rejected deal is taken back off the record and the mulligan counted
+// instead.
+//
+// The player's latest ranked `PlayerRating` is kept with the statistics too,
+// updated from `GameEvent::RatingChanged`.
+//
+// Players can move their history to another device, or keep it safe before
+// clearing the browser's storage, with an `export`. Importing one `merge`s it
+// into the statistics there: counts are added up and the best time kept, so
+// importing the same export twice counts its games twice.
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::GameEvent;
+use crate::rating::PlayerRating;
+
+/// Games played and won across all sessions.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Statistics {
+    played: u32,
+    won: u32,
+    /// Wins in which no move was taken back.
+    won_without_undo: u32,
+    /// Games given up, see `lifecycle`.
+    abandoned: u32,
+    mulligans: u32,
+    /// The quickest win, in milliseconds of play.
+    best_time_ms: Option<u64>,
+    rating: Option<PlayerRating>,
+    /// Whether the current deal has been won already.
+    #[serde(skip)]
+    deal_won: bool,
+}
+
+/// Version of the format `export` writes.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Statistics as exported, to import elsewhere.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsExport {
+    pub version: u32,
+    pub statistics: Statistics,
+}
+
+impl Statistics {
+    /// Feed one event. Returns whether the statistics changed.
+    pub fn handle(&mut self, event: &GameEvent) -> bool {
+        match event {
+            GameEvent::GameStarted { .. } => {
+                self.played += 1;
+                self.deal_won = false;
+            }
+            GameEvent::GameWon { .. } if self.deal_won => return false,
+            GameEvent::GameWon { time_ms, undos, .. } => {
+                self.deal_won = true;
+                self.won += 1;
+                if *undos == 0 {
+                    self.won_without_undo += 1;
+                }
+                self.best_time_ms = Some(self.best_time_ms.map_or(*time_ms, |t| t.min(*time_ms)));
+            }
+            GameEvent::GameAbandoned { .. } => self.abandoned += 1,
+            GameEvent::RatingChanged { rating, .. } => self.rating = Some(*rating),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Take the current deal off the record: it was rejected as a mulligan.
+    pub fn record_mulligan(&mut self) {
+        self.played = self.played.saturating_sub(1);
+        self.mulligans += 1;
+    }
+
+    pub fn played(&self) -> u32 {
+        self.played
+    }
+
+    pub fn won(&self) -> u32 {
+        self.won
+    }
+
+    /// Wins in which no move was taken back.
+    pub fn won_without_undo(&self) -> u32 {
+        self.won_without_undo
+    }
+
+    /// Games given up or not won yet, including the one in progress.
+    pub fn lost(&self) -> u32 {
+        self.played.saturating_sub(self.won)
+    }
+
+    /// Games given up, which `lost` counts as well.
+    pub fn abandoned(&self) -> u32 {
+        self.abandoned
+    }
+
+    /// Deals rejected as mulligans.
+    pub fn mulligans(&self) -> u32 {
+        self.mulligans
+    }
+
+    pub fn best_time_ms(&self) -> Option<u64> {
+        self.best_time_ms
+    }
+
+    /// The rating of ranked play, once the server has told it.
+    pub fn rating(&self) -> Option<PlayerRating> {
+        self.rating
+    }
+
+    /// Add `other`'s games to these and keep the better best time. Of the
+    /// two ratings, the one with more ranked games behind it is kept.
+    pub fn merge(&mut self, other: &Statistics) {
+        self.played += other.played;
+        self.won += other.won;
+        self.won_without_undo += other.won_without_undo;
+        self.abandoned += other.abandoned;
+        self.mulligans += other.mulligans;
+        self.best_time_ms = match (self.best_time_ms, other.best_time_ms) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        if other
+            .rating
+            .is_some_and(|r| self.rating.is_none_or(|ours| r.games > ours.games))
+        {
+            self.rating = other.rating;
+        }
+    }
+
+    /// The statistics in the format `import` reads.
+    pub fn export(&self) -> String {
+        serde_json::to_string(&StatsExport {
+            version: EXPORT_VERSION,
+            statistics: self.clone(),
+        })
+        .expect("statistics always serialize")
+    }
+
+    /// Read statistics written by `export`.
+    pub fn import(json: &str) -> Result<Statistics, String> {
+        let export: StatsExport = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if export.version > EXPORT_VERSION {
+            return Err(format!(
+                "statistics exported by a newer version ({})",
+                export.version
+            ));
+        }
+        let statistics = export.statistics;
+        if statistics.won > statistics.played || statistics.won_without_undo > statistics.won {
+            return Err("the statistics count more wins than games".to_string());
+        }
+        Ok(statistics)
+    }
+
+    /// Serialize the statistics.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("statistics always serialize")
+    }
+
+    /// Restore statistics saved with `to_json`.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mulligans_do_not_count_as_losses() {
+        let mut stats = Statistics::default();
+        let started = GameEvent::GameStarted { seed: 1 };
+        stats.handle(&started);
+        stats.record_mulligan();
+        stats.handle(&started);
+        stats.handle(&GameEvent::GameWon {
+            moves: 100,
+            undos: 0,
+            time_ms: 60_000,
+            time_bonus: 0,
+        });
+        stats.handle(&started);
+        stats.handle(&GameEvent::GameAbandoned {
+            moves: 3,
+            time_ms: 9_000,
+        });
+        let rating = PlayerRating {
+            rating: 1512.0,
+            games: 1,
+        };
+        stats.handle(&GameEvent::RatingChanged {
+            rating,
+            change: 12.0,
+        });
+
+        let stats = Statistics::from_json(&stats.to_json()).unwrap();
+        assert_eq!(stats.played(), 2);
+        assert_eq!(stats.won(), 1);
+        assert_eq!(stats.lost(), 1);
+        assert_eq!(stats.abandoned(), 1);
+        assert_eq!(stats.mulligans(), 1);
+        assert_eq!(stats.rating(), Some(rating));
+    }
+
+    #[test]
+    fn an_undo_disqualifies_a_win_without_undo() {
+        let won = |undos| GameEvent::GameWon {
+            moves: 100,
+            undos,
+            time_ms: 60_000,
+            time_bonus: 0,
+        };
+        let mut stats = Statistics::default();
+        for undos in [0, 1] {
+            stats.handle(&GameEvent::GameStarted { seed: 1 });
+            stats.handle(&won(undos));
+        }
+        assert_eq!((stats.won(), stats.won_without_undo()), (2, 1));
+    }
+
+    #[test]
+    fn a_deal_is_won_only_once() {
+        let won = GameEvent::GameWon {
+            moves: 100,
+            undos: 1,
+            time_ms: 60_000,
+            time_bonus: 0,
+        };
+        let mut stats = Statistics::default();
+        stats.handle(&GameEvent::GameStarted { seed: 1 });
+        assert!(stats.handle(&won));
+        // The winning move taken back and played again.
+        assert!(!stats.handle(&won));
+        assert_eq!((stats.played(), stats.won(), stats.lost()), (1, 1, 0));
+
+        let inflated = r#"{ "version": 1, "statistics": { "played": 1, "won": 2 } }"#;
+        assert!(Statistics::import(inflated).is_err());
+    }
+
+    #[cfg(feature = "solver")]
+    #[test]
+    fn undoing_a_win_and_winning_again_counts_one_win() {
+        let (seed, _) = crate::solver::find_winnable_deal(1, 20, 20_000).unwrap();
+        let mut game = crate::SolitaireGame::with_seed(seed);
+        game.setup_board();
+        game.set_autoplay_settings(10.0, false);
+        // Playback is one undo step, so undo takes the whole win back.
+        for undo in [false, true] {
+            if undo {
+                game.undo().unwrap();
+            }
+            game.start_solution_playback().unwrap();
+            while game.is_autoplaying() {
+                game.advance(16.0);
+            }
+            assert!(game.is_won());
+        }
+        let stats = Statistics::from_json(&game.statistics()).unwrap();
+        assert_eq!((stats.played(), stats.won(), stats.lost()), (1, 1, 0));
+    }
+
+    #[test]
+    fn imports_add_up_games_and_keep_the_best_time() {
+        let won = |time_ms| GameEvent::GameWon {
+            moves: 90,
+            undos: 0,
+            time_ms,
+            time_bonus: 0,
+        };
+        let mut phone = Statistics::default();
+        phone.handle(&GameEvent::GameStarted { seed: 1 });
+        phone.handle(&won(80_000));
+        let mut laptop = Statistics::default();
+        laptop.handle(&GameEvent::GameStarted { seed: 2 });
+        laptop.handle(&won(95_000));
+        laptop.handle(&GameEvent::GameStarted { seed: 3 });
+        laptop.handle(&won(70_000));
+        laptop.handle(&GameEvent::GameStarted { seed: 4 });
+
+        let imported = Statistics::import(&phone.export()).unwrap();
+        laptop.merge(&imported);
+        assert_eq!((laptop.played(), laptop.won()), (4, 3));
+        assert_eq!(laptop.best_time_ms(), Some(70_000));
+        assert_eq!(laptop.won_without_undo(), 3);
+
+        let newer = r#"{ "version": 99, "statistics": {} }"#;
+        assert!(Statistics::import(newer).is_err());
+        assert!(Statistics::import("{}").is_err());
+    }
+}
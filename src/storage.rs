@@ -0,0 +1,48 @@
+// Persistent key/value storage.
+//
+// In the browser this is `window.localStorage`. Native builds (and browsers
+// where storage is disabled, e.g. some private browsing modes) fall back to
+// doing nothing: loads return `None` and saves are silently dropped, so the
+// game keeps working without persistence.
+
+/// Read the string stored under `key`.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub fn load(key: &str) -> Option<String> {
+    local_storage()?.get_item(key).ok().flatten()
+}
+
+/// Store `value` under `key`, replacing any previous value.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub fn save(key: &str, value: &str) {
+    if let Some(storage) = local_storage() {
+        // Quota errors are not fatal for a card game; just skip the save.
+        let _ = storage.set_item(key, value);
+    }
+}
+
+/// Remove the value stored under `key`, if any.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub fn remove(key: &str) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.remove_item(key);
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+/// Read the string stored under `key`.
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+pub fn load(_key: &str) -> Option<String> {
+    None
+}
+
+/// Store `value` under `key`, replacing any previous value.
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+pub fn save(_key: &str, _value: &str) {}
+
+/// Remove the value stored under `key`, if any.
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+pub fn remove(_key: &str) {}
@@ -0,0 +1,115 @@
+// Supermoves for FreeCell-style games.
+//
+// In FreeCell only one card moves at a time, but a run of cards can be moved
+// as a whole when enough free cells and empty cascades are around to shuffle
+// it across card by card. With `f` free cells and `e` empty cascades (not
+// counting the target) a run of up to `(f + 1) * 2^e` cards can move.
+//
+// The game has no FreeCell variant yet, so nothing here knows about piles:
+// locations are whatever the caller uses to name a cell or cascade. Once the
+// variant lands, its rules can accept a supermove when `max_run` allows it and
+// play the single-card `Step`s from `decompose`, so undo and the audit log
+// stay exact.
+
+/// One card moving from one location to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step<L> {
+    pub from: L,
+    pub to: L,
+}
+
+/// The longest run that can move with `free_cells` empty cells and
+/// `empty_cascades` empty cascades besides the target.
+pub fn max_run(free_cells: usize, empty_cascades: usize) -> usize {
+    (free_cells + 1)
+        .checked_shl(empty_cascades as u32)
+        .unwrap_or(usize::MAX)
+}
+
+/// Break moving the top `count` cards of `from` onto `to` into single-card
+/// steps, using the empty `cells` and `cascades` as temporary space. The run
+/// arrives in its original order. Returns `None` when the run is longer than
+/// `max_run` allows.
+pub fn decompose<L: Copy>(
+    count: usize,
+    from: L,
+    to: L,
+    cells: &[L],
+    cascades: &[L],
+) -> Option<Vec<Step<L>>> {
+    if count > max_run(cells.len(), cascades.len()) {
+        return None;
+    }
+    let mut steps = Vec::new();
+    plan(count, from, to, cells, cascades, &mut steps);
+    Some(steps)
+}
+
+fn plan<L: Copy>(
+    count: usize,
+    from: L,
+    to: L,
+    cells: &[L],
+    cascades: &[L],
+    steps: &mut Vec<Step<L>>,
+) {
+    if count == 0 {
+        return;
+    }
+    if count <= cells.len() + 1 {
+        // Park all but the bottom card in cells, move it, then unpark.
+        let parked = &cells[..count - 1];
+        for &cell in parked {
+            steps.push(Step { from, to: cell });
+        }
+        steps.push(Step { from, to });
+        for &cell in parked.iter().rev() {
+            steps.push(Step { from: cell, to });
+        }
+        return;
+    }
+    // Move the top part to an empty cascade, the rest to the target, and
+    // then the top part after it, each with one empty cascade fewer.
+    let (&spare, rest) = cascades.split_first().expect("checked by max_run");
+    let top = max_run(cells.len(), rest.len()).min(count - 1);
+    plan(top, from, spare, cells, rest, steps);
+    plan(count - top, from, to, cells, rest, steps);
+    plan(top, spare, to, cells, rest, steps);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Play the steps on stacks of numbered cards and check that cells never
+    /// hold more than one card.
+    fn play(steps: &[Step<usize>], stacks: &mut [Vec<u32>], cells: &[usize]) {
+        for step in steps {
+            let card = stacks[step.from].pop().expect("a card to move");
+            stacks[step.to].push(card);
+            for &cell in cells {
+                assert!(stacks[cell].len() <= 1, "cell {cell} overfull");
+            }
+        }
+    }
+
+    #[test]
+    fn run_size_doubles_per_empty_cascade() {
+        assert_eq!(max_run(0, 0), 1);
+        assert_eq!(max_run(4, 0), 5);
+        assert_eq!(max_run(2, 2), 12);
+    }
+
+    #[test]
+    fn decomposed_runs_arrive_in_order() {
+        // 0 is the source, 1 the target, 2–3 cells, 4–5 empty cascades.
+        let cells = [2, 3];
+        let run: Vec<u32> = (1..=12).collect();
+        let mut stacks = vec![run.clone(), vec![], vec![], vec![], vec![], vec![]];
+        let steps = decompose(run.len(), 0, 1, &cells, &[4, 5]).unwrap();
+        play(&steps, &mut stacks, &cells);
+        assert_eq!(stacks[1], run);
+        assert!(stacks[0].is_empty() && stacks[4].is_empty() && stacks[5].is_empty());
+        assert!(decompose(13, 0, 1, &cells, &[4, 5]).is_none());
+    }
+}
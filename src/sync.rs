@@ -0,0 +1,196 @@
+//! Keeping the autosave and statistics in step across devices.
+//!
+//! A player's `SyncDoc` — the autosave, see `save`, and the `Statistics` —
+//! is kept at a REST endpoint of their choosing, one document per player:
+//!
+//! * `GET {endpoint}` answers the document, or 404 before the first push;
+//! * `PUT {endpoint}` replaces it, with an `If-Match` header holding the
+//!   revision the client last saw, so a server can refuse to overwrite a
+//!   document that moved on in the meantime.
+//!
+//! Both carry the player's token as `Authorization: Bearer {token}`.
+//!
+//! Every save bumps the document's revision. `resolve` compares the local
+//! document with the remote one: the higher revision wins and is pushed or
+//! pulled; two documents with the same revision but saved at different
+//! times went separate ways from a common one, and the later save wins.
+//!
+//! In the browser, `SyncClient` does the requests:
+//!
+//! ```ignore
+//! const sync = new SyncClient("https://example.com/saves/me", token);
+//! const pulled = await sync.sync(game.sync_document());
+//! if (pulled) game.apply_sync_document(pulled);
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::save::SavedGame;
+use crate::stats::Statistics;
+
+/// Everything synced for one player.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncDoc {
+    pub revision: u64,
+    /// Milliseconds since the Unix epoch of the last save.
+    pub saved_ms: f64,
+    pub autosave: Option<SavedGame>,
+    pub statistics: Statistics,
+}
+
+/// What to do to bring the two sides together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    UpToDate,
+    /// Send the local document to the server.
+    Push,
+    /// Take over the remote document.
+    Pull,
+}
+
+/// Which of `local` and `remote` wins; `None` for a server that has no
+/// document yet.
+pub fn resolve(local: &SyncDoc, remote: Option<&SyncDoc>) -> Resolution {
+    let Some(remote) = remote else {
+        return Resolution::Push;
+    };
+    let newer = (local.revision, local.saved_ms).partial_cmp(&(remote.revision, remote.saved_ms));
+    match newer {
+        Some(std::cmp::Ordering::Greater) => Resolution::Push,
+        Some(std::cmp::Ordering::Less) => Resolution::Pull,
+        _ => Resolution::UpToDate,
+    }
+}
+
+#[cfg(feature = "wasm")]
+pub use web::SyncClient;
+
+/// Browser side of syncing: the requests, made with `fetch`.
+#[cfg(feature = "wasm")]
+mod web {
+    use super::*;
+    use js_sys::Promise;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_futures::{JsFuture, future_to_promise};
+    use web_sys::{Headers, Request, RequestInit, Response};
+
+    /// Syncs with the document at `endpoint`.
+    #[wasm_bindgen]
+    pub struct SyncClient {
+        endpoint: String,
+        token: String,
+    }
+
+    #[wasm_bindgen]
+    impl SyncClient {
+        #[wasm_bindgen(constructor)]
+        pub fn new(endpoint: &str, token: &str) -> SyncClient {
+            SyncClient {
+                endpoint: endpoint.to_string(),
+                token: token.to_string(),
+            }
+        }
+
+        /// Sync `local_json`, a document from
+        /// `SolitaireGame::sync_document`. The Promise resolves with the
+        /// remote document as JSON when it wins, for
+        /// `SolitaireGame::apply_sync_document`, or `null` when the local one
+        /// was pushed or both were the same already.
+        pub fn sync(&self, local_json: &str) -> Result<Promise, JsValue> {
+            let local: SyncDoc = serde_json::from_str(local_json)
+                .map_err(|e| JsValue::from_str(&format!("Invalid document: {e}")))?;
+            let (endpoint, token) = (self.endpoint.clone(), self.token.clone());
+            Ok(future_to_promise(async move {
+                let remote_text = pull(&endpoint, &token).await?;
+                let remote = match &remote_text {
+                    Some(text) => Some(
+                        serde_json::from_str::<SyncDoc>(text)
+                            .map_err(|e| JsValue::from_str(&format!("Invalid document: {e}")))?,
+                    ),
+                    None => None,
+                };
+                match resolve(&local, remote.as_ref()) {
+                    Resolution::UpToDate => Ok(JsValue::NULL),
+                    Resolution::Pull => Ok(JsValue::from_str(
+                        &remote_text.expect("only documents the server has are pulled"),
+                    )),
+                    Resolution::Push => {
+                        let seen = remote.map_or(0, |r| r.revision);
+                        push(&endpoint, &token, &local, seen).await?;
+                        Ok(JsValue::NULL)
+                    }
+                }
+            }))
+        }
+    }
+
+    fn headers(token: &str) -> Result<Headers, JsValue> {
+        let headers = Headers::new()?;
+        headers.set("Authorization", &format!("Bearer {token}"))?;
+        Ok(headers)
+    }
+
+    /// The remote document, or `None` if there is none yet.
+    async fn pull(endpoint: &str, token: &str) -> Result<Option<String>, JsValue> {
+        let init = RequestInit::new();
+        init.set_headers(&headers(token)?.into());
+        let request = Request::new_with_str_and_init(endpoint, &init)?;
+        let response = send(request).await?;
+        if response.status() == 404 {
+            return Ok(None);
+        }
+        let text = JsFuture::from(response.text()?).await?;
+        Ok(text.as_string())
+    }
+
+    async fn push(endpoint: &str, token: &str, doc: &SyncDoc, seen: u64) -> Result<(), JsValue> {
+        let headers = headers(token)?;
+        headers.set("Content-Type", "application/json")?;
+        headers.set("If-Match", &seen.to_string())?;
+        let init = RequestInit::new();
+        init.set_method("PUT");
+        init.set_headers(&headers.into());
+        let body = serde_json::to_string(doc).expect("documents always serialize");
+        init.set_body(&JsValue::from_str(&body));
+        send(Request::new_with_str_and_init(endpoint, &init)?).await?;
+        Ok(())
+    }
+
+    /// Perform a request; errors other than 404 reject.
+    async fn send(request: Request) -> Result<Response, JsValue> {
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window"))?;
+        let response: Response = JsFuture::from(window.fetch_with_request(&request))
+            .await?
+            .dyn_into()?;
+        if !response.ok() && response.status() != 404 {
+            return Err(JsValue::from_str(&format!("HTTP {}", response.status())));
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(revision: u64, saved_ms: f64) -> SyncDoc {
+        SyncDoc {
+            revision,
+            saved_ms,
+            autosave: None,
+            statistics: Statistics::default(),
+        }
+    }
+
+    #[test]
+    fn the_newer_revision_wins() {
+        let local = doc(5, 1_000.0);
+        assert_eq!(resolve(&local, None), Resolution::Push);
+        assert_eq!(resolve(&local, Some(&doc(4, 9_000.0))), Resolution::Push);
+        assert_eq!(resolve(&local, Some(&doc(6, 0.0))), Resolution::Pull);
+        assert_eq!(resolve(&local, Some(&local.clone())), Resolution::UpToDate);
+        // Saved apart from the same revision: the later save wins.
+        assert_eq!(resolve(&local, Some(&doc(5, 2_000.0))), Resolution::Pull);
+    }
+}
@@ -0,0 +1,247 @@
+// Colours, shapes and fonts of the table.
+//
+// A `Theme` holds the values a renderer draws with: the table and card
+// colours, how round card corners are, the fonts and the card back. The page gets them as
+// CSS custom properties, `--solitaire-table-color` and so on, set on one
+// element with `apply`; its stylesheet refers to them with `var(...)`, so
+// switching themes restyles the page without touching any other element.
+//
+// Beneath the piles the table is painted with its `Background`: the table
+// colour alone, a gradient, a tiled texture image or a felt pattern made in
+// code. Renderers get it resolved by `table_background`, with felt turned
+// into a texture of its own, so they only paint colours, gradients and
+// tiles.
+//
+// The theme is also kept as a resource in the world, for the renderers on
+// the Rust side such as `snapshot`. Without the resource the light theme is
+// used.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::faces::CardBack;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub table_color: String,
+    pub card_color: String,
+    /// Outlines of cards and empty piles.
+    pub line_color: String,
+    /// Scores, counters and other text on the table.
+    pub text_color: String,
+    /// Highlights, such as legal drop targets.
+    pub accent_color: String,
+    /// Corner radius as a fraction of the card width.
+    pub card_radius: f32,
+    pub font_family: String,
+    pub card_font_family: String,
+    pub background: Background,
+    pub card_back: CardBack,
+}
+
+/// How the table is painted beneath the piles.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Background {
+    /// The table colour alone.
+    #[default]
+    Solid,
+    /// A radial gradient from `center` in the middle of the table to `edge`
+    /// at its corners.
+    Gradient { center: String, edge: String },
+    /// An image tiled across the table, `tile_size` pixels square.
+    Texture { url: String, tile_size: f32 },
+    /// A woven felt look over the table colour; `grain` from 0 to 1 is how
+    /// coarse it is.
+    Felt { grain: f32 },
+}
+
+/// Size of the generated felt tile, in pixels.
+const FELT_TILE: f32 = 64.0;
+
+/// The table beneath the piles, as renderers paint it: the colour, and a
+/// gradient or tiles over it. Never `Background::Felt`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableBackground {
+    pub color: String,
+    #[serde(flatten)]
+    pub background: Background,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::preset(ThemePreset::Light)
+    }
+}
+
+/// The themes that come with the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemePreset {
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub fn preset(preset: ThemePreset) -> Self {
+        match preset {
+            ThemePreset::Light => Theme {
+                table_color: "#1f6b3a".into(),
+                card_color: "#ffffff".into(),
+                line_color: "rgba(255,255,255,0.35)".into(),
+                text_color: "#ffffff".into(),
+                accent_color: "#ffd54f".into(),
+                card_radius: 0.08,
+                font_family: "sans-serif".into(),
+                card_font_family: "serif".into(),
+                background: Background::Felt { grain: 0.4 },
+                card_back: CardBack::Lattice,
+            },
+            ThemePreset::Dark => Theme {
+                table_color: "#15201a".into(),
+                card_color: "#d8d8d2".into(),
+                line_color: "rgba(255,255,255,0.2)".into(),
+                text_color: "#c8c8c0".into(),
+                accent_color: "#c9a227".into(),
+                card_radius: 0.08,
+                font_family: "sans-serif".into(),
+                card_font_family: "serif".into(),
+                background: Background::Gradient {
+                    center: "#22332a".into(),
+                    edge: "#0d1410".into(),
+                },
+                card_back: CardBack::Plain,
+            },
+        }
+    }
+
+    /// The theme as CSS custom properties, for cards `card_width` pixels
+    /// wide.
+    pub fn css_properties(&self, card_width: f32) -> Vec<(&'static str, String)> {
+        vec![
+            ("--solitaire-table-color", self.table_color.clone()),
+            ("--solitaire-card-color", self.card_color.clone()),
+            ("--solitaire-line-color", self.line_color.clone()),
+            ("--solitaire-text-color", self.text_color.clone()),
+            ("--solitaire-accent-color", self.accent_color.clone()),
+            (
+                "--solitaire-card-radius",
+                format!("{}px", self.card_radius * card_width),
+            ),
+            (
+                "--solitaire-table-background",
+                self.table_background().css(),
+            ),
+            ("--solitaire-font-family", self.font_family.clone()),
+            (
+                "--solitaire-card-font-family",
+                self.card_font_family.clone(),
+            ),
+        ]
+    }
+
+    /// The table background, with felt turned into a texture.
+    pub fn table_background(&self) -> TableBackground {
+        let background = match &self.background {
+            Background::Felt { grain } => Background::Texture {
+                url: svg_data_url(&felt_svg(&self.table_color, *grain)),
+                tile_size: FELT_TILE,
+            },
+            other => other.clone(),
+        };
+        TableBackground {
+            color: self.table_color.clone(),
+            background,
+        }
+    }
+}
+
+impl TableBackground {
+    /// The background as a value of the CSS `background` property.
+    pub fn css(&self) -> String {
+        match &self.background {
+            Background::Solid | Background::Felt { .. } => self.color.clone(),
+            Background::Gradient { center, edge } => {
+                format!("radial-gradient(circle, {center}, {edge}) {}", self.color)
+            }
+            Background::Texture { url, tile_size } => format!(
+                "url(\"{url}\") 0 0 / {tile_size}px {tile_size}px repeat {}",
+                self.color
+            ),
+        }
+    }
+}
+
+/// A seamless felt tile: fractal noise, greyed and faded to `grain`, over
+/// `color`.
+pub fn felt_svg(color: &str, grain: f32) -> String {
+    let grain = grain.clamp(0.0, 1.0);
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{t}" height="{t}"><filter id="felt"><feTurbulence type="fractalNoise" baseFrequency="{}" numOctaves="3" stitchTiles="stitch"/><feColorMatrix type="saturate" values="0"/><feComponentTransfer><feFuncA type="linear" slope="{}"/></feComponentTransfer></filter><rect width="{t}" height="{t}" fill="{color}"/><rect width="{t}" height="{t}" filter="url(#felt)"/></svg>"#,
+        1.2 - grain * 0.6,
+        0.15 + grain * 0.35,
+        t = FELT_TILE,
+    )
+}
+
+/// `svg` as a `data:` URL, usable wherever an image URL is.
+pub fn svg_data_url(svg: &str) -> String {
+    let mut url = String::from("data:image/svg+xml,");
+    for c in svg.chars() {
+        match c {
+            '%' | '#' | '<' | '>' | '"' | '\n' => url.push_str(&format!("%{:02X}", c as u32)),
+            c => url.push(c),
+        }
+    }
+    url
+}
+
+/// The current theme of `world`.
+pub fn current(world: &World) -> Theme {
+    world.resource::<Theme>().cloned().unwrap_or_default()
+}
+
+/// Set the CSS custom properties of `theme` on `element`.
+#[cfg(feature = "wasm")]
+pub fn apply(
+    theme: &Theme,
+    card_width: f32,
+    element: &web_sys::HtmlElement,
+) -> Result<(), wasm_bindgen::JsValue> {
+    let style = element.style();
+    for (name, value) in theme.css_properties(card_width) {
+        style.set_property(name, &value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_become_css_properties() {
+        let dark = Theme::preset(ThemePreset::Dark);
+        let properties = dark.css_properties(100.0);
+        assert!(properties.contains(&("--solitaire-table-color", "#15201a".to_string())));
+        assert!(properties.contains(&("--solitaire-card-radius", "8px".to_string())));
+
+        let custom: Theme = serde_json::from_str(r##"{ "table_color": "#000" }"##).unwrap();
+        assert_eq!(custom.table_color, "#000");
+        assert_eq!(custom.card_color, Theme::default().card_color);
+
+        let felt = Theme::default().table_background();
+        let Background::Texture { url, tile_size } = &felt.background else {
+            panic!("felt is painted as a texture");
+        };
+        assert!(url.starts_with("data:image/svg+xml,%3Csvg") && !url.contains('#'));
+        assert_eq!(*tile_size, FELT_TILE);
+        let gradient = Theme::preset(ThemePreset::Dark).table_background();
+        assert!(
+            gradient
+                .css()
+                .starts_with("radial-gradient(circle, #22332a, #0d1410)")
+        );
+    }
+}
@@ -0,0 +1,154 @@
+// Time attack: play against a countdown.
+//
+// The `TimeAttack` resource turns the game clock into a countdown. Every
+// card on a foundation buys a few more seconds, so the time left is worked
+// out from the board rather than kept as a running total, which keeps undo
+// honest: taking a card back off a foundation takes its bonus back too.
+//
+// The game ends either by clearing the board in time or when the countdown
+// reaches zero, whichever comes first. Either way a `TimeAttackEnded` event
+// reports how it went, and a `TimeUp` marker stops further moves once time
+// has run out.
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::GameClock;
+use crate::ecs::World;
+use crate::events::GameEvent;
+use crate::game::Pile;
+use crate::rules;
+
+/// Resource turning the game into a time attack.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeAttack {
+    /// Time on the clock at the start.
+    pub limit_ms: f64,
+    /// Time added for every card on the foundations.
+    pub bonus_ms: f64,
+}
+
+impl TimeAttack {
+    /// A countdown of `limit_ms` with the usual five-second card bonus.
+    pub fn new(limit_ms: f64) -> Self {
+        TimeAttack {
+            limit_ms,
+            bonus_ms: 5_000.0,
+        }
+    }
+
+    /// Time left at time `now`.
+    pub fn remaining_ms(&self, world: &World, now: f64) -> f64 {
+        let elapsed = world
+            .resource::<GameClock>()
+            .map_or(0.0, |c| c.elapsed_ms(now));
+        let earned = f64::from(foundation_cards(world)) * self.bonus_ms;
+        (self.limit_ms + earned - elapsed).max(0.0)
+    }
+}
+
+/// Resource present once the countdown has run out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeUp;
+
+/// How a time attack ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeAttackResult {
+    /// Every card reached the foundations in time.
+    Cleared,
+    /// The countdown ran out first.
+    TimeUp,
+}
+
+/// Number of cards on the foundations.
+pub fn foundation_cards(world: &World) -> u32 {
+    world
+        .iter::<Pile>()
+        .filter(|(_, pile)| matches!(pile, Pile::Foundation(_)))
+        .count() as u32
+}
+
+/// End the time attack if its countdown has run out by time `now`: moves
+/// are refused from then on, the clock stops, and the returned event gives
+/// the result. Does nothing outside time attacks or once the game is over.
+pub fn check(world: &mut World, now: f64) -> Option<GameEvent> {
+    let attack = *world.resource::<TimeAttack>()?;
+    if world.resource::<TimeUp>().is_some()
+        || rules::is_won(world)
+        || attack.remaining_ms(world, now) > 0.0
+    {
+        return None;
+    }
+    world.insert_resource(TimeUp);
+    if let Some(clock) = world.resource_mut::<GameClock>() {
+        clock.pause(now);
+    }
+    Some(GameEvent::TimeAttackEnded {
+        result: TimeAttackResult::TimeUp,
+        foundation_cards: foundation_cards(world),
+        remaining_ms: 0,
+    })
+}
+
+/// The event for a time attack won at time `now`, if this is one.
+pub fn cleared(world: &World, now: f64) -> Option<GameEvent> {
+    let attack = world.resource::<TimeAttack>()?;
+    Some(GameEvent::TimeAttackEnded {
+        result: TimeAttackResult::Cleared,
+        foundation_cards: foundation_cards(world),
+        remaining_ms: attack.remaining_ms(world, now) as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::GameError;
+    use crate::game::{Card, FaceUp, PileIndex, Rank, Suit};
+    use crate::rules::Move;
+
+    #[test]
+    fn foundation_cards_buy_time_until_it_runs_out() {
+        let mut world = World::new();
+        world.spawn_batch([
+            (
+                Card::new(Suit::Hearts, Rank::Ace),
+                Pile::Tableau(0),
+                PileIndex(0),
+                FaceUp(true),
+            ),
+            (
+                Card::new(Suit::Hearts, Rank::King),
+                Pile::Tableau(1),
+                PileIndex(0),
+                FaceUp(true),
+            ),
+        ]);
+        world.insert_resource(GameClock::start(0.0));
+        world.insert_resource(TimeAttack::new(10_000.0));
+
+        assert_eq!(check(&mut world, 9_000.0), None);
+        rules::apply(
+            &mut world,
+            Move::Transfer {
+                from: Pile::Tableau(0),
+                to: Pile::Foundation(0),
+                count: 1,
+            },
+        )
+        .unwrap();
+        let attack = *world.resource::<TimeAttack>().unwrap();
+        assert_eq!(attack.remaining_ms(&world, 12_000.0), 3_000.0);
+
+        assert_eq!(
+            check(&mut world, 15_000.0),
+            Some(GameEvent::TimeAttackEnded {
+                result: TimeAttackResult::TimeUp,
+                foundation_cards: 1,
+                remaining_ms: 0,
+            })
+        );
+        assert_eq!(check(&mut world, 16_000.0), None);
+        assert_eq!(rules::undo(&mut world), Err(GameError::TimeUp));
+    }
+}
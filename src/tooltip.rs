@@ -0,0 +1,206 @@
+// Inspecting cards under the pointer, for tooltips.
+//
+// `card_info_at` tells everything about the card at a canvas point that the
+// player is allowed to know: the card itself unless it is hidden, the pile it
+// lies on and where, and the piles the rules would let it move to.
+//
+// With the `Tooltips` resource present, scenes carry a ready-made tooltip as
+// well. The page reports the pointer with `hover`; once it has rested on a
+// card for `TOOLTIP_DELAY_MS`, the tooltip appears next to it on the overlay
+// layer.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{Entity, World};
+use crate::faces::{self, SuitStyle};
+use crate::game::{Card, FaceUp, Pile, PileIndex};
+use crate::layout::{self, Position, RenderLayer};
+use crate::rules::{self, Move};
+use crate::view::{self, Thoughtful, Visibility};
+
+/// How long the pointer rests on a card before its tooltip shows.
+pub const TOOLTIP_DELAY_MS: f64 = 500.0;
+
+/// What the player may know about one card on the table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardInfo {
+    pub entity: Entity,
+    /// `None` for hidden cards.
+    pub card: Option<Card>,
+    pub visibility: Visibility,
+    pub pile: String,
+    /// How far from the bottom of its pile the card lies.
+    pub index: usize,
+    #[serde(flatten)]
+    pub position: Position,
+    /// Piles the card, and the cards on top of it, can move to now.
+    pub targets: Vec<String>,
+    /// Whether there is any legal move for the card, including drawing it
+    /// from the stock.
+    pub movable: bool,
+}
+
+/// Marker resource asking for tooltips in scenes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tooltips;
+
+/// Resource with where the pointer rests and since when.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Hover {
+    x: f32,
+    y: f32,
+    card: Option<Entity>,
+    since_ms: f64,
+}
+
+/// A tooltip to draw on the overlay layer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tooltip {
+    pub text: String,
+    #[serde(flatten)]
+    pub position: Position,
+}
+
+/// Inspect the card under `(x, y)`, as laid out by the last layout.
+pub fn card_info_at(world: &World, x: f32, y: f32) -> Option<CardInfo> {
+    let entity = layout::card_at(world, x, y)?;
+    let pile = *world.get_component::<Pile>(entity)?;
+    let index = world.get_component::<PileIndex>(entity).map_or(0, |i| i.0);
+    let face_up = world.get_component::<FaceUp>(entity).is_some_and(|f| f.0);
+    let shown = view::view_of(
+        world.get_component::<Card>(entity).copied(),
+        face_up,
+        world.resource::<Thoughtful>().is_some(),
+    );
+    let count = rules::pile_cards(world, pile).len() - index;
+    let targets: Vec<String> = if face_up {
+        rules::all_piles(world)
+            .into_iter()
+            .filter(|&to| {
+                to != pile
+                    && rules::validate(
+                        world,
+                        Move::Transfer {
+                            from: pile,
+                            to,
+                            count,
+                        },
+                    )
+                    .is_ok()
+            })
+            .map(|to| to.to_string())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let draws = pile == Pile::Stock && count == 1 && rules::validate(world, Move::Draw).is_ok();
+    Some(CardInfo {
+        entity,
+        card: shown.card,
+        visibility: shown.visibility,
+        pile: pile.to_string(),
+        index,
+        position: world.get_component::<Position>(entity).copied()?,
+        movable: draws || !targets.is_empty(),
+        targets,
+    })
+}
+
+/// Report the pointer at `(x, y)`, or gone from the canvas.
+pub fn hover(world: &mut World, pointer: Option<(f32, f32)>, now_ms: f64) {
+    let Some((x, y)) = pointer else {
+        world.remove_resource::<Hover>();
+        return;
+    };
+    let card = layout::card_at(world, x, y);
+    let since_ms = match world.resource::<Hover>() {
+        Some(hover) if hover.card == card => hover.since_ms,
+        _ => now_ms,
+    };
+    world.insert_resource(Hover {
+        x,
+        y,
+        card,
+        since_ms,
+    });
+}
+
+/// The tooltip to show at `now_ms`, if tooltips are on and the pointer has
+/// rested on a card long enough.
+pub fn tooltip(world: &World, now_ms: f64) -> Option<Tooltip> {
+    world.resource::<Tooltips>()?;
+    let hover = world.resource::<Hover>()?;
+    if now_ms - hover.since_ms < TOOLTIP_DELAY_MS {
+        return None;
+    }
+    let info = card_info_at(world, hover.x, hover.y)?;
+    let name = info.card.map_or_else(
+        || "Face-down card".to_string(),
+        |card| card_name(card, faces::suit_style(world)),
+    );
+    let moves = if info.movable { "can move" } else { "no moves" };
+    Some(Tooltip {
+        text: format!("{name} · {} · {moves}", info.pile),
+        position: Position {
+            x: hover.x + 12.0,
+            y: hover.y + 12.0,
+            z: RenderLayer::Overlay.z(0),
+        },
+    })
+}
+
+/// Resource with the tooltip in the last scene.
+#[derive(Debug, Clone, PartialEq)]
+struct Shown(Option<Tooltip>);
+
+/// The tooltip for the scene at `now_ms`, and whether it differs from the
+/// one in the previous scene, so the canvas needs repainting.
+pub fn frame(world: &mut World, now_ms: f64) -> (Option<Tooltip>, bool) {
+    let tooltip = tooltip(world, now_ms);
+    let before = world.remove_resource::<Shown>().and_then(|s| s.0);
+    let changed = before != tooltip;
+    world.insert_resource(Shown(tooltip.clone()));
+    (tooltip, changed)
+}
+
+/// A card's name and the colour it is drawn in, such as "Queen of Hearts
+/// (red)".
+fn card_name(card: Card, suits: SuitStyle) -> String {
+    format!(
+        "{:?} of {:?} ({})",
+        card.rank,
+        card.suit,
+        suits.color_name(card.suit)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Deck;
+
+    #[test]
+    fn hovering_shows_what_a_card_can_do() {
+        let mut world = World::new();
+        rules::deal(&mut world, &Deck::standard().cards);
+        layout::layout_system(&mut world);
+        let stock = rules::top_card(&world, Pile::Stock).unwrap();
+        let at = *world.get_component::<Position>(stock).unwrap();
+
+        let info = card_info_at(&world, at.x + 1.0, at.y + 1.0).unwrap();
+        assert_eq!(info.entity, stock);
+        assert_eq!(info.card, None);
+        assert_eq!(info.pile, "stock");
+        assert!(info.movable && info.targets.is_empty());
+        assert!(card_info_at(&world, -10.0, -10.0).is_none());
+
+        world.insert_resource(Tooltips);
+        hover(&mut world, Some((at.x + 1.0, at.y + 1.0)), 0.0);
+        hover(&mut world, Some((at.x + 2.0, at.y + 2.0)), 300.0);
+        assert!(tooltip(&world, 400.0).is_none());
+        let shown = tooltip(&world, TOOLTIP_DELAY_MS).unwrap();
+        assert_eq!(shown.text, "Face-down card · stock · can move");
+        hover(&mut world, None, 600.0);
+        assert!(tooltip(&world, 1_000.0).is_none());
+    }
+}
@@ -0,0 +1,194 @@
+//! Single-elimination tournaments run by the game server.
+//!
+//! A player joins a bracket with `protocol::NetMessage::JoinBracket` and the
+//! server confirms with `BracketJoined`. Each round, the server pairs the
+//! players still in and sends every one of them a `Pairing`: the opponent,
+//! or none for a bye, and the seed both of them play. Players deal it with
+//! `SolitaireGame::start_round`, play, and send a `RoundResult` back. The
+//! server answers `FetchStanding`, and announces every change, with a
+//! `BracketStanding` of wins, losses and who is out, ending with the
+//! champion.
+//!
+//! What the client knows of its bracket is kept in the `Tournament`
+//! resource. `handle` updates it from the server's messages and returns the
+//! `GameEvent` for each, so a bracket UI can follow along with the other
+//! events of the game.
+//!
+//! `pair_round` pairs a round the way the client expects, so a native
+//! server can share it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::events::GameEvent;
+use crate::protocol::NetMessage;
+
+/// One player's match in a round.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pairing {
+    pub bracket: String,
+    /// Starting at 1.
+    pub round: u32,
+    /// `None` for a bye: the player goes through without playing.
+    pub opponent: Option<String>,
+    /// The deal both players play.
+    pub seed: u64,
+}
+
+/// How a player did in a round.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundResult {
+    pub bracket: String,
+    pub round: u32,
+    /// The seed played, for the server to check against the pairing.
+    pub seed: u64,
+    pub won: bool,
+    pub score: i32,
+    pub moves: u32,
+    pub time_ms: u64,
+}
+
+/// One player's record in a bracket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Standing {
+    pub player: String,
+    pub wins: u32,
+    pub losses: u32,
+    pub eliminated: bool,
+}
+
+/// Where a bracket stands.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BracketStanding {
+    pub bracket: String,
+    /// The round being played, or the last one once it is over.
+    pub round: u32,
+    pub standings: Vec<Standing>,
+    pub champion: Option<String>,
+}
+
+/// Resource with the bracket this client is in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tournament {
+    pub bracket: String,
+    /// Whether the server has let this client in.
+    pub joined: bool,
+    pub players: Vec<String>,
+    /// The match of the current round, once paired.
+    pub pairing: Option<Pairing>,
+    /// Whether the result of the current round has been sent.
+    pub submitted: bool,
+    pub standing: Option<BracketStanding>,
+}
+
+impl Tournament {
+    /// Asking to join `bracket`.
+    pub fn new(bracket: String) -> Self {
+        Tournament {
+            bracket,
+            joined: false,
+            players: Vec::new(),
+            pairing: None,
+            submitted: false,
+            standing: None,
+        }
+    }
+}
+
+/// Take in a message from the server about the bracket, returning the event
+/// it makes for the UI. Messages about other brackets are ignored.
+pub fn handle(world: &mut World, message: NetMessage) -> Option<GameEvent> {
+    let tournament = world.resource_mut::<Tournament>()?;
+    match message {
+        NetMessage::BracketJoined { bracket, players } if bracket == tournament.bracket => {
+            tournament.joined = true;
+            tournament.players = players;
+            Some(GameEvent::BracketJoined { bracket })
+        }
+        NetMessage::RoundPairing(pairing) if pairing.bracket == tournament.bracket => {
+            let event = GameEvent::RoundPaired {
+                round: pairing.round,
+                opponent: pairing.opponent.clone(),
+            };
+            tournament.pairing = Some(pairing);
+            tournament.submitted = false;
+            Some(event)
+        }
+        NetMessage::BracketStanding(standing) if standing.bracket == tournament.bracket => {
+            let event = GameEvent::StandingUpdated {
+                round: standing.round,
+                champion: standing.champion.clone(),
+            };
+            tournament.standing = Some(standing);
+            Some(event)
+        }
+        _ => None,
+    }
+}
+
+/// On the server: pair the `players` still in for `round`, first against
+/// second and so on, with a bye for the last one if they are odd in number.
+pub fn pair_round(
+    bracket: &str,
+    round: u32,
+    players: &[String],
+    seed: u64,
+) -> Vec<(String, Pairing)> {
+    let pairing = |opponent: Option<&String>| Pairing {
+        bracket: bracket.to_string(),
+        round,
+        opponent: opponent.cloned(),
+        seed,
+    };
+    players
+        .chunks(2)
+        .flat_map(|pair| match pair {
+            [a, b] => vec![(a.clone(), pairing(Some(b))), (b.clone(), pairing(Some(a)))],
+            [a] => vec![(a.clone(), pairing(None))],
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_are_paired_and_followed_by_the_client() {
+        let players: Vec<String> = ["ann", "bo", "cy"].map(String::from).into();
+        let pairings = pair_round("spring", 1, &players, 7);
+        assert_eq!(pairings.len(), 3);
+        assert_eq!(pairings[1].1.opponent.as_deref(), Some("ann"));
+        assert_eq!(pairings[2].1.opponent, None);
+
+        let mut world = World::new();
+        assert_eq!(
+            handle(&mut world, NetMessage::RoundPairing(pairings[0].1.clone())),
+            None
+        );
+        world.insert_resource(Tournament::new("spring".into()));
+        let joined = NetMessage::BracketJoined {
+            bracket: "spring".into(),
+            players,
+        };
+        assert!(handle(&mut world, joined).is_some());
+        let event = handle(&mut world, NetMessage::RoundPairing(pairings[0].1.clone()));
+        assert_eq!(
+            event,
+            Some(GameEvent::RoundPaired {
+                round: 1,
+                opponent: Some("bo".into())
+            })
+        );
+        let other = Pairing {
+            bracket: "autumn".into(),
+            ..pairings[2].1.clone()
+        };
+        assert_eq!(handle(&mut world, NetMessage::RoundPairing(other)), None);
+
+        let tournament = world.resource::<Tournament>().unwrap();
+        assert!(tournament.joined);
+        assert_eq!(tournament.pairing.as_ref().unwrap().seed, 7);
+    }
+}
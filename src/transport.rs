@@ -0,0 +1,336 @@
+//! The connections messages travel over.
+//!
+//! The game talks to the server through a `Transport`: anything that can
+//! send text frames, hand over the ones received, and tell whether it is
+//! open or has given up. `network::NetworkClient`, a WebSocket, is the
+//! usual one. Some networks block WebSockets, though, so there is also
+//! `LongPoll`, which carries the same frames over plain HTTP requests made
+//! with `fetch`:
+//!
+//! * `POST {base}/poll/send?client={id}` with one frame as the body, and
+//! * `GET {base}/poll/receive?client={id}`, which the server holds open
+//!   until it has frames for the client and answers with a JSON array of
+//!   them.
+//!
+//! `{base}` is the WebSocket URL with `ws` turned into `http`, and `{id}` a
+//! random name the client picks. `Fallback` keeps count of WebSocket
+//! connections that failed before they ever opened; after
+//! `MAX_SOCKET_FAILURES` of them to the same server, the game switches to
+//! long polling on its own.
+
+/// WebSocket connections that may fail before long polling is used.
+pub const MAX_SOCKET_FAILURES: u32 = 3;
+
+/// The server being connected to, and how often its socket failed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fallback {
+    url: Option<String>,
+    socket_failures: u32,
+}
+
+impl Fallback {
+    /// A connection to `url` is about to be made. Failures of another
+    /// server do not count against this one.
+    pub fn connecting(&mut self, url: &str) {
+        if self.url.as_deref() != Some(url) {
+            self.url = Some(url.to_string());
+            self.socket_failures = 0;
+        }
+    }
+
+    /// The server last connected to.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// A WebSocket connection failed before it opened.
+    pub fn socket_failed(&mut self) {
+        self.socket_failures += 1;
+    }
+
+    pub fn use_long_poll(&self) -> bool {
+        self.socket_failures >= MAX_SOCKET_FAILURES
+    }
+}
+
+/// The HTTP address long polling uses for the WebSocket URL `url`.
+pub fn long_poll_url(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    if let Some(rest) = url.strip_prefix("wss://") {
+        format!("https://{rest}")
+    } else if let Some(rest) = url.strip_prefix("ws://") {
+        format!("http://{rest}")
+    } else {
+        url.to_string()
+    }
+}
+
+#[cfg(feature = "wasm")]
+pub use web::{LongPoll, Transport};
+
+#[cfg(feature = "wasm")]
+mod web {
+    use std::cell::{Cell, RefCell};
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    use js_sys::Promise;
+    use rand::RngCore;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_futures::{JsFuture, spawn_local};
+    use web_sys::{Request, RequestInit};
+
+    use crate::leaderboard::fetch_text;
+    use crate::rng::GameRng;
+
+    /// A connection to the game server.
+    pub trait Transport {
+        /// Send one text frame.
+        fn send(&self, text: &str) -> Result<(), JsValue>;
+        /// The text frames received since the last call, oldest first.
+        fn take_received(&self) -> Vec<String>;
+        /// Whether frames can be sent.
+        fn is_open(&self) -> bool;
+        /// Whether the connection is still being opened.
+        fn is_connecting(&self) -> bool;
+        /// Whether the connection gave up before it ever opened.
+        fn has_failed(&self) -> bool;
+        fn close(&self);
+        /// Whether this is a WebSocket, as opposed to a fallback.
+        fn is_socket(&self) -> bool;
+    }
+
+    /// Polls failing in a row before the connection is given up.
+    const MAX_POLL_ERRORS: u32 = 5;
+    /// Pause after a failed poll, in milliseconds.
+    const RETRY_MS: i32 = 1_000;
+
+    /// What the requests in flight share with the transport.
+    #[derive(Default)]
+    struct Poll {
+        received: RefCell<Vec<String>>,
+        outbox: RefCell<VecDeque<String>>,
+        sending: Cell<bool>,
+        open: Cell<bool>,
+        failed: Cell<bool>,
+        closed: Cell<bool>,
+    }
+
+    /// Frames over HTTP long polling, for where WebSockets are blocked.
+    pub struct LongPoll {
+        base_url: String,
+        client: String,
+        poll: Rc<Poll>,
+    }
+
+    impl LongPoll {
+        /// Start polling the server at `base_url`, an `http` or `https`
+        /// address.
+        pub fn new(base_url: &str) -> LongPoll {
+            let client = format!("{:016x}", GameRng::from_entropy().next_u64());
+            let transport = LongPoll {
+                base_url: base_url.trim_end_matches('/').to_string(),
+                client,
+                poll: Rc::default(),
+            };
+            spawn_local(receive_loop(
+                transport.url("receive"),
+                Rc::clone(&transport.poll),
+            ));
+            transport
+        }
+
+        fn url(&self, endpoint: &str) -> String {
+            format!("{}/poll/{endpoint}?client={}", self.base_url, self.client)
+        }
+    }
+
+    impl Drop for LongPoll {
+        fn drop(&mut self) {
+            self.close();
+        }
+    }
+
+    impl Transport for LongPoll {
+        fn send(&self, text: &str) -> Result<(), JsValue> {
+            if self.poll.closed.get() || self.poll.failed.get() {
+                return Err(JsValue::from_str("Not connected"));
+            }
+            self.poll.outbox.borrow_mut().push_back(text.to_string());
+            // One request at a time, so frames arrive in the order sent.
+            if !self.poll.sending.replace(true) {
+                spawn_local(send_loop(self.url("send"), Rc::clone(&self.poll)));
+            }
+            Ok(())
+        }
+
+        fn take_received(&self) -> Vec<String> {
+            self.poll.received.take()
+        }
+
+        fn is_open(&self) -> bool {
+            self.poll.open.get() && !self.poll.closed.get()
+        }
+
+        fn is_connecting(&self) -> bool {
+            !self.poll.open.get() && !self.poll.failed.get() && !self.poll.closed.get()
+        }
+
+        fn has_failed(&self) -> bool {
+            self.poll.failed.get()
+        }
+
+        fn close(&self) {
+            self.poll.closed.set(true);
+        }
+
+        fn is_socket(&self) -> bool {
+            false
+        }
+    }
+
+    async fn receive_loop(url: String, poll: Rc<Poll>) {
+        let mut errors = 0;
+        while !poll.closed.get() {
+            let frames = match Request::new_with_str(&url) {
+                Ok(request) => fetch_text(request).await,
+                Err(e) => Err(e),
+            }
+            .and_then(|text| {
+                serde_json::from_str::<Vec<String>>(&text)
+                    .map_err(|e| JsValue::from_str(&e.to_string()))
+            });
+            match frames {
+                Ok(frames) => {
+                    errors = 0;
+                    poll.open.set(true);
+                    poll.received.borrow_mut().extend(frames);
+                }
+                Err(_) => {
+                    errors += 1;
+                    if errors >= MAX_POLL_ERRORS {
+                        poll.open.set(false);
+                        poll.failed.set(true);
+                        return;
+                    }
+                    sleep(RETRY_MS).await;
+                }
+            }
+        }
+    }
+
+    async fn send_loop(url: String, poll: Rc<Poll>) {
+        loop {
+            let Some(text) = poll.outbox.borrow_mut().pop_front() else {
+                break;
+            };
+            let init = RequestInit::new();
+            init.set_method("POST");
+            init.set_body(&JsValue::from_str(&text));
+            if let Ok(request) = Request::new_with_str_and_init(&url, &init) {
+                // A frame the server did not take is lost, as on a socket
+                // that drops.
+                let _ = fetch_text(request).await;
+            }
+        }
+        poll.sending.set(false);
+    }
+
+    /// Wait `ms` milliseconds.
+    async fn sleep(ms: i32) {
+        let promise = Promise::new(&mut |resolve, _| {
+            if let Some(window) = web_sys::window() {
+                let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms);
+            }
+        });
+        let _ = JsFuture::from(promise).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_socket_failures_switch_to_long_polling() {
+        let mut fallback = Fallback::default();
+        fallback.connecting("wss://play.example.com/ws/");
+        for _ in 1..MAX_SOCKET_FAILURES {
+            fallback.socket_failed();
+        }
+        assert!(!fallback.use_long_poll());
+        fallback.socket_failed();
+        assert!(fallback.use_long_poll());
+        // Another server gets its own chances.
+        fallback.connecting("ws://localhost:8080");
+        assert!(!fallback.use_long_poll());
+
+        assert_eq!(
+            long_poll_url("wss://play.example.com/ws/"),
+            "https://play.example.com/ws"
+        );
+        assert_eq!(
+            long_poll_url("ws://localhost:8080"),
+            "http://localhost:8080"
+        );
+    }
+
+    #[cfg(all(feature = "wasm", feature = "networking"))]
+    #[test]
+    fn the_game_keeps_to_its_budget_on_any_transport() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        use wasm_bindgen::JsValue;
+
+        use crate::protocol::NetMessage;
+        use crate::ratelimit::MessageClass;
+
+        /// A transport that keeps what it is given.
+        struct Recorder(Rc<RefCell<Vec<String>>>);
+
+        impl Transport for Recorder {
+            fn send(&self, text: &str) -> Result<(), JsValue> {
+                self.0.borrow_mut().push(text.to_string());
+                Ok(())
+            }
+            fn take_received(&self) -> Vec<String> {
+                Vec::new()
+            }
+            fn is_open(&self) -> bool {
+                true
+            }
+            fn is_connecting(&self) -> bool {
+                false
+            }
+            fn has_failed(&self) -> bool {
+                false
+            }
+            fn close(&self) {}
+            fn is_socket(&self) -> bool {
+                false
+            }
+        }
+
+        let sent: Rc<RefCell<Vec<String>>> = Rc::default();
+        let mut game = crate::SolitaireGame::new();
+        game.network = Some(Box::new(Recorder(Rc::clone(&sent))));
+        game.channels.set_framed(true);
+        let chat = NetMessage::Chat {
+            from: "ann".into(),
+            text: "hi".into(),
+        }
+        .to_json();
+        for _ in 0..20 {
+            game.send(&chat).unwrap();
+            game.advance(16.0);
+        }
+        let burst = MessageClass::Chat.budget().unwrap().burst as usize;
+        assert_eq!(sent.borrow().len(), burst);
+        // What got through is numbered without gaps, so the peer's ordered
+        // chat channel delivers all of it.
+        let mut peer = crate::channel::Mux::default();
+        let delivered: usize = sent.borrow().iter().map(|f| peer.receive(f).len()).sum();
+        assert_eq!(delivered, burst);
+    }
+}
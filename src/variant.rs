@@ -0,0 +1,236 @@
+// Solitaire games played with Klondike's rules on a differently sized table.
+//
+// The rules in `rules.rs` do not care how many piles there are: they read
+// the shape of the table from the `Table` resource, and a world without one
+// is a classic single-deck Klondike. Picking a `Variant` therefore just means
+// inserting its `Table` before dealing.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::game::{Deck, FOUNDATION_PILES, Pile, Rank, Suit, TABLEAU_PILES};
+use crate::scoring::TimeBonus;
+
+/// The games that can be played.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    /// One deck, seven tableau piles, four foundations, draw one.
+    #[default]
+    Klondike,
+    /// Two decks, nine tableau piles, eight foundations, draw three.
+    DoubleKlondike,
+}
+
+impl Variant {
+    /// The name to show players, such as "Double Klondike".
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Klondike => "Klondike",
+            Variant::DoubleKlondike => "Double Klondike",
+        }
+    }
+
+    pub fn table(self) -> Table {
+        match self {
+            Variant::Klondike => Table::KLONDIKE,
+            Variant::DoubleKlondike => Table::DOUBLE_KLONDIKE,
+        }
+    }
+}
+
+/// Variants are written in snake case, `"klondike"` or `"double_klondike"`,
+/// as in their JSON form.
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Klondike => write!(f, "klondike"),
+            Variant::DoubleKlondike => write!(f, "double_klondike"),
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "klondike" => Ok(Variant::Klondike),
+            "double_klondike" => Ok(Variant::DoubleKlondike),
+            _ => Err(format!("unknown variant \"{s}\"")),
+        }
+    }
+}
+
+/// Resource describing the table of the game being played, along with the
+/// house rules that go with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Table {
+    /// Number of 52-card decks shuffled together.
+    pub decks: u8,
+    pub tableau_piles: u8,
+    pub foundation_piles: u8,
+    /// Cards turned from the stock onto the waste per draw.
+    pub draw_count: u8,
+    /// How many times the waste may be turned back into the stock, or
+    /// `None` for no limit.
+    pub redeal_limit: Option<u32>,
+    /// The rank every foundation starts with.
+    pub foundation_base: Rank,
+    /// Whether recycling shuffles the waste instead of just turning it over.
+    pub reshuffle_on_recycle: bool,
+    /// Whether every foundation is kept for one suit, see `foundation_suit`.
+    /// Otherwise any suit may start any empty foundation.
+    pub fixed_foundation_suits: bool,
+    /// The bonus for a fast win, under standard scoring.
+    #[serde(default)]
+    pub time_bonus: TimeBonus,
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Table::KLONDIKE
+    }
+}
+
+impl Table {
+    pub const KLONDIKE: Table = Table {
+        decks: 1,
+        tableau_piles: TABLEAU_PILES,
+        foundation_piles: FOUNDATION_PILES,
+        draw_count: 1,
+        redeal_limit: None,
+        foundation_base: Rank::Ace,
+        reshuffle_on_recycle: false,
+        fixed_foundation_suits: false,
+        time_bonus: TimeBonus::STANDARD,
+    };
+
+    pub const DOUBLE_KLONDIKE: Table = Table {
+        decks: 2,
+        tableau_piles: 9,
+        foundation_piles: 8,
+        draw_count: 3,
+        redeal_limit: None,
+        foundation_base: Rank::Ace,
+        reshuffle_on_recycle: false,
+        fixed_foundation_suits: false,
+        time_bonus: TimeBonus {
+            points: 1_400_000,
+            min_seconds: 60,
+        },
+    };
+
+    /// The suits of fixed foundations, from foundation 0 on. A second deck
+    /// repeats them.
+    pub const FOUNDATION_SUITS: [Suit; 4] =
+        [Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs];
+
+    /// An unshuffled deck with the right number of cards.
+    pub fn deck(&self) -> Deck {
+        Deck::with_decks(self.decks)
+    }
+
+    /// Every pile on the table: stock, waste, the foundations, then the
+    /// tableau.
+    pub fn piles(&self) -> Vec<Pile> {
+        let mut piles = vec![Pile::Stock, Pile::Waste];
+        piles.extend((0..self.foundation_piles).map(Pile::Foundation));
+        piles.extend((0..self.tableau_piles).map(Pile::Tableau));
+        piles
+    }
+
+    /// The suit `foundation` is kept for, if foundations have fixed suits.
+    pub fn foundation_suit(&self, foundation: u8) -> Option<Suit> {
+        self.fixed_foundation_suits
+            .then(|| Table::FOUNDATION_SUITS[usize::from(foundation) % 4])
+    }
+
+    /// Whether `pile` is on this table.
+    pub fn contains(&self, pile: Pile) -> bool {
+        match pile {
+            Pile::Stock | Pile::Waste => true,
+            Pile::Foundation(i) => i < self.foundation_piles,
+            Pile::Tableau(i) => i < self.tableau_piles,
+        }
+    }
+}
+
+/// The table of the game in `world`.
+pub fn table(world: &World) -> Table {
+    world.resource::<Table>().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Card, FaceUp, PileIndex};
+    use crate::rules::{self, Move};
+
+    #[test]
+    fn double_klondike_deals_two_decks_onto_nine_piles() {
+        let table = Variant::DoubleKlondike.table();
+        let mut world = World::new();
+        world.insert_resource(table);
+        rules::deal(&mut world, &table.deck().cards);
+
+        assert_eq!(table.deck().cards.len(), 104);
+        assert_eq!(rules::pile_cards(&world, Pile::Tableau(8)).len(), 9);
+        assert_eq!(rules::pile_cards(&world, Pile::Stock).len(), 104 - 45);
+        assert!(world.find_by_name("foundation-7").is_some());
+        assert!(
+            rules::validate(
+                &world,
+                Move::Transfer {
+                    from: Pile::Tableau(8),
+                    to: Pile::Foundation(8),
+                    count: 1,
+                }
+            )
+            .is_err()
+        );
+
+        rules::apply(&mut world, Move::Draw).unwrap();
+        assert_eq!(rules::pile_cards(&world, Pile::Waste).len(), 3);
+        rules::undo(&mut world).unwrap();
+        assert!(rules::pile_cards(&world, Pile::Waste).is_empty());
+    }
+
+    #[test]
+    fn fixed_foundations_only_take_their_suit() {
+        let table = Table {
+            fixed_foundation_suits: true,
+            ..Table::DOUBLE_KLONDIKE
+        };
+        assert_eq!(table.foundation_suit(0), Some(Suit::Spades));
+        assert_eq!(table.foundation_suit(5), Some(Suit::Hearts));
+        assert_eq!(Table::KLONDIKE.foundation_suit(0), None);
+
+        let mut world = World::new();
+        world.insert_resource(table);
+        world.spawn_batch([(
+            Card::new(Suit::Hearts, Rank::Ace),
+            Pile::Waste,
+            PileIndex(0),
+            FaceUp(true),
+        )]);
+        let to = |foundation| Move::Transfer {
+            from: Pile::Waste,
+            to: Pile::Foundation(foundation),
+            count: 1,
+        };
+        assert!(rules::validate(&world, to(0)).is_err());
+        assert!(rules::validate(&world, to(1)).is_ok());
+    }
+
+    #[test]
+    fn variants_round_trip_through_their_names() {
+        for variant in [Variant::Klondike, Variant::DoubleKlondike] {
+            assert_eq!(variant.to_string().parse::<Variant>(), Ok(variant));
+        }
+        assert!("spider".parse::<Variant>().is_err());
+    }
+}
@@ -0,0 +1,138 @@
+// What the player gets to see of the board.
+//
+// The frontend draws the table from a `BoardView` rather than reading the
+// ECS directly. A face-down card is normally sent without its identity, so
+// the page cannot be inspected to peek at it. In *thoughtful* Klondike, the
+// research variant where every card is known, face-down cards are sent
+// *revealed* instead: still face down as far as the rules are concerned,
+// but shown greyed with their rank and suit.
+//
+// When foundations are kept for fixed suits, each foundation also names its
+// suit so the renderer can draw it on the empty placeholder.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::World;
+use crate::game::{Card, FaceUp, Pile, Suit};
+use crate::rules;
+use crate::variant;
+
+/// Resource switching on thoughtful Klondike: all face-down cards are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Thoughtful;
+
+/// How a card should be drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    FaceUp,
+    /// Face down, but shown to the player (thoughtful mode).
+    Revealed,
+    /// Face down and unknown to the player.
+    Hidden,
+}
+
+/// One card as the player sees it. `card` is `None` for hidden cards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardView {
+    pub card: Option<Card>,
+    pub visibility: Visibility,
+}
+
+/// One pile, from the bottom card to the top card.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PileView {
+    pub pile: String,
+    pub cards: Vec<CardView>,
+    /// The only suit this foundation takes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suit: Option<Suit>,
+}
+
+/// The whole table as the player sees it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardView {
+    pub piles: Vec<PileView>,
+}
+
+impl BoardView {
+    pub fn from_world(world: &World) -> Self {
+        let thoughtful = world.resource::<Thoughtful>().is_some();
+        let table = variant::table(world);
+        let piles = rules::all_piles(world)
+            .into_iter()
+            .map(|pile| PileView {
+                pile: pile.to_string(),
+                cards: rules::pile_cards(world, pile)
+                    .into_iter()
+                    .map(|entity| {
+                        let card = world.get_component::<Card>(entity).copied();
+                        let face_up = world.get_component::<FaceUp>(entity).is_some_and(|f| f.0);
+                        view_of(card, face_up, thoughtful)
+                    })
+                    .collect(),
+                suit: match pile {
+                    Pile::Foundation(foundation) => table.foundation_suit(foundation),
+                    _ => None,
+                },
+            })
+            .collect();
+        BoardView { piles }
+    }
+}
+
+/// Thoughtful mode reveals the stock as well as the tableau: the player
+/// knows the position of every card.
+pub(crate) fn view_of(card: Option<Card>, face_up: bool, thoughtful: bool) -> CardView {
+    let visibility = match (face_up, thoughtful) {
+        (true, _) => Visibility::FaceUp,
+        (false, true) => Visibility::Revealed,
+        (false, false) => Visibility::Hidden,
+    };
+    CardView {
+        card: if visibility == Visibility::Hidden {
+            None
+        } else {
+            card
+        },
+        visibility,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Deck;
+
+    fn tableau_six(world: &World) -> Vec<Visibility> {
+        let board = BoardView::from_world(world);
+        let pile = board.piles.iter().find(|p| p.pile == "tableau-6").unwrap();
+        pile.cards.iter().map(|c| c.visibility).collect()
+    }
+
+    #[test]
+    fn thoughtful_mode_reveals_face_down_cards() {
+        let mut world = World::new();
+        rules::deal(&mut world, &Deck::standard().cards);
+        let normal = tableau_six(&world);
+        assert_eq!(
+            normal.iter().filter(|v| **v == Visibility::Hidden).count(),
+            6
+        );
+        assert_eq!(normal.last(), Some(&Visibility::FaceUp));
+        let board = BoardView::from_world(&world);
+        assert!(board.piles[0].cards.iter().all(|c| c.card.is_none()));
+
+        world.insert_resource(Thoughtful);
+        let revealed = tableau_six(&world);
+        assert_eq!(
+            revealed
+                .iter()
+                .filter(|v| **v == Visibility::Revealed)
+                .count(),
+            6
+        );
+        let board = BoardView::from_world(&world);
+        assert!(board.piles[0].cards.iter().all(|c| c.card.is_some()));
+    }
+}
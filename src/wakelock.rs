@@ -0,0 +1,252 @@
+// Keeping the screen awake during play, through the Screen Wake Lock API.
+//
+// Phones dim and lock their screens while the player is thinking about the
+// next move. With the lock on, the screen stays awake from the deal until the
+// game is paused, won, given up or runs out of time, and wakes up again on
+// resume.
+//
+// Browsers drop the lock whenever the page is hidden, so it is requested
+// again when the page becomes visible. Where `navigator.wakeLock` does not
+// exist, or the request is refused, the screen simply sleeps as usual.
+
+use crate::events::GameEvent;
+
+/// Whether the screen should stay awake after `event`, if the event
+/// changes that.
+pub fn wanted_after(event: &GameEvent) -> Option<bool> {
+    match event {
+        GameEvent::GameStarted { .. } | GameEvent::Resumed => Some(true),
+        GameEvent::Paused { .. }
+        | GameEvent::GameWon { .. }
+        | GameEvent::GameAbandoned { .. }
+        | GameEvent::TimeAttackEnded { .. } => Some(false),
+        _ => None,
+    }
+}
+
+/// The player's choice, and whether the game currently wants the lock.
+pub struct WakeLock {
+    enabled: bool,
+    wanted: bool,
+    screen: Screen,
+}
+
+impl Default for WakeLock {
+    fn default() -> Self {
+        WakeLock {
+            enabled: true,
+            wanted: false,
+            screen: Screen::new(),
+        }
+    }
+}
+
+impl WakeLock {
+    /// Turn the lock on or off. Turning it on in the middle of a game takes
+    /// the lock straight away.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.update();
+    }
+
+    /// Take or let go of the lock as `event` asks.
+    pub fn handle(&mut self, event: &GameEvent) {
+        if let Some(wanted) = wanted_after(event) {
+            self.wanted = wanted;
+            self.update();
+        }
+    }
+
+    /// Whether the screen is meant to stay awake now. The browser may still
+    /// have refused or dropped the lock.
+    pub fn is_held(&self) -> bool {
+        self.enabled && self.wanted
+    }
+
+    fn update(&mut self) {
+        if self.is_held() {
+            self.screen.acquire();
+        } else {
+            self.screen.release();
+        }
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+struct Screen;
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+impl Screen {
+    fn new() -> Screen {
+        Screen
+    }
+
+    fn acquire(&mut self) {}
+
+    fn release(&mut self) {}
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+use web::Screen;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+mod web {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    use js_sys::{Function, Promise, Reflect};
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::{JsFuture, spawn_local};
+
+    /// The screen lock in the browser, shared with the pending request and
+    /// the visibility listener.
+    #[derive(Default)]
+    struct Lock {
+        wanted: Cell<bool>,
+        pending: Cell<bool>,
+        sentinel: RefCell<Option<JsValue>>,
+    }
+
+    #[derive(Default)]
+    pub struct Screen {
+        lock: Rc<Lock>,
+        // Registered on the first `acquire`, kept alive until dropped.
+        on_visible: Option<Closure<dyn FnMut()>>,
+    }
+
+    impl Screen {
+        pub fn new() -> Screen {
+            Screen::default()
+        }
+
+        pub fn acquire(&mut self) {
+            self.lock.wanted.set(true);
+            self.listen();
+            request(&self.lock);
+        }
+
+        pub fn release(&mut self) {
+            self.lock.wanted.set(false);
+            if let Some(sentinel) = self.lock.sentinel.take() {
+                call(&sentinel, "release", None);
+            }
+        }
+
+        fn listen(&mut self) {
+            if self.on_visible.is_some() {
+                return;
+            }
+            let Some(document) = web_sys::window().and_then(|w| w.document()) else {
+                return;
+            };
+            let lock = Rc::clone(&self.lock);
+            let hidden = document.clone();
+            let on_visible = Closure::<dyn FnMut()>::new(move || {
+                if !hidden.hidden() {
+                    request(&lock);
+                }
+            });
+            let _ = document.add_event_listener_with_callback(
+                "visibilitychange",
+                on_visible.as_ref().unchecked_ref(),
+            );
+            self.on_visible = Some(on_visible);
+        }
+    }
+
+    impl Drop for Screen {
+        fn drop(&mut self) {
+            self.release();
+            if let Some(on_visible) = &self.on_visible
+                && let Some(document) = web_sys::window().and_then(|w| w.document())
+            {
+                let _ = document.remove_event_listener_with_callback(
+                    "visibilitychange",
+                    on_visible.as_ref().unchecked_ref(),
+                );
+            }
+        }
+    }
+
+    /// Ask for the lock unless it is held or on its way.
+    fn request(lock: &Rc<Lock>) {
+        if !lock.wanted.get() || lock.pending.get() {
+            return;
+        }
+        let held = lock.sentinel.borrow().as_ref().is_some_and(|sentinel| {
+            !Reflect::get(sentinel, &JsValue::from_str("released"))
+                .is_ok_and(|released| released.is_truthy())
+        });
+        if held {
+            return;
+        }
+        let wake_lock = web_sys::window()
+            .map(|w| w.navigator())
+            .and_then(|n| Reflect::get(&n, &JsValue::from_str("wakeLock")).ok())
+            .filter(|w| !w.is_undefined());
+        let Some(promise) = wake_lock
+            .and_then(|w| call(&w, "request", Some(&JsValue::from_str("screen"))))
+            .and_then(|p| p.dyn_into::<Promise>().ok())
+        else {
+            return;
+        };
+        lock.pending.set(true);
+        let lock = Rc::clone(lock);
+        spawn_local(async move {
+            let sentinel = JsFuture::from(promise).await;
+            lock.pending.set(false);
+            // Refusals, such as from a page in the background, are left be.
+            if let Ok(sentinel) = sentinel {
+                if lock.wanted.get() {
+                    lock.sentinel.replace(Some(sentinel));
+                } else {
+                    call(&sentinel, "release", None);
+                }
+            }
+        });
+    }
+
+    /// Call the method `name` of `target`, if it has one.
+    fn call(target: &JsValue, name: &str, arg: Option<&JsValue>) -> Option<JsValue> {
+        let method: Function = Reflect::get(target, &JsValue::from_str(name))
+            .ok()?
+            .dyn_into()
+            .ok()?;
+        match arg {
+            Some(arg) => method.call1(target, arg),
+            None => method.call0(target),
+        }
+        .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_lock_is_held_from_the_deal_until_a_pause_or_win() {
+        let mut lock = WakeLock::default();
+        assert!(!lock.is_held());
+        lock.handle(&GameEvent::GameStarted { seed: 1 });
+        assert!(lock.is_held());
+        lock.handle(&GameEvent::StockRecycled);
+        assert!(lock.is_held());
+        lock.handle(&GameEvent::Paused { hide_cards: false });
+        assert!(!lock.is_held());
+        lock.handle(&GameEvent::Resumed);
+        assert!(lock.is_held());
+
+        lock.set_enabled(false);
+        assert!(!lock.is_held());
+        lock.set_enabled(true);
+        lock.handle(&GameEvent::GameWon {
+            moves: 90,
+            undos: 0,
+            time_ms: 1,
+            time_bonus: 0,
+        });
+        assert!(!lock.is_held());
+    }
+}
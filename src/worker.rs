@@ -0,0 +1,498 @@
+//! Running heavy jobs, such as the solver, in a dedicated Web Worker.
+//!
+//! A worker is a second instance of this same wasm module, loaded by a tiny
+//! worker script. The page and the worker talk over the worker's message
+//! channel using the JSON messages below, so the search never runs on the
+//! main thread:
+//!
+//! ```json
+//! { "job": "solve", "id": 7, "position": { ... }, "node_limit": 200000 }
+//! { "job": "solved", "id": 7, "outcome": { "result": "winnable", "moves": [...] } }
+//! ```
+//!
+//! The worker script only needs to load the module and hand over control:
+//!
+//! ```ignore
+//! // solver-worker.js
+//! import init, { start_solver_worker } from "./solitaire.js";
+//! await init();
+//! start_solver_worker();
+//! ```
+//!
+//! The page then uses `SolverWorker`, whose methods return Promises:
+//!
+//! ```ignore
+//! const worker = new SolverWorker("./solver-worker.js");
+//! const outcome = JSON.parse(await worker.solve(game.solver_position(), 200000));
+//! ```
+//!
+//! `solve_async` wraps this for `SolitaireGame::solve_async`: it solves on a
+//! worker when there is one, and otherwise runs a `solver::Search` a few
+//! milliseconds at a time, yielding to the event loop between slices so
+//! frames keep being drawn.
+//!
+//! `handle` does the actual work and has no browser dependencies, which keeps
+//! it testable natively.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::rules::Move;
+use crate::solver::{self, Outcome, Position};
+
+/// A job posted to the worker. `id` is echoed in the response so several
+/// jobs can be in flight at once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "job", rename_all = "snake_case")]
+pub enum WorkerRequest {
+    /// Search for a winning line from `position`, for at most `timeout_ms`
+    /// milliseconds when it is given.
+    Solve {
+        id: u32,
+        position: Position,
+        node_limit: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_ms: Option<f64>,
+    },
+    /// Look for a game seed with a winnable opening deal.
+    FindWinnableDeal {
+        id: u32,
+        first_seed: u64,
+        attempts: u32,
+        node_limit: usize,
+    },
+}
+
+/// The worker's answer to a `WorkerRequest`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "job", rename_all = "snake_case")]
+pub enum WorkerResponse {
+    Solved {
+        id: u32,
+        outcome: Outcome,
+    },
+    /// `seed` is `None` when none of the attempted seeds could be won.
+    WinnableDeal {
+        id: u32,
+        seed: Option<u64>,
+        moves: Vec<Move>,
+    },
+    /// The request could not be understood.
+    Failed {
+        id: Option<u32>,
+        message: String,
+    },
+}
+
+impl WorkerResponse {
+    /// The id of the request this answers, if it could be read.
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            WorkerResponse::Solved { id, .. } | WorkerResponse::WinnableDeal { id, .. } => {
+                Some(*id)
+            }
+            WorkerResponse::Failed { id, .. } => *id,
+        }
+    }
+}
+
+/// The requests waiting for an answer, each with whatever settles it.
+#[derive(Debug)]
+pub struct PendingRequests<T> {
+    waiting: HashMap<u32, T>,
+}
+
+impl<T> Default for PendingRequests<T> {
+    fn default() -> Self {
+        PendingRequests {
+            waiting: HashMap::new(),
+        }
+    }
+}
+
+impl<T> PendingRequests<T> {
+    pub fn insert(&mut self, id: u32, settle: T) {
+        self.waiting.insert(id, settle);
+    }
+
+    /// Take the requests `response` settles: the one it answers or, for a
+    /// failure that names no request, every one waiting, since any of them
+    /// may be the one that failed and none would hear otherwise.
+    pub fn settled_by(&mut self, response: &WorkerResponse) -> Vec<T> {
+        match response.id() {
+            Some(id) => self.waiting.remove(&id).into_iter().collect(),
+            None => self.take_all(),
+        }
+    }
+
+    /// Take every request waiting.
+    pub fn take_all(&mut self) -> Vec<T> {
+        self.waiting.drain().map(|(_, settle)| settle).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+}
+
+/// Run one job.
+pub fn run(request: WorkerRequest) -> WorkerResponse {
+    match request {
+        WorkerRequest::Solve {
+            id,
+            position,
+            node_limit,
+            timeout_ms,
+        } => WorkerResponse::Solved {
+            id,
+            // The worker's clock is not the page's, so the deadline is
+            // taken from here.
+            outcome: match timeout_ms {
+                Some(timeout) => {
+                    solver::solve_until(&position, node_limit, clock::now_ms() + timeout)
+                }
+                None => solver::solve(&position, node_limit),
+            },
+        },
+        WorkerRequest::FindWinnableDeal {
+            id,
+            first_seed,
+            attempts,
+            node_limit,
+        } => {
+            let found = solver::find_winnable_deal(first_seed, attempts, node_limit);
+            WorkerResponse::WinnableDeal {
+                id,
+                seed: found.as_ref().map(|(seed, _)| *seed),
+                moves: found.map(|(_, moves)| moves).unwrap_or_default(),
+            }
+        }
+    }
+}
+
+/// Decode a JSON request, run it and encode the response.
+pub fn handle(request_json: &str) -> String {
+    let response = match serde_json::from_str(request_json) {
+        Ok(request) => run(request),
+        Err(err) => WorkerResponse::Failed {
+            // Salvage the id so the caller's Promise can still be settled.
+            id: serde_json::from_str::<serde_json::Value>(request_json)
+                .ok()
+                .and_then(|v| v.get("id")?.as_u64())
+                .and_then(|id| u32::try_from(id).ok()),
+            message: err.to_string(),
+        },
+    };
+    serde_json::to_string(&response).expect("worker responses always serialize")
+}
+
+#[cfg(feature = "wasm")]
+pub use web::{SolverWorker, solve_async};
+
+#[cfg(feature = "wasm")]
+mod web {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    use js_sys::{Function, Promise};
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_futures::{JsFuture, future_to_promise};
+    use web_sys::{
+        DedicatedWorkerGlobalScope, ErrorEvent, MessageEvent, Worker, WorkerOptions, WorkerType,
+    };
+
+    use super::*;
+    use crate::solver::Search;
+
+    /// How long one slice of a search on the main thread may run, well
+    /// within a 60 Hz frame.
+    const SLICE_MS: f64 = 4.0;
+
+    /// Worker side: answer every message posted to this worker.
+    ///
+    /// Call once from the worker script after the module has loaded.
+    #[wasm_bindgen]
+    pub fn start_solver_worker() -> Result<(), JsValue> {
+        let scope: DedicatedWorkerGlobalScope = js_sys::global().dyn_into()?;
+        let reply_to = scope.clone();
+        let onmessage = Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+            if let Some(text) = e.data().as_string() {
+                let _ = reply_to.post_message(&JsValue::from_str(&handle(&text)));
+            }
+        });
+        scope.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        onmessage.forget();
+        Ok(())
+    }
+
+    /// Promises waiting for an answer, with their resolve and reject
+    /// functions.
+    type Pending = Rc<RefCell<PendingRequests<(Function, Function)>>>;
+
+    /// Main-thread handle to a solver worker.
+    #[wasm_bindgen]
+    pub struct SolverWorker {
+        worker: Worker,
+        pending: Pending,
+        next_id: Cell<u32>,
+        // Kept alive for as long as the worker can answer.
+        _onmessage: Closure<dyn FnMut(MessageEvent)>,
+        _onerror: Closure<dyn FnMut(ErrorEvent)>,
+    }
+
+    #[wasm_bindgen]
+    impl SolverWorker {
+        /// Start a worker from `script_url`, a module script that calls
+        /// `start_solver_worker`.
+        #[wasm_bindgen(constructor)]
+        pub fn new(script_url: &str) -> Result<SolverWorker, JsValue> {
+            let options = WorkerOptions::new();
+            options.set_type(WorkerType::Module);
+            let worker = Worker::new_with_options(script_url, &options)?;
+
+            let pending: Pending = Rc::default();
+            let waiting = pending.clone();
+            let onmessage = Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+                let text = e.data().as_string().unwrap_or_default();
+                let response = serde_json::from_str(&text).unwrap_or(WorkerResponse::Failed {
+                    id: None,
+                    message: "unexpected answer from the solver worker".to_string(),
+                });
+                let settled = waiting.borrow_mut().settled_by(&response);
+                for (resolve, reject) in settled {
+                    match &response {
+                        WorkerResponse::Failed { message, .. } => {
+                            let _ = reject.call1(&JsValue::NULL, &js_sys::Error::new(message));
+                        }
+                        _ => {
+                            let _ = resolve.call1(&JsValue::NULL, &JsValue::from_str(&text));
+                        }
+                    }
+                }
+            });
+            worker.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+
+            // A worker that fails to load or throws answers nothing more.
+            let waiting = pending.clone();
+            let onerror = Closure::<dyn FnMut(ErrorEvent)>::new(move |e: ErrorEvent| {
+                reject_all(
+                    &waiting,
+                    &format!("the solver worker failed: {}", e.message()),
+                );
+            });
+            worker.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+
+            Ok(SolverWorker {
+                worker,
+                pending,
+                next_id: Cell::new(0),
+                _onmessage: onmessage,
+                _onerror: onerror,
+            })
+        }
+
+        /// Solve the position given as JSON (see
+        /// `SolitaireGame::solver_position`). Resolves with the JSON of the
+        /// `solved` response, and rejects when the worker fails.
+        pub fn solve(&self, position_json: &str, node_limit: usize) -> Result<Promise, JsValue> {
+            let position: Position = serde_json::from_str(position_json)
+                .map_err(|e| JsValue::from_str(&e.to_string()))?;
+            let id = self.take_id();
+            self.post(
+                id,
+                WorkerRequest::Solve {
+                    id,
+                    position,
+                    node_limit,
+                    timeout_ms: None,
+                },
+            )
+        }
+
+        /// Find a game seed with a winnable opening deal. Resolves with the
+        /// JSON of the `winnable_deal` response, and rejects when the worker
+        /// fails.
+        pub fn find_winnable_deal(
+            &self,
+            first_seed: u64,
+            attempts: u32,
+            node_limit: usize,
+        ) -> Result<Promise, JsValue> {
+            let id = self.take_id();
+            self.post(
+                id,
+                WorkerRequest::FindWinnableDeal {
+                    id,
+                    first_seed,
+                    attempts,
+                    node_limit,
+                },
+            )
+        }
+
+        /// Stop the worker. Pending Promises are rejected.
+        pub fn terminate(&self) {
+            self.worker.terminate();
+            reject_all(&self.pending, "the solver worker was terminated");
+        }
+    }
+
+    /// Solve `position` within `timeout_ms` without blocking the page: on
+    /// `worker` when there is one, and otherwise a slice at a time on this
+    /// thread. Resolves with the JSON of the `Outcome`.
+    pub fn solve_async(
+        worker: Option<&SolverWorker>,
+        position: Position,
+        node_limit: usize,
+        timeout_ms: f64,
+    ) -> Promise {
+        let timeout_ms = timeout_ms.max(0.0);
+        let Some(worker) = worker else {
+            return future_to_promise(async move {
+                let outcome = solve_in_slices(&position, node_limit, timeout_ms).await?;
+                Ok(JsValue::from_str(&to_json(&outcome)))
+            });
+        };
+        let id = worker.take_id();
+        let request = WorkerRequest::Solve {
+            id,
+            position,
+            node_limit,
+            timeout_ms: Some(timeout_ms),
+        };
+        let reply = worker.post(id, request);
+        future_to_promise(async move {
+            let text = JsFuture::from(reply?)
+                .await?
+                .as_string()
+                .unwrap_or_default();
+            match serde_json::from_str(&text) {
+                Ok(WorkerResponse::Solved { outcome, .. }) => {
+                    Ok(JsValue::from_str(&to_json(&outcome)))
+                }
+                Ok(WorkerResponse::Failed { message, .. }) => Err(JsValue::from_str(&message)),
+                _ => Err(JsValue::from_str(
+                    "unexpected answer from the solver worker",
+                )),
+            }
+        })
+    }
+
+    async fn solve_in_slices(
+        position: &Position,
+        node_limit: usize,
+        timeout_ms: f64,
+    ) -> Result<Outcome, JsValue> {
+        let deadline = clock::now_ms() + timeout_ms;
+        let mut search = Search::new(position, node_limit);
+        loop {
+            let now = clock::now_ms();
+            if now >= deadline {
+                return Ok(Outcome::Unknown);
+            }
+            if let Some(outcome) = search.step_until((now + SLICE_MS).min(deadline)) {
+                return Ok(outcome);
+            }
+            next_task().await?;
+        }
+    }
+
+    /// Wait for a fresh task of the event loop, letting the page draw a
+    /// frame. Works in windows and workers alike.
+    async fn next_task() -> Result<(), JsValue> {
+        let set_timeout: Function =
+            js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("setTimeout"))?
+                .dyn_into()?;
+        let wait = Promise::new(&mut |resolve, _reject| {
+            let _ = set_timeout.call2(&JsValue::NULL, &resolve, &JsValue::from(0));
+        });
+        JsFuture::from(wait).await.map(drop)
+    }
+
+    fn reject_all(pending: &Pending, message: &str) {
+        let settled = pending.borrow_mut().take_all();
+        for (_, reject) in settled {
+            let _ = reject.call1(&JsValue::NULL, &js_sys::Error::new(message));
+        }
+    }
+
+    fn to_json(outcome: &Outcome) -> String {
+        serde_json::to_string(outcome).expect("outcomes always serialize")
+    }
+
+    impl SolverWorker {
+        fn take_id(&self) -> u32 {
+            let id = self.next_id.get();
+            self.next_id.set(id.wrapping_add(1));
+            id
+        }
+
+        fn post(&self, id: u32, request: WorkerRequest) -> Result<Promise, JsValue> {
+            let json = serde_json::to_string(&request).expect("worker requests always serialize");
+            self.worker.post_message(&JsValue::from_str(&json))?;
+            let pending = self.pending.clone();
+            Ok(Promise::new(&mut |resolve, reject| {
+                pending.borrow_mut().insert(id, (resolve, reject));
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_round_trip_through_json() {
+        let request = WorkerRequest::Solve {
+            id: 3,
+            position: Position::empty(),
+            node_limit: 10,
+            timeout_ms: Some(50.0),
+        };
+        let response: WorkerResponse =
+            serde_json::from_str(&handle(&serde_json::to_string(&request).unwrap())).unwrap();
+        assert_eq!(
+            response,
+            WorkerResponse::Solved {
+                id: 3,
+                outcome: Outcome::Winnable { moves: Vec::new() },
+            }
+        );
+
+        let response: WorkerResponse =
+            serde_json::from_str(&handle(r#"{ "job": "solve", "id": 9, "position": 1 }"#)).unwrap();
+        assert!(matches!(
+            response,
+            WorkerResponse::Failed { id: Some(9), .. }
+        ));
+    }
+
+    #[test]
+    fn a_failure_naming_no_request_settles_every_one() {
+        let mut pending = PendingRequests::default();
+        for id in 0..3 {
+            pending.insert(id, id);
+        }
+        let solved = WorkerResponse::Solved {
+            id: 1,
+            outcome: Outcome::Unknown,
+        };
+        assert_eq!(pending.settled_by(&solved), vec![1]);
+        assert_eq!(pending.settled_by(&solved), Vec::<u32>::new());
+
+        let response: WorkerResponse = serde_json::from_str(&handle("not json")).unwrap();
+        assert_eq!(response.id(), None);
+        let mut settled = pending.settled_by(&response);
+        settled.sort_unstable();
+        assert_eq!(settled, vec![0, 2]);
+        assert!(pending.is_empty());
+    }
+}